@@ -0,0 +1,72 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use shipping::cpu_metrics::CgroupCpuStats;
+use shipping::shipping_service::quote::{create_quote_from_count, DiscountEngine, QuoteStrategy};
+use shipping::shipping_service::zones::ShippingZone;
+use shipping::shipping_service::{CartItem, DeliverySpeed, QuoteOrder, TenantConfig};
+use uuid::Uuid;
+
+fn items(count: u32) -> Vec<CartItem> {
+    vec![CartItem {
+        quantity: count,
+        length_cm: Some(30_f64),
+        width_cm: Some(20_f64),
+        height_cm: Some(10_f64),
+        weight_g: Some(500),
+    }]
+}
+
+fn bench_create_quote_from_count(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let tenant = TenantConfig::default();
+    let discounts = DiscountEngine::default();
+    let mut group = c.benchmark_group("create_quote_from_count");
+    for count in [1, 10, 100, 1000] {
+        let items = items(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            // Tiered keeps the quote service out of the measurement.
+            b.to_async(&runtime).iter(|| {
+                let order = QuoteOrder {
+                    zone: ShippingZone::Europe,
+                    items: &items,
+                    speed: DeliverySpeed::Standard,
+                    strategy: QuoteStrategy::Tiered,
+                    canary: false,
+                    tenant: &tenant,
+                    discounts: &discounts,
+                };
+                async move { create_quote_from_count(black_box(count), &order).await }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_try_cgroups_v2(c: &mut Criterion) {
+    let root = std::env::temp_dir().join(format!("cgroup-{}", Uuid::new_v4()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(
+        root.join("cpu.stat"),
+        "usage_usec 2500000\nuser_usec 2000000\nsystem_usec 500000\n\
+         nr_periods 40\nnr_throttled 3\nthrottled_usec 120000\n",
+    )
+    .unwrap();
+
+    c.bench_function("CgroupCpuStats::try_cgroups_v2", |b| {
+        b.iter(|| CgroupCpuStats::try_cgroups_v2(black_box(&root)))
+    });
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+criterion_group!(benches, bench_create_quote_from_count, bench_try_cgroups_v2);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+/// Runs `cmd` and returns its trimmed stdout, or `None` if it fails.
+fn command_output(cmd: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(cmd).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Version of `package` resolved in `Cargo.lock`.
+fn locked_version(package: &str) -> Option<String> {
+    let lock = fs::read_to_string("Cargo.lock").ok()?;
+    let name = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+    lines.find(|line| *line == name)?;
+    let version = lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')?;
+    Some(version.to_string())
+}
+
+fn main() {
+    // Values passed in the build environment win, so images built without the
+    // git checkout (e.g. in Docker) can still be stamped.
+    let git_commit = env::var("GIT_COMMIT")
+        .ok()
+        .or_else(|| command_output("git", &["rev-parse", "--short", "HEAD"]));
+    let build_time = env::var("BUILD_TIME")
+        .ok()
+        .or_else(|| command_output("date", &["-u", "+%Y-%m-%dT%H:%M:%SZ"]));
+
+    if let Some(sdk_version) = locked_version("opentelemetry_sdk") {
+        println!("cargo:rustc-env=OTEL_SDK_VERSION={sdk_version}");
+    }
+    if let Some(git_commit) = git_commit {
+        println!("cargo:rustc-env=GIT_COMMIT={git_commit}");
+    }
+    if let Some(build_time) = build_time {
+        println!("cargo:rustc-env=BUILD_TIME={build_time}");
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=BUILD_TIME");
+}
@@ -0,0 +1,98 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{http::StatusCode, post, web, Either, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+
+/// Registers the admin endpoints when `enabled`.
+pub fn configure(enabled: bool) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        if enabled {
+            cfg.service(gc);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AllocatorStats {
+    pub resident_bytes: u64,
+    pub allocated_bytes: u64,
+}
+
+/// Refreshes jemalloc's cached statistics and reads them back.
+#[cfg(feature = "tikv-jemallocator")]
+fn allocator_stats() -> Result<AllocatorStats, String> {
+    use tikv_jemalloc_ctl::{epoch, stats};
+
+    epoch::mib()
+        .and_then(|mib| mib.advance())
+        .map_err(|e| e.to_string())?;
+    Ok(AllocatorStats {
+        resident_bytes: stats::resident::read().map_err(|e| e.to_string())? as u64,
+        allocated_bytes: stats::allocated::read().map_err(|e| e.to_string())? as u64,
+    })
+}
+
+#[cfg(not(feature = "tikv-jemallocator"))]
+fn allocator_stats() -> Result<AllocatorStats, String> {
+    Err("built without the tikv-jemallocator feature".to_string())
+}
+
+/// Lets ops compare allocator-resident memory with RSS, to tell allocator
+/// fragmentation from a leak.
+#[post("/admin/gc")]
+pub async fn gc(correlation_id: CorrelationId) -> impl Responder {
+    match allocator_stats() {
+        Ok(stats) => Either::Left(HttpResponse::Ok().json(stats)),
+        Err(e) => Either::Right(ErrorResponse::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "allocator_stats_unavailable",
+            format!("Allocator stats unavailable: {e}"),
+            correlation_id,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, App};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_gc_disabled() {
+        let app = test::init_service(App::new().configure(configure(false))).await;
+        let req = test::TestRequest::post().uri("/admin/gc").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(feature = "tikv-jemallocator")]
+    #[actix_web::test]
+    async fn test_gc_reports_resident_bytes() {
+        let app = test::init_service(App::new().configure(configure(true))).await;
+        let req = test::TestRequest::post().uri("/admin/gc").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let stats: serde_json::Value = test::read_body_json(resp).await;
+        assert!(stats["resident_bytes"].as_u64().is_some_and(|b| b > 0));
+    }
+
+    #[cfg(not(feature = "tikv-jemallocator"))]
+    #[actix_web::test]
+    async fn test_gc_without_jemalloc() {
+        let app = test::init_service(App::new().configure(configure(true))).await;
+        let req = test::TestRequest::post().uri("/admin/gc").to_request();
+        let resp = test::call_service(&app, req).await;
+        crate::error_response::tests::assert_error(
+            resp,
+            StatusCode::NOT_IMPLEMENTED,
+            "allocator_stats_unavailable",
+        )
+        .await;
+    }
+}
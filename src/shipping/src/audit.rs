@@ -0,0 +1,111 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity};
+use opentelemetry::Key;
+use opentelemetry_sdk::logs::{SdkLogger, SdkLoggerProvider};
+use opentelemetry_semantic_conventions::attribute::LOG_RECORD_UID;
+use uuid::Uuid;
+
+pub const QUOTE_ISSUED: &str = "quote.issued";
+pub const ORDER_PLACED: &str = "order.placed";
+
+// Both are deprecated in the semantic conventions, but kept as attributes
+// for backends that don't read the record's event name.
+const EVENT_NAME: &str = "event.name";
+const EVENT_DOMAIN: &str = "event.domain";
+const SHIPPING_DOMAIN: &str = "shipping";
+
+/// Emits shipping events straight to OTel as log records, each with its own
+/// `log.record.uid`. Audit events are `Info`; `debug` ones are `Debug`, so
+/// they can be told apart and filtered by severity.
+pub struct AuditLogger {
+    logger: SdkLogger,
+}
+
+impl AuditLogger {
+    pub fn new(provider: &SdkLoggerProvider) -> Self {
+        AuditLogger {
+            logger: provider.logger("otel_demo.shipping.audit"),
+        }
+    }
+
+    /// Records a business event, such as `QUOTE_ISSUED`.
+    pub fn audit(&self, event: &'static str, attributes: Vec<(Key, AnyValue)>) {
+        self.emit(Severity::Info, event, attributes);
+    }
+
+    pub fn debug(&self, event: &'static str, attributes: Vec<(Key, AnyValue)>) {
+        self.emit(Severity::Debug, event, attributes);
+    }
+
+    fn emit(&self, severity: Severity, event: &'static str, attributes: Vec<(Key, AnyValue)>) {
+        let mut record = self.logger.create_log_record();
+        record.set_severity_number(severity);
+        record.set_severity_text(severity.name());
+        record.set_event_name(event);
+        record.set_body(AnyValue::from(event));
+        record.add_attribute(LOG_RECORD_UID, Uuid::new_v4().to_string());
+        record.add_attribute(EVENT_NAME, event);
+        record.add_attribute(EVENT_DOMAIN, SHIPPING_DOMAIN);
+        record.add_attributes(attributes);
+        self.logger.emit(record);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use opentelemetry_sdk::logs::{InMemoryLogExporter, SdkLogRecord, SimpleLogProcessor};
+
+    use super::*;
+
+    /// An `AuditLogger` whose records end up in the returned exporter.
+    pub fn test_audit_logger() -> (AuditLogger, InMemoryLogExporter) {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_log_processor(SimpleLogProcessor::new(exporter.clone()))
+            .build();
+        (AuditLogger::new(&provider), exporter)
+    }
+
+    pub fn attribute(record: &SdkLogRecord, key: &'static str) -> Option<AnyValue> {
+        record
+            .attributes_iter()
+            .find(|(k, _)| *k == Key::from_static_str(key))
+            .map(|(_, value)| value.clone())
+    }
+
+    #[test]
+    fn test_audit_and_debug_severity() {
+        let (logger, exporter) = test_audit_logger();
+        logger.audit(
+            ORDER_PLACED,
+            vec![(Key::new("order.tracking_id"), "L-123".into())],
+        );
+        logger.debug("quote.cache_hit", Vec::new());
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        let audit = &logs[0].record;
+        assert_eq!(audit.severity_number(), Some(Severity::Info));
+        assert_eq!(audit.event_name(), Some(ORDER_PLACED));
+        assert_eq!(
+            attribute(audit, EVENT_NAME),
+            Some(AnyValue::from(ORDER_PLACED))
+        );
+        assert_eq!(
+            attribute(audit, EVENT_DOMAIN),
+            Some(AnyValue::from("shipping"))
+        );
+        assert_eq!(
+            attribute(audit, "order.tracking_id"),
+            Some(AnyValue::from("L-123"))
+        );
+        assert_eq!(logs[1].record.severity_number(), Some(Severity::Debug));
+
+        let uids: Vec<_> = logs
+            .iter()
+            .map(|log| attribute(&log.record, LOG_RECORD_UID).unwrap())
+            .collect();
+        assert_ne!(uids[0], uids[1]);
+    }
+}
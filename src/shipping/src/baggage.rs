@@ -0,0 +1,199 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use opentelemetry::{
+    baggage::{Baggage, BaggageExt},
+    trace::get_active_span,
+    Context, KeyValue, Value,
+};
+
+mod semconv_keys;
+use semconv_keys::SEMCONV_KEYS;
+
+/// At most this many baggage entries are copied onto a span.
+const MAX_BAGGAGE_ATTRIBUTES: usize = 32;
+/// Longer baggage values are not copied onto spans.
+const MAX_BAGGAGE_VALUE_BYTES: usize = 256;
+
+static SEMCONV_KEY_SET: LazyLock<HashSet<&'static str>> =
+    LazyLock::new(|| SEMCONV_KEYS.into_iter().collect());
+
+/// Whether `key` is a semantic-convention attribute, or one of a template
+/// attribute such as `http.request.header.<key>`, so its meaning is fixed by
+/// the spec rather than by this service.
+pub fn is_semconv_key(key: &str) -> bool {
+    SEMCONV_KEY_SET.contains(key)
+        || key
+            .match_indices('.')
+            .any(|(end, _)| SEMCONV_KEY_SET.contains(&key[..end]))
+}
+
+/// The span attributes `baggage` contributes: every entry except those with
+/// a semantic-convention key, which baggage must never overwrite. Entries
+/// with long values are skipped, and only the first
+/// `MAX_BAGGAGE_ATTRIBUTES` by key are kept.
+pub fn baggage_attributes(baggage: &Baggage) -> Vec<KeyValue> {
+    let mut attributes: Vec<KeyValue> = baggage
+        .iter()
+        .filter(|(key, (value, _))| {
+            !is_semconv_key(key.as_str()) && value.as_str().len() <= MAX_BAGGAGE_VALUE_BYTES
+        })
+        .map(|(key, (value, _))| KeyValue::new(key.clone(), Value::String(value.clone())))
+        .collect();
+    attributes.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+    attributes.truncate(MAX_BAGGAGE_ATTRIBUTES);
+    attributes
+}
+
+/// Keeps only the last value of each repeated key. The SDK appends on
+/// `set_attribute`, so this is what makes later values replace earlier ones.
+pub fn dedup_attributes(attributes: &mut Vec<KeyValue>) {
+    let mut seen = HashSet::new();
+    let mut deduped: Vec<KeyValue> = attributes
+        .drain(..)
+        .rev()
+        .filter(|kv| seen.insert(kv.key.clone()))
+        .collect();
+    deduped.reverse();
+    *attributes = deduped;
+}
+
+/// Copies the request's W3C baggage onto its span once the handler has run,
+/// so baggage overwrites attributes the handler set, except
+/// semantic-convention ones. Must be registered inside `RequestTracing`.
+pub async fn copy_baggage(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let res = next.call(req).await?;
+    let attributes = baggage_attributes(Context::current().baggage());
+    if !attributes.is_empty() {
+        get_active_span(|span| span.set_attributes(attributes));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, web, App, HttpResponse};
+    use opentelemetry::{
+        global,
+        trace::{FutureExt, Span, TraceContextExt, Tracer},
+    };
+    use opentelemetry_semantic_conventions::attribute::SERVICE_NAME;
+
+    use super::*;
+    use crate::test_utils::global_span_exporter;
+
+    /// Runs a handler that sets `shipping.tenant=handler` and
+    /// `service.name=shipping` under `baggage`, returning the request span's
+    /// attributes with repeated keys resolved.
+    async fn request_attributes(baggage: Vec<KeyValue>) -> Vec<KeyValue> {
+        use actix_web::test;
+
+        let exporter = global_span_exporter();
+        let span = global::tracer("test").start("request");
+        let span_context = span.span_context().clone();
+        let cx = Context::current_with_span(span).with_baggage(baggage);
+
+        let app = test::init_service(App::new().wrap(from_fn(copy_baggage)).route(
+            "/",
+            web::get().to(|| async {
+                get_active_span(|span| {
+                    span.set_attribute(KeyValue::new("shipping.tenant", "handler"));
+                    span.set_attribute(KeyValue::new(SERVICE_NAME, "shipping"));
+                });
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        test::call_service(&app, test::TestRequest::get().to_request())
+            .with_context(cx.clone())
+            .await;
+        cx.span().end();
+
+        let span = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| span.span_context == span_context)
+            .unwrap();
+        let mut attributes = span.attributes;
+        dedup_attributes(&mut attributes);
+        attributes
+    }
+
+    fn value<'a>(attributes: &'a [KeyValue], key: &str) -> Option<&'a Value> {
+        attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| &kv.value)
+    }
+
+    #[actix_web::test]
+    async fn test_no_collision() {
+        let attributes = request_attributes(vec![KeyValue::new("tenant.tier", "gold")]).await;
+        assert_eq!(value(&attributes, "tenant.tier"), Some(&"gold".into()));
+        assert_eq!(
+            value(&attributes, "shipping.tenant"),
+            Some(&"handler".into())
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_baggage_overwrites_user_attribute() {
+        let attributes = request_attributes(vec![KeyValue::new("shipping.tenant", "acme")]).await;
+        assert_eq!(value(&attributes, "shipping.tenant"), Some(&"acme".into()));
+        assert_eq!(
+            attributes
+                .iter()
+                .filter(|kv| kv.key.as_str() == "shipping.tenant")
+                .count(),
+            1
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_baggage_never_overwrites_semconv_attribute() {
+        let attributes = request_attributes(vec![
+            KeyValue::new(SERVICE_NAME, "spoofed"),
+            KeyValue::new("http.request.method", "DELETE"),
+        ])
+        .await;
+        assert_eq!(value(&attributes, SERVICE_NAME), Some(&"shipping".into()));
+        assert_eq!(value(&attributes, "http.request.method"), None);
+    }
+
+    #[test]
+    fn test_is_semconv_key() {
+        assert!(is_semconv_key(SERVICE_NAME));
+        assert!(is_semconv_key("http.request.header.x-tenant"));
+        assert!(!is_semconv_key("http"));
+        assert!(!is_semconv_key("service.tier"));
+        assert!(!is_semconv_key("user.plan"));
+    }
+
+    #[test]
+    fn test_baggage_attributes_are_capped() {
+        let long = "x".repeat(MAX_BAGGAGE_VALUE_BYTES + 1);
+        let baggage = Baggage::from_iter(
+            (0..MAX_BAGGAGE_ATTRIBUTES + 5)
+                .map(|i| KeyValue::new(format!("entry.{i:02}"), "v"))
+                .chain([KeyValue::new("a.long", long)]),
+        );
+
+        let attributes = baggage_attributes(&baggage);
+        assert_eq!(attributes.len(), MAX_BAGGAGE_ATTRIBUTES);
+        assert_eq!(attributes[0].key.as_str(), "entry.00");
+        assert_eq!(value(&attributes, "a.long"), None);
+    }
+}
@@ -0,0 +1,739 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use opentelemetry_semantic_conventions::attribute::*;
+
+/// Every key in `opentelemetry_semantic_conventions::attribute`, deprecated
+/// ones included, as baggage must not overwrite those either.
+#[allow(deprecated)]
+pub(super) const SEMCONV_KEYS: [&str; 729] = [
+    ANDROID_APP_STATE,
+    ANDROID_OS_API_LEVEL,
+    ANDROID_STATE,
+    APP_INSTALLATION_ID,
+    APP_SCREEN_COORDINATE_X,
+    APP_SCREEN_COORDINATE_Y,
+    APP_WIDGET_ID,
+    APP_WIDGET_NAME,
+    ARTIFACT_ATTESTATION_FILENAME,
+    ARTIFACT_ATTESTATION_HASH,
+    ARTIFACT_ATTESTATION_ID,
+    ARTIFACT_FILENAME,
+    ARTIFACT_HASH,
+    ARTIFACT_PURL,
+    ARTIFACT_VERSION,
+    ASPNETCORE_DIAGNOSTICS_EXCEPTION_RESULT,
+    ASPNETCORE_DIAGNOSTICS_HANDLER_TYPE,
+    ASPNETCORE_RATE_LIMITING_POLICY,
+    ASPNETCORE_RATE_LIMITING_RESULT,
+    ASPNETCORE_REQUEST_IS_UNHANDLED,
+    ASPNETCORE_ROUTING_IS_FALLBACK,
+    ASPNETCORE_ROUTING_MATCH_STATUS,
+    AWS_DYNAMODB_ATTRIBUTE_DEFINITIONS,
+    AWS_DYNAMODB_ATTRIBUTES_TO_GET,
+    AWS_DYNAMODB_CONSISTENT_READ,
+    AWS_DYNAMODB_CONSUMED_CAPACITY,
+    AWS_DYNAMODB_COUNT,
+    AWS_DYNAMODB_EXCLUSIVE_START_TABLE,
+    AWS_DYNAMODB_GLOBAL_SECONDARY_INDEX_UPDATES,
+    AWS_DYNAMODB_GLOBAL_SECONDARY_INDEXES,
+    AWS_DYNAMODB_INDEX_NAME,
+    AWS_DYNAMODB_ITEM_COLLECTION_METRICS,
+    AWS_DYNAMODB_LIMIT,
+    AWS_DYNAMODB_LOCAL_SECONDARY_INDEXES,
+    AWS_DYNAMODB_PROJECTION,
+    AWS_DYNAMODB_PROVISIONED_READ_CAPACITY,
+    AWS_DYNAMODB_PROVISIONED_WRITE_CAPACITY,
+    AWS_DYNAMODB_SCAN_FORWARD,
+    AWS_DYNAMODB_SCANNED_COUNT,
+    AWS_DYNAMODB_SEGMENT,
+    AWS_DYNAMODB_SELECT,
+    AWS_DYNAMODB_TABLE_COUNT,
+    AWS_DYNAMODB_TABLE_NAMES,
+    AWS_DYNAMODB_TOTAL_SEGMENTS,
+    AWS_ECS_CLUSTER_ARN,
+    AWS_ECS_CONTAINER_ARN,
+    AWS_ECS_LAUNCHTYPE,
+    AWS_ECS_TASK_ARN,
+    AWS_ECS_TASK_FAMILY,
+    AWS_ECS_TASK_ID,
+    AWS_ECS_TASK_REVISION,
+    AWS_EKS_CLUSTER_ARN,
+    AWS_EXTENDED_REQUEST_ID,
+    AWS_LAMBDA_INVOKED_ARN,
+    AWS_LOG_GROUP_ARNS,
+    AWS_LOG_GROUP_NAMES,
+    AWS_LOG_STREAM_ARNS,
+    AWS_LOG_STREAM_NAMES,
+    AWS_REQUEST_ID,
+    AWS_S3_BUCKET,
+    AWS_S3_COPY_SOURCE,
+    AWS_S3_DELETE,
+    AWS_S3_KEY,
+    AWS_S3_PART_NUMBER,
+    AWS_S3_UPLOAD_ID,
+    AZ_NAMESPACE,
+    AZ_SERVICE_REQUEST_ID,
+    AZURE_CLIENT_ID,
+    AZURE_COSMOSDB_CONNECTION_MODE,
+    AZURE_COSMOSDB_CONSISTENCY_LEVEL,
+    AZURE_COSMOSDB_OPERATION_CONTACTED_REGIONS,
+    AZURE_COSMOSDB_OPERATION_REQUEST_CHARGE,
+    AZURE_COSMOSDB_REQUEST_BODY_SIZE,
+    AZURE_COSMOSDB_RESPONSE_SUB_STATUS_CODE,
+    BROWSER_BRANDS,
+    BROWSER_LANGUAGE,
+    BROWSER_MOBILE,
+    BROWSER_PLATFORM,
+    CASSANDRA_CONSISTENCY_LEVEL,
+    CASSANDRA_COORDINATOR_DC,
+    CASSANDRA_COORDINATOR_ID,
+    CASSANDRA_PAGE_SIZE,
+    CASSANDRA_QUERY_IDEMPOTENT,
+    CASSANDRA_SPECULATIVE_EXECUTION_COUNT,
+    CICD_PIPELINE_ACTION_NAME,
+    CICD_PIPELINE_NAME,
+    CICD_PIPELINE_RESULT,
+    CICD_PIPELINE_RUN_ID,
+    CICD_PIPELINE_RUN_STATE,
+    CICD_PIPELINE_RUN_URL_FULL,
+    CICD_PIPELINE_TASK_NAME,
+    CICD_PIPELINE_TASK_RUN_ID,
+    CICD_PIPELINE_TASK_RUN_RESULT,
+    CICD_PIPELINE_TASK_RUN_URL_FULL,
+    CICD_PIPELINE_TASK_TYPE,
+    CICD_SYSTEM_COMPONENT,
+    CICD_WORKER_ID,
+    CICD_WORKER_NAME,
+    CICD_WORKER_STATE,
+    CICD_WORKER_URL_FULL,
+    CLIENT_ADDRESS,
+    CLIENT_PORT,
+    CLOUD_ACCOUNT_ID,
+    CLOUD_AVAILABILITY_ZONE,
+    CLOUD_PLATFORM,
+    CLOUD_PROVIDER,
+    CLOUD_REGION,
+    CLOUD_RESOURCE_ID,
+    CLOUDEVENTS_EVENT_ID,
+    CLOUDEVENTS_EVENT_SOURCE,
+    CLOUDEVENTS_EVENT_SPEC_VERSION,
+    CLOUDEVENTS_EVENT_SUBJECT,
+    CLOUDEVENTS_EVENT_TYPE,
+    CLOUDFOUNDRY_APP_ID,
+    CLOUDFOUNDRY_APP_INSTANCE_ID,
+    CLOUDFOUNDRY_APP_NAME,
+    CLOUDFOUNDRY_ORG_ID,
+    CLOUDFOUNDRY_ORG_NAME,
+    CLOUDFOUNDRY_PROCESS_ID,
+    CLOUDFOUNDRY_PROCESS_TYPE,
+    CLOUDFOUNDRY_SPACE_ID,
+    CLOUDFOUNDRY_SPACE_NAME,
+    CLOUDFOUNDRY_SYSTEM_ID,
+    CLOUDFOUNDRY_SYSTEM_INSTANCE_ID,
+    CODE_COLUMN,
+    CODE_COLUMN_NUMBER,
+    CODE_FILE_PATH,
+    CODE_FILEPATH,
+    CODE_FUNCTION,
+    CODE_FUNCTION_NAME,
+    CODE_LINE_NUMBER,
+    CODE_LINENO,
+    CODE_NAMESPACE,
+    CODE_STACKTRACE,
+    CONTAINER_COMMAND,
+    CONTAINER_COMMAND_ARGS,
+    CONTAINER_COMMAND_LINE,
+    CONTAINER_CPU_STATE,
+    CONTAINER_CSI_PLUGIN_NAME,
+    CONTAINER_CSI_VOLUME_ID,
+    CONTAINER_ID,
+    CONTAINER_IMAGE_ID,
+    CONTAINER_IMAGE_NAME,
+    CONTAINER_IMAGE_REPO_DIGESTS,
+    CONTAINER_IMAGE_TAGS,
+    CONTAINER_LABEL,
+    CONTAINER_LABELS,
+    CONTAINER_NAME,
+    CONTAINER_RUNTIME,
+    CPU_LOGICAL_NUMBER,
+    CPU_MODE,
+    CPYTHON_GC_GENERATION,
+    DB_CASSANDRA_CONSISTENCY_LEVEL,
+    DB_CASSANDRA_COORDINATOR_DC,
+    DB_CASSANDRA_COORDINATOR_ID,
+    DB_CASSANDRA_IDEMPOTENCE,
+    DB_CASSANDRA_PAGE_SIZE,
+    DB_CASSANDRA_SPECULATIVE_EXECUTION_COUNT,
+    DB_CASSANDRA_TABLE,
+    DB_CLIENT_CONNECTION_POOL_NAME,
+    DB_CLIENT_CONNECTION_STATE,
+    DB_CLIENT_CONNECTIONS_POOL_NAME,
+    DB_CLIENT_CONNECTIONS_STATE,
+    DB_COLLECTION_NAME,
+    DB_CONNECTION_STRING,
+    DB_COSMOSDB_CLIENT_ID,
+    DB_COSMOSDB_CONNECTION_MODE,
+    DB_COSMOSDB_CONSISTENCY_LEVEL,
+    DB_COSMOSDB_CONTAINER,
+    DB_COSMOSDB_OPERATION_TYPE,
+    DB_COSMOSDB_REGIONS_CONTACTED,
+    DB_COSMOSDB_REQUEST_CHARGE,
+    DB_COSMOSDB_REQUEST_CONTENT_LENGTH,
+    DB_COSMOSDB_STATUS_CODE,
+    DB_COSMOSDB_SUB_STATUS_CODE,
+    DB_ELASTICSEARCH_CLUSTER_NAME,
+    DB_ELASTICSEARCH_NODE_NAME,
+    DB_ELASTICSEARCH_PATH_PARTS,
+    DB_INSTANCE_ID,
+    DB_JDBC_DRIVER_CLASSNAME,
+    DB_MONGODB_COLLECTION,
+    DB_MSSQL_INSTANCE_NAME,
+    DB_NAME,
+    DB_NAMESPACE,
+    DB_OPERATION,
+    DB_OPERATION_BATCH_SIZE,
+    DB_OPERATION_NAME,
+    DB_OPERATION_PARAMETER,
+    DB_QUERY_PARAMETER,
+    DB_QUERY_SUMMARY,
+    DB_QUERY_TEXT,
+    DB_REDIS_DATABASE_INDEX,
+    DB_RESPONSE_RETURNED_ROWS,
+    DB_RESPONSE_STATUS_CODE,
+    DB_SQL_TABLE,
+    DB_STATEMENT,
+    DB_STORED_PROCEDURE_NAME,
+    DB_SYSTEM,
+    DB_SYSTEM_NAME,
+    DB_USER,
+    DEPLOYMENT_ENVIRONMENT,
+    DEPLOYMENT_ENVIRONMENT_NAME,
+    DEPLOYMENT_ID,
+    DEPLOYMENT_NAME,
+    DEPLOYMENT_STATUS,
+    DESTINATION_ADDRESS,
+    DESTINATION_PORT,
+    DEVICE_ID,
+    DEVICE_MANUFACTURER,
+    DEVICE_MODEL_IDENTIFIER,
+    DEVICE_MODEL_NAME,
+    DISK_IO_DIRECTION,
+    DNS_QUESTION_NAME,
+    DOTNET_GC_HEAP_GENERATION,
+    ELASTICSEARCH_NODE_NAME,
+    ENDUSER_ID,
+    ENDUSER_PSEUDO_ID,
+    ENDUSER_ROLE,
+    ENDUSER_SCOPE,
+    ERROR_MESSAGE,
+    ERROR_TYPE,
+    EVENT_NAME,
+    EXCEPTION_ESCAPED,
+    EXCEPTION_MESSAGE,
+    EXCEPTION_STACKTRACE,
+    EXCEPTION_TYPE,
+    FAAS_COLDSTART,
+    FAAS_CRON,
+    FAAS_DOCUMENT_COLLECTION,
+    FAAS_DOCUMENT_NAME,
+    FAAS_DOCUMENT_OPERATION,
+    FAAS_DOCUMENT_TIME,
+    FAAS_INSTANCE,
+    FAAS_INVOCATION_ID,
+    FAAS_INVOKED_NAME,
+    FAAS_INVOKED_PROVIDER,
+    FAAS_INVOKED_REGION,
+    FAAS_MAX_MEMORY,
+    FAAS_NAME,
+    FAAS_TIME,
+    FAAS_TRIGGER,
+    FAAS_VERSION,
+    FEATURE_FLAG_CONTEXT_ID,
+    FEATURE_FLAG_EVALUATION_ERROR_MESSAGE,
+    FEATURE_FLAG_EVALUATION_REASON,
+    FEATURE_FLAG_KEY,
+    FEATURE_FLAG_PROVIDER_NAME,
+    FEATURE_FLAG_RESULT_REASON,
+    FEATURE_FLAG_RESULT_VARIANT,
+    FEATURE_FLAG_SET_ID,
+    FEATURE_FLAG_VARIANT,
+    FEATURE_FLAG_VERSION,
+    FILE_ACCESSED,
+    FILE_ATTRIBUTES,
+    FILE_CHANGED,
+    FILE_CREATED,
+    FILE_DIRECTORY,
+    FILE_EXTENSION,
+    FILE_FORK_NAME,
+    FILE_GROUP_ID,
+    FILE_GROUP_NAME,
+    FILE_INODE,
+    FILE_MODE,
+    FILE_MODIFIED,
+    FILE_NAME,
+    FILE_OWNER_ID,
+    FILE_OWNER_NAME,
+    FILE_PATH,
+    FILE_SIZE,
+    FILE_SYMBOLIC_LINK_TARGET_PATH,
+    GCP_APPHUB_APPLICATION_CONTAINER,
+    GCP_APPHUB_APPLICATION_ID,
+    GCP_APPHUB_APPLICATION_LOCATION,
+    GCP_APPHUB_SERVICE_CRITICALITY_TYPE,
+    GCP_APPHUB_SERVICE_ENVIRONMENT_TYPE,
+    GCP_APPHUB_SERVICE_ID,
+    GCP_APPHUB_WORKLOAD_CRITICALITY_TYPE,
+    GCP_APPHUB_WORKLOAD_ENVIRONMENT_TYPE,
+    GCP_APPHUB_WORKLOAD_ID,
+    GCP_CLIENT_SERVICE,
+    GCP_CLOUD_RUN_JOB_EXECUTION,
+    GCP_CLOUD_RUN_JOB_TASK_INDEX,
+    GCP_GCE_INSTANCE_HOSTNAME,
+    GCP_GCE_INSTANCE_NAME,
+    GEN_AI_AGENT_DESCRIPTION,
+    GEN_AI_AGENT_ID,
+    GEN_AI_AGENT_NAME,
+    GEN_AI_COMPLETION,
+    GEN_AI_OPENAI_REQUEST_RESPONSE_FORMAT,
+    GEN_AI_OPENAI_REQUEST_SEED,
+    GEN_AI_OPENAI_REQUEST_SERVICE_TIER,
+    GEN_AI_OPENAI_RESPONSE_SERVICE_TIER,
+    GEN_AI_OPENAI_RESPONSE_SYSTEM_FINGERPRINT,
+    GEN_AI_OPERATION_NAME,
+    GEN_AI_OUTPUT_TYPE,
+    GEN_AI_PROMPT,
+    GEN_AI_REQUEST_CHOICE_COUNT,
+    GEN_AI_REQUEST_ENCODING_FORMATS,
+    GEN_AI_REQUEST_FREQUENCY_PENALTY,
+    GEN_AI_REQUEST_MAX_TOKENS,
+    GEN_AI_REQUEST_MODEL,
+    GEN_AI_REQUEST_PRESENCE_PENALTY,
+    GEN_AI_REQUEST_SEED,
+    GEN_AI_REQUEST_STOP_SEQUENCES,
+    GEN_AI_REQUEST_TEMPERATURE,
+    GEN_AI_REQUEST_TOP_K,
+    GEN_AI_REQUEST_TOP_P,
+    GEN_AI_RESPONSE_FINISH_REASONS,
+    GEN_AI_RESPONSE_ID,
+    GEN_AI_RESPONSE_MODEL,
+    GEN_AI_SYSTEM,
+    GEN_AI_TOKEN_TYPE,
+    GEN_AI_TOOL_CALL_ID,
+    GEN_AI_TOOL_DESCRIPTION,
+    GEN_AI_TOOL_NAME,
+    GEN_AI_TOOL_TYPE,
+    GEN_AI_USAGE_COMPLETION_TOKENS,
+    GEN_AI_USAGE_INPUT_TOKENS,
+    GEN_AI_USAGE_OUTPUT_TOKENS,
+    GEN_AI_USAGE_PROMPT_TOKENS,
+    GEO_CONTINENT_CODE,
+    GEO_COUNTRY_ISO_CODE,
+    GEO_LOCALITY_NAME,
+    GEO_LOCATION_LAT,
+    GEO_LOCATION_LON,
+    GEO_POSTAL_CODE,
+    GEO_REGION_ISO_CODE,
+    GO_MEMORY_TYPE,
+    GRAPHQL_DOCUMENT,
+    GRAPHQL_OPERATION_NAME,
+    GRAPHQL_OPERATION_TYPE,
+    HEROKU_APP_ID,
+    HEROKU_RELEASE_COMMIT,
+    HEROKU_RELEASE_CREATION_TIMESTAMP,
+    HOST_ARCH,
+    HOST_CPU_CACHE_L2_SIZE,
+    HOST_CPU_FAMILY,
+    HOST_CPU_MODEL_ID,
+    HOST_CPU_MODEL_NAME,
+    HOST_CPU_STEPPING,
+    HOST_CPU_VENDOR_ID,
+    HOST_ID,
+    HOST_IMAGE_ID,
+    HOST_IMAGE_NAME,
+    HOST_IMAGE_VERSION,
+    HOST_IP,
+    HOST_MAC,
+    HOST_NAME,
+    HOST_TYPE,
+    HTTP_CLIENT_IP,
+    HTTP_CONNECTION_STATE,
+    HTTP_FLAVOR,
+    HTTP_HOST,
+    HTTP_METHOD,
+    HTTP_REQUEST_BODY_SIZE,
+    HTTP_REQUEST_HEADER,
+    HTTP_REQUEST_METHOD,
+    HTTP_REQUEST_METHOD_ORIGINAL,
+    HTTP_REQUEST_RESEND_COUNT,
+    HTTP_REQUEST_SIZE,
+    HTTP_REQUEST_CONTENT_LENGTH,
+    HTTP_REQUEST_CONTENT_LENGTH_UNCOMPRESSED,
+    HTTP_RESPONSE_BODY_SIZE,
+    HTTP_RESPONSE_HEADER,
+    HTTP_RESPONSE_SIZE,
+    HTTP_RESPONSE_STATUS_CODE,
+    HTTP_RESPONSE_CONTENT_LENGTH,
+    HTTP_RESPONSE_CONTENT_LENGTH_UNCOMPRESSED,
+    HTTP_ROUTE,
+    HTTP_SCHEME,
+    HTTP_SERVER_NAME,
+    HTTP_STATUS_CODE,
+    HTTP_TARGET,
+    HTTP_URL,
+    HTTP_USER_AGENT,
+    HW_ID,
+    HW_NAME,
+    HW_PARENT,
+    HW_STATE,
+    HW_TYPE,
+    IOS_APP_STATE,
+    IOS_STATE,
+    JVM_BUFFER_POOL_NAME,
+    JVM_GC_ACTION,
+    JVM_GC_CAUSE,
+    JVM_GC_NAME,
+    JVM_MEMORY_POOL_NAME,
+    JVM_MEMORY_TYPE,
+    JVM_THREAD_DAEMON,
+    JVM_THREAD_STATE,
+    K8S_CLUSTER_NAME,
+    K8S_CLUSTER_UID,
+    K8S_CONTAINER_NAME,
+    K8S_CONTAINER_RESTART_COUNT,
+    K8S_CONTAINER_STATUS_LAST_TERMINATED_REASON,
+    K8S_CRONJOB_ANNOTATION,
+    K8S_CRONJOB_LABEL,
+    K8S_CRONJOB_NAME,
+    K8S_CRONJOB_UID,
+    K8S_DAEMONSET_ANNOTATION,
+    K8S_DAEMONSET_LABEL,
+    K8S_DAEMONSET_NAME,
+    K8S_DAEMONSET_UID,
+    K8S_DEPLOYMENT_ANNOTATION,
+    K8S_DEPLOYMENT_LABEL,
+    K8S_DEPLOYMENT_NAME,
+    K8S_DEPLOYMENT_UID,
+    K8S_HPA_NAME,
+    K8S_HPA_UID,
+    K8S_JOB_ANNOTATION,
+    K8S_JOB_LABEL,
+    K8S_JOB_NAME,
+    K8S_JOB_UID,
+    K8S_NAMESPACE_ANNOTATION,
+    K8S_NAMESPACE_LABEL,
+    K8S_NAMESPACE_NAME,
+    K8S_NAMESPACE_PHASE,
+    K8S_NODE_ANNOTATION,
+    K8S_NODE_LABEL,
+    K8S_NODE_NAME,
+    K8S_NODE_UID,
+    K8S_POD_ANNOTATION,
+    K8S_POD_LABEL,
+    K8S_POD_LABELS,
+    K8S_POD_NAME,
+    K8S_POD_UID,
+    K8S_REPLICASET_ANNOTATION,
+    K8S_REPLICASET_LABEL,
+    K8S_REPLICASET_NAME,
+    K8S_REPLICASET_UID,
+    K8S_REPLICATIONCONTROLLER_NAME,
+    K8S_REPLICATIONCONTROLLER_UID,
+    K8S_RESOURCEQUOTA_NAME,
+    K8S_RESOURCEQUOTA_UID,
+    K8S_STATEFULSET_ANNOTATION,
+    K8S_STATEFULSET_LABEL,
+    K8S_STATEFULSET_NAME,
+    K8S_STATEFULSET_UID,
+    K8S_VOLUME_NAME,
+    K8S_VOLUME_TYPE,
+    LINUX_MEMORY_SLAB_STATE,
+    LOG_FILE_NAME,
+    LOG_FILE_NAME_RESOLVED,
+    LOG_FILE_PATH,
+    LOG_FILE_PATH_RESOLVED,
+    LOG_IOSTREAM,
+    LOG_RECORD_ORIGINAL,
+    LOG_RECORD_UID,
+    MESSAGE_COMPRESSED_SIZE,
+    MESSAGE_ID,
+    MESSAGE_TYPE,
+    MESSAGE_UNCOMPRESSED_SIZE,
+    MESSAGING_BATCH_MESSAGE_COUNT,
+    MESSAGING_CLIENT_ID,
+    MESSAGING_CONSUMER_GROUP_NAME,
+    MESSAGING_DESTINATION_ANONYMOUS,
+    MESSAGING_DESTINATION_NAME,
+    MESSAGING_DESTINATION_PARTITION_ID,
+    MESSAGING_DESTINATION_SUBSCRIPTION_NAME,
+    MESSAGING_DESTINATION_TEMPLATE,
+    MESSAGING_DESTINATION_TEMPORARY,
+    MESSAGING_DESTINATION_PUBLISH_ANONYMOUS,
+    MESSAGING_DESTINATION_PUBLISH_NAME,
+    MESSAGING_EVENTHUBS_CONSUMER_GROUP,
+    MESSAGING_EVENTHUBS_MESSAGE_ENQUEUED_TIME,
+    MESSAGING_GCP_PUBSUB_MESSAGE_ACK_DEADLINE,
+    MESSAGING_GCP_PUBSUB_MESSAGE_ACK_ID,
+    MESSAGING_GCP_PUBSUB_MESSAGE_DELIVERY_ATTEMPT,
+    MESSAGING_GCP_PUBSUB_MESSAGE_ORDERING_KEY,
+    MESSAGING_KAFKA_CONSUMER_GROUP,
+    MESSAGING_KAFKA_DESTINATION_PARTITION,
+    MESSAGING_KAFKA_MESSAGE_KEY,
+    MESSAGING_KAFKA_MESSAGE_OFFSET,
+    MESSAGING_KAFKA_MESSAGE_TOMBSTONE,
+    MESSAGING_KAFKA_OFFSET,
+    MESSAGING_MESSAGE_BODY_SIZE,
+    MESSAGING_MESSAGE_CONVERSATION_ID,
+    MESSAGING_MESSAGE_ENVELOPE_SIZE,
+    MESSAGING_MESSAGE_ID,
+    MESSAGING_OPERATION,
+    MESSAGING_OPERATION_NAME,
+    MESSAGING_OPERATION_TYPE,
+    MESSAGING_RABBITMQ_DESTINATION_ROUTING_KEY,
+    MESSAGING_RABBITMQ_MESSAGE_DELIVERY_TAG,
+    MESSAGING_ROCKETMQ_CLIENT_GROUP,
+    MESSAGING_ROCKETMQ_CONSUMPTION_MODEL,
+    MESSAGING_ROCKETMQ_MESSAGE_DELAY_TIME_LEVEL,
+    MESSAGING_ROCKETMQ_MESSAGE_DELIVERY_TIMESTAMP,
+    MESSAGING_ROCKETMQ_MESSAGE_GROUP,
+    MESSAGING_ROCKETMQ_MESSAGE_KEYS,
+    MESSAGING_ROCKETMQ_MESSAGE_TAG,
+    MESSAGING_ROCKETMQ_MESSAGE_TYPE,
+    MESSAGING_ROCKETMQ_NAMESPACE,
+    MESSAGING_SERVICEBUS_DESTINATION_SUBSCRIPTION_NAME,
+    MESSAGING_SERVICEBUS_DISPOSITION_STATUS,
+    MESSAGING_SERVICEBUS_MESSAGE_DELIVERY_COUNT,
+    MESSAGING_SERVICEBUS_MESSAGE_ENQUEUED_TIME,
+    MESSAGING_SYSTEM,
+    NET_HOST_IP,
+    NET_HOST_NAME,
+    NET_HOST_PORT,
+    NET_PEER_IP,
+    NET_PEER_NAME,
+    NET_PEER_PORT,
+    NET_PROTOCOL_NAME,
+    NET_PROTOCOL_VERSION,
+    NET_SOCK_FAMILY,
+    NET_SOCK_HOST_ADDR,
+    NET_SOCK_HOST_PORT,
+    NET_SOCK_PEER_ADDR,
+    NET_SOCK_PEER_NAME,
+    NET_SOCK_PEER_PORT,
+    NET_TRANSPORT,
+    NETWORK_CARRIER_ICC,
+    NETWORK_CARRIER_MCC,
+    NETWORK_CARRIER_MNC,
+    NETWORK_CARRIER_NAME,
+    NETWORK_CONNECTION_STATE,
+    NETWORK_CONNECTION_SUBTYPE,
+    NETWORK_CONNECTION_TYPE,
+    NETWORK_INTERFACE_NAME,
+    NETWORK_IO_DIRECTION,
+    NETWORK_LOCAL_ADDRESS,
+    NETWORK_LOCAL_PORT,
+    NETWORK_PEER_ADDRESS,
+    NETWORK_PEER_PORT,
+    NETWORK_PROTOCOL_NAME,
+    NETWORK_PROTOCOL_VERSION,
+    NETWORK_TRANSPORT,
+    NETWORK_TYPE,
+    NODEJS_EVENTLOOP_STATE,
+    OCI_MANIFEST_DIGEST,
+    OPENTRACING_REF_TYPE,
+    OS_BUILD_ID,
+    OS_DESCRIPTION,
+    OS_NAME,
+    OS_TYPE,
+    OS_VERSION,
+    OTEL_COMPONENT_NAME,
+    OTEL_COMPONENT_TYPE,
+    OTEL_LIBRARY_NAME,
+    OTEL_LIBRARY_VERSION,
+    OTEL_SCOPE_NAME,
+    OTEL_SCOPE_VERSION,
+    OTEL_SPAN_SAMPLING_RESULT,
+    OTEL_STATUS_CODE,
+    OTEL_STATUS_DESCRIPTION,
+    STATE,
+    PEER_SERVICE,
+    POOL_NAME,
+    PROCESS_ARGS_COUNT,
+    PROCESS_COMMAND,
+    PROCESS_COMMAND_ARGS,
+    PROCESS_COMMAND_LINE,
+    PROCESS_CONTEXT_SWITCH_TYPE,
+    PROCESS_CPU_STATE,
+    PROCESS_CREATION_TIME,
+    PROCESS_ENVIRONMENT_VARIABLE,
+    PROCESS_EXECUTABLE_BUILD_ID_GNU,
+    PROCESS_EXECUTABLE_BUILD_ID_GO,
+    PROCESS_EXECUTABLE_BUILD_ID_HTLHASH,
+    PROCESS_EXECUTABLE_BUILD_ID_PROFILING,
+    PROCESS_EXECUTABLE_NAME,
+    PROCESS_EXECUTABLE_PATH,
+    PROCESS_EXIT_CODE,
+    PROCESS_EXIT_TIME,
+    PROCESS_GROUP_LEADER_PID,
+    PROCESS_INTERACTIVE,
+    PROCESS_LINUX_CGROUP,
+    PROCESS_OWNER,
+    PROCESS_PAGING_FAULT_TYPE,
+    PROCESS_PARENT_PID,
+    PROCESS_PID,
+    PROCESS_REAL_USER_ID,
+    PROCESS_REAL_USER_NAME,
+    PROCESS_RUNTIME_DESCRIPTION,
+    PROCESS_RUNTIME_NAME,
+    PROCESS_RUNTIME_VERSION,
+    PROCESS_SAVED_USER_ID,
+    PROCESS_SAVED_USER_NAME,
+    PROCESS_SESSION_LEADER_PID,
+    PROCESS_TITLE,
+    PROCESS_USER_ID,
+    PROCESS_USER_NAME,
+    PROCESS_VPID,
+    PROCESS_WORKING_DIRECTORY,
+    PROFILE_FRAME_TYPE,
+    RPC_CONNECT_RPC_ERROR_CODE,
+    RPC_CONNECT_RPC_REQUEST_METADATA,
+    RPC_CONNECT_RPC_RESPONSE_METADATA,
+    RPC_GRPC_REQUEST_METADATA,
+    RPC_GRPC_RESPONSE_METADATA,
+    RPC_GRPC_STATUS_CODE,
+    RPC_JSONRPC_ERROR_CODE,
+    RPC_JSONRPC_ERROR_MESSAGE,
+    RPC_JSONRPC_REQUEST_ID,
+    RPC_JSONRPC_VERSION,
+    RPC_MESSAGE_COMPRESSED_SIZE,
+    RPC_MESSAGE_ID,
+    RPC_MESSAGE_TYPE,
+    RPC_MESSAGE_UNCOMPRESSED_SIZE,
+    RPC_METHOD,
+    RPC_SERVICE,
+    RPC_SYSTEM,
+    SECURITY_RULE_CATEGORY,
+    SECURITY_RULE_DESCRIPTION,
+    SECURITY_RULE_LICENSE,
+    SECURITY_RULE_NAME,
+    SECURITY_RULE_REFERENCE,
+    SECURITY_RULE_RULESET_NAME,
+    SECURITY_RULE_UUID,
+    SECURITY_RULE_VERSION,
+    SERVER_ADDRESS,
+    SERVER_PORT,
+    SERVICE_INSTANCE_ID,
+    SERVICE_NAME,
+    SERVICE_NAMESPACE,
+    SERVICE_VERSION,
+    SESSION_ID,
+    SESSION_PREVIOUS_ID,
+    SIGNALR_CONNECTION_STATUS,
+    SIGNALR_TRANSPORT,
+    SOURCE_ADDRESS,
+    SOURCE_PORT,
+    SYSTEM_CPU_LOGICAL_NUMBER,
+    SYSTEM_CPU_STATE,
+    SYSTEM_DEVICE,
+    SYSTEM_FILESYSTEM_MODE,
+    SYSTEM_FILESYSTEM_MOUNTPOINT,
+    SYSTEM_FILESYSTEM_STATE,
+    SYSTEM_FILESYSTEM_TYPE,
+    SYSTEM_MEMORY_STATE,
+    SYSTEM_NETWORK_STATE,
+    SYSTEM_PAGING_DIRECTION,
+    SYSTEM_PAGING_STATE,
+    SYSTEM_PAGING_TYPE,
+    SYSTEM_PROCESS_STATUS,
+    SYSTEM_PROCESSES_STATUS,
+    TELEMETRY_DISTRO_NAME,
+    TELEMETRY_DISTRO_VERSION,
+    TELEMETRY_SDK_LANGUAGE,
+    TELEMETRY_SDK_NAME,
+    TELEMETRY_SDK_VERSION,
+    TEST_CASE_NAME,
+    TEST_CASE_RESULT_STATUS,
+    TEST_SUITE_NAME,
+    TEST_SUITE_RUN_STATUS,
+    THREAD_ID,
+    THREAD_NAME,
+    TLS_CIPHER,
+    TLS_CLIENT_CERTIFICATE,
+    TLS_CLIENT_CERTIFICATE_CHAIN,
+    TLS_CLIENT_HASH_MD5,
+    TLS_CLIENT_HASH_SHA1,
+    TLS_CLIENT_HASH_SHA256,
+    TLS_CLIENT_ISSUER,
+    TLS_CLIENT_JA3,
+    TLS_CLIENT_NOT_AFTER,
+    TLS_CLIENT_NOT_BEFORE,
+    TLS_CLIENT_SERVER_NAME,
+    TLS_CLIENT_SUBJECT,
+    TLS_CLIENT_SUPPORTED_CIPHERS,
+    TLS_CURVE,
+    TLS_ESTABLISHED,
+    TLS_NEXT_PROTOCOL,
+    TLS_PROTOCOL_NAME,
+    TLS_PROTOCOL_VERSION,
+    TLS_RESUMED,
+    TLS_SERVER_CERTIFICATE,
+    TLS_SERVER_CERTIFICATE_CHAIN,
+    TLS_SERVER_HASH_MD5,
+    TLS_SERVER_HASH_SHA1,
+    TLS_SERVER_HASH_SHA256,
+    TLS_SERVER_ISSUER,
+    TLS_SERVER_JA3S,
+    TLS_SERVER_NOT_AFTER,
+    TLS_SERVER_NOT_BEFORE,
+    TLS_SERVER_SUBJECT,
+    URL_DOMAIN,
+    URL_EXTENSION,
+    URL_FRAGMENT,
+    URL_FULL,
+    URL_ORIGINAL,
+    URL_PATH,
+    URL_PORT,
+    URL_QUERY,
+    URL_REGISTERED_DOMAIN,
+    URL_SCHEME,
+    URL_SUBDOMAIN,
+    URL_TEMPLATE,
+    URL_TOP_LEVEL_DOMAIN,
+    USER_EMAIL,
+    USER_FULL_NAME,
+    USER_HASH,
+    USER_ID,
+    USER_NAME,
+    USER_ROLES,
+    USER_AGENT_NAME,
+    USER_AGENT_ORIGINAL,
+    USER_AGENT_OS_NAME,
+    USER_AGENT_OS_VERSION,
+    USER_AGENT_SYNTHETIC_TYPE,
+    USER_AGENT_VERSION,
+    V8JS_GC_TYPE,
+    V8JS_HEAP_SPACE_NAME,
+    VCS_CHANGE_ID,
+    VCS_CHANGE_STATE,
+    VCS_CHANGE_TITLE,
+    VCS_LINE_CHANGE_TYPE,
+    VCS_OWNER_NAME,
+    VCS_PROVIDER_NAME,
+    VCS_REF_BASE_NAME,
+    VCS_REF_BASE_REVISION,
+    VCS_REF_BASE_TYPE,
+    VCS_REF_HEAD_NAME,
+    VCS_REF_HEAD_REVISION,
+    VCS_REF_HEAD_TYPE,
+    VCS_REF_TYPE,
+    VCS_REPOSITORY_CHANGE_ID,
+    VCS_REPOSITORY_CHANGE_TITLE,
+    VCS_REPOSITORY_NAME,
+    VCS_REPOSITORY_REF_NAME,
+    VCS_REPOSITORY_REF_REVISION,
+    VCS_REPOSITORY_REF_TYPE,
+    VCS_REPOSITORY_URL_FULL,
+    VCS_REVISION_DELTA_DIRECTION,
+    WEBENGINE_DESCRIPTION,
+    WEBENGINE_NAME,
+    WEBENGINE_VERSION,
+];
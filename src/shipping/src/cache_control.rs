@@ -0,0 +1,181 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::env;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderValue, CACHE_CONTROL},
+    middleware::Next,
+    web, Error,
+};
+use anyhow::{Context, Result};
+use tracing::warn;
+
+const DEFAULT_RULES: [(&str, &str); 3] = [
+    // Prices change with every request
+    ("/get-quote", "no-store"),
+    ("/version", "max-age=60"),
+    ("/health/*", "no-cache"),
+];
+
+/// `Cache-Control` value per route. A route is an exact path, or a prefix
+/// ending in `/*`; exact paths win over prefixes, and longer prefixes over
+/// shorter ones.
+#[derive(Debug, Clone)]
+pub struct CacheControlRules {
+    rules: HashMap<String, HeaderValue>,
+}
+
+impl Default for CacheControlRules {
+    fn default() -> Self {
+        CacheControlRules {
+            rules: DEFAULT_RULES
+                .into_iter()
+                .map(|(route, value)| (route.to_string(), HeaderValue::from_static(value)))
+                .collect(),
+        }
+    }
+}
+
+impl CacheControlRules {
+    /// Parses a `{"route": "cache-control value"}` object, applied over the
+    /// defaults.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let overrides: HashMap<String, String> =
+            serde_json::from_str(json).context("invalid Cache-Control rules")?;
+        let mut rules = Self::default();
+        for (route, value) in overrides {
+            let value = HeaderValue::from_str(&value)
+                .with_context(|| format!("invalid Cache-Control value for {route}"))?;
+            rules.rules.insert(route, value);
+        }
+        Ok(rules)
+    }
+
+    /// Reads `CACHE_CONTROL_RULES`. An unset or invalid value keeps the
+    /// defaults.
+    pub fn from_env() -> Self {
+        let Ok(json) = env::var("CACHE_CONTROL_RULES") else {
+            return Self::default();
+        };
+        Self::from_json(&json).unwrap_or_else(|err| {
+            warn!(
+                name = "InvalidCacheControlRules",
+                error = format!("{err:#}"),
+                message = "Ignoring CACHE_CONTROL_RULES"
+            );
+            Self::default()
+        })
+    }
+
+    pub fn for_path(&self, path: &str) -> Option<&HeaderValue> {
+        self.rules.get(path).or_else(|| {
+            self.rules
+                .iter()
+                .filter_map(|(route, value)| Some((route.strip_suffix('*')?, value)))
+                .filter(|(prefix, _)| path.starts_with(prefix))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, value)| value)
+        })
+    }
+}
+
+/// Sets `Cache-Control` from the `CacheControlRules` in the app data, unless
+/// the handler already did.
+pub async fn cache_control(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let rules = req.app_data::<web::Data<CacheControlRules>>().cloned();
+    let path = req.path().to_string();
+    let mut res = next.call(req).await?;
+    if let Some(value) = rules.as_ref().and_then(|rules| rules.for_path(&path)) {
+        if !res.headers().contains_key(CACHE_CONTROL) {
+            res.headers_mut().insert(CACHE_CONTROL, value.clone());
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, test, App, HttpResponse};
+
+    use super::*;
+
+    async fn cache_control_for(rules: CacheControlRules, path: &str) -> Option<String> {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(rules))
+                .wrap(from_fn(cache_control))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri(path).to_request();
+        let resp = test::call_service(&app, req).await;
+        resp.headers()
+            .get(CACHE_CONTROL)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_default_rules() {
+        for (path, expected) in [
+            ("/get-quote", Some("no-store")),
+            ("/version", Some("max-age=60")),
+            ("/health/live", Some("no-cache")),
+            ("/config", None),
+        ] {
+            let value = cache_control_for(CacheControlRules::default(), path).await;
+            assert_eq!(value.as_deref(), expected, "{path}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rules_override_defaults() {
+        let rules = CacheControlRules::from_json(
+            r#"{"/version": "no-cache", "/health/live": "no-store", "/config": "private"}"#,
+        )
+        .unwrap();
+        for (path, expected) in [
+            ("/get-quote", "no-store"),
+            ("/version", "no-cache"),
+            ("/health/live", "no-store"),
+            ("/health/ready", "no-cache"),
+            ("/config", "private"),
+        ] {
+            let value = cache_control_for(rules.clone(), path).await;
+            assert_eq!(value.as_deref(), Some(expected), "{path}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_handler_header_is_kept() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(CacheControlRules::default()))
+                .wrap(from_fn(cache_control))
+                .route(
+                    "/version",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .insert_header((CACHE_CONTROL, "max-age=5"))
+                            .finish()
+                    }),
+                ),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(CACHE_CONTROL).unwrap(), "max-age=5");
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_rules() {
+        assert!(CacheControlRules::from_json("[]").is_err());
+        assert!(CacheControlRules::from_json(r#"{"/version": "bad\nvalue"}"#).is_err());
+    }
+}
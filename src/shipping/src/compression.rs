@@ -0,0 +1,167 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::CONTENT_ENCODING,
+    middleware::{Compress, Condition, Next},
+    web, Error,
+};
+use opentelemetry::{
+    metrics::{Counter, Meter},
+    KeyValue,
+};
+
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+/// Compresses responses with the best encoding in `Accept-Encoding`, e.g.
+/// gzip or brotli, when `enabled`.
+pub fn compression(enabled: bool) -> Condition<Compress> {
+    Condition::new(enabled, Compress::default())
+}
+
+/// `http_response_compressed_total`, by the `encoding` responses were sent
+/// with.
+pub struct CompressionCounter(Counter<u64>);
+
+impl CompressionCounter {
+    pub fn new(meter: &Meter) -> Self {
+        CompressionCounter(
+            meter
+                .u64_counter(checked_instrument_name("http_response_compressed_total"))
+                .with_description("Responses by the Content-Encoding they were sent with")
+                .build(),
+        )
+    }
+}
+
+/// Counts each response by its `Content-Encoding`, `identity` when it has
+/// none. Must be registered outside `compression`.
+pub async fn count_encodings(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let counter = req.app_data::<web::Data<CompressionCounter>>().cloned();
+    let res = next.call(req).await?;
+    if let Some(counter) = counter {
+        let encoding = res
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("identity")
+            .to_string();
+        counter.0.add(
+            1,
+            &KUBERNETES_LABELS.with(&[KeyValue::new("encoding", encoding)]),
+        );
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header::ACCEPT_ENCODING, middleware::from_fn, test, App, HttpResponse};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{
+        data::{AggregatedMetrics, MetricData},
+        InMemoryMetricExporter,
+    };
+
+    use super::*;
+    use crate::test_utils::test_meter_provider;
+
+    /// Like a quote for a large cart: several KB of repetitive JSON.
+    async fn large() -> HttpResponse {
+        let items: Vec<_> = (0..200)
+            .map(|i| serde_json::json!({ "product_id": format!("OLJCESPC7Z-{i}"), "quantity": 1 }))
+            .collect();
+        HttpResponse::Ok().json(serde_json::json!({ "items": items }))
+    }
+
+    fn encoding_count(exporter: &InMemoryMetricExporter, encoding: &str) -> u64 {
+        exporter
+            .get_finished_metrics()
+            .unwrap()
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .filter(|m| m.name() == "http_response_compressed_total")
+            .flat_map(|m| match m.data() {
+                AggregatedMetrics::U64(MetricData::Sum(sum)) => sum
+                    .data_points()
+                    .filter(|p| {
+                        p.attributes()
+                            .any(|kv| *kv == KeyValue::new("encoding", encoding.to_string()))
+                    })
+                    .map(|p| p.value())
+                    .collect::<Vec<_>>(),
+                _ => Vec::new(),
+            })
+            .sum()
+    }
+
+    /// Returns the `Content-Encoding` and size of the body.
+    async fn fetch(enabled: bool, accept_encoding: Option<&str>) -> (Option<String>, usize) {
+        let app = test::init_service(
+            App::new()
+                .wrap(compression(enabled))
+                .route("/", web::get().to(large)),
+        )
+        .await;
+        let mut req = test::TestRequest::get();
+        if let Some(accept_encoding) = accept_encoding {
+            req = req.insert_header((ACCEPT_ENCODING, accept_encoding));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        let encoding = resp
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map(|v| v.to_str().unwrap().to_string());
+        (encoding, test::read_body(resp).await.len())
+    }
+
+    #[actix_web::test]
+    async fn test_gzip_is_smaller_than_uncompressed() {
+        let (encoding, baseline) = fetch(true, None).await;
+        assert_eq!(encoding, None);
+
+        let (encoding, gzipped) = fetch(true, Some("gzip")).await;
+        assert_eq!(encoding.as_deref(), Some("gzip"));
+        assert!(gzipped < baseline, "{gzipped} >= {baseline}");
+
+        let (encoding, _) = fetch(true, Some("br")).await;
+        assert_eq!(encoding.as_deref(), Some("br"));
+    }
+
+    #[actix_web::test]
+    async fn test_disabled_sends_uncompressed() {
+        let (encoding, _) = fetch(false, Some("gzip")).await;
+        assert_eq!(encoding, None);
+    }
+
+    #[actix_web::test]
+    async fn test_counts_responses_by_encoding() {
+        let (provider, exporter) = test_meter_provider();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(CompressionCounter::new(
+                    &provider.meter("test"),
+                )))
+                .wrap(compression(true))
+                .wrap(from_fn(count_encodings))
+                .route("/", web::get().to(large)),
+        )
+        .await;
+        for accept_encoding in ["gzip", "gzip", "br", "identity"] {
+            let req = test::TestRequest::get()
+                .insert_header((ACCEPT_ENCODING, accept_encoding))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        provider.force_flush().unwrap();
+        assert_eq!(encoding_count(&exporter, "gzip"), 2);
+        assert_eq!(encoding_count(&exporter, "br"), 1);
+        assert_eq!(encoding_count(&exporter, "identity"), 1);
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt;
+use std::future::{ready, Ready};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use opentelemetry::{trace::get_active_span, KeyValue};
+use tracing::{Instrument, Metadata};
+use uuid::{Uuid, Version};
+
+pub const CORRELATION_ID_HEADER: HeaderName = HeaderName::from_static("x-correlation-id");
+const CORRELATION_SPAN: &str = "correlation";
+
+/// Per-request ID that is independent of the trace ID, so it survives hops
+/// that don't propagate trace context.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorrelationId(pub Uuid);
+
+impl CorrelationId {
+    /// Reuses `value` if it is a UUID v4, otherwise generates a new ID.
+    fn from_header(value: Option<&HeaderValue>) -> Self {
+        value
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| Uuid::parse_str(v).ok())
+            .filter(|id| id.get_version() == Some(Version::Random))
+            .map(CorrelationId)
+            .unwrap_or_else(|| CorrelationId(Uuid::new_v4()))
+    }
+
+    /// The ID set by `correlation_id`, or a fresh one when the middleware
+    /// isn't installed.
+    pub fn of(req: &HttpRequest) -> Self {
+        req.extensions()
+            .get::<CorrelationId>()
+            .copied()
+            .unwrap_or_else(|| CorrelationId(Uuid::new_v4()))
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Handlers take `CorrelationId::of` the request.
+impl FromRequest for CorrelationId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(CorrelationId::of(req)))
+    }
+}
+
+/// Whether `metadata` is the `tracing` span `correlation_id` opens. It only
+/// carries the ID to log events, so it is kept out of the exported traces.
+pub fn is_correlation_span(metadata: &Metadata<'_>) -> bool {
+    metadata.is_span() && metadata.name() == CORRELATION_SPAN && metadata.target() == module_path!()
+}
+
+/// Must be registered inside `RequestTracing` so the active span is the
+/// request span. The rest of the request runs in a `tracing` span with
+/// `correlation.id`, so log events carry it.
+pub async fn correlation_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = CorrelationId::from_header(req.headers().get(CORRELATION_ID_HEADER));
+
+    get_active_span(|span| span.set_attribute(KeyValue::new("correlation.id", id.to_string())));
+    req.extensions_mut().insert(id);
+
+    let span = tracing::info_span!(CORRELATION_SPAN, correlation.id = %id);
+    let mut res = next.call(req).instrument(span).await?;
+    res.headers_mut().insert(
+        CORRELATION_ID_HEADER,
+        HeaderValue::from_str(&id.to_string()).expect("UUIDs are valid header values"),
+    );
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{middleware::from_fn, test, web, App, HttpResponse};
+
+    use super::*;
+
+    async fn echo(id: CorrelationId) -> HttpResponse {
+        HttpResponse::Ok().body(id.to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_generates_and_echoes_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(correlation_id))
+                .route("/", web::get().to(echo)),
+        )
+        .await;
+        let resp = test::call_service(&app, test::TestRequest::get().to_request()).await;
+
+        let header = resp.headers().get(CORRELATION_ID_HEADER).unwrap().clone();
+        let id = Uuid::parse_str(header.to_str().unwrap()).unwrap();
+        assert_eq!(id.get_version(), Some(Version::Random));
+
+        let body = test::read_body(resp).await;
+        assert_eq!(body, header.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn test_passes_through_valid_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(correlation_id))
+                .route("/", web::get().to(echo)),
+        )
+        .await;
+        let sent = Uuid::new_v4().to_string();
+        let req = test::TestRequest::get()
+            .insert_header((CORRELATION_ID_HEADER, sent.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(
+            resp.headers().get(CORRELATION_ID_HEADER).unwrap(),
+            sent.as_str()
+        );
+        assert_eq!(test::read_body(resp).await, sent.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn test_log_events_carry_the_id() {
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl io::Write for Output {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        async fn log(id: CorrelationId) -> HttpResponse {
+            assert!(tracing::Span::current()
+                .metadata()
+                .is_some_and(is_correlation_span));
+            tracing::info!(message = "handled");
+            HttpResponse::Ok().body(id.to_string())
+        }
+
+        let output = Output::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let output = output.clone();
+                move || output.clone()
+            })
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(correlation_id))
+                .route("/", web::get().to(log)),
+        )
+        .await;
+        let resp = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        let id = resp.headers().get(CORRELATION_ID_HEADER).unwrap().clone();
+
+        let logged = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let line = logged
+            .lines()
+            .find(|line| line.contains("handled"))
+            .unwrap();
+        assert!(
+            line.contains(&format!("correlation.id={}", id.to_str().unwrap())),
+            "{line}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_replaces_invalid_id() {
+        let nil = HeaderValue::from_static("00000000-0000-0000-0000-000000000000");
+        assert_ne!(CorrelationId::from_header(Some(&nil)).0, Uuid::nil());
+
+        let garbage = HeaderValue::from_static("not-a-uuid");
+        let id = CorrelationId::from_header(Some(&garbage));
+        assert_eq!(id.0.get_version(), Some(Version::Random));
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+
+use actix_cors::Cors;
+use actix_web::http::{
+    header::{HeaderName, CONTENT_TYPE},
+    Method, Uri,
+};
+use tracing::warn;
+
+use crate::correlation_id::CORRELATION_ID_HEADER;
+use crate::request_duration::REQUEST_DURATION_HEADER;
+use crate::shipping_service::REQUEST_HEADERS;
+
+pub const DEFAULT_CORS_MAX_AGE_SECS: usize = 3600;
+
+/// Which browser origins may call the API. No origin is allowed unless
+/// `CORS_ALLOWED_ORIGINS` lists it, or is `*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsConfig {
+    /// `None` allows any origin.
+    allowed_origins: Option<Vec<String>>,
+    allowed_methods: Vec<Method>,
+    max_age_secs: usize,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: Some(Vec::new()),
+            allowed_methods: vec![Method::GET, Method::POST],
+            max_age_secs: DEFAULT_CORS_MAX_AGE_SECS,
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Reads the comma-separated `CORS_ALLOWED_ORIGINS` and
+    /// `CORS_ALLOWED_METHODS` (default `GET,POST`), and `CORS_MAX_AGE_SECS`.
+    /// Invalid entries are skipped with a warning.
+    pub fn from_env() -> Self {
+        let mut config = CorsConfig::default();
+        if let Ok(origins) = env::var("CORS_ALLOWED_ORIGINS") {
+            config.allowed_origins = parse_origins(&origins);
+        }
+        if let Ok(methods) = env::var("CORS_ALLOWED_METHODS") {
+            config.allowed_methods = parse_list(&methods, "CORS_ALLOWED_METHODS", |method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes()).ok()
+            });
+        }
+        if let Ok(max_age) = env::var("CORS_MAX_AGE_SECS") {
+            match max_age.trim().parse() {
+                Ok(secs) => config.max_age_secs = secs,
+                Err(_) => warn!(
+                    name = "InvalidCorsConfig",
+                    variable = "CORS_MAX_AGE_SECS",
+                    value = max_age.as_str(),
+                    message = "Using the default CORS max age"
+                ),
+            }
+        }
+        config
+    }
+
+    /// A new middleware, as `Cors` can't be shared between workers.
+    pub fn cors(&self) -> Cors {
+        let mut cors = Cors::default()
+            .allowed_methods(self.allowed_methods.clone())
+            .allowed_headers(
+                [CONTENT_TYPE, CORRELATION_ID_HEADER]
+                    .into_iter()
+                    .chain(REQUEST_HEADERS.map(HeaderName::from_static))
+                    .chain(["traceparent", "tracestate", "baggage"].map(HeaderName::from_static)),
+            )
+            .expose_headers([CORRELATION_ID_HEADER, REQUEST_DURATION_HEADER])
+            .max_age(self.max_age_secs);
+        match &self.allowed_origins {
+            Some(origins) => {
+                for origin in origins {
+                    cors = cors.allowed_origin(origin);
+                }
+            }
+            None => cors = cors.allow_any_origin(),
+        }
+        cors
+    }
+}
+
+fn parse_origins(origins: &str) -> Option<Vec<String>> {
+    if origins.split(',').any(|origin| origin.trim() == "*") {
+        return None;
+    }
+    Some(parse_list(origins, "CORS_ALLOWED_ORIGINS", |origin| {
+        // An origin is a scheme and host, with an optional port
+        let uri = origin.parse::<Uri>().ok()?;
+        let is_origin = uri.scheme().is_some()
+            && uri.host().is_some()
+            && uri.path_and_query().is_none_or(|path| path == "/");
+        is_origin.then(|| origin.trim_end_matches('/').to_string())
+    }))
+}
+
+fn parse_list<T>(list: &str, variable: &str, parse: impl Fn(&str) -> Option<T>) -> Vec<T> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .filter_map(|item| {
+            let parsed = parse(item);
+            if parsed.is_none() {
+                warn!(
+                    name = "InvalidCorsConfig",
+                    variable,
+                    value = item,
+                    message = "Skipping invalid CORS entry"
+                );
+            }
+            parsed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::{
+            header::{
+                ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+                ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+                ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+            },
+            StatusCode,
+        },
+        web, App, HttpResponse,
+    };
+
+    use super::*;
+
+    fn allowing(origins: &str) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: parse_origins(origins),
+            ..Default::default()
+        }
+    }
+
+    async fn preflight(config: CorsConfig, origin: &str, method: &str) -> Preflight {
+        preflight_with_headers(config, origin, method, None).await
+    }
+
+    async fn preflight_with_headers(
+        config: CorsConfig,
+        origin: &str,
+        method: &str,
+        request_headers: Option<&str>,
+    ) -> Preflight {
+        use actix_web::test;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(config.cors())
+                .route("/get-quote", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+        let mut req = test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/get-quote")
+            .insert_header((ORIGIN, origin))
+            .insert_header((ACCESS_CONTROL_REQUEST_METHOD, method));
+        if let Some(headers) = request_headers {
+            req = req.insert_header((ACCESS_CONTROL_REQUEST_HEADERS, headers));
+        }
+        let req = req.to_request();
+        let resp = test::call_service(&app, req).await;
+        let header = |name: HeaderName| {
+            resp.headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+        Preflight {
+            status: resp.status(),
+            allow_origin: header(ACCESS_CONTROL_ALLOW_ORIGIN),
+            allow_methods: header(ACCESS_CONTROL_ALLOW_METHODS),
+            allow_headers: header(ACCESS_CONTROL_ALLOW_HEADERS),
+            max_age: header(ACCESS_CONTROL_MAX_AGE),
+        }
+    }
+
+    struct Preflight {
+        status: StatusCode,
+        allow_origin: Option<String>,
+        allow_methods: Option<String>,
+        allow_headers: Option<String>,
+        max_age: Option<String>,
+    }
+
+    #[actix_web::test]
+    async fn test_preflight_from_allowed_origin() {
+        let config = allowing("http://localhost:3000, https://shop.example.com");
+        let resp = preflight(config, "https://shop.example.com", "POST").await;
+        assert_eq!(resp.status, StatusCode::OK);
+        assert_eq!(
+            resp.allow_origin.as_deref(),
+            Some("https://shop.example.com")
+        );
+        let methods = resp.allow_methods.unwrap();
+        assert!(methods.contains("POST"), "{methods}");
+        assert_eq!(resp.max_age.as_deref(), Some("3600"));
+    }
+
+    #[actix_web::test]
+    async fn test_preflight_for_handler_headers() {
+        let resp = preflight_with_headers(
+            allowing("http://localhost:3000"),
+            "http://localhost:3000",
+            "POST",
+            Some("x-api-key, x-canary, x-idempotency-key, content-type"),
+        )
+        .await;
+        assert_eq!(resp.status, StatusCode::OK);
+        let headers = resp.allow_headers.unwrap();
+        for header in ["x-api-key", "x-canary", "x-idempotency-key"] {
+            assert!(headers.contains(header), "{header} not in {headers}");
+        }
+
+        let resp = preflight_with_headers(
+            allowing("http://localhost:3000"),
+            "http://localhost:3000",
+            "POST",
+            Some("x-unknown"),
+        )
+        .await;
+        assert!(!resp.status.is_success(), "{}", resp.status);
+    }
+
+    #[actix_web::test]
+    async fn test_preflight_from_denied_origin() {
+        let config = allowing("http://localhost:3000");
+        let resp = preflight(config, "https://evil.example.com", "POST").await;
+        assert!(!resp.status.is_success(), "{}", resp.status);
+        assert_eq!(resp.allow_origin, None);
+    }
+
+    #[actix_web::test]
+    async fn test_all_origins_denied_by_default() {
+        let resp = preflight(CorsConfig::default(), "http://localhost:3000", "GET").await;
+        assert!(!resp.status.is_success(), "{}", resp.status);
+        assert_eq!(resp.allow_origin, None);
+    }
+
+    #[actix_web::test]
+    async fn test_preflight_for_disallowed_method() {
+        let config = allowing("http://localhost:3000");
+        let resp = preflight(config, "http://localhost:3000", "DELETE").await;
+        assert!(!resp.status.is_success(), "{}", resp.status);
+    }
+
+    #[actix_web::test]
+    async fn test_wildcard_allows_any_origin() {
+        let resp = preflight(allowing("*"), "https://anywhere.example.com", "GET").await;
+        assert_eq!(resp.status, StatusCode::OK);
+        assert_eq!(
+            resp.allow_origin.as_deref(),
+            Some("https://anywhere.example.com")
+        );
+    }
+
+    #[test]
+    fn test_invalid_origins_are_skipped() {
+        assert_eq!(
+            parse_origins("http://localhost:3000/, localhost, https://a.example.com/path, ,"),
+            Some(vec!["http://localhost:3000".to_string()])
+        );
+    }
+}
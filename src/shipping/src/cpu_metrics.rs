@@ -0,0 +1,1581 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use opentelemetry::{
+    global,
+    metrics::Counter,
+    trace::{TraceContextExt, Tracer},
+    KeyValue,
+};
+use serde::{Serialize, Serializer};
+use tracing::{debug, error, warn};
+
+use crate::metrics::{checked_instrument_name, sort_attributes, KUBERNETES_LABELS};
+use crate::runtime_metrics::WorkerRuntimes;
+use crate::span_processors::panic_message;
+use crate::utils::spawn_with_context;
+
+pub const METER_NAME: &str = "otel_demo.shipping.cpu";
+pub const DEFAULT_COLLECTION_INTERVAL: Duration = Duration::from_secs(5);
+const CPU_SYSFS_ROOT: &str = "/sys/devices/system/cpu";
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const PROC_SELF_STATUS: &str = "/proc/self/status";
+/// Clock ticks per second of the `/proc/<pid>/stat` times, fixed by the
+/// kernel ABI.
+const USER_HZ: f64 = 100_f64;
+const DEFAULT_MEMORY_LEAK_WINDOW_SAMPLES: usize = 60;
+const DEFAULT_MEMORY_LEAK_GROWTH_THRESHOLD_BYTES_PER_SEC: f64 = 10_240_f64;
+pub const DEFAULT_EMA_ALPHA: f64 = 0.3;
+const DEFAULT_ANOMALY_WINDOW_SAMPLES: usize = 10;
+const DEFAULT_ANOMALY_K: f64 = 3_f64;
+/// Floor for the anomaly window's standard deviation, in cores, so a flat
+/// window doesn't turn the first small change into an outlier.
+const ANOMALY_MIN_STD_DEV: f64 = 0.01;
+/// Share of the soft open-files limit past which a warning is logged,
+/// unless `FD_WARN_THRESHOLD` sets a count.
+const FD_WARN_LIMIT_RATIO: f64 = 0.8;
+/// Reads of the cgroup CPU stats slower than this get a `cgroup.slow_read`
+/// span event.
+const SLOW_CGROUP_READ: Duration = Duration::from_millis(100);
+
+/// How the cgroup filesystem is mounted, which decides where the CPU
+/// controllers are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgroupMode {
+    /// Only the legacy per-controller hierarchies.
+    V1,
+    /// A single v2 hierarchy holding every controller.
+    V2Unified,
+    /// v1 controllers, with a v2 hierarchy mounted under `unified`.
+    V2Hybrid,
+    Unknown,
+}
+
+impl CgroupMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CgroupMode::V1 => "v1",
+            CgroupMode::V2Unified => "v2",
+            CgroupMode::V2Hybrid => "hybrid",
+            CgroupMode::Unknown => "none",
+        }
+    }
+}
+
+pub struct CgroupHierarchyDetector;
+
+impl CgroupHierarchyDetector {
+    /// Detects the mode of the cgroup filesystem at `CGROUP_ROOT`.
+    pub fn detect() -> CgroupMode {
+        Self::detect_at(Path::new(CGROUP_ROOT))
+    }
+
+    /// `V2Unified` if `root` has a `cgroup.controllers` file, `V2Hybrid` if
+    /// only its `unified` directory has one, `V1` if it has the legacy CPU
+    /// directories.
+    pub fn detect_at(root: &Path) -> CgroupMode {
+        if root.join("cgroup.controllers").exists() {
+            CgroupMode::V2Unified
+        } else if root.join("unified/cgroup.controllers").exists() {
+            CgroupMode::V2Hybrid
+        } else if root.join("cpu").exists() || root.join("cpuacct").exists() {
+            CgroupMode::V1
+        } else {
+            CgroupMode::Unknown
+        }
+    }
+}
+
+/// Current frequency of every logical CPU, as `(cpu index, Hz)` pairs.
+///
+/// Returns `None` when cpufreq isn't exposed, which is the usual case inside
+/// containers and on most VMs.
+pub fn read_cpu_frequencies(root: &Path) -> Option<Vec<(u32, u64)>> {
+    let mut frequencies: Vec<(u32, u64)> = fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let cpu = name.to_str()?.strip_prefix("cpu")?.parse::<u32>().ok()?;
+            let khz = fs::read_to_string(entry.path().join("cpufreq/scaling_cur_freq")).ok()?;
+            Some((cpu, khz.trim().parse::<u64>().ok()? * 1000))
+        })
+        .collect();
+
+    if frequencies.is_empty() {
+        return None;
+    }
+    frequencies.sort_unstable();
+    Some(frequencies)
+}
+
+/// Resident set size of the process in bytes, from the `VmRSS` line of a
+/// `/proc/<pid>/status` file.
+pub fn read_process_memory_bytes(status: &Path) -> Option<u64> {
+    let status = fs::read_to_string(status).ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// Number of open file descriptors, as entries of a `/proc/<pid>/fd`
+/// directory.
+pub fn read_open_fd_count(fd_dir: &Path) -> Option<u64> {
+    Some(fs::read_dir(fd_dir).ok()?.count() as u64)
+}
+
+/// Soft `Max open files` limit from a `/proc/<pid>/limits` file, `None` if
+/// unlimited.
+pub fn read_fd_soft_limit(limits: &Path) -> Option<u64> {
+    let limits = fs::read_to_string(limits).ok()?;
+    limits
+        .lines()
+        .find_map(|line| line.strip_prefix("Max open files"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// `FD_WARN_THRESHOLD`, or 80% of the soft limit in `limits`.
+fn fd_warn_threshold(limits: &Path) -> Option<u64> {
+    env::var("FD_WARN_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .or_else(|| {
+            read_fd_soft_limit(limits).map(|limit| (limit as f64 * FD_WARN_LIMIT_RATIO) as u64)
+        })
+}
+
+/// Least-squares slope of `samples`, given as `(seconds, value)` pairs.
+///
+/// Returns `None` with fewer than two samples or when they all share the
+/// same timestamp.
+pub fn linear_regression_slope(samples: &VecDeque<(f64, f64)>) -> Option<f64> {
+    let n = samples.len() as f64;
+    if samples.len() < 2 {
+        return None;
+    }
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (covariance, variance) =
+        samples
+            .iter()
+            .fold((0_f64, 0_f64), |(covariance, variance), (x, y)| {
+                let dx = x - mean_x;
+                (covariance + dx * (y - mean_y), variance + dx * dx)
+            });
+    if variance == 0_f64 {
+        return None;
+    }
+    Some(covariance / variance)
+}
+
+/// Keeps the last `window` memory samples and flags sustained growth.
+#[derive(Debug)]
+pub struct MemoryLeakDetector {
+    samples: VecDeque<(f64, f64)>,
+    window: usize,
+    threshold_bytes_per_sec: f64,
+}
+
+impl MemoryLeakDetector {
+    pub fn new(window: usize, threshold_bytes_per_sec: f64) -> Self {
+        MemoryLeakDetector {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(2),
+            threshold_bytes_per_sec,
+        }
+    }
+
+    /// Reads `MEMORY_LEAK_WINDOW_SAMPLES` and
+    /// `MEMORY_LEAK_GROWTH_THRESHOLD_BYTES_PER_SEC`.
+    pub fn from_env() -> Self {
+        let window = env::var("MEMORY_LEAK_WINDOW_SAMPLES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MEMORY_LEAK_WINDOW_SAMPLES);
+        let threshold = env::var("MEMORY_LEAK_GROWTH_THRESHOLD_BYTES_PER_SEC")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MEMORY_LEAK_GROWTH_THRESHOLD_BYTES_PER_SEC);
+        Self::new(window, threshold)
+    }
+
+    /// Adds a sample and, once the window is full, returns the growth rate in
+    /// bytes per second if it exceeds the threshold.
+    pub fn record(&mut self, seconds: f64, bytes: u64) -> Option<f64> {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((seconds, bytes as f64));
+        if self.samples.len() < self.window {
+            return None;
+        }
+        linear_regression_slope(&self.samples).filter(|slope| *slope > self.threshold_bytes_per_sec)
+    }
+}
+
+/// Exponential moving average: each sample moves the value `alpha` of the
+/// way towards it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// `alpha` must be in `(0, 1]`; anything else falls back to
+    /// `DEFAULT_EMA_ALPHA`.
+    pub fn new(alpha: f64) -> Self {
+        let alpha = if alpha > 0.0 && alpha <= 1.0 {
+            alpha
+        } else {
+            DEFAULT_EMA_ALPHA
+        };
+        Ema { alpha, value: None }
+    }
+
+    /// Reads `METRICS_EMA_ALPHA`.
+    pub fn from_env() -> Self {
+        let alpha = env::var("METRICS_EMA_ALPHA")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_EMA_ALPHA);
+        Self::new(alpha)
+    }
+
+    /// Adds a sample and returns the new average. The first sample is
+    /// taken as is.
+    pub fn update(&mut self, sample: f64) -> f64 {
+        let value = match self.value {
+            Some(value) => value + self.alpha * (sample - value),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+}
+
+/// Replaces samples more than `k` standard deviations (at least
+/// `ANOMALY_MIN_STD_DEV`) from the mean of the last `window` samples with
+/// the previous valid one, to drop one-tick measurement artifacts.
+///
+/// The window keeps the raw samples, replaced ones included, so a lasting
+/// change in level is let through once the window has seen enough of it.
+#[derive(Debug)]
+pub struct KepsilonFilter {
+    samples: VecDeque<f64>,
+    window: usize,
+    k: f64,
+    last_valid: Option<f64>,
+}
+
+impl KepsilonFilter {
+    pub fn new(window: usize, k: f64) -> Self {
+        KepsilonFilter {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(2),
+            k,
+            last_valid: None,
+        }
+    }
+
+    /// Reads `ANOMALY_FILTER_WINDOW_SAMPLES` and `ANOMALY_FILTER_K`.
+    pub fn from_env() -> Self {
+        let window = env::var("ANOMALY_FILTER_WINDOW_SAMPLES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_ANOMALY_WINDOW_SAMPLES);
+        let k = env::var("ANOMALY_FILTER_K")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|k: &f64| *k > 0_f64)
+            .unwrap_or(DEFAULT_ANOMALY_K);
+        Self::new(window, k)
+    }
+
+    /// Returns the sample, or the previous valid sample and `true` if it is
+    /// an outlier. Nothing is replaced until the window is full.
+    pub fn filter(&mut self, sample: f64) -> (f64, bool) {
+        let outlier = self.samples.len() == self.window && {
+            let n = self.samples.len() as f64;
+            let mean = self.samples.iter().sum::<f64>() / n;
+            let variance = self.samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+            (sample - mean).abs() > self.k * variance.sqrt().max(ANOMALY_MIN_STD_DEV)
+        };
+
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+
+        match self.last_valid {
+            Some(previous) if outlier => (previous, true),
+            _ => {
+                self.last_valid = Some(sample);
+                (sample, false)
+            }
+        }
+    }
+}
+
+fn check_memory_growth(
+    detector: &mut MemoryLeakDetector,
+    alerts: &Counter<u64>,
+    seconds: f64,
+    bytes: u64,
+) {
+    if let Some(slope) = detector.record(seconds, bytes) {
+        warn!(
+            name = "MemoryGrowthDetected",
+            growth_bytes_per_sec = slope,
+            process_memory_bytes = bytes,
+            window_samples = detector.window,
+            message = "Process memory is growing steadily, possible leak"
+        );
+        alerts.add(1, &[]);
+    }
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Swap used by the cgroup under `root`: `memory.swap.current` on v2, or
+/// `memory.memsw.usage_in_bytes` less `memory.usage_in_bytes` on v1, which
+/// isn't probed in `V2Unified` mode. `None` when swap isn't accounted, e.g.
+/// with `swapaccount=0`.
+pub fn read_cgroup_swap_bytes(root: &Path, mode: CgroupMode) -> Option<u64> {
+    read_u64(&root.join("memory.swap.current")).or_else(|| {
+        if mode == CgroupMode::V2Unified {
+            return None;
+        }
+        let memory = root.join("memory");
+        let memory_and_swap = read_u64(&memory.join("memory.memsw.usage_in_bytes"))?;
+        let usage = read_u64(&memory.join("memory.usage_in_bytes"))?;
+        Some(memory_and_swap.saturating_sub(usage))
+    })
+}
+
+/// Converts a cgroup v2 `cpu.weight` (1-10000) to v1 `cpu.shares`
+/// (2-262144), the inverse of the mapping Kubernetes applies.
+fn weight_to_shares(weight: u64) -> u64 {
+    2 + (weight.clamp(1, 10_000) - 1) * 262_142 / 9_999
+}
+
+/// CPU accounting and limits of the container's cgroup.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CgroupCpuStats {
+    pub usage_usec: u64,
+    pub user_usec: u64,
+    pub system_usec: u64,
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+    pub throttled_usec: u64,
+    /// `cpu.shares`, derived from `cpu.weight` on v2.
+    pub shares: Option<u64>,
+    /// CFS quota per period; `None` when unlimited.
+    pub quota_us: Option<u64>,
+    pub period_us: Option<u64>,
+}
+
+/// Reads the stats under a cgroup root in the given mode, along with the
+/// version that was read (or the mode, if neither layout had stats).
+type CgroupReader = fn(&Path, CgroupMode) -> (Option<CgroupCpuStats>, &'static str);
+
+impl CgroupCpuStats {
+    /// Tries the v2 layout under `root` first, then v1 unless `mode` is
+    /// `V2Unified`, in a `cgroup.read_cpu_stats` span.
+    pub fn read(root: &Path, mode: CgroupMode) -> Option<Self> {
+        Self::read_traced(root, mode, Self::read_versioned)
+    }
+
+    fn read_versioned(root: &Path, mode: CgroupMode) -> (Option<Self>, &'static str) {
+        if let Some(stats) = Self::try_cgroups_v2(root) {
+            (Some(stats), "v2")
+        } else if let Some(stats) = (mode != CgroupMode::V2Unified)
+            .then(|| Self::try_cgroups_v1(root))
+            .flatten()
+        {
+            (Some(stats), "v1")
+        } else {
+            (None, mode.as_str())
+        }
+    }
+
+    /// Records how long `read` took in `cgroup_read_duration_seconds`, and
+    /// adds a `cgroup.slow_read` event past `SLOW_CGROUP_READ`.
+    fn read_traced(root: &Path, mode: CgroupMode, read: CgroupReader) -> Option<Self> {
+        global::tracer(METER_NAME).in_span("cgroup.read_cpu_stats", |cx| {
+            let start = Instant::now();
+            let (stats, version) = read(root, mode);
+            let elapsed = start.elapsed();
+
+            let span = cx.span();
+            span.set_attribute(KeyValue::new("cgroup.version", version));
+            span.set_attribute(KeyValue::new("cgroup.success", stats.is_some()));
+            if elapsed > SLOW_CGROUP_READ {
+                span.add_event(
+                    "cgroup.slow_read",
+                    vec![KeyValue::new("duration_ms", elapsed.as_secs_f64() * 1000.0)],
+                );
+            }
+            global::meter(METER_NAME)
+                .f64_histogram(checked_instrument_name("cgroup_read_duration_seconds"))
+                .with_unit("s")
+                .with_description("Time to read the cgroup CPU stats")
+                .build()
+                .record(
+                    elapsed.as_secs_f64(),
+                    &KUBERNETES_LABELS.with(&[KeyValue::new("cgroup.version", version)]),
+                );
+            stats
+        })
+    }
+
+    /// Reads the `cpu` and `cpuacct` controllers of a cgroup v1 hierarchy.
+    /// Returns `None` if neither exposes anything.
+    pub fn try_cgroups_v1(root: &Path) -> Option<Self> {
+        let cpu = root.join("cpu");
+        let cpuacct = root.join("cpuacct");
+
+        let mut stats = CgroupCpuStats {
+            shares: read_u64(&cpu.join("cpu.shares")),
+            // -1 means unlimited, which fails to parse as u64
+            quota_us: read_u64(&cpu.join("cpu.cfs_quota_us")),
+            period_us: read_u64(&cpu.join("cpu.cfs_period_us")),
+            ..Default::default()
+        };
+        let usage_ns = read_u64(&cpuacct.join("cpuacct.usage"));
+        if let Some(usage_ns) = usage_ns {
+            stats.usage_usec = usage_ns / 1000;
+        }
+        if let Ok(contents) = fs::read_to_string(cpu.join("cpu.stat")) {
+            for (key, value) in contents.lines().filter_map(|line| line.split_once(' ')) {
+                let Ok(value) = value.trim().parse::<u64>() else {
+                    continue;
+                };
+                match key {
+                    "nr_periods" => stats.nr_periods = value,
+                    "nr_throttled" => stats.nr_throttled = value,
+                    "throttled_time" => stats.throttled_usec = value / 1000,
+                    _ => {}
+                }
+            }
+        }
+
+        (usage_ns.is_some() || stats.shares.is_some()).then_some(stats)
+    }
+
+    /// Reads `cpu.stat`, `cpu.weight` and `cpu.max` from a cgroup v2
+    /// directory. Returns `None` if `cpu.stat` is missing or has no
+    /// `usage_usec` line; unknown keys are ignored.
+    pub fn try_cgroups_v2(root: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(root.join("cpu.stat")).ok()?;
+        let mut stats = CgroupCpuStats::default();
+        let mut has_usage = false;
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<u64>() else {
+                continue;
+            };
+            match key {
+                "usage_usec" => {
+                    stats.usage_usec = value;
+                    has_usage = true;
+                }
+                "user_usec" => stats.user_usec = value,
+                "system_usec" => stats.system_usec = value,
+                "nr_periods" => stats.nr_periods = value,
+                "nr_throttled" => stats.nr_throttled = value,
+                "throttled_usec" => stats.throttled_usec = value,
+                _ => {}
+            }
+        }
+        if !has_usage {
+            return None;
+        }
+
+        stats.shares = read_u64(&root.join("cpu.weight")).map(weight_to_shares);
+        // "$MAX $PERIOD", where $MAX is "max" when unlimited
+        if let Ok(max) = fs::read_to_string(root.join("cpu.max")) {
+            let mut fields = max.split_whitespace();
+            stats.quota_us = fields.next().and_then(|quota| quota.parse().ok());
+            stats.period_us = fields.next().and_then(|period| period.parse().ok());
+        }
+        Some(stats)
+    }
+}
+
+/// User plus system CPU time of the process, in seconds, from the
+/// `utime` and `stime` fields of a `/proc/<pid>/stat` file.
+pub fn read_process_cpu_seconds(stat: &Path) -> Option<f64> {
+    let stat = fs::read_to_string(stat).ok()?;
+    // The command name can contain spaces, so count fields from its end.
+    let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace();
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some((utime + stime) as f64 / USER_HZ)
+}
+
+/// Reads process CPU seconds from `stat` and resident bytes from `status`.
+pub type ProcessReader = fn(stat: &Path, status: &Path) -> (Option<f64>, Option<u64>);
+
+fn read_process_usage(stat: &Path, status: &Path) -> (Option<f64>, Option<u64>) {
+    (
+        read_process_cpu_seconds(stat),
+        read_process_memory_bytes(status),
+    )
+}
+
+#[derive(Debug)]
+pub struct CpuMetricsState {
+    cpu_sysfs_root: PathBuf,
+    proc_status: PathBuf,
+    proc_stat: PathBuf,
+    cpufreq_available: bool,
+    pub cpu_frequencies: Vec<(u32, u64)>,
+    pub process_cpu_seconds: Option<f64>,
+    pub process_memory_bytes: Option<u64>,
+    pub open_file_descriptors: Option<u64>,
+    fd_dir: PathBuf,
+    fd_warn_threshold: Option<u64>,
+    /// Detected once, as the hierarchy doesn't change while running.
+    cgroup_mode: CgroupMode,
+    pub cgroup_cpu: Option<CgroupCpuStats>,
+    pub container_memory_swap_bytes: Option<u64>,
+    swap_unavailable_logged: bool,
+    /// CPU cores the cgroup used since the previous refresh.
+    pub container_cpu_usage_raw: Option<f64>,
+    /// `container_cpu_usage_raw` smoothed with `cpu_usage_ema`.
+    pub container_cpu_usage: Option<f64>,
+    cpu_usage_ema: Ema,
+    cpu_usage_filter: KepsilonFilter,
+    previous_cpu_sample: Option<(u64, Instant)>,
+    pub last_measurement_time: SystemTime,
+    /// When `last_measurement_time` was taken, on the monotonic clock.
+    pub last_measured_at: Instant,
+    read_process: ProcessReader,
+    collection_errors: Counter<u64>,
+    anomalies_filtered: Counter<u64>,
+}
+
+/// `None` until `CpuMetricsState` has been initialized.
+pub type SharedState = Arc<Mutex<Option<CpuMetricsState>>>;
+
+/// The values of a `CpuMetricsState`, without its file paths.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CpuMetricsSnapshot {
+    /// Smoothed cgroup CPU usage, in cores.
+    pub container_cpu_usage: Option<f64>,
+    /// Cgroup CPU usage over the last interval, in cores.
+    pub container_cpu_usage_raw: Option<f64>,
+    /// Process CPU time, in seconds.
+    pub process_cpu_usage: Option<f64>,
+    /// Resident set size, in bytes.
+    pub process_memory_usage: Option<u64>,
+    /// Milliseconds since the Unix epoch.
+    pub last_measurement_time: u64,
+    /// Seconds since the measurement, computed when serialized.
+    #[serde(
+        rename = "last_measurement_age_secs",
+        serialize_with = "serialize_instant_as_age"
+    )]
+    pub last_measured_at: Instant,
+}
+
+/// Serializes an `Instant`, which has no fixed epoch, as the seconds elapsed
+/// since it.
+fn serialize_instant_as_age<S: Serializer>(
+    instant: &Instant,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(Instant::now().duration_since(*instant).as_secs_f64())
+}
+
+impl CpuMetricsState {
+    pub fn try_new(cgroup_mode: CgroupMode) -> Result<Self> {
+        Self::try_with_roots(CPU_SYSFS_ROOT, PROC_SELF_STATUS, cgroup_mode)
+    }
+
+    /// Fails if process memory can't be read from `proc_status`; CPU
+    /// frequency and cgroup stats are optional. The cgroup stats are read
+    /// as `cgroup_mode` lays them out.
+    pub fn try_with_roots(
+        cpu_sysfs_root: impl Into<PathBuf>,
+        proc_status: impl Into<PathBuf>,
+        cgroup_mode: CgroupMode,
+    ) -> Result<Self> {
+        let cpu_sysfs_root = cpu_sysfs_root.into();
+        let proc_status = proc_status.into();
+        let proc_stat = proc_status.with_file_name("stat");
+        let fd_dir = proc_status.with_file_name("fd");
+        let fd_warn_threshold = fd_warn_threshold(&proc_status.with_file_name("limits"));
+        let process_memory_bytes = read_process_memory_bytes(&proc_status)
+            .with_context(|| format!("Failed to read VmRSS from {}", proc_status.display()))?;
+
+        let cpufreq_available = read_cpu_frequencies(&cpu_sysfs_root).is_some();
+        if !cpufreq_available {
+            debug!(
+                name = "CpuFrequencyUnavailable",
+                path = %cpu_sysfs_root.display(),
+                message = "cpufreq is not exposed, skipping CPU frequency metrics"
+            );
+        }
+
+        let mut state = CpuMetricsState {
+            cpu_sysfs_root,
+            proc_status,
+            proc_stat,
+            cpufreq_available,
+            cpu_frequencies: Vec::new(),
+            process_cpu_seconds: None,
+            process_memory_bytes: Some(process_memory_bytes),
+            open_file_descriptors: None,
+            fd_dir,
+            fd_warn_threshold,
+            cgroup_mode,
+            cgroup_cpu: None,
+            container_memory_swap_bytes: None,
+            swap_unavailable_logged: false,
+            container_cpu_usage_raw: None,
+            container_cpu_usage: None,
+            cpu_usage_ema: Ema::from_env(),
+            cpu_usage_filter: KepsilonFilter::from_env(),
+            previous_cpu_sample: None,
+            last_measurement_time: SystemTime::now(),
+            last_measured_at: Instant::now(),
+            read_process: read_process_usage,
+            collection_errors: global::meter(METER_NAME)
+                .u64_counter(checked_instrument_name("metrics_collection_errors_total"))
+                .with_description("Metric refreshes that failed, by error_type")
+                .build(),
+            anomalies_filtered: global::meter(METER_NAME)
+                .u64_counter(checked_instrument_name("metrics_anomaly_filtered_total"))
+                .with_description("Outlier samples replaced by the previous valid one")
+                .build(),
+        };
+        state.refresh();
+        Ok(state)
+    }
+
+    pub fn refresh(&mut self) {
+        if self.cpufreq_available {
+            self.cpu_frequencies = read_cpu_frequencies(&self.cpu_sysfs_root).unwrap_or_default();
+        }
+        self.refresh_process();
+        self.refresh_open_file_descriptors();
+        self.cgroup_cpu = CgroupCpuStats::read(Path::new(CGROUP_ROOT), self.cgroup_mode);
+        if let Some(usage_usec) = self.cgroup_cpu.as_ref().map(|stats| stats.usage_usec) {
+            self.record_cpu_usage(usage_usec, Instant::now());
+        }
+        self.refresh_swap(Path::new(CGROUP_ROOT));
+        self.last_measurement_time = SystemTime::now();
+        self.last_measured_at = Instant::now();
+    }
+
+    fn refresh_swap(&mut self, cgroup_root: &Path) {
+        self.container_memory_swap_bytes = read_cgroup_swap_bytes(cgroup_root, self.cgroup_mode);
+        if self.container_memory_swap_bytes.is_none() && !self.swap_unavailable_logged {
+            self.swap_unavailable_logged = true;
+            debug!(
+                name = "CgroupSwapUnavailable",
+                path = %cgroup_root.display(),
+                message = "cgroup swap usage is not exposed, skipping swap metrics"
+            );
+        }
+    }
+
+    /// Reads process CPU and memory. A panicking read reports 0 for both
+    /// instead of taking the collection loop down.
+    fn refresh_process(&mut self) {
+        let read = self.read_process;
+        let (stat, status) = (&self.proc_stat, &self.proc_status);
+        match catch_unwind(AssertUnwindSafe(|| read(stat, status))) {
+            Ok((cpu_seconds, memory_bytes)) => {
+                self.process_cpu_seconds = cpu_seconds;
+                self.process_memory_bytes = memory_bytes;
+            }
+            Err(panic) => {
+                error!(
+                    name = "ProcessMetricsRefreshFailed",
+                    error = panic_message(panic.as_ref()),
+                    message = "Reading process CPU and memory panicked, reporting 0"
+                );
+                self.process_cpu_seconds = Some(0.0);
+                self.process_memory_bytes = Some(0);
+                self.collection_errors
+                    .add(1, &[KeyValue::new("error_type", "proc_refresh")]);
+            }
+        }
+    }
+
+    /// Counts open file descriptors, warning when the count first crosses
+    /// the threshold.
+    fn refresh_open_file_descriptors(&mut self) {
+        let previous = self.open_file_descriptors;
+        self.open_file_descriptors = read_open_fd_count(&self.fd_dir);
+        let (Some(count), Some(threshold)) = (self.open_file_descriptors, self.fd_warn_threshold)
+        else {
+            return;
+        };
+        if count > threshold && previous.is_none_or(|previous| previous <= threshold) {
+            warn!(
+                name = "OpenFileDescriptorsHigh",
+                process.open_file_descriptors = count,
+                threshold = threshold,
+                message = "Process is close to its open file descriptor limit"
+            );
+        }
+    }
+
+    /// Turns cumulative cgroup CPU time into usage over the time since the
+    /// previous sample, then filters out spikes and smooths it.
+    fn record_cpu_usage(&mut self, usage_usec: u64, now: Instant) {
+        if let Some((previous_usec, previous_at)) = self.previous_cpu_sample {
+            let elapsed = now.duration_since(previous_at).as_secs_f64();
+            if elapsed > 0.0 {
+                let raw = usage_usec.saturating_sub(previous_usec) as f64 / 1e6 / elapsed;
+                self.container_cpu_usage_raw = Some(raw);
+                let (filtered, replaced) = self.cpu_usage_filter.filter(raw);
+                if replaced {
+                    self.anomalies_filtered
+                        .add(1, &[KeyValue::new("metric", "container_cpu_usage")]);
+                }
+                self.container_cpu_usage = Some(self.cpu_usage_ema.update(filtered));
+            }
+        }
+        self.previous_cpu_sample = Some((usage_usec, now));
+    }
+
+    pub fn snapshot(&self) -> CpuMetricsSnapshot {
+        CpuMetricsSnapshot {
+            container_cpu_usage: self.container_cpu_usage,
+            container_cpu_usage_raw: self.container_cpu_usage_raw,
+            process_cpu_usage: self.process_cpu_seconds,
+            process_memory_usage: self.process_memory_bytes,
+            last_measurement_time: self
+                .last_measurement_time
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            last_measured_at: self.last_measured_at,
+        }
+    }
+}
+
+/// Refreshes `state`, or tries to initialize it with `init` if that hasn't
+/// succeeded yet.
+fn init_or_refresh(
+    state: &mut Option<CpuMetricsState>,
+    init: impl FnOnce() -> Result<CpuMetricsState>,
+) {
+    match state {
+        Some(state) => state.refresh(),
+        None => match init() {
+            Ok(initialized) => *state = Some(initialized),
+            Err(err) => warn!(
+                name = "CpuMetricsInitFailed",
+                error = format!("{err:#}"),
+                message = "Failed to initialize CPU metrics, retrying next interval"
+            ),
+        },
+    }
+}
+
+fn observe_state<T>(
+    state: &SharedState,
+    f: impl FnOnce(&CpuMetricsState) -> Option<T>,
+) -> Option<T> {
+    state.lock().unwrap().as_ref().and_then(f)
+}
+
+/// `attributes` and the Kubernetes labels, sorted by key.
+fn observed_attributes(attributes: &[KeyValue]) -> Vec<KeyValue> {
+    let mut attrs = KUBERNETES_LABELS.with(attributes);
+    sort_attributes(&mut attrs);
+    attrs
+}
+
+fn register_instruments(state: SharedState) {
+    let meter = global::meter(METER_NAME);
+
+    let initialized_state = state.clone();
+    meter
+        .u64_observable_gauge(checked_instrument_name("metrics_collection_initialized"))
+        .with_description("1 once CPU metrics collection has initialized, 0 before")
+        .with_callback(move |observer| {
+            observer.observe(
+                initialized_state.lock().unwrap().is_some() as u64,
+                &observed_attributes(&[]),
+            );
+        })
+        .build();
+
+    let frequency_state = state.clone();
+    meter
+        .u64_observable_gauge(checked_instrument_name("cpu_frequency_hz"))
+        .with_description("Current frequency of each logical CPU")
+        .with_unit("Hz")
+        .with_callback(move |observer| {
+            let frequencies = observe_state(&frequency_state, |state| {
+                Some(state.cpu_frequencies.clone())
+            });
+            for (cpu, hz) in frequencies.unwrap_or_default() {
+                observer.observe(
+                    hz,
+                    &observed_attributes(&[KeyValue::new("cpu", cpu.to_string())]),
+                );
+            }
+        })
+        .build();
+
+    let usage_state = state.clone();
+    meter
+        .f64_observable_counter(checked_instrument_name("cgroup_cpu_usage_seconds_total"))
+        .with_description("CPU time consumed by the container's cgroup")
+        .with_unit("s")
+        .with_callback(move |observer| {
+            let usage = observe_state(&usage_state, |state| {
+                state.cgroup_cpu.as_ref().map(|stats| stats.usage_usec)
+            });
+            if let Some(usage_usec) = usage {
+                observer.observe(usage_usec as f64 / 1e6, &observed_attributes(&[]));
+            }
+        })
+        .build();
+
+    let throttled_state = state.clone();
+    meter
+        .f64_observable_counter(checked_instrument_name(
+            "cgroup_cpu_throttled_seconds_total",
+        ))
+        .with_description("Time the container's cgroup was throttled by its CPU quota")
+        .with_unit("s")
+        .with_callback(move |observer| {
+            let throttled = observe_state(&throttled_state, |state| {
+                state.cgroup_cpu.as_ref().map(|stats| stats.throttled_usec)
+            });
+            if let Some(throttled_usec) = throttled {
+                observer.observe(throttled_usec as f64 / 1e6, &observed_attributes(&[]));
+            }
+        })
+        .build();
+
+    for (name, description, usage) in [
+        (
+            "container_cpu_usage_raw",
+            "CPU cores used by the container's cgroup over the last interval",
+            (|state| state.container_cpu_usage_raw) as fn(&CpuMetricsState) -> Option<f64>,
+        ),
+        (
+            "container_cpu_usage",
+            "CPU cores used by the container's cgroup, smoothed with METRICS_EMA_ALPHA",
+            |state| state.container_cpu_usage,
+        ),
+    ] {
+        let usage_state = state.clone();
+        meter
+            .f64_observable_gauge(checked_instrument_name(name))
+            .with_description(description)
+            .with_unit("1")
+            .with_callback(move |observer| {
+                if let Some(value) = observe_state(&usage_state, usage) {
+                    observer.observe(value, &observed_attributes(&[]));
+                }
+            })
+            .build();
+    }
+
+    for (name, description, limit) in [
+        (
+            "container_cpu_shares",
+            "Relative CPU weight of the container's cgroup",
+            (|stats| stats.shares) as fn(&CgroupCpuStats) -> Option<u64>,
+        ),
+        (
+            "container_cpu_quota_us",
+            "CPU time the container may use per CFS period, unset when unlimited",
+            |stats| stats.quota_us,
+        ),
+        (
+            "container_cpu_period_us",
+            "Length of the container's CFS period",
+            |stats| stats.period_us,
+        ),
+    ] {
+        let limit_state = state.clone();
+        meter
+            .u64_observable_gauge(checked_instrument_name(name))
+            .with_description(description)
+            .with_callback(move |observer| {
+                if let Some(value) = observe_state(&limit_state, |state| {
+                    state.cgroup_cpu.as_ref().and_then(limit)
+                }) {
+                    observer.observe(value, &observed_attributes(&[]));
+                }
+            })
+            .build();
+    }
+
+    let swap_state = state.clone();
+    meter
+        .u64_observable_gauge(checked_instrument_name("container_memory_swap_usage_bytes"))
+        .with_description("Swap used by the container's cgroup")
+        .with_unit("By")
+        .with_callback(move |observer| {
+            if let Some(bytes) =
+                observe_state(&swap_state, |state| state.container_memory_swap_bytes)
+            {
+                observer.observe(bytes, &observed_attributes(&[]));
+            }
+        })
+        .build();
+
+    let fd_state = state.clone();
+    meter
+        .u64_observable_gauge(checked_instrument_name("process_open_file_descriptors"))
+        .with_description("File descriptors the process has open")
+        .with_callback(move |observer| {
+            if let Some(count) = observe_state(&fd_state, |state| state.open_file_descriptors) {
+                observer.observe(count, &observed_attributes(&[]));
+            }
+        })
+        .build();
+
+    meter
+        .u64_observable_gauge(checked_instrument_name("process_memory_usage"))
+        .with_description("Resident set size of the process")
+        .with_unit("By")
+        .with_callback(move |observer| {
+            if let Some(bytes) = observe_state(&state, |state| state.process_memory_bytes) {
+                observer.observe(bytes, &observed_attributes(&[]));
+            }
+        })
+        .build();
+}
+
+/// Refreshes the CPU metrics and the metrics of the worker `runtimes` every
+/// `interval` and reports them through the global meter provider. If the
+/// state can't be initialized, it is retried on every tick until it can.
+/// Returns the state being refreshed.
+pub fn start_cpu_metrics_collection(
+    interval: Duration,
+    cgroup_mode: CgroupMode,
+    runtimes: WorkerRuntimes,
+) -> SharedState {
+    let state: SharedState = Arc::new(Mutex::new(None));
+
+    register_instruments(state.clone());
+
+    let alerts = global::meter(METER_NAME)
+        .u64_counter(checked_instrument_name("memory_growth_alerts_total"))
+        .with_description("Times sustained process memory growth was detected")
+        .build();
+    let mut detector = MemoryLeakDetector::from_env();
+    let started = Instant::now();
+
+    let refreshed = state.clone();
+    spawn_with_context(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let memory = {
+                let mut state = refreshed.lock().unwrap();
+                init_or_refresh(&mut state, || CpuMetricsState::try_new(cgroup_mode));
+                state.as_ref().and_then(|state| state.process_memory_bytes)
+            };
+            runtimes.refresh();
+            if let Some(bytes) = memory {
+                let seconds = started.elapsed().as_secs_f64();
+                check_memory_growth(&mut detector, &alerts, seconds, bytes);
+            }
+        }
+    });
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::metrics::MeterProvider;
+    use tracing_subscriber::prelude::*;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::test_utils::{
+        global_span_exporter, test_meter_provider, u64_counter_total, CaptureLayer,
+    };
+
+    fn mock_cpu_root(frequencies_khz: &[(u32, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("cpu-{}", Uuid::new_v4()));
+        for (cpu, khz) in frequencies_khz {
+            let dir = root.join(format!("cpu{cpu}/cpufreq"));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("scaling_cur_freq"), khz).unwrap();
+        }
+        // not a CPU, must be ignored
+        fs::create_dir_all(root.join("cpufreq")).unwrap();
+        root
+    }
+
+    fn mock_proc_status(root: &Path) -> PathBuf {
+        let status = root.join("status");
+        fs::write(
+            &status,
+            "Name:\tshipping\nVmRSS:\t   2048 kB\nThreads:\t4\n",
+        )
+        .unwrap();
+        status
+    }
+
+    #[test]
+    fn test_read_process_cpu_seconds() {
+        let root = mock_cpu_root(&[]);
+        let stat = root.join("stat");
+        fs::write(
+            &stat,
+            "42 (ship ping) S 1 42 42 0 -1 4194560 100 0 0 0 250 120 0 0 20 0 4 0 100\n",
+        )
+        .unwrap();
+        assert_eq!(read_process_cpu_seconds(&stat), Some(3.7));
+        assert_eq!(read_process_cpu_seconds(&root.join("missing")), None);
+    }
+
+    #[test]
+    fn test_init_retries_until_success() {
+        let root = mock_cpu_root(&[(0, "2400000\n")]);
+        let status = root.join("status");
+        let mut state = None;
+
+        // no status file yet, so initialization fails and is retried
+        for _ in 0..3 {
+            init_or_refresh(&mut state, || {
+                CpuMetricsState::try_with_roots(&root, &status, CgroupMode::Unknown)
+            });
+            assert!(state.is_none());
+        }
+
+        mock_proc_status(&root);
+        init_or_refresh(&mut state, || {
+            CpuMetricsState::try_with_roots(&root, &status, CgroupMode::Unknown)
+        });
+        let initialized = state.as_ref().unwrap();
+        assert_eq!(initialized.process_memory_bytes, Some(2048 * 1024));
+        assert_eq!(initialized.cpu_frequencies, vec![(0, 2_400_000_000)]);
+
+        // once initialized, it only refreshes
+        init_or_refresh(&mut state, || panic!("must not re-initialize"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_survives_panicking_reader() {
+        let root = mock_cpu_root(&[]);
+        let mut state =
+            CpuMetricsState::try_with_roots(&root, mock_proc_status(&root), CgroupMode::Unknown)
+                .unwrap();
+        let (provider, exporter) = test_meter_provider();
+        state.collection_errors = provider
+            .meter("test")
+            .u64_counter("metrics_collection_errors_total")
+            .build();
+        state.read_process = |_, _| panic!("/proc is not readable");
+
+        state.refresh();
+        state.refresh();
+
+        assert_eq!(state.process_cpu_seconds, Some(0.0));
+        assert_eq!(state.process_memory_bytes, Some(0));
+        provider.force_flush().unwrap();
+        assert_eq!(
+            u64_counter_total(&exporter, "metrics_collection_errors_total"),
+            2
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_read_open_fd_count() {
+        let root = mock_cpu_root(&[]);
+        let fd_dir = root.join("fd");
+        fs::create_dir_all(&fd_dir).unwrap();
+        for fd in 0..5 {
+            fs::write(fd_dir.join(fd.to_string()), "").unwrap();
+        }
+        assert_eq!(read_open_fd_count(&fd_dir), Some(5));
+        assert_eq!(read_open_fd_count(&root.join("missing")), None);
+
+        // other tests open and close files concurrently, so only check that
+        // ours are counted
+        let files: Vec<_> = (0..5)
+            .map(|fd| fs::File::open(fd_dir.join(fd.to_string())).unwrap())
+            .collect();
+        let open = read_open_fd_count(Path::new("/proc/self/fd")).unwrap();
+        assert!(open > files.len() as u64, "{open} open");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_read_fd_soft_limit() {
+        let root = mock_cpu_root(&[]);
+        let limits = root.join("limits");
+        fs::write(
+            &limits,
+            "Limit                     Soft Limit           Hard Limit           Units     \n\
+             Max processes             unlimited            unlimited            processes \n\
+             Max open files            1024                 4096                 files     \n",
+        )
+        .unwrap();
+        assert_eq!(read_fd_soft_limit(&limits), Some(1024));
+        fs::write(
+            &limits,
+            "Max open files            unlimited            unlimited            files     \n",
+        )
+        .unwrap();
+        assert_eq!(read_fd_soft_limit(&limits), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_ema_converges() {
+        let mut ema = Ema::new(0.5);
+        let smoothed: Vec<f64> = [1.0, 3.0, 3.0, 3.0].map(|raw| ema.update(raw)).to_vec();
+        assert_eq!(smoothed, [1.0, 2.0, 2.5, 2.75]);
+
+        for _ in 0..50 {
+            ema.update(3.0);
+        }
+        assert!((ema.update(3.0) - 3.0).abs() < 1e-9);
+
+        assert_eq!(Ema::new(0.0), Ema::new(DEFAULT_EMA_ALPHA));
+    }
+
+    #[test]
+    fn test_kepsilon_filter_removes_spike() {
+        let mut filter = KepsilonFilter::new(10, 3_f64);
+        let samples = [
+            0.50, 0.52, 0.48, 0.51, 0.49, 0.50, 0.53, 0.47, 0.50, 0.51, 4.00, 0.49, 0.52,
+        ];
+        let filtered: Vec<(f64, bool)> = samples.iter().map(|s| filter.filter(*s)).collect();
+
+        let replaced: Vec<usize> = (0..filtered.len()).filter(|i| filtered[*i].1).collect();
+        assert_eq!(replaced, [10]);
+        assert_eq!(filtered[10].0, 0.51);
+        for (i, (value, _)) in filtered.iter().enumerate().filter(|(i, _)| *i != 10) {
+            assert_eq!(*value, samples[i]);
+        }
+    }
+
+    #[test]
+    fn test_kepsilon_filter_follows_level_shift() {
+        let mut filter = KepsilonFilter::new(4, 3_f64);
+        for s in [1.0, 1.1, 0.9, 1.0] {
+            filter.filter(s);
+        }
+        let shifted: Vec<bool> = (0..4).map(|_| filter.filter(3.0).1).collect();
+        assert_eq!(shifted, [true, false, false, false]);
+    }
+
+    #[test]
+    fn test_kepsilon_filter_flat_window() {
+        let mut filter = KepsilonFilter::new(4, 3_f64);
+        for _ in 0..4 {
+            filter.filter(0.5);
+        }
+        assert_eq!(filter.filter(0.52), (0.52, false));
+
+        let mut filter = KepsilonFilter::new(4, 3_f64);
+        for _ in 0..4 {
+            filter.filter(0.5);
+        }
+        assert_eq!(filter.filter(4.0), (0.5, true));
+    }
+
+    #[test]
+    fn test_cpu_usage_smoothing() {
+        let root = mock_cpu_root(&[]);
+        let mut state =
+            CpuMetricsState::try_with_roots(&root, mock_proc_status(&root), CgroupMode::Unknown)
+                .unwrap();
+        state.cpu_usage_ema = Ema::new(DEFAULT_EMA_ALPHA);
+        state.previous_cpu_sample = None;
+        let start = Instant::now();
+
+        // 1 core for 5s, then 2 cores for 5s
+        state.record_cpu_usage(0, start);
+        assert_eq!(state.container_cpu_usage, None);
+        state.record_cpu_usage(5_000_000, start + Duration::from_secs(5));
+        assert_eq!(state.container_cpu_usage, Some(1.0));
+        state.record_cpu_usage(15_000_000, start + Duration::from_secs(10));
+        assert_eq!(state.container_cpu_usage_raw, Some(2.0));
+        assert!((state.container_cpu_usage.unwrap() - 1.3).abs() < 1e-9);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_serializes_measurement_age() {
+        let root = mock_cpu_root(&[]);
+        let mut state =
+            CpuMetricsState::try_with_roots(&root, mock_proc_status(&root), CgroupMode::Unknown)
+                .unwrap();
+        state.last_measured_at = Instant::now() - Duration::from_secs(2);
+
+        let json = serde_json::to_value(state.snapshot()).unwrap();
+        let expected = state.last_measured_at.elapsed().as_secs_f64();
+        let age = json["last_measurement_age_secs"].as_f64().unwrap();
+        assert!((age - expected).abs() < 0.01, "{age} vs {expected}");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_detect_cgroup_mode() {
+        let root = mock_cpu_root(&[]);
+        assert_eq!(
+            CgroupHierarchyDetector::detect_at(&root),
+            CgroupMode::Unknown
+        );
+
+        fs::create_dir_all(root.join("cpuacct")).unwrap();
+        assert_eq!(CgroupHierarchyDetector::detect_at(&root), CgroupMode::V1);
+
+        fs::create_dir_all(root.join("unified")).unwrap();
+        assert_eq!(CgroupHierarchyDetector::detect_at(&root), CgroupMode::V1);
+        fs::write(root.join("unified/cgroup.controllers"), "").unwrap();
+        assert_eq!(
+            CgroupHierarchyDetector::detect_at(&root),
+            CgroupMode::V2Hybrid
+        );
+
+        fs::write(root.join("cgroup.controllers"), "cpu memory").unwrap();
+        assert_eq!(
+            CgroupHierarchyDetector::detect_at(&root),
+            CgroupMode::V2Unified
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_unified_hierarchy_skips_v1() {
+        let root = mock_cpu_root(&[]);
+        fs::create_dir_all(root.join("cpuacct")).unwrap();
+        fs::write(root.join("cpuacct/cpuacct.usage"), "1000000\n").unwrap();
+
+        for mode in [CgroupMode::V1, CgroupMode::V2Hybrid, CgroupMode::Unknown] {
+            assert_eq!(
+                CgroupCpuStats::read_versioned(&root, mode).1,
+                "v1",
+                "{mode:?}"
+            );
+        }
+        assert_eq!(
+            CgroupCpuStats::read_versioned(&root, CgroupMode::V2Unified),
+            (None, "v2")
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_read_cpu_frequencies() {
+        let root = mock_cpu_root(&[(1, "800000\n"), (0, "2400000\n")]);
+
+        let frequencies = read_cpu_frequencies(&root).unwrap();
+        assert_eq!(frequencies, vec![(0, 2_400_000_000), (1, 800_000_000)]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cpufreq_missing() {
+        let root = mock_cpu_root(&[]);
+        fs::create_dir_all(root.join("cpu0")).unwrap();
+
+        assert_eq!(read_cpu_frequencies(&root), None);
+        let state =
+            CpuMetricsState::try_with_roots(&root, mock_proc_status(&root), CgroupMode::Unknown)
+                .unwrap();
+        assert!(state.cpu_frequencies.is_empty());
+
+        assert_eq!(read_cpu_frequencies(&root.join("missing")), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_swap_v2() {
+        let root = mock_cpu_root(&[]);
+        fs::write(root.join("memory.swap.current"), "4096\n").unwrap();
+        assert_eq!(
+            read_cgroup_swap_bytes(&root, CgroupMode::V2Unified),
+            Some(4096)
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_swap_v1() {
+        let root = mock_cpu_root(&[]);
+        let memory = root.join("memory");
+        fs::create_dir_all(&memory).unwrap();
+        fs::write(memory.join("memory.usage_in_bytes"), "1048576\n").unwrap();
+        assert_eq!(read_cgroup_swap_bytes(&root, CgroupMode::V1), None);
+
+        fs::write(memory.join("memory.memsw.usage_in_bytes"), "1312768\n").unwrap();
+        assert_eq!(read_cgroup_swap_bytes(&root, CgroupMode::V1), Some(264_192));
+        assert_eq!(
+            read_cgroup_swap_bytes(&root, CgroupMode::V2Hybrid),
+            Some(264_192)
+        );
+        // Unified hierarchies have no v1 memory controller to fall back to
+        assert_eq!(read_cgroup_swap_bytes(&root, CgroupMode::V2Unified), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_swap_unavailable_logged_once() {
+        let root = mock_cpu_root(&[]);
+        let mut state =
+            CpuMetricsState::try_with_roots(&root, mock_proc_status(&root), CgroupMode::Unknown)
+                .unwrap();
+        state.swap_unavailable_logged = false;
+
+        let capture = CaptureLayer::default();
+        tracing::subscriber::with_default(
+            tracing_subscriber::registry().with(capture.clone()),
+            || {
+                state.refresh_swap(&root);
+                state.refresh_swap(&root);
+            },
+        );
+        assert_eq!(state.container_memory_swap_bytes, None);
+        let logged = capture
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, fields)| {
+                fields.get("name").map(String::as_str) == Some("CgroupSwapUnavailable")
+            })
+            .count();
+        assert_eq!(logged, 1);
+
+        fs::write(root.join("memory.swap.current"), "0\n").unwrap();
+        state.refresh_swap(&root);
+        assert_eq!(state.container_memory_swap_bytes, Some(0));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cgroups_v2_cpu_stat() {
+        let root = mock_cpu_root(&[]);
+        assert_eq!(CgroupCpuStats::try_cgroups_v2(&root), None);
+
+        fs::write(
+            root.join("cpu.stat"),
+            "usage_usec 2500000\nuser_usec 2000000\nsystem_usec 500000\n\
+             core_sched.force_idle_usec 0\nnr_periods 40\nnr_throttled 3\nthrottled_usec 120000\n",
+        )
+        .unwrap();
+        assert_eq!(
+            CgroupCpuStats::try_cgroups_v2(&root),
+            Some(CgroupCpuStats {
+                usage_usec: 2_500_000,
+                user_usec: 2_000_000,
+                system_usec: 500_000,
+                nr_periods: 40,
+                nr_throttled: 3,
+                throttled_usec: 120_000,
+                ..Default::default()
+            })
+        );
+
+        fs::write(root.join("cpu.weight"), "10000\n").unwrap();
+        fs::write(root.join("cpu.max"), "max 100000\n").unwrap();
+        let stats = CgroupCpuStats::read(&root, CgroupMode::Unknown).unwrap();
+        assert_eq!(stats.shares, Some(262_144));
+        assert_eq!(stats.quota_us, None);
+        assert_eq!(stats.period_us, Some(100_000));
+
+        fs::write(root.join("cpu.weight"), "1\n").unwrap();
+        fs::write(root.join("cpu.max"), "50000 100000\n").unwrap();
+        let stats = CgroupCpuStats::read(&root, CgroupMode::Unknown).unwrap();
+        assert_eq!(stats.shares, Some(2));
+        assert_eq!(stats.quota_us, Some(50_000));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cgroups_v1_cpu_limits() {
+        let root = mock_cpu_root(&[]);
+        assert_eq!(CgroupCpuStats::read(&root, CgroupMode::Unknown), None);
+
+        fs::create_dir_all(root.join("cpu")).unwrap();
+        fs::create_dir_all(root.join("cpuacct")).unwrap();
+        fs::write(root.join("cpu/cpu.shares"), "512\n").unwrap();
+        fs::write(root.join("cpu/cpu.cfs_quota_us"), "-1\n").unwrap();
+        fs::write(root.join("cpu/cpu.cfs_period_us"), "100000\n").unwrap();
+        fs::write(
+            root.join("cpu/cpu.stat"),
+            "nr_periods 10\nnr_throttled 2\nthrottled_time 5000000\n",
+        )
+        .unwrap();
+        fs::write(root.join("cpuacct/cpuacct.usage"), "2500000000\n").unwrap();
+
+        assert_eq!(
+            CgroupCpuStats::read(&root, CgroupMode::Unknown),
+            Some(CgroupCpuStats {
+                usage_usec: 2_500_000,
+                nr_periods: 10,
+                nr_throttled: 2,
+                throttled_usec: 5_000,
+                shares: Some(512),
+                quota_us: None,
+                period_us: Some(100_000),
+                ..Default::default()
+            })
+        );
+
+        fs::write(root.join("cpu/cpu.cfs_quota_us"), "200000\n").unwrap();
+        assert_eq!(
+            CgroupCpuStats::read(&root, CgroupMode::Unknown)
+                .unwrap()
+                .quota_us,
+            Some(200_000)
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_slow_cgroup_read_is_traced() {
+        let exporter = global_span_exporter();
+        let root = mock_cpu_root(&[]);
+        fs::write(root.join("cgroup.controllers"), "cpu\n").unwrap();
+        fs::write(root.join("cpu.stat"), "usage_usec 100\n").unwrap();
+        let slow: CgroupReader = |root, mode| {
+            std::thread::sleep(Duration::from_millis(150));
+            CgroupCpuStats::read_versioned(root, mode)
+        };
+
+        let trace_id = global::tracer("test").in_span("refresh", |cx| {
+            assert!(CgroupCpuStats::read_traced(&root, CgroupMode::V2Unified, slow).is_some());
+            fs::remove_file(root.join("cpu.stat")).unwrap();
+            assert!(CgroupCpuStats::read(&root, CgroupMode::V2Unified).is_none());
+            cx.span().span_context().trace_id()
+        });
+        let spans: Vec<_> = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .filter(|span| span.span_context.trace_id() == trace_id)
+            .filter(|span| span.name == "cgroup.read_cpu_stats")
+            .collect();
+        assert_eq!(spans.len(), 2);
+
+        let (slow, missing) = (&spans[0], &spans[1]);
+        assert!(slow
+            .attributes
+            .contains(&KeyValue::new("cgroup.version", "v2")));
+        assert!(slow
+            .attributes
+            .contains(&KeyValue::new("cgroup.success", true)));
+        let event = slow
+            .events
+            .iter()
+            .find(|event| event.name == "cgroup.slow_read")
+            .unwrap();
+        let duration_ms = event
+            .attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == "duration_ms")
+            .unwrap();
+        assert!(matches!(duration_ms.value, opentelemetry::Value::F64(ms) if ms >= 150.0));
+
+        assert!(missing
+            .attributes
+            .contains(&KeyValue::new("cgroup.success", false)));
+        assert!(missing.events.is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_read_process_memory_bytes() {
+        let root = mock_cpu_root(&[]);
+        let status = root.join("status");
+        fs::write(
+            &status,
+            "Name:\tshipping\nVmRSS:\t   2048 kB\nThreads:\t4\n",
+        )
+        .unwrap();
+
+        assert_eq!(read_process_memory_bytes(&status), Some(2048 * 1024));
+        assert_eq!(read_process_memory_bytes(&root.join("missing")), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_linear_regression_slope() {
+        let samples: VecDeque<_> = (0..10)
+            .map(|x| (x as f64, 3_f64 * x as f64 + 7_f64))
+            .collect();
+        assert_eq!(linear_regression_slope(&samples), Some(3_f64));
+
+        let flat: VecDeque<_> = [(1_f64, 5_f64), (1_f64, 9_f64)].into();
+        assert_eq!(linear_regression_slope(&flat), None);
+        assert_eq!(linear_regression_slope(&VecDeque::new()), None);
+    }
+
+    #[test]
+    fn test_memory_growth_triggers_alert() {
+        let (provider, exporter) = test_meter_provider();
+        let alerts = provider
+            .meter("test")
+            .u64_counter("memory_growth_alerts_total")
+            .build();
+        let mut detector = MemoryLeakDetector::new(6, 1000_f64);
+
+        // 5s apart, growing 10 KiB (~2 KiB/s) every sample
+        for sample in 0..8_u64 {
+            let seconds = (sample * 5) as f64;
+            check_memory_growth(
+                &mut detector,
+                &alerts,
+                seconds,
+                50_000_000 + sample * 10_240,
+            );
+        }
+
+        provider.force_flush().unwrap();
+        // window fills on the 6th sample, so samples 6 to 8 alert
+        assert_eq!(
+            u64_counter_total(&exporter, "memory_growth_alerts_total"),
+            3
+        );
+    }
+
+    #[test]
+    fn test_stable_memory_does_not_alert() {
+        let mut detector = MemoryLeakDetector::new(4, 1000_f64);
+        for (sample, bytes) in [50_000_000, 50_004_000, 49_998_000, 50_001_000, 50_000_500]
+            .into_iter()
+            .enumerate()
+        {
+            assert_eq!(detector.record((sample * 5) as f64, bytes), None);
+        }
+    }
+}
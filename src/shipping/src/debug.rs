@@ -0,0 +1,126 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{get, http::StatusCode, web, Either, HttpResponse, Responder};
+
+use crate::correlation_id::CorrelationId;
+use crate::cpu_metrics::SharedState;
+use crate::error_response::ErrorResponse;
+
+/// Registers the debug endpoints when `enabled`. They read the
+/// `SharedState` in the app data.
+pub fn configure(enabled: bool) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        if enabled {
+            cfg.service(metrics);
+        }
+    }
+}
+
+/// The latest CPU and memory measurements, for debugging without an OTel
+/// backend.
+#[get("/debug/metrics")]
+pub async fn metrics(
+    state: web::Data<SharedState>,
+    correlation_id: CorrelationId,
+) -> impl Responder {
+    let snapshot = state.lock().unwrap().as_ref().map(|state| state.snapshot());
+    match snapshot {
+        Some(snapshot) => Either::Left(HttpResponse::Ok().json(snapshot)),
+        None => Either::Right(ErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "metrics_not_initialized",
+            "CPU metrics are not initialized yet",
+            correlation_id,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use actix_web::{http::StatusCode, test, App};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::cpu_metrics::{CgroupMode, CpuMetricsState};
+    use crate::error_response::tests::assert_error;
+
+    fn app_state(state: Option<CpuMetricsState>) -> web::Data<SharedState> {
+        web::Data::new(Arc::new(Mutex::new(state)))
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_json() {
+        let root = std::env::temp_dir().join(format!("debug-{}", Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("status"), "VmRSS:\t2048 kB\n").unwrap();
+        let mut state =
+            CpuMetricsState::try_with_roots(&root, root.join("status"), CgroupMode::Unknown)
+                .unwrap();
+        state.process_cpu_seconds = Some(3.7);
+        state.process_memory_bytes = Some(2_097_152);
+        state.container_cpu_usage_raw = Some(2.0);
+        state.container_cpu_usage = Some(1.5);
+        state.last_measurement_time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(Some(state)))
+                .configure(configure(true)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/debug/metrics").to_request();
+        let mut json: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let age = json
+            .as_object_mut()
+            .unwrap()
+            .remove("last_measurement_age_secs")
+            .unwrap();
+        assert!(age.as_f64().unwrap() >= 0.0);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "container_cpu_usage": 1.5,
+                "container_cpu_usage_raw": 2.0,
+                "process_cpu_usage": 3.7,
+                "process_memory_usage": 2_097_152,
+                "last_measurement_time": 1_700_000_000_123_u64,
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_not_initialized() {
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(None))
+                .configure(configure(true)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/debug/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(
+            resp,
+            StatusCode::SERVICE_UNAVAILABLE,
+            "metrics_not_initialized",
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_disabled() {
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(None))
+                .configure(configure(false)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/debug/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
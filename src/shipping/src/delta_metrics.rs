@@ -0,0 +1,626 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use opentelemetry_proto::tonic::{
+    collector::metrics::v1::{
+        metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest,
+    },
+    common::v1::KeyValue,
+    metrics::v1::{
+        exponential_histogram_data_point::Buckets, metric::Data, number_data_point::Value,
+        AggregationTemporality, ExponentialHistogramDataPoint, HistogramDataPoint, NumberDataPoint,
+    },
+};
+use opentelemetry_sdk::{
+    error::{OTelSdkError, OTelSdkResult},
+    metrics::{data::ResourceMetrics, exporter::PushMetricExporter, Temporality},
+};
+use tonic::{metadata::MetadataMap, transport::Channel, Request};
+
+/// Whether `METRICS_DELTA_CONVERSION=true`.
+pub fn delta_conversion_enabled() -> bool {
+    env::var("METRICS_DELTA_CONVERSION").is_ok_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+const CUMULATIVE: i32 = AggregationTemporality::Cumulative as i32;
+const DELTA: i32 = AggregationTemporality::Delta as i32;
+
+#[derive(Debug, Clone)]
+enum Point {
+    Int(i64),
+    Double(f64),
+    Histogram {
+        count: u64,
+        sum: f64,
+        bucket_counts: Vec<u64>,
+    },
+    ExponentialHistogram {
+        count: u64,
+        sum: f64,
+        scale: i32,
+        zero_count: u64,
+        positive: Buckets,
+        negative: Buckets,
+    },
+}
+
+/// The last cumulative point exported for a series.
+#[derive(Debug, Clone)]
+struct Previous {
+    start_time_unix_nano: u64,
+    time_unix_nano: u64,
+    point: Point,
+}
+
+impl Previous {
+    /// Whether `start`/`time` continue this series rather than restart it.
+    fn continued_by(&self, start_time_unix_nano: u64, time_unix_nano: u64) -> bool {
+        self.start_time_unix_nano == start_time_unix_nano && self.time_unix_nano <= time_unix_nano
+    }
+}
+
+/// Rewrites cumulative counters and histograms in OTLP export payloads as
+/// deltas from the previous export, for backends that only take deltas.
+///
+/// Unlike `OTEL_METRIC_TEMPORALITY_PREFERENCE`, the SDK keeps aggregating
+/// cumulatively; only what is sent changes. Up-down counters stay
+/// cumulative, as with the SDK's own delta preference. A series whose value
+/// drops or whose start time changes has been reset, and its first point is
+/// sent as is.
+#[derive(Debug, Default)]
+pub struct DeltaAccumulator {
+    previous: Mutex<HashMap<String, Previous>>,
+}
+
+impl DeltaAccumulator {
+    pub fn apply(&self, request: &mut ExportMetricsServiceRequest) {
+        let mut previous = self.previous.lock().unwrap();
+        for resource in &mut request.resource_metrics {
+            for scope in &mut resource.scope_metrics {
+                let scope_name = scope.scope.as_ref().map_or("", |scope| scope.name.as_str());
+                for metric in &mut scope.metrics {
+                    let instrument = format!("{scope_name}/{}", metric.name);
+                    match &mut metric.data {
+                        Some(Data::Sum(sum))
+                            if sum.is_monotonic && sum.aggregation_temporality == CUMULATIVE =>
+                        {
+                            sum.aggregation_temporality = DELTA;
+                            for point in &mut sum.data_points {
+                                let key = series_key(&instrument, &point.attributes);
+                                number_delta(&mut previous, key, point);
+                            }
+                        }
+                        Some(Data::Histogram(histogram))
+                            if histogram.aggregation_temporality == CUMULATIVE =>
+                        {
+                            histogram.aggregation_temporality = DELTA;
+                            for point in &mut histogram.data_points {
+                                let key = series_key(&instrument, &point.attributes);
+                                histogram_delta(&mut previous, key, point);
+                            }
+                        }
+                        Some(Data::ExponentialHistogram(histogram))
+                            if histogram.aggregation_temporality == CUMULATIVE =>
+                        {
+                            histogram.aggregation_temporality = DELTA;
+                            for point in &mut histogram.data_points {
+                                let key = series_key(&instrument, &point.attributes);
+                                exponential_histogram_delta(&mut previous, key, point);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn series_key(instrument: &str, attributes: &[KeyValue]) -> String {
+    let mut attributes: Vec<_> = attributes
+        .iter()
+        .map(|kv| format!("{}={:?}", kv.key, kv.value))
+        .collect();
+    attributes.sort();
+    format!("{instrument}{{{}}}", attributes.join(","))
+}
+
+fn number_delta(
+    previous: &mut HashMap<String, Previous>,
+    key: String,
+    point: &mut NumberDataPoint,
+) {
+    let current = match point.value {
+        Some(Value::AsInt(value)) => Point::Int(value),
+        Some(Value::AsDouble(value)) => Point::Double(value),
+        None => return,
+    };
+    let last = previous.insert(
+        key,
+        Previous {
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            point: current,
+        },
+    );
+    let Some(last) =
+        last.filter(|last| last.continued_by(point.start_time_unix_nano, point.time_unix_nano))
+    else {
+        return;
+    };
+    let continued = match (&last.point, &mut point.value) {
+        (Point::Int(last), Some(Value::AsInt(value))) if *value >= *last => {
+            *value -= last;
+            true
+        }
+        (Point::Double(last), Some(Value::AsDouble(value))) if *value >= *last => {
+            *value -= last;
+            true
+        }
+        _ => false,
+    };
+    if continued {
+        point.start_time_unix_nano = last.time_unix_nano;
+    }
+}
+
+fn histogram_delta(
+    previous: &mut HashMap<String, Previous>,
+    key: String,
+    point: &mut HistogramDataPoint,
+) {
+    let last = previous.insert(
+        key,
+        Previous {
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            point: Point::Histogram {
+                count: point.count,
+                sum: point.sum.unwrap_or_default(),
+                bucket_counts: point.bucket_counts.clone(),
+            },
+        },
+    );
+    let Some(Previous {
+        time_unix_nano,
+        point:
+            Point::Histogram {
+                count,
+                sum,
+                bucket_counts,
+            },
+        ..
+    }) = last.filter(|last| last.continued_by(point.start_time_unix_nano, point.time_unix_nano))
+    else {
+        return;
+    };
+    if point.count < count || point.bucket_counts.len() != bucket_counts.len() {
+        return;
+    }
+    point.count -= count;
+    point.sum = point.sum.map(|current| current - sum);
+    for (current, last) in point.bucket_counts.iter_mut().zip(bucket_counts) {
+        *current = current.saturating_sub(last);
+    }
+    // The cumulative extremes say nothing about this interval alone
+    point.min = None;
+    point.max = None;
+    point.start_time_unix_nano = time_unix_nano;
+}
+
+fn exponential_histogram_delta(
+    previous: &mut HashMap<String, Previous>,
+    key: String,
+    point: &mut ExponentialHistogramDataPoint,
+) {
+    let mut positive = point.positive.clone().unwrap_or_default();
+    let mut negative = point.negative.clone().unwrap_or_default();
+    let last = previous.insert(
+        key,
+        Previous {
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            point: Point::ExponentialHistogram {
+                count: point.count,
+                sum: point.sum.unwrap_or_default(),
+                scale: point.scale,
+                zero_count: point.zero_count,
+                positive: positive.clone(),
+                negative: negative.clone(),
+            },
+        },
+    );
+    let Some(Previous {
+        time_unix_nano,
+        point:
+            Point::ExponentialHistogram {
+                count,
+                sum,
+                scale,
+                zero_count,
+                positive: last_positive,
+                negative: last_negative,
+            },
+        ..
+    }) = last.filter(|last| last.continued_by(point.start_time_unix_nano, point.time_unix_nano))
+    else {
+        return;
+    };
+    // A cumulative histogram only ever lowers its scale
+    if point.count < count || point.zero_count < zero_count || point.scale > scale {
+        return;
+    }
+    let downscale = (scale - point.scale) as u32;
+    if !subtract_buckets(&mut positive, &last_positive, downscale)
+        || !subtract_buckets(&mut negative, &last_negative, downscale)
+    {
+        return;
+    }
+    point.count -= count;
+    point.sum = point.sum.map(|current| current - sum);
+    point.zero_count -= zero_count;
+    point.positive = Some(positive);
+    point.negative = Some(negative);
+    point.min = None;
+    point.max = None;
+    point.start_time_unix_nano = time_unix_nano;
+}
+
+/// Subtracts `last`, first merged down `downscale` scales, from `current`.
+/// False if `last` has counts outside of `current`'s buckets.
+fn subtract_buckets(current: &mut Buckets, last: &Buckets, downscale: u32) -> bool {
+    let mut merged: HashMap<i32, u64> = HashMap::new();
+    for (i, count) in last.bucket_counts.iter().enumerate() {
+        if *count > 0 {
+            *merged
+                .entry((last.offset + i as i32) >> downscale)
+                .or_default() += count;
+        }
+    }
+    for (index, count) in merged {
+        let Some(bucket) = usize::try_from(index - current.offset)
+            .ok()
+            .and_then(|i| current.bucket_counts.get_mut(i))
+        else {
+            return false;
+        };
+        *bucket = bucket.saturating_sub(count);
+    }
+    true
+}
+
+/// OTLP/gRPC metric exporter that sends deltas computed by a
+/// [`DeltaAccumulator`]. The SDK must aggregate cumulatively for it.
+#[derive(Debug)]
+pub struct DeltaMetricExporter {
+    client: MetricsServiceClient<Channel>,
+    metadata: MetadataMap,
+    accumulator: DeltaAccumulator,
+}
+
+impl DeltaMetricExporter {
+    pub fn new(channel: Channel, metadata: MetadataMap) -> Self {
+        DeltaMetricExporter {
+            client: MetricsServiceClient::new(channel),
+            metadata,
+            accumulator: DeltaAccumulator::default(),
+        }
+    }
+}
+
+impl PushMetricExporter for DeltaMetricExporter {
+    async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        let mut payload = ExportMetricsServiceRequest::from(metrics);
+        self.accumulator.apply(&mut payload);
+        let mut request = Request::new(payload);
+        *request.metadata_mut() = self.metadata.clone();
+        self.client
+            .clone()
+            .export(request)
+            .await
+            .map(drop)
+            .map_err(|status| {
+                OTelSdkError::InternalFailure(format!("Delta metric export failed: {status}"))
+            })
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn temporality(&self) -> Temporality {
+        Temporality::Cumulative
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use opentelemetry::{metrics::MeterProvider, KeyValue as SdkKeyValue};
+    use opentelemetry_proto::tonic::{
+        collector::metrics::v1::{
+            metrics_service_server::{MetricsService, MetricsServiceServer},
+            ExportMetricsServiceResponse,
+        },
+        common::v1::{any_value, AnyValue},
+        metrics::v1::{
+            ExponentialHistogram, Histogram, Metric, ResourceMetrics, ScopeMetrics, Sum,
+        },
+    };
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use tonic::{
+        transport::{server::TcpIncoming, Server},
+        Response, Status,
+    };
+
+    use super::*;
+
+    fn request(data: Data) -> ExportMetricsServiceRequest {
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                scope_metrics: vec![ScopeMetrics {
+                    metrics: vec![Metric {
+                        name: "app.shipping.items_count".to_string(),
+                        data: Some(data),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn zone(name: &str) -> Vec<KeyValue> {
+        vec![KeyValue {
+            key: "shipping.zone".to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(name.to_string())),
+            }),
+        }]
+    }
+
+    fn counter(
+        is_monotonic: bool,
+        time: u64,
+        values: &[(&str, i64)],
+    ) -> ExportMetricsServiceRequest {
+        request(Data::Sum(Sum {
+            data_points: values
+                .iter()
+                .map(|(name, value)| NumberDataPoint {
+                    attributes: zone(name),
+                    start_time_unix_nano: 100,
+                    time_unix_nano: time,
+                    value: Some(Value::AsInt(*value)),
+                    ..Default::default()
+                })
+                .collect(),
+            aggregation_temporality: CUMULATIVE,
+            is_monotonic,
+        }))
+    }
+
+    fn points(request: &ExportMetricsServiceRequest) -> Vec<(u64, i64)> {
+        let Some(Data::Sum(sum)) = &request.resource_metrics[0].scope_metrics[0].metrics[0].data
+        else {
+            panic!("expected a sum");
+        };
+        sum.data_points
+            .iter()
+            .map(|point| match point.value {
+                Some(Value::AsInt(value)) => (point.start_time_unix_nano, value),
+                _ => panic!("expected an int"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cumulative_counter_becomes_deltas() {
+        let accumulator = DeltaAccumulator::default();
+        let mut deltas = Vec::new();
+        for (time, cumulative) in [(200, 5), (300, 8), (400, 8), (500, 20), (600, 4)] {
+            let mut request = counter(true, time, &[("europe", cumulative)]);
+            accumulator.apply(&mut request);
+            let Some(Data::Sum(sum)) =
+                &request.resource_metrics[0].scope_metrics[0].metrics[0].data
+            else {
+                panic!("expected a sum");
+            };
+            assert_eq!(sum.aggregation_temporality, DELTA);
+            deltas.extend(points(&request));
+        }
+        // The drop to 4 is a reset, sent as is
+        assert_eq!(deltas, [(100, 5), (200, 3), (300, 0), (400, 12), (100, 4)]);
+    }
+
+    #[test]
+    fn test_series_are_tracked_by_attributes() {
+        let accumulator = DeltaAccumulator::default();
+        accumulator.apply(&mut counter(true, 200, &[("europe", 5), ("domestic", 1)]));
+        let mut request = counter(true, 300, &[("domestic", 4), ("europe", 6), ("asia", 2)]);
+        accumulator.apply(&mut request);
+        assert_eq!(points(&request), [(200, 3), (200, 1), (100, 2)]);
+    }
+
+    #[test]
+    fn test_up_down_counters_stay_cumulative() {
+        let accumulator = DeltaAccumulator::default();
+        accumulator.apply(&mut counter(false, 200, &[("europe", 5)]));
+        let mut request = counter(false, 300, &[("europe", 3)]);
+        accumulator.apply(&mut request);
+        assert_eq!(points(&request), [(100, 3)]);
+    }
+
+    #[test]
+    fn test_histogram_becomes_deltas() {
+        let histogram = |time, count, sum, bucket_counts: Vec<u64>| {
+            request(Data::Histogram(Histogram {
+                data_points: vec![HistogramDataPoint {
+                    start_time_unix_nano: 100,
+                    time_unix_nano: time,
+                    count,
+                    sum: Some(sum),
+                    bucket_counts,
+                    explicit_bounds: vec![10.0],
+                    min: Some(1.0),
+                    max: Some(30.0),
+                    ..Default::default()
+                }],
+                aggregation_temporality: CUMULATIVE,
+            }))
+        };
+        let accumulator = DeltaAccumulator::default();
+        accumulator.apply(&mut histogram(200, 2, 31.0, vec![1, 1]));
+        let mut request = histogram(300, 5, 61.0, vec![3, 2]);
+        accumulator.apply(&mut request);
+
+        let Some(Data::Histogram(histogram)) =
+            &request.resource_metrics[0].scope_metrics[0].metrics[0].data
+        else {
+            panic!("expected a histogram");
+        };
+        let point = &histogram.data_points[0];
+        assert_eq!(histogram.aggregation_temporality, DELTA);
+        assert_eq!(
+            (point.start_time_unix_nano, point.count, point.sum),
+            (200, 3, Some(30.0))
+        );
+        assert_eq!(point.bucket_counts, [2, 1]);
+        assert_eq!((point.min, point.max), (None, None));
+    }
+
+    #[test]
+    fn test_exponential_histogram_becomes_deltas() {
+        let histogram = |time, count, scale, offset, bucket_counts: Vec<u64>| {
+            request(Data::ExponentialHistogram(ExponentialHistogram {
+                data_points: vec![ExponentialHistogramDataPoint {
+                    start_time_unix_nano: 100,
+                    time_unix_nano: time,
+                    count,
+                    sum: Some(count as f64),
+                    scale,
+                    positive: Some(Buckets {
+                        offset,
+                        bucket_counts,
+                    }),
+                    min: Some(0.5),
+                    max: Some(8.0),
+                    ..Default::default()
+                }],
+                aggregation_temporality: CUMULATIVE,
+            }))
+        };
+        let accumulator = DeltaAccumulator::default();
+        accumulator.apply(&mut histogram(200, 3, 1, -1, vec![1, 1, 1]));
+        // New observations widened the range, so the SDK halved the
+        // resolution: buckets -1, 0 and 1 at scale 1 are -1, 0 and 0 at scale 0
+        let mut request = histogram(300, 7, 0, -1, vec![2, 3, 2]);
+        accumulator.apply(&mut request);
+
+        let Some(Data::ExponentialHistogram(histogram)) =
+            &request.resource_metrics[0].scope_metrics[0].metrics[0].data
+        else {
+            panic!("expected an exponential histogram");
+        };
+        let point = &histogram.data_points[0];
+        assert_eq!(histogram.aggregation_temporality, DELTA);
+        assert_eq!(
+            (point.start_time_unix_nano, point.count, point.sum),
+            (200, 4, Some(4.0))
+        );
+        let positive = point.positive.as_ref().unwrap();
+        assert_eq!(
+            (positive.offset, &positive.bucket_counts[..]),
+            (-1, &[1, 1, 2][..])
+        );
+        assert_eq!((point.min, point.max), (None, None));
+    }
+
+    #[derive(Default, Clone)]
+    struct RecordingMetricsService {
+        requests: Arc<Mutex<Vec<ExportMetricsServiceRequest>>>,
+    }
+
+    #[tonic::async_trait]
+    impl MetricsService for RecordingMetricsService {
+        async fn export(
+            &self,
+            request: tonic::Request<ExportMetricsServiceRequest>,
+        ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+            self.requests.lock().unwrap().push(request.into_inner());
+            Ok(Response::new(ExportMetricsServiceResponse::default()))
+        }
+    }
+
+    fn sent_points(service: &RecordingMetricsService) -> Vec<(i32, i64)> {
+        service
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|request| &request.resource_metrics)
+            .flat_map(|resource| &resource.scope_metrics)
+            .flat_map(|scope| &scope.metrics)
+            .filter_map(|metric| match &metric.data {
+                Some(Data::Sum(sum)) => Some(sum),
+                _ => None,
+            })
+            .flat_map(|sum| {
+                sum.data_points.iter().map(|point| match point.value {
+                    Some(Value::AsInt(value)) => (sum.aggregation_temporality, value),
+                    _ => panic!("expected an int"),
+                })
+            })
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_exporter_sends_deltas() {
+        let service = RecordingMetricsService::default();
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(MetricsServiceServer::new(service.clone()))
+                .serve_with_incoming(incoming),
+        );
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .connect_lazy();
+
+        let provider = SdkMeterProvider::builder()
+            .with_reader(
+                PeriodicReader::builder(DeltaMetricExporter::new(channel, MetadataMap::new()))
+                    .build(),
+            )
+            .build();
+        let counter = provider.meter("test").u64_counter("items").build();
+        // Flushing blocks until the reader's thread has exported, which needs
+        // this runtime to drive the gRPC channel
+        for items in [5, 3] {
+            counter.add(items, &[SdkKeyValue::new("shipping.zone", "europe")]);
+            let provider = provider.clone();
+            tokio::task::spawn_blocking(move || provider.force_flush())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        assert_eq!(sent_points(&service), [(DELTA, 5), (DELTA, 3)]);
+        tokio::task::spawn_blocking(move || provider.shutdown())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
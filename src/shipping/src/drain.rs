@@ -0,0 +1,315 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServerHandle, ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    post, web, Error, HttpResponse, Responder,
+};
+use opentelemetry::metrics::Meter;
+use tracing::{info, warn};
+
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+use crate::utils::spawn_with_context;
+
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Registers `POST /shutdown` when `enabled`. It and `track_in_flight` read
+/// the `Drain` in the app data.
+pub fn configure(enabled: bool) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        if enabled {
+            cfg.service(shutdown);
+        }
+    }
+}
+
+/// Counts in-flight requests, so a shutdown can wait for them before
+/// stopping the server.
+pub struct Drain {
+    draining: AtomicBool,
+    in_flight: Arc<AtomicUsize>,
+    timeout: Duration,
+    server: Mutex<Option<ServerHandle>>,
+}
+
+/// Decrements the in-flight count when the request is done.
+struct RequestGuard(Arc<AtomicUsize>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Drain {
+    pub fn new(timeout: Duration) -> Self {
+        Drain {
+            draining: AtomicBool::new(false),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            timeout,
+            server: Mutex::new(None),
+        }
+    }
+
+    /// The server to stop once drained. Without one, draining only rejects
+    /// new requests.
+    pub fn set_server(&self, server: ServerHandle) {
+        *self.server.lock().unwrap() = Some(server);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    fn enter(&self) -> RequestGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        RequestGuard(self.in_flight.clone())
+    }
+
+    pub fn register_in_flight_gauge(&self, meter: &Meter) {
+        let in_flight = self.in_flight.clone();
+        meter
+            .u64_observable_gauge(checked_instrument_name("http_requests_in_flight"))
+            .with_description("Requests being handled, which a shutdown waits for")
+            .with_callback(move |observer| {
+                observer.observe(
+                    in_flight.load(Ordering::SeqCst) as u64,
+                    &KUBERNETES_LABELS.as_key_values(),
+                )
+            })
+            .build();
+    }
+
+    /// Returns false if a drain had already started.
+    fn start(&self) -> bool {
+        !self.draining.swap(true, Ordering::SeqCst)
+    }
+
+    /// Waits up to the drain timeout for in-flight requests to finish.
+    /// Returns false if some were still running then.
+    pub async fn wait_idle(&self) -> bool {
+        let deadline = Instant::now() + self.timeout;
+        while self.in_flight() > 0 {
+            if Instant::now() >= deadline {
+                warn!(
+                    name = "DrainTimedOut",
+                    in_flight = self.in_flight(),
+                    timeout_secs = self.timeout.as_secs_f64(),
+                    message = "Stopping with requests still in flight"
+                );
+                return false;
+            }
+            tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+        }
+        true
+    }
+
+    /// Stops the server, after which `main` flushes OTel and exits.
+    async fn stop_server(&self) {
+        let server = self.server.lock().unwrap().take();
+        if let Some(server) = server {
+            server.stop(true).await;
+        }
+    }
+}
+
+/// Counts the request as in flight, or answers 503 once a drain has
+/// started.
+pub async fn track_in_flight(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(drain) = req.app_data::<web::Data<Drain>>().cloned() else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    if drain.is_draining() {
+        let res = ErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "shutting_down",
+            "Shutting down",
+            CorrelationId::of(req.request()),
+        )
+        .into_response();
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    let _guard = drain.enter();
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/// Starts draining: new requests get 503, and once in-flight ones finish
+/// (or `DRAIN_TIMEOUT_SECS` passes) the server stops.
+#[post("/shutdown")]
+pub async fn shutdown(drain: web::Data<Drain>) -> impl Responder {
+    if drain.start() {
+        info!(
+            name = "DrainStarted",
+            in_flight = drain.in_flight(),
+            timeout_secs = drain.timeout.as_secs_f64(),
+            message = "Draining requests before shutdown"
+        );
+        let drain = drain.into_inner();
+        spawn_with_context(async move {
+            drain.wait_idle().await;
+            drain.stop_server().await;
+        });
+    }
+    HttpResponse::Accepted().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use actix_web::{middleware::from_fn, test, App};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{
+        data::{AggregatedMetrics, MetricData},
+        InMemoryMetricExporter,
+    };
+
+    use super::*;
+    use crate::error_response::tests::assert_error;
+    use crate::test_utils::test_meter_provider;
+
+    async fn slow() -> HttpResponse {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        HttpResponse::Ok().body("done")
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_disabled() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Drain::new(DEFAULT_DRAIN_TIMEOUT)))
+                .configure(configure(false)),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/shutdown").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_drain_finishes_in_flight_and_rejects_new() {
+        let drain = web::Data::new(Drain::new(DEFAULT_DRAIN_TIMEOUT));
+        let app = Rc::new(
+            test::init_service(
+                App::new()
+                    .app_data(drain.clone())
+                    .wrap(from_fn(track_in_flight))
+                    .route("/slow", web::get().to(slow))
+                    .configure(configure(true)),
+            )
+            .await,
+        );
+
+        let in_flight = actix_web::rt::spawn({
+            let app = app.clone();
+            async move {
+                let req = test::TestRequest::get().uri("/slow").to_request();
+                let resp = test::call_service(&*app, req).await;
+                (resp.status(), test::read_body(resp).await)
+            }
+        });
+        while drain.in_flight() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let req = test::TestRequest::post().uri("/shutdown").to_request();
+        let resp = test::call_service(&*app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert!(drain.is_draining());
+
+        let req = test::TestRequest::get().uri("/slow").to_request();
+        let resp = test::call_service(&*app, req).await;
+        assert_error(resp, StatusCode::SERVICE_UNAVAILABLE, "shutting_down").await;
+
+        let (status, body) = in_flight.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "done");
+        assert!(drain.wait_idle().await);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_waits_for_slow_handler() {
+        let (provider, exporter) = test_meter_provider();
+        let drain = web::Data::new(Drain::new(DEFAULT_DRAIN_TIMEOUT));
+        drain.register_in_flight_gauge(&provider.meter("test"));
+        let app = Rc::new(
+            test::init_service(
+                App::new()
+                    .app_data(drain.clone())
+                    .wrap(from_fn(track_in_flight))
+                    .route("/slow", web::get().to(slow))
+                    .configure(configure(true)),
+            )
+            .await,
+        );
+
+        let started = Instant::now();
+        let slow_request = actix_web::rt::spawn({
+            let app = app.clone();
+            async move {
+                let req = test::TestRequest::get().uri("/slow").to_request();
+                test::call_service(&*app, req).await.status()
+            }
+        });
+        while drain.in_flight() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        provider.force_flush().unwrap();
+        assert_eq!(in_flight_gauge(&exporter), 1);
+
+        let req = test::TestRequest::post().uri("/shutdown").to_request();
+        test::call_service(&*app, req).await;
+        assert!(drain.wait_idle().await);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(slow_request.is_finished());
+        assert_eq!(slow_request.await.unwrap(), StatusCode::OK);
+
+        exporter.reset();
+        provider.force_flush().unwrap();
+        assert_eq!(in_flight_gauge(&exporter), 0);
+    }
+
+    fn in_flight_gauge(exporter: &InMemoryMetricExporter) -> u64 {
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metric = metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .find(|m| m.name() == "http_requests_in_flight")
+            .unwrap();
+        let AggregatedMetrics::U64(MetricData::Gauge(gauge)) = metric.data() else {
+            panic!("http_requests_in_flight is not a u64 gauge");
+        };
+        let value = gauge.data_points().next().unwrap().value();
+        value
+    }
+
+    #[actix_web::test]
+    async fn test_wait_idle_times_out() {
+        let drain = Drain::new(Duration::from_millis(50));
+        let _guard = drain.enter();
+        assert!(!drain.wait_idle().await);
+    }
+}
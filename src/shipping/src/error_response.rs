@@ -0,0 +1,154 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{
+    body::BoxBody,
+    error::{InternalError, JsonPayloadError},
+    http::StatusCode,
+    Error, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use opentelemetry::trace::{get_active_span, TraceId};
+use serde::Serialize;
+
+use crate::correlation_id::CorrelationId;
+
+/// JSON body of every 4xx and 5xx a handler returns. `code` is stable for
+/// clients to match on, and `trace_id` lets a frontend error be traced back
+/// to the request.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorResponse {
+    #[serde(skip)]
+    status: StatusCode,
+    pub code: String,
+    pub message: String,
+    /// The active trace, empty outside one.
+    pub trace_id: String,
+    /// The request's correlation ID.
+    pub request_id: String,
+}
+
+impl ErrorResponse {
+    pub fn new(
+        status: StatusCode,
+        code: &str,
+        message: impl Into<String>,
+        request_id: CorrelationId,
+    ) -> Self {
+        let trace_id = get_active_span(|span| span.span_context().trace_id());
+        ErrorResponse {
+            status,
+            code: code.to_string(),
+            message: message.into(),
+            trace_id: if trace_id == TraceId::INVALID {
+                String::new()
+            } else {
+                trace_id.to_string()
+            },
+            request_id: request_id.to_string(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        HttpResponse::build(self.status).json(self)
+    }
+}
+
+impl Responder for ErrorResponse {
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        self.into_response()
+    }
+}
+
+/// `JsonConfig::error_handler` answering bodies `web::Json` can't read
+/// with an `ErrorResponse`.
+pub fn json_error(err: JsonPayloadError, req: &HttpRequest) -> Error {
+    let status = err.status_code();
+    let code = match status {
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        _ => "invalid_json",
+    };
+    let response =
+        ErrorResponse::new(status, code, err.to_string(), CorrelationId::of(req)).into_response();
+    InternalError::from_response(err, response).into()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use actix_web::{body::MessageBody, dev::ServiceResponse, test};
+    use opentelemetry::{
+        trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceState},
+        Context,
+    };
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// Asserts `resp` is an `ErrorResponse` with `status` and `code`, and
+    /// returns its body.
+    pub async fn assert_error<B: MessageBody>(
+        resp: ServiceResponse<B>,
+        status: StatusCode,
+        code: &str,
+    ) -> Value {
+        assert_eq!(resp.status(), status);
+        let body: Value = test::read_body_json(resp).await;
+        let mut keys: Vec<_> = body.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            ["code", "message", "request_id", "trace_id"],
+            "{body}"
+        );
+        assert_eq!(body["code"], code, "{body}");
+        assert!(!body["message"].as_str().unwrap().is_empty(), "{body}");
+        assert!(!body["request_id"].as_str().unwrap().is_empty(), "{body}");
+        body
+    }
+
+    #[actix_web::test]
+    async fn test_error_response_shape() {
+        let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+        let request_id = CorrelationId(Uuid::new_v4());
+        let error = {
+            let _guard = Context::current()
+                .with_remote_span_context(SpanContext::new(
+                    trace_id,
+                    SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+                    TraceFlags::SAMPLED,
+                    true,
+                    TraceState::default(),
+                ))
+                .attach();
+            ErrorResponse::new(StatusCode::CONFLICT, "conflict", "Already done", request_id)
+        };
+        let resp = ServiceResponse::new(
+            test::TestRequest::default().to_http_request(),
+            error.into_response(),
+        );
+
+        let body = assert_error(resp, StatusCode::CONFLICT, "conflict").await;
+        assert_eq!(body["message"], "Already done");
+        assert_eq!(body["trace_id"], trace_id.to_string());
+        assert_eq!(body["request_id"], request_id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_trace_id_empty_outside_trace() {
+        let error = ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+            "No",
+            CorrelationId(Uuid::new_v4()),
+        );
+        assert_eq!(error.trace_id, "");
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    web, Error,
+};
+use opentelemetry::{metrics::Meter, Context};
+use opentelemetry_sdk::{
+    error::OTelSdkResult,
+    trace::{Span, SpanData, SpanExporter, SpanProcessor},
+    Resource,
+};
+use tracing::{info, warn};
+
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+pub const DEFAULT_OTEL_QUEUE_WARN_RATIO: f64 = 0.8;
+/// The batch span processor's default `OTEL_BSP_MAX_QUEUE_SIZE`.
+const DEFAULT_MAX_QUEUE_SIZE: usize = 2048;
+
+/// Watcher for the default OTLP span exporter's queue, shared by the tracer
+/// provider and the backpressure middleware.
+pub static EXPORT_QUEUE: LazyLock<Arc<QueueDepthWatcher>> =
+    LazyLock::new(|| Arc::new(QueueDepthWatcher::from_env()));
+
+/// Approximates how full a batch span processor's queue is, which the SDK
+/// doesn't expose: spans ended minus spans handed to the exporter. The
+/// processor drops spans once its queue is full, so the count never goes
+/// past the queue's capacity.
+#[derive(Debug)]
+pub struct QueueDepthWatcher {
+    capacity: usize,
+    warn_ratio: f64,
+    queued: AtomicUsize,
+    /// Whether the fill ratio is above `warn_ratio`, so each crossing is
+    /// logged once.
+    saturated: AtomicBool,
+}
+
+impl QueueDepthWatcher {
+    pub fn new(capacity: usize, warn_ratio: f64) -> Self {
+        QueueDepthWatcher {
+            capacity: capacity.max(1),
+            warn_ratio,
+            queued: AtomicUsize::new(0),
+            saturated: AtomicBool::new(false),
+        }
+    }
+
+    /// Reads the capacity from `OTEL_BSP_MAX_QUEUE_SIZE`, like the SDK, and
+    /// the threshold from `OTEL_QUEUE_WARN_RATIO`, defaulting to 0.8.
+    pub fn from_env() -> Self {
+        let capacity = env::var("OTEL_BSP_MAX_QUEUE_SIZE")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_QUEUE_SIZE);
+        let warn_ratio = env::var("OTEL_QUEUE_WARN_RATIO")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|ratio: &f64| *ratio > 0.0 && *ratio <= 1.0)
+            .unwrap_or(DEFAULT_OTEL_QUEUE_WARN_RATIO);
+        Self::new(capacity, warn_ratio)
+    }
+
+    fn enqueued(&self) {
+        let _ = self
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+                Some((queued + 1).min(self.capacity))
+            });
+        self.check_fill_ratio();
+    }
+
+    fn exported(&self, spans: usize) {
+        let _ = self
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+                Some(queued.saturating_sub(spans))
+            });
+        self.check_fill_ratio();
+    }
+
+    pub fn fill_ratio(&self) -> f64 {
+        self.queued.load(Ordering::Relaxed) as f64 / self.capacity as f64
+    }
+
+    /// Whether the fill ratio is above `OTEL_QUEUE_WARN_RATIO`.
+    pub fn is_saturated(&self) -> bool {
+        self.fill_ratio() > self.warn_ratio
+    }
+
+    fn check_fill_ratio(&self) {
+        let fill_ratio = self.fill_ratio();
+        let saturated = fill_ratio > self.warn_ratio;
+        if self.saturated.swap(saturated, Ordering::Relaxed) == saturated {
+            return;
+        }
+        if saturated {
+            warn!(
+                name = "OtelExportQueueFilling",
+                queue_fill_ratio = fill_ratio,
+                queue_warn_ratio = self.warn_ratio,
+                message = "Span export queue is nearly full, rejecting new requests"
+            );
+        } else {
+            info!(
+                name = "OtelExportQueueDrained",
+                queue_fill_ratio = fill_ratio,
+                message = "Span export queue is back under the warning ratio"
+            );
+        }
+    }
+
+    pub fn register_fill_ratio_gauge(self: &Arc<Self>, meter: &Meter) {
+        let watcher = self.clone();
+        meter
+            .f64_observable_gauge(checked_instrument_name("otel_exporter_queue_fill_ratio"))
+            .with_description("Approximate fill ratio of the OTLP span export queue")
+            .with_callback(move |observer| {
+                observer.observe(watcher.fill_ratio(), &KUBERNETES_LABELS.as_key_values())
+            })
+            .build();
+    }
+}
+
+/// Counts the spans ended into `inner`, the batch processor whose queue
+/// `watcher` tracks.
+#[derive(Debug)]
+pub struct QueueCountingProcessor {
+    inner: Box<dyn SpanProcessor>,
+    watcher: Arc<QueueDepthWatcher>,
+}
+
+impl QueueCountingProcessor {
+    pub fn new(inner: Box<dyn SpanProcessor>, watcher: Arc<QueueDepthWatcher>) -> Self {
+        QueueCountingProcessor { inner, watcher }
+    }
+}
+
+impl SpanProcessor for QueueCountingProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        // The batch processor drops unsampled spans before queueing them
+        if span.span_context.is_sampled() {
+            self.watcher.enqueued();
+        }
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+/// Counts the spans the batch processor takes off its queue to export.
+#[derive(Debug)]
+pub struct QueueCountingExporter<E> {
+    inner: E,
+    watcher: Arc<QueueDepthWatcher>,
+}
+
+impl<E> QueueCountingExporter<E> {
+    pub fn new(inner: E, watcher: Arc<QueueDepthWatcher>) -> Self {
+        QueueCountingExporter { inner, watcher }
+    }
+}
+
+impl<E: SpanExporter> SpanExporter for QueueCountingExporter<E> {
+    fn export(
+        &self,
+        batch: Vec<SpanData>,
+    ) -> impl std::future::Future<Output = OTelSdkResult> + Send {
+        self.watcher.exported(batch.len());
+        self.inner.export(batch)
+    }
+
+    fn shutdown_with_timeout(&mut self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn force_flush(&mut self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+/// Answers 503 while the app's `QueueDepthWatcher` is saturated, so spans
+/// aren't dropped for requests that can wait. Health checks are always
+/// served.
+pub async fn export_backpressure(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let saturated = req
+        .app_data::<web::Data<QueueDepthWatcher>>()
+        .is_some_and(|watcher| watcher.is_saturated());
+    if saturated && !req.path().starts_with("/health") {
+        let res = ErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "export_queue_full",
+            "Telemetry export queue is full",
+            CorrelationId::of(req.request()),
+        )
+        .into_response();
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, App, HttpResponse};
+    use opentelemetry::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SimpleSpanProcessor};
+    use tracing::Level;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::error_response::tests::assert_error;
+    use crate::test_utils::CaptureLayer;
+
+    #[test]
+    fn test_fill_ratio_threshold() {
+        let watcher = QueueDepthWatcher::new(10, 0.8);
+        for _ in 0..8 {
+            watcher.enqueued();
+        }
+        assert_eq!(watcher.fill_ratio(), 0.8);
+        assert!(!watcher.is_saturated());
+        watcher.enqueued();
+        assert!(watcher.is_saturated());
+
+        // Dropped spans don't count past the capacity
+        for _ in 0..5 {
+            watcher.enqueued();
+        }
+        assert_eq!(watcher.fill_ratio(), 1.0);
+        watcher.exported(4);
+        assert_eq!(watcher.fill_ratio(), 0.6);
+        assert!(!watcher.is_saturated());
+        watcher.exported(20);
+        assert_eq!(watcher.fill_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_crossing_the_threshold_logs_once() {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let watcher = QueueDepthWatcher::new(4, 0.5);
+            for _ in 0..6 {
+                watcher.enqueued();
+            }
+            watcher.exported(4);
+        });
+
+        let events = capture.events.lock().unwrap();
+        let logged: Vec<_> = events
+            .iter()
+            .map(|(level, fields)| (*level, fields["name"].as_str()))
+            .collect();
+        assert_eq!(
+            logged,
+            [
+                (Level::WARN, "OtelExportQueueFilling"),
+                (Level::INFO, "OtelExportQueueDrained")
+            ]
+        );
+        assert_eq!(events[0].1["queue_fill_ratio"], "0.75");
+    }
+
+    #[test]
+    fn test_counts_spans_through_the_processor_and_exporter() {
+        let watcher = Arc::new(QueueDepthWatcher::new(10, 0.8));
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(QueueCountingProcessor::new(
+                Box::new(SimpleSpanProcessor::new(QueueCountingExporter::new(
+                    exporter.clone(),
+                    watcher.clone(),
+                ))),
+                watcher.clone(),
+            ))
+            .build();
+        provider.tracer("test").in_span("quote", |_| {});
+        assert_eq!(exporter.get_finished_spans().unwrap().len(), 1);
+        assert_eq!(watcher.fill_ratio(), 0.0);
+    }
+
+    #[actix_web::test]
+    async fn test_saturated_queue_rejects_requests() {
+        use actix_web::test;
+
+        let watcher = web::Data::new(QueueDepthWatcher::new(2, 0.4));
+        let app = test::init_service(
+            App::new()
+                .app_data(watcher.clone())
+                .wrap(from_fn(export_backpressure))
+                .route("/get-quote", web::post().to(HttpResponse::Ok))
+                .route("/health/live", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let quote = || test::TestRequest::post().uri("/get-quote").to_request();
+        assert_eq!(
+            test::call_service(&app, quote()).await.status(),
+            StatusCode::OK
+        );
+
+        watcher.enqueued();
+        assert_error(
+            test::call_service(&app, quote()).await,
+            StatusCode::SERVICE_UNAVAILABLE,
+            "export_queue_full",
+        )
+        .await;
+        let live = test::TestRequest::get().uri("/health/live").to_request();
+        assert_eq!(
+            test::call_service(&app, live).await.status(),
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+use std::time::Duration;
+
+use actix_web::{get, HttpResponse, Responder};
+use anyhow::{anyhow, bail, Context, Result};
+
+/// The argument that makes the binary probe a running instance instead of
+/// serving, for the image's `HEALTHCHECK`. The distroless image has no
+/// shell or `curl` to do it.
+pub const HEALTHCHECK_ARG: &str = "--healthcheck";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Answers 200 as long as the server is handling requests.
+#[get("/health/live")]
+pub async fn liveness() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+/// Fails unless `GET {base_url}/health/live` answers with a success.
+pub async fn probe(base_url: &str) -> Result<()> {
+    let resp = awc::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .finish()
+        .get(format!("{base_url}/health/live"))
+        .send()
+        .await
+        .map_err(|err| anyhow!("Failed to reach the service: {err}"))?;
+    if !resp.status().is_success() {
+        bail!("Liveness check answered {}", resp.status());
+    }
+    Ok(())
+}
+
+/// Probes the instance listening on `SHIPPING_PORT` on this host.
+pub async fn probe_local() -> Result<()> {
+    let port = env::var("SHIPPING_PORT").context("SHIPPING_PORT is not set")?;
+    probe(&format!("http://127.0.0.1:{port}")).await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, App};
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_live() {
+        let app = test::init_service(App::new().service(liveness)).await;
+        let req = test::TestRequest::get().uri("/health/live").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_probe() {
+        let server = MockServer::start_async().await;
+        let live = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/health/live");
+                then.status(200);
+            })
+            .await;
+        probe(&server.base_url()).await.unwrap();
+
+        live.delete_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/health/live");
+                then.status(503);
+            })
+            .await;
+        let err = probe(&server.base_url()).await.unwrap_err();
+        assert!(err.to_string().contains("503"), "{err}");
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::{resource::ResourceDetector, Resource};
+use opentelemetry_semantic_conventions::resource::{
+    HOST_ARCH, HOST_NAME, OS_DESCRIPTION, OS_TYPE, OS_VERSION,
+};
+
+/// `host.arch` for a `std::env::consts::ARCH`, using the semantic
+/// convention's names where they differ from Rust's.
+fn host_arch(arch: &str) -> &str {
+    match arch {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "arm" => "arm32",
+        "powerpc" => "ppc32",
+        "powerpc64" => "ppc64",
+        arch => arch,
+    }
+}
+
+/// Value of `key` in an os-release file, unquoted.
+fn os_release_value<'a>(os_release: &'a str, key: &str) -> Option<&'a str> {
+    os_release.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix('=')?;
+        Some(value.trim().trim_matches('"'))
+    })
+}
+
+/// Detects `host.name`, `host.arch`, `os.type`, `os.version` and
+/// `os.description` (the kernel release).
+///
+/// Files are read under `root`, so tests can point it at a fake filesystem.
+#[derive(Debug, Clone)]
+pub struct HostResourceDetector {
+    root: PathBuf,
+    hostname: Option<String>,
+}
+
+impl Default for HostResourceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostResourceDetector {
+    /// Uses `HOSTNAME` if set, the kernel's hostname otherwise.
+    pub fn new() -> Self {
+        Self::with_root("/", env::var("HOSTNAME").ok())
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>, hostname: Option<String>) -> Self {
+        HostResourceDetector {
+            root: root.into(),
+            hostname: hostname.filter(|name| !name.trim().is_empty()),
+        }
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        let contents = fs::read_to_string(self.root.join(path)).ok()?;
+        let contents = contents.trim();
+        (!contents.is_empty()).then(|| contents.to_string())
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.hostname
+            .clone()
+            .or_else(|| self.read("proc/sys/kernel/hostname"))
+            .or_else(|| self.read("etc/hostname"))
+    }
+}
+
+impl ResourceDetector for HostResourceDetector {
+    fn detect(&self) -> Resource {
+        let mut attributes = vec![
+            KeyValue::new(HOST_ARCH, host_arch(env::consts::ARCH).to_string()),
+            KeyValue::new(OS_TYPE, env::consts::OS),
+        ];
+        if let Some(hostname) = self.hostname() {
+            attributes.push(KeyValue::new(HOST_NAME, hostname));
+        }
+        if let Some(os_release) = self
+            .read("etc/os-release")
+            .or_else(|| self.read("usr/lib/os-release"))
+        {
+            if let Some(version) = os_release_value(&os_release, "VERSION_ID") {
+                attributes.push(KeyValue::new(OS_VERSION, version.to_string()));
+            }
+        }
+        if let Some(kernel) = self.read("proc/sys/kernel/osrelease") {
+            attributes.push(KeyValue::new(OS_DESCRIPTION, kernel));
+        }
+        Resource::builder_empty()
+            .with_attributes(attributes)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::{Key, Value};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn mock_root(files: &[(&str, &str)]) -> PathBuf {
+        let root = env::temp_dir().join(format!("host-{}", Uuid::new_v4()));
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    fn get(resource: &Resource, key: &'static str) -> Option<Value> {
+        resource.get(&Key::from_static_str(key))
+    }
+
+    #[test]
+    fn test_detect_from_files() {
+        let root = mock_root(&[
+            ("proc/sys/kernel/hostname", "shipping-7d9f\n"),
+            ("proc/sys/kernel/osrelease", "6.1.0-18-amd64\n"),
+            (
+                "etc/os-release",
+                "PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nVERSION_ID=\"12\"\nID=debian\n",
+            ),
+        ]);
+        let resource = HostResourceDetector::with_root(&root, None).detect();
+
+        assert_eq!(get(&resource, HOST_NAME), Some("shipping-7d9f".into()));
+        assert_eq!(get(&resource, OS_VERSION), Some("12".into()));
+        assert_eq!(
+            get(&resource, OS_DESCRIPTION),
+            Some("6.1.0-18-amd64".into())
+        );
+        assert_eq!(get(&resource, OS_TYPE), Some(env::consts::OS.into()));
+        assert_eq!(
+            get(&resource, HOST_ARCH),
+            Some(host_arch(env::consts::ARCH).to_string().into())
+        );
+    }
+
+    #[test]
+    fn test_hostname_env_wins() {
+        let root = mock_root(&[("etc/hostname", "from-file\n")]);
+        let resource =
+            HostResourceDetector::with_root(&root, Some("from-env".to_string())).detect();
+        assert_eq!(get(&resource, HOST_NAME), Some("from-env".into()));
+
+        let resource = HostResourceDetector::with_root(&root, Some(" ".to_string())).detect();
+        assert_eq!(get(&resource, HOST_NAME), Some("from-file".into()));
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let resource = HostResourceDetector::with_root(mock_root(&[]), None).detect();
+        assert_eq!(get(&resource, HOST_NAME), None);
+        assert_eq!(get(&resource, OS_VERSION), None);
+        assert!(get(&resource, HOST_ARCH).is_some());
+    }
+
+    #[test]
+    fn test_host_arch() {
+        assert_eq!(host_arch("x86_64"), "amd64");
+        assert_eq!(host_arch("aarch64"), "arm64");
+        assert_eq!(host_arch("s390x"), "s390x");
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use base64::{
+    alphabet,
+    engine::{general_purpose::GeneralPurpose, DecodePaddingMode, GeneralPurposeConfig},
+    Engine,
+};
+use opentelemetry::{
+    propagation::{text_map_propagator::FieldIter, Extractor, Injector, TextMapPropagator},
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use tracing::debug;
+
+pub const INTERNAL_CONTEXT_HEADER: &str = "x-internal-context";
+
+/// Unpadded on the way out, padding optional on the way in.
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Carries the span context in a single `X-Internal-Context` header, as
+/// `base64(trace_id:span_id:flags)`, for internal proxies that strip the
+/// standard W3C headers.
+#[derive(Debug)]
+pub struct InternalContextPropagator {
+    fields: [String; 1],
+}
+
+impl Default for InternalContextPropagator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InternalContextPropagator {
+    pub fn new() -> Self {
+        InternalContextPropagator {
+            fields: [INTERNAL_CONTEXT_HEADER.to_string()],
+        }
+    }
+}
+
+pub fn encode(span_context: &SpanContext) -> String {
+    ENGINE.encode(format!(
+        "{}:{}:{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    ))
+}
+
+pub fn decode(value: &str) -> Result<SpanContext, String> {
+    let decoded = ENGINE
+        .decode(value.trim())
+        .map_err(|e| format!("invalid base64: {e}"))?;
+    let decoded = String::from_utf8(decoded).map_err(|e| format!("invalid UTF-8: {e}"))?;
+
+    let mut parts = decoded.split(':');
+    let (Some(trace_id), Some(span_id), Some(flags), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!("expected trace_id:span_id:flags, got {decoded:?}"));
+    };
+
+    let trace_id = TraceId::from_hex(trace_id).map_err(|e| format!("invalid trace id: {e}"))?;
+    let span_id = SpanId::from_hex(span_id).map_err(|e| format!("invalid span id: {e}"))?;
+    let flags = u8::from_str_radix(flags, 16).map_err(|e| format!("invalid flags: {e}"))?;
+
+    let span_context = SpanContext::new(
+        trace_id,
+        span_id,
+        TraceFlags::new(flags) & TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    if !span_context.is_valid() {
+        return Err("trace id and span id must be non-zero".to_string());
+    }
+    Ok(span_context)
+}
+
+impl TextMapPropagator for InternalContextPropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let span_context = span.span_context();
+        if span_context.is_valid() {
+            injector.set(INTERNAL_CONTEXT_HEADER, encode(span_context));
+        }
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        let Some(value) = extractor.get(INTERNAL_CONTEXT_HEADER) else {
+            return cx.clone();
+        };
+        match decode(value) {
+            Ok(span_context) => cx.with_remote_span_context(span_context),
+            Err(error) => {
+                debug!(
+                    name = "InvalidInternalContext",
+                    error = error.as_str(),
+                    message = "Ignoring unparseable X-Internal-Context header"
+                );
+                cx.clone()
+            }
+        }
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(&self.fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn span_context(flags: TraceFlags) -> SpanContext {
+        SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            flags,
+            true,
+            TraceState::default(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for flags in [TraceFlags::SAMPLED, TraceFlags::NOT_SAMPLED] {
+            let original = span_context(flags);
+            let mut carrier = HashMap::new();
+            InternalContextPropagator::new().inject_context(
+                &Context::new().with_remote_span_context(original.clone()),
+                &mut carrier,
+            );
+
+            let encoded = &carrier[INTERNAL_CONTEXT_HEADER];
+            assert!(!encoded.ends_with('='));
+            assert_eq!(decode(encoded).unwrap(), original);
+
+            let cx = InternalContextPropagator::new().extract(&carrier);
+            assert_eq!(cx.span().span_context(), &original);
+        }
+    }
+
+    #[test]
+    fn test_decode_accepts_padding() {
+        let padded = base64::engine::general_purpose::STANDARD
+            .encode("4bf92f3577b34da6a3ce929d0e0e4736:00f067aa0ba902b7:01");
+        assert_eq!(decode(&padded).unwrap(), span_context(TraceFlags::SAMPLED));
+    }
+
+    #[test]
+    fn test_decode_rejects_corrupted_values() {
+        let valid = encode(&span_context(TraceFlags::SAMPLED));
+        let zero = ENGINE.encode(format!("{}:{}:01", TraceId::INVALID, SpanId::INVALID));
+        for value in [
+            "",
+            "not base64!",
+            &valid[..valid.len() / 2],
+            &ENGINE.encode("4bf92f3577b34da6a3ce929d0e0e4736:00f067aa0ba902b7"),
+            &ENGINE.encode("4bf92f3577b34da6a3ce929d0e0e4736:zz:01:extra"),
+            &ENGINE.encode("nothex:00f067aa0ba902b7:01"),
+            &zero,
+        ] {
+            assert!(decode(value).is_err(), "{value:?} should not decode");
+        }
+    }
+
+    #[test]
+    fn test_extract_ignores_invalid_header() {
+        let carrier = HashMap::from([(
+            INTERNAL_CONTEXT_HEADER.to_string(),
+            "%%%garbage".to_string(),
+        )]);
+        let cx = InternalContextPropagator::new().extract(&carrier);
+        assert!(!cx.span().span_context().is_valid());
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "tikv-jemallocator")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+pub mod admin;
+pub mod audit;
+pub mod baggage;
+pub mod cache_control;
+pub mod compression;
+pub mod config;
+pub mod config_api;
+pub mod correlation_id;
+pub mod cors;
+pub mod cpu_metrics;
+pub mod debug;
+pub mod delta_metrics;
+pub mod drain;
+pub mod error_response;
+pub mod export_queue;
+pub mod health;
+pub mod host_resource;
+pub mod internal_context;
+pub mod log_bridge;
+pub mod memory_profiling;
+pub mod metrics;
+pub mod metrics_utils;
+pub mod otel_context;
+pub mod panic_hook;
+#[cfg(feature = "prometheus_push")]
+pub mod prometheus_push;
+pub mod rate_limiter;
+pub mod request_duration;
+pub mod resource_detection;
+pub mod route_sampler;
+pub mod runtime_metrics;
+pub mod security_headers;
+pub mod shipping_service;
+pub mod slo;
+pub mod span_processors;
+pub mod span_status;
+pub mod stats;
+pub mod telemetry_conf;
+pub mod test_trace_context;
+#[cfg(test)]
+mod test_utils;
+pub mod utils;
+pub mod version;
@@ -0,0 +1,120 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity};
+use opentelemetry_sdk::logs::{SdkLogger, SdkLoggerProvider};
+
+/// OTel severity for a `log` crate level.
+///
+/// A free function because the orphan rule rules out
+/// `impl From<log::Level> for Severity` outside either crate.
+pub fn severity(level: Level) -> Severity {
+    match level {
+        Level::Error => Severity::Error,
+        Level::Warn => Severity::Warn,
+        Level::Info => Severity::Info,
+        Level::Debug => Severity::Debug,
+        Level::Trace => Severity::Trace,
+    }
+}
+
+/// Forwards records from the `log` crate, used by actix and other
+/// dependencies, to OTel. The record's `target` (the emitting module path)
+/// is kept as the `logger.name` attribute.
+pub struct OtelLogBridge {
+    logger: SdkLogger,
+    max_level: LevelFilter,
+}
+
+impl OtelLogBridge {
+    pub fn new(provider: &SdkLoggerProvider, max_level: LevelFilter) -> Self {
+        OtelLogBridge {
+            logger: provider.logger("otel_demo.shipping.log"),
+            max_level,
+        }
+    }
+
+    /// Installs the bridge as the global `log` logger.
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        let max_level = self.max_level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for OtelLogBridge {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.max_level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut log_record = self.logger.create_log_record();
+        log_record.set_severity_number(severity(record.level()));
+        log_record.set_severity_text(record.level().as_str());
+        log_record.set_body(AnyValue::from(record.args().to_string()));
+        log_record.set_target(record.target().to_string());
+        log_record.add_attribute("logger.name", record.target().to_string());
+        self.logger.emit(log_record);
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::Key;
+    use opentelemetry_sdk::logs::{InMemoryLogExporter, SimpleLogProcessor};
+
+    use super::*;
+
+    #[test]
+    fn test_severity() {
+        for (level, expected) in [
+            (Level::Error, Severity::Error),
+            (Level::Warn, Severity::Warn),
+            (Level::Info, Severity::Info),
+            (Level::Debug, Severity::Debug),
+            (Level::Trace, Severity::Trace),
+        ] {
+            assert_eq!(severity(level), expected, "{level}");
+        }
+    }
+
+    #[test]
+    fn test_bridge_emits_target_as_logger_name() {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_log_processor(SimpleLogProcessor::new(exporter.clone()))
+            .build();
+        let bridge = OtelLogBridge::new(&provider, LevelFilter::Info);
+
+        for level in [Level::Warn, Level::Debug] {
+            bridge.log(
+                &Record::builder()
+                    .level(level)
+                    .target("actix_server::builder")
+                    .args(format_args!("starting workers"))
+                    .build(),
+            );
+        }
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        assert_eq!(logs.len(), 1, "debug is below the max level");
+        let record = &logs[0].record;
+        assert_eq!(record.severity_number(), Some(Severity::Warn));
+        assert_eq!(record.severity_text(), Some("WARN"));
+        assert_eq!(
+            record
+                .attributes_iter()
+                .find(|(key, _)| *key == Key::new("logger.name"))
+                .map(|(_, value)| value.clone()),
+            Some(AnyValue::from("actix_server::builder".to_string()))
+        );
+    }
+}
@@ -1,46 +1,232 @@
 // Copyright The OpenTelemetry Authors
 // SPDX-License-Identifier: Apache-2.0
 
-use actix_web::{App, HttpServer};
+use actix_web::{middleware::from_fn, web, App, HttpServer};
+use opentelemetry::global;
 use opentelemetry_instrumentation_actix_web::{RequestMetrics, RequestTracing};
-use std::env;
-use tracing::info;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
 
-mod telemetry_conf;
-use telemetry_conf::init_otel;
-mod shipping_service;
-use shipping_service::{get_quote, ship_order};
+use shipping::admin;
+use shipping::audit::AuditLogger;
+use shipping::baggage::copy_baggage;
+use shipping::cache_control::{cache_control, CacheControlRules};
+use shipping::compression::{compression, count_encodings, CompressionCounter};
+use shipping::config::ShippingServiceConfig;
+use shipping::config_api;
+use shipping::correlation_id::correlation_id;
+use shipping::cors::CorsConfig;
+use shipping::cpu_metrics::start_cpu_metrics_collection;
+use shipping::debug;
+use shipping::drain::{self, track_in_flight, Drain};
+use shipping::error_response::json_error;
+use shipping::export_queue::{export_backpressure, EXPORT_QUEUE};
+use shipping::health::{self, probe_local, HEALTHCHECK_ARG};
+use shipping::memory_profiling::{track_allocations, MemoryProfiler};
+use shipping::otel_context::store_otel_context;
+use shipping::panic_hook::install_panic_hook;
+use shipping::rate_limiter::{rate_limit, register_rate_limiter_metrics, RateLimiter};
+use shipping::request_duration::request_duration;
+use shipping::runtime_metrics::register_runtime_metrics;
+use shipping::security_headers::security_headers;
+use shipping::shipping_service::{
+    get_quote, retry_ship_order, ship_order, start_dead_letter_retries,
+    start_exchange_rate_refresh, start_idempotency_eviction, DeadLetterQueue, ExchangeRates,
+    IdempotencyCache, KafkaProducerWrapper, QuoteCache, QuoteDedup, RealShippingBackend,
+    ShippingBackend, Tenants,
+};
+use shipping::slo::{track_slo, SloTracker};
+use shipping::span_status::span_status;
+use shipping::stats::{self, track_stats, ServiceStats};
+use shipping::telemetry_conf::{
+    check_collector_connectivity, init_otel, log_sdk_version_info, register_otel_connected_gauge,
+    shutdown_otel,
+};
+use shipping::version::get_version;
+
+const OTEL_SELF_TEST_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    match init_otel() {
-        Ok(_) => {
+    if std::env::args().nth(1).as_deref() == Some(HEALTHCHECK_ARG) {
+        if let Err(err) = probe_local().await {
+            eprintln!("Unhealthy: {err:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let providers = match init_otel().await {
+        Ok(providers) => {
             info!("Successfully configured OTel");
+            providers
         }
         Err(err) => {
             panic!("Couldn't start OTel: {0}", err);
         }
     };
+    install_panic_hook(providers.tracer_provider.clone());
+    log_sdk_version_info();
+
+    let mut config = ShippingServiceConfig::from_env().map_err(std::io::Error::other)?;
+    match check_collector_connectivity(OTEL_SELF_TEST_TIMEOUT).await {
+        Ok(()) => config.otel_connected = true,
+        Err(err) if config.otel_required => {
+            return Err(std::io::Error::other(format!(
+                "OTEL_REQUIRED is set and the collector is unreachable: {err:#}"
+            )));
+        }
+        Err(err) => warn!(
+            name = "OtelSelfTestFailed",
+            otlp.endpoint = config.otlp_endpoint.as_str(),
+            error = format!("{err:#}"),
+            message = "Collector is unreachable, starting in degraded mode"
+        ),
+    }
+    register_otel_connected_gauge(config.otel_connected);
+    config.log_startup_info();
 
-    let port: u16 = env::var("SHIPPING_PORT")
-        .expect("$SHIPPING_PORT is not set")
-        .parse()
-        .expect("$SHIPPING_PORT is not a valid port");
-    let addr = format!("0.0.0.0:{}", port);
+    let worker_runtimes = register_runtime_metrics();
+    let cpu_metrics = web::Data::new(start_cpu_metrics_collection(
+        config.metrics_interval,
+        config.cgroup_mode,
+        worker_runtimes.clone(),
+    ));
+
+    let dead_letters = Arc::new(Mutex::new(DeadLetterQueue::from_env()));
+    start_dead_letter_retries(dead_letters.clone(), retry_ship_order);
+    let dead_letters = web::Data::from(dead_letters);
+    let quote_cache: Arc<QuoteCache> = Arc::new(Mutex::new(IdempotencyCache::from_env()));
+    start_idempotency_eviction(quote_cache.clone());
+    let quote_cache = web::Data::from(quote_cache);
+    let quote_dedup = web::Data::new(QuoteDedup::default());
+    let quote_config = web::Data::new(config.quote_config());
+    let audit = web::Data::new(AuditLogger::new(&providers.logger_provider));
+    let tenants = web::Data::new(Tenants::from_env());
+    let service_stats = web::Data::new(ServiceStats::new());
+    let slo_tracker = web::Data::new(SloTracker::new(
+        config.slo_target,
+        config.slo_critical_burn_rate,
+    ));
+    slo_tracker
+        .clone()
+        .into_inner()
+        .register_burn_rate_gauges(&global::meter("otel_demo.shipping.slo"));
+    let cache_control_rules = web::Data::new(CacheControlRules::from_env());
+    let cors_config = CorsConfig::from_env();
+    let compression_counter = web::Data::new(CompressionCounter::new(&global::meter(
+        "otel_demo.shipping.http",
+    )));
+    let exchange_rates = Arc::new(ExchangeRates::revalidating(
+        config.exchange_rates_refresh,
+        config.exchange_rates_stale_extension,
+    ));
+    if !config.exchange_rates_url.is_empty() {
+        start_exchange_rate_refresh(exchange_rates.clone(), config.exchange_rates_url.clone());
+        exchange_rates
+            .register_staleness_gauge(&global::meter("otel_demo.shipping.exchange_rates"));
+    }
+    let exchange_rates = web::Data::from(exchange_rates);
+    let order_events = web::Data::new(KafkaProducerWrapper::from_addr(
+        &config.kafka_addr,
+        &global::meter("otel_demo.shipping.kafka"),
+    ));
+    let backend: web::Data<dyn ShippingBackend> =
+        web::Data::from(Arc::new(RealShippingBackend) as Arc<dyn ShippingBackend>);
+
+    let admin_enabled = config.admin_api_enabled;
+    let debug_enabled = config.debug_api_enabled;
+    let config_enabled = config.config_api_enabled;
+    let shutdown_enabled = config.shutdown_api_enabled;
+    let stats_enabled = config.stats_api_enabled;
+    let compression_enabled = config.response_compression_enabled;
+    let drain = web::Data::new(Drain::new(config.drain_timeout));
+    drain.register_in_flight_gauge(&global::meter("otel_demo.shipping.drain"));
+    let config_data = web::Data::new(config.clone());
+    let rate_limiter = web::Data::new(RateLimiter::new(
+        config.rate_limit_algorithm,
+        config.rate_limit_rps,
+        config.rate_limit_burst_size,
+    ));
+    register_rate_limiter_metrics(rate_limiter.clone().into_inner());
+    EXPORT_QUEUE.register_fill_ratio_gauge(&global::meter("otel_demo.shipping.export_queue"));
+    let export_queue = web::Data::from(EXPORT_QUEUE.clone());
+    let memory_profiler = web::Data::new(MemoryProfiler::new(
+        config.memory_profiling_enabled,
+        &global::meter("otel_demo.shipping.memory"),
+    ));
+
+    let addr = format!("0.0.0.0:{}", config.port);
     info!(
         name = "ServerStartedSuccessfully",
         addr = addr.as_str(),
         message = "Shipping service is running"
     );
 
-    HttpServer::new(|| {
+    let server_drain = drain.clone();
+    let server = HttpServer::new(move || {
+        worker_runtimes.register();
         App::new()
+            .app_data(server_drain.clone())
+            .app_data(dead_letters.clone())
+            .app_data(quote_cache.clone())
+            .app_data(quote_dedup.clone())
+            .app_data(quote_config.clone())
+            .app_data(audit.clone())
+            .app_data(tenants.clone())
+            .app_data(service_stats.clone())
+            .app_data(slo_tracker.clone())
+            .app_data(cache_control_rules.clone())
+            .app_data(compression_counter.clone())
+            .app_data(exchange_rates.clone())
+            .app_data(order_events.clone())
+            .app_data(backend.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(cpu_metrics.clone())
+            .app_data(config_data.clone())
+            .app_data(export_queue.clone())
+            .app_data(memory_profiler.clone())
+            .app_data(web::JsonConfig::default().error_handler(json_error))
+            .wrap(from_fn(track_allocations))
+            .wrap(from_fn(span_status))
+            .wrap(from_fn(cache_control))
+            .wrap(from_fn(copy_baggage))
+            .wrap(from_fn(rate_limit))
+            .wrap(from_fn(export_backpressure))
+            .wrap(from_fn(track_in_flight))
+            .wrap(from_fn(track_stats))
+            .wrap(from_fn(track_slo))
+            .wrap(from_fn(correlation_id))
+            .wrap(from_fn(store_otel_context))
+            .wrap(from_fn(request_duration))
+            .wrap(compression(compression_enabled))
+            .wrap(from_fn(count_encodings))
+            .wrap(cors_config.cors())
+            .wrap(security_headers())
             .wrap(RequestTracing::new())
             .wrap(RequestMetrics::default())
             .service(get_quote)
             .service(ship_order)
+            .service(get_version)
+            .service(health::liveness)
+            .configure(admin::configure(admin_enabled))
+            .configure(debug::configure(debug_enabled))
+            .configure(config_api::configure(config_enabled))
+            .configure(drain::configure(shutdown_enabled))
+            .configure(stats::configure(stats_enabled))
     })
     .bind(&addr)?
-    .run()
-    .await
+    .run();
+    drain.set_server(server.handle());
+    server.await?;
+
+    if !shutdown_otel(providers, config.otel_shutdown_timeout).await {
+        warn!(
+            name = "ForcedExit",
+            message = "Exiting without waiting for OpenTelemetry to finish flushing"
+        );
+        std::process::exit(1);
+    }
+    Ok(())
 }
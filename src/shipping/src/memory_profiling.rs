@@ -0,0 +1,174 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error,
+};
+use opentelemetry::{
+    metrics::{Histogram, Meter},
+    KeyValue,
+};
+
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+const ALLOCATION_BOUNDARIES: [f64; 8] = [
+    1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0,
+];
+
+/// Per-request jemalloc allocation stats, with `MEMORY_PROFILING_ENABLED=true`
+/// in a `tikv-jemallocator` build. Reading the thread counters on every
+/// request isn't free, so it is off by default.
+#[derive(Debug)]
+pub struct MemoryProfiler {
+    enabled: bool,
+    allocated: Histogram<u64>,
+    deallocated: Histogram<u64>,
+}
+
+impl MemoryProfiler {
+    pub fn new(enabled: bool, meter: &Meter) -> Self {
+        let enabled = enabled && jemalloc_available();
+        MemoryProfiler {
+            enabled,
+            allocated: allocation_histogram(meter, "request_memory_allocated_bytes", "allocated"),
+            deallocated: allocation_histogram(meter, "request_memory_deallocated_bytes", "freed"),
+        }
+    }
+}
+
+fn allocation_histogram(meter: &Meter, name: &'static str, verb: &str) -> Histogram<u64> {
+    meter
+        .u64_histogram(checked_instrument_name(name))
+        .with_unit("By")
+        .with_description(format!("Bytes {verb} while handling a request"))
+        .with_boundaries(ALLOCATION_BOUNDARIES.to_vec())
+        .build()
+}
+
+#[cfg(feature = "tikv-jemallocator")]
+fn jemalloc_available() -> bool {
+    true
+}
+
+#[cfg(not(feature = "tikv-jemallocator"))]
+fn jemalloc_available() -> bool {
+    tracing::warn!(
+        name = "MemoryProfilingUnavailable",
+        message = "MEMORY_PROFILING_ENABLED needs the tikv-jemallocator feature, ignoring it"
+    );
+    false
+}
+
+/// Bytes allocated and deallocated by the current thread so far.
+#[cfg(feature = "tikv-jemallocator")]
+fn thread_allocations() -> Option<(u64, u64)> {
+    use tikv_jemalloc_ctl::thread::{allocatedp, deallocatedp};
+
+    Some((
+        allocatedp::read().ok()?.get(),
+        deallocatedp::read().ok()?.get(),
+    ))
+}
+
+#[cfg(not(feature = "tikv-jemallocator"))]
+fn thread_allocations() -> Option<(u64, u64)> {
+    None
+}
+
+/// Records what the worker thread allocated between the request's entry and
+/// exit. Actix runs each request on a single worker thread, but requests
+/// interleaved on it while this one awaits are counted too.
+pub async fn track_allocations(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let profiler = req
+        .app_data::<web::Data<MemoryProfiler>>()
+        .filter(|profiler| profiler.enabled)
+        .cloned();
+    let Some(profiler) = profiler else {
+        return next.call(req).await;
+    };
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let start = thread_allocations();
+    let res = next.call(req).await?;
+    if let (Some((allocated, deallocated)), Some((allocated_end, deallocated_end))) =
+        (start, thread_allocations())
+    {
+        let attributes = KUBERNETES_LABELS.with(&[KeyValue::new("http.route", route)]);
+        profiler
+            .allocated
+            .record(allocated_end.saturating_sub(allocated), &attributes);
+        profiler
+            .deallocated
+            .record(deallocated_end.saturating_sub(deallocated), &attributes);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, middleware::from_fn, App};
+    use opentelemetry::metrics::MeterProvider;
+    use serde_json::json;
+
+    use super::*;
+    use crate::shipping_service::{get_quote, MockShippingBackend, Quote, ShippingBackend};
+    use crate::test_utils::{test_meter_provider, u64_histogram_points};
+
+    async fn quote_once(profiler: MemoryProfiler) {
+        use actix_web::test;
+
+        let backend: Arc<dyn ShippingBackend> = Arc::new(MockShippingBackend {
+            quote: Some(Quote {
+                dollars: 1,
+                cents: 0,
+            }),
+            ..Default::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(profiler))
+                .app_data(web::Data::from(backend))
+                .wrap(from_fn(track_allocations))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .set_json(json!({"items": [{"quantity": 1}]}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_disabled_records_nothing() {
+        let (provider, exporter) = test_meter_provider();
+        quote_once(MemoryProfiler::new(false, &provider.meter("test"))).await;
+        provider.force_flush().unwrap();
+        assert!(u64_histogram_points(&exporter, "request_memory_allocated_bytes").is_empty());
+    }
+
+    #[cfg(feature = "tikv-jemallocator")]
+    #[actix_web::test]
+    async fn test_records_quote_allocations() {
+        let (provider, exporter) = test_meter_provider();
+        quote_once(MemoryProfiler::new(true, &provider.meter("test"))).await;
+        provider.force_flush().unwrap();
+
+        let points = u64_histogram_points(&exporter, "request_memory_allocated_bytes");
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].count(), 1);
+        assert!(points[0].sum() > 0);
+        assert!(points[0]
+            .attributes()
+            .any(|kv| *kv == KeyValue::new("http.route", "/get-quote")));
+    }
+}
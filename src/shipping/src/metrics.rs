@@ -0,0 +1,622 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, LazyLock,
+};
+
+use anyhow::{Context as _, Result};
+use opentelemetry::{
+    metrics::Meter,
+    trace::{get_active_span, TraceContextExt},
+    Context, Key, KeyValue,
+};
+use opentelemetry_sdk::metrics::{Aggregation, Instrument, Stream};
+use opentelemetry_semantic_conventions::attribute::{
+    K8S_NAMESPACE_NAME, K8S_NODE_NAME, K8S_POD_NAME,
+};
+use tracing::warn;
+
+const MAX_INSTRUMENT_NAME_LEN: usize = 255;
+const DEFAULT_EXEMPLAR_SAMPLE_RATE: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstrumentNameError {
+    Empty,
+    TooLong(usize),
+    InvalidLeadingChar(char),
+    InvalidChar(char),
+}
+
+impl fmt::Display for InstrumentNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstrumentNameError::Empty => write!(f, "instrument name is empty"),
+            InstrumentNameError::TooLong(len) => write!(
+                f,
+                "instrument name is {len} characters, the limit is {MAX_INSTRUMENT_NAME_LEN}"
+            ),
+            InstrumentNameError::InvalidLeadingChar(c) => {
+                write!(f, "instrument name must start with a letter, not {c:?}")
+            }
+            InstrumentNameError::InvalidChar(c) => {
+                write!(f, "instrument name contains invalid character {c:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InstrumentNameError {}
+
+/// Checks `name` against the OTel instrument name syntax,
+/// `[a-zA-Z][a-zA-Z0-9_\-./]{0,254}`.
+pub fn validate_instrument_name(name: &str) -> Result<(), InstrumentNameError> {
+    let mut chars = name.chars();
+    let first = chars.next().ok_or(InstrumentNameError::Empty)?;
+    if !first.is_ascii_alphabetic() {
+        return Err(InstrumentNameError::InvalidLeadingChar(first));
+    }
+    if let Some(c) = chars.find(|c| !(c.is_ascii_alphanumeric() || "_-./".contains(*c))) {
+        return Err(InstrumentNameError::InvalidChar(c));
+    }
+    if name.len() > MAX_INSTRUMENT_NAME_LEN {
+        return Err(InstrumentNameError::TooLong(name.len()));
+    }
+    Ok(())
+}
+
+/// Returns `name`, logging a warning first if it isn't a valid instrument
+/// name. The SDK drops such instruments, so this makes the loss visible.
+pub fn checked_instrument_name(name: &'static str) -> &'static str {
+    if let Err(err) = validate_instrument_name(name) {
+        warn!(
+            name = "InvalidInstrumentName",
+            instrument = name,
+            error = err.to_string(),
+            message = "Metric instrument name is invalid and will not be exported"
+        );
+    }
+    name
+}
+
+/// Picks every `rate`th observation of an instrument as an exemplar, tagged
+/// with the trace and span it was recorded in.
+///
+/// The 0.30 SDK has no exemplar hook, so `record` attaches sampled
+/// measurements to the active span as an `exemplar` event instead of to the
+/// metric data point.
+#[derive(Debug)]
+pub struct ExemplarFilter {
+    rate: u64,
+    observations: AtomicU64,
+}
+
+impl ExemplarFilter {
+    pub fn new(rate: u64) -> Self {
+        ExemplarFilter {
+            rate: rate.max(1),
+            observations: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads `EXEMPLAR_SAMPLE_RATE`, defaulting to every 10th observation.
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("EXEMPLAR_SAMPLE_RATE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_EXEMPLAR_SAMPLE_RATE),
+        )
+    }
+
+    /// The exemplar's `filtered_attributes` if this observation is sampled
+    /// and was made inside a valid span.
+    pub fn sample(&self) -> Option<Vec<KeyValue>> {
+        let observation = self.observations.fetch_add(1, Ordering::Relaxed) + 1;
+        if observation % self.rate != 0 {
+            return None;
+        }
+        let cx = Context::current();
+        let span = cx.span();
+        let span_context = span.span_context();
+        span_context.is_valid().then(|| {
+            vec![
+                KeyValue::new("trace.id", span_context.trace_id().to_string()),
+                KeyValue::new("span.id", span_context.span_id().to_string()),
+            ]
+        })
+    }
+
+    pub fn record(&self, instrument: &'static str, value: f64) {
+        if let Some(mut attributes) = self.sample() {
+            attributes.push(KeyValue::new("metric.name", instrument));
+            attributes.push(KeyValue::new("metric.value", value));
+            get_active_span(|span| span.add_event("exemplar", attributes));
+        }
+    }
+}
+
+/// Replica labels read at startup, added to every observable instrument.
+pub static KUBERNETES_LABELS: LazyLock<KubernetesLabels> =
+    LazyLock::new(KubernetesLabels::from_env);
+
+const KUBERNETES_LABEL_KEYS: [&str; 3] = [K8S_POD_NAME, K8S_NODE_NAME, K8S_NAMESPACE_NAME];
+
+/// Identifies the replica a measurement came from, so replicas don't share
+/// time series. Unset variables are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KubernetesLabels {
+    pub pod_name: Option<String>,
+    pub node_name: Option<String>,
+    pub namespace_name: Option<String>,
+}
+
+impl KubernetesLabels {
+    /// Reads `HOSTNAME`, which Kubernetes sets to the pod name, and the
+    /// downward API's `K8S_NODE_NAME` and `K8S_NAMESPACE`.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|var| env::var(var).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |var| lookup(var).filter(|value| !value.trim().is_empty());
+        KubernetesLabels {
+            pod_name: get("HOSTNAME"),
+            node_name: get("K8S_NODE_NAME"),
+            namespace_name: get("K8S_NAMESPACE"),
+        }
+    }
+
+    pub fn as_key_values(&self) -> Vec<KeyValue> {
+        KUBERNETES_LABEL_KEYS
+            .into_iter()
+            .zip([&self.pod_name, &self.node_name, &self.namespace_name])
+            .filter_map(|(key, value)| Some(KeyValue::new(key, value.clone()?)))
+            .collect()
+    }
+
+    /// `attributes` followed by the labels.
+    pub fn with(&self, attributes: &[KeyValue]) -> Vec<KeyValue> {
+        attributes
+            .iter()
+            .cloned()
+            .chain(self.as_key_values())
+            .collect()
+    }
+}
+
+/// Sorts `attrs` by key, so the same set always reaches the SDK in the same
+/// order and can't be split across two time series.
+pub fn sort_attributes(attrs: &mut [KeyValue]) {
+    attrs.sort_by(|a, b| a.key.cmp(&b.key));
+}
+
+/// Latency histograms aggregated as base-2 exponential histograms, which
+/// rescale their buckets to the observed range instead of using fixed
+/// boundaries.
+pub const EXPONENTIAL_HISTOGRAMS: [&str; 1] = ["quote_generation_duration_seconds"];
+
+pub const EXPONENTIAL_HISTOGRAM: Aggregation = Aggregation::Base2ExponentialHistogram {
+    max_size: 160,
+    max_scale: 20,
+    record_min_max: true,
+};
+
+/// Attribute whitelist shared by the meter provider's view and the places
+/// that record measurements.
+pub static ATTRIBUTE_WHITELIST: LazyLock<AttributeWhitelist> =
+    LazyLock::new(AttributeWhitelist::from_env);
+
+/// Per-instrument allow-lists of attribute keys, so a high-cardinality
+/// attribute can't create a time series per value. Instruments without an
+/// entry keep all their attributes. The Kubernetes labels are always kept.
+#[derive(Debug, Clone, Default)]
+pub struct AttributeWhitelist {
+    allowed: HashMap<String, HashSet<String>>,
+    overflows: Arc<AtomicU64>,
+}
+
+impl AttributeWhitelist {
+    /// Parses a `{"instrument_name": ["attr1", "attr2"]}` object.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let allowed: HashMap<String, HashSet<String>> =
+            serde_json::from_str(json).context("invalid attribute whitelist")?;
+        Ok(AttributeWhitelist {
+            allowed,
+            ..Default::default()
+        })
+    }
+
+    /// Reads `METRICS_ATTRIBUTE_WHITELIST`. An unset or invalid value keeps
+    /// every attribute.
+    pub fn from_env() -> Self {
+        let Ok(json) = env::var("METRICS_ATTRIBUTE_WHITELIST") else {
+            return Self::default();
+        };
+        Self::from_json(&json).unwrap_or_else(|err| {
+            warn!(
+                name = "InvalidAttributeWhitelist",
+                error = format!("{err:#}"),
+                message = "Ignoring METRICS_ATTRIBUTE_WHITELIST"
+            );
+            Self::default()
+        })
+    }
+
+    /// A view for `SdkMeterProviderBuilder::with_view` that drops every
+    /// attribute key not whitelisted for the instrument. It also sets the
+    /// aggregation of `EXPONENTIAL_HISTOGRAMS`, as the SDK makes a separate
+    /// stream for every view an instrument matches.
+    pub fn view(&self) -> impl Fn(&Instrument) -> Option<Stream> + Send + Sync + 'static {
+        let allowed = self.allowed.clone();
+        move |instrument: &Instrument| {
+            let keys = allowed.get(instrument.name());
+            let exponential = EXPONENTIAL_HISTOGRAMS.contains(&instrument.name());
+            if keys.is_none() && !exponential {
+                return None;
+            }
+            let mut stream = Stream::builder();
+            if let Some(keys) = keys {
+                stream = stream.with_allowed_attribute_keys(
+                    keys.iter()
+                        .cloned()
+                        .map(Key::new)
+                        .chain(KUBERNETES_LABEL_KEYS.map(Key::from_static_str)),
+                );
+            }
+            if exponential {
+                stream = stream.with_aggregation(EXPONENTIAL_HISTOGRAM);
+            }
+            stream.build().ok()
+        }
+    }
+
+    /// Counts the measurement as an overflow if the view will drop any of
+    /// its attributes. Returns whether it did.
+    pub fn check(&self, instrument: &str, attributes: &[KeyValue]) -> bool {
+        let Some(keys) = self.allowed.get(instrument) else {
+            return false;
+        };
+        let overflow = attributes.iter().any(|kv| {
+            !keys.contains(kv.key.as_str()) && !KUBERNETES_LABEL_KEYS.contains(&kv.key.as_str())
+        });
+        if overflow {
+            self.overflows.fetch_add(1, Ordering::Relaxed);
+        }
+        overflow
+    }
+
+    pub fn overflows(&self) -> u64 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    pub fn register_overflow_gauge(&self, meter: &Meter) {
+        let overflows = self.overflows.clone();
+        meter
+            .u64_observable_gauge(checked_instrument_name("metrics_attribute_overflow_total"))
+            .with_description("Measurements recorded with attributes outside the whitelist")
+            .with_callback(move |observer| {
+                observer.observe(
+                    overflows.load(Ordering::Relaxed),
+                    &KUBERNETES_LABELS.as_key_values(),
+                )
+            })
+            .build();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::{
+        metrics::MeterProvider,
+        trace::{TraceId, Tracer, TracerProvider},
+    };
+    use opentelemetry_sdk::{
+        metrics::{
+            data::{AggregatedMetrics, ExponentialHistogramDataPoint, MetricData},
+            InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+        },
+        trace::{InMemorySpanExporter, SdkTracerProvider},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_sort_attributes_ignores_insertion_order() {
+        let mut first = vec![
+            KeyValue::new("service.name", "s"),
+            KeyValue::new("metric.type", "t"),
+        ];
+        let mut second = vec![
+            KeyValue::new("metric.type", "t"),
+            KeyValue::new("service.name", "s"),
+        ];
+        sort_attributes(&mut first);
+        sort_attributes(&mut second);
+        assert_eq!(first, second);
+        assert_eq!(first[0].key.as_str(), "metric.type");
+    }
+
+    const WHITELIST: &str = r#"{"quote_amount_usd": ["shipping.zone"]}"#;
+
+    #[test]
+    fn test_attribute_whitelist_view_drops_other_keys() {
+        let whitelist = AttributeWhitelist::from_json(WHITELIST).unwrap();
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .with_view(whitelist.view())
+            .build();
+        let meter = provider.meter("test");
+        let histogram = meter.f64_histogram("quote_amount_usd").build();
+        whitelist.register_overflow_gauge(&meter);
+
+        for (zone, discount) in [("europe", 0), ("europe", 10), ("asia", 20)] {
+            let attributes = [
+                KeyValue::new("shipping.zone", zone),
+                KeyValue::new("quote.discount_percent", discount),
+            ];
+            assert!(whitelist.check("quote_amount_usd", &attributes));
+            histogram.record(1.0, &attributes);
+        }
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metrics: Vec<_> = metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .collect();
+        let amount = metrics
+            .iter()
+            .find(|m| m.name() == "quote_amount_usd")
+            .unwrap();
+        let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = amount.data() else {
+            panic!("quote_amount_usd is not a histogram");
+        };
+        assert_eq!(histogram.data_points().count(), 2, "one series per zone");
+        for point in histogram.data_points() {
+            assert!(point
+                .attributes()
+                .all(|kv| kv.key.as_str() == "shipping.zone"));
+        }
+
+        let overflow = metrics
+            .iter()
+            .find(|m| m.name() == "metrics_attribute_overflow_total")
+            .unwrap();
+        let AggregatedMetrics::U64(MetricData::Gauge(gauge)) = overflow.data() else {
+            panic!("metrics_attribute_overflow_total is not a u64 gauge");
+        };
+        assert_eq!(gauge.data_points().next().unwrap().value(), 3);
+    }
+
+    /// The value at quantile `q`, interpolating log-linearly within the
+    /// bucket it falls in.
+    fn exponential_quantile(point: &ExponentialHistogramDataPoint<f64>, q: f64) -> f64 {
+        let base = 2_f64.powf(2_f64.powi(-i32::from(point.scale())));
+        let rank = q * point.count() as f64;
+        let mut seen = point.zero_count() as f64;
+        let buckets = point.positive_bucket();
+        for (i, count) in buckets.counts().enumerate() {
+            let count = count as f64;
+            if count > 0.0 && seen + count >= rank {
+                // Bucket `index` holds values in (base^index, base^(index + 1)]
+                let index = f64::from(buckets.offset()) + i as f64;
+                return base.powf(index + (rank - seen) / count);
+            }
+            seen += count;
+        }
+        panic!("quantile {q} is past the last bucket");
+    }
+
+    #[test]
+    fn test_quote_latency_is_an_exponential_histogram() {
+        let whitelist = AttributeWhitelist::default();
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .with_view(whitelist.view())
+            .build();
+        let histogram = provider
+            .meter("test")
+            .f64_histogram("quote_generation_duration_seconds")
+            .build();
+
+        // 1ms to 10s, spread evenly on a log scale
+        let observations: Vec<f64> = (0..10_000)
+            .map(|i| 10_f64.powf(-3.0 + 4.0 * f64::from(i) / 9_999.0))
+            .collect();
+        for value in &observations {
+            histogram.record(*value, &[]);
+        }
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metric = metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .find(|m| m.name() == "quote_generation_duration_seconds")
+            .unwrap();
+        let AggregatedMetrics::F64(MetricData::ExponentialHistogram(histogram)) = metric.data()
+        else {
+            panic!("quote_generation_duration_seconds is not an exponential histogram");
+        };
+        let point = histogram.data_points().next().unwrap();
+        assert_eq!(point.count(), observations.len());
+        assert!(point.positive_bucket().counts().count() <= 160);
+        assert_eq!(point.min(), Some(observations[0]));
+        assert_eq!(point.max(), observations.last().copied());
+
+        for q in [0.5, 0.9, 0.99] {
+            let expected = observations[(q * observations.len() as f64).ceil() as usize - 1];
+            let estimate = exponential_quantile(point, q);
+            assert!(
+                (estimate - expected).abs() / expected < 0.01,
+                "p{}: {estimate} vs {expected}",
+                q * 100.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_attribute_whitelist_check() {
+        let whitelist = AttributeWhitelist::from_json(WHITELIST).unwrap();
+        let zone = KeyValue::new("shipping.zone", "europe");
+        assert!(!whitelist.check("quote_amount_usd", std::slice::from_ref(&zone)));
+        assert!(!whitelist.check(
+            "app.shipping.items_count",
+            &[zone.clone(), KeyValue::new("canary", true)]
+        ));
+        assert_eq!(whitelist.overflows(), 0);
+
+        assert!(whitelist.check("quote_amount_usd", &[zone, KeyValue::new("canary", true)]));
+        assert_eq!(whitelist.overflows(), 1);
+    }
+
+    #[test]
+    fn test_kubernetes_labels() {
+        let vars = HashMap::from([
+            ("HOSTNAME", "shipping-7d9f-x2k4"),
+            ("K8S_NODE_NAME", "node-1"),
+            ("K8S_NAMESPACE", "otel-demo"),
+        ]);
+        let labels = KubernetesLabels::from_lookup(|var| vars.get(var).map(|v| v.to_string()));
+        assert_eq!(
+            labels.as_key_values(),
+            [
+                KeyValue::new(K8S_POD_NAME, "shipping-7d9f-x2k4"),
+                KeyValue::new(K8S_NODE_NAME, "node-1"),
+                KeyValue::new(K8S_NAMESPACE_NAME, "otel-demo"),
+            ]
+        );
+
+        let labels = KubernetesLabels::from_lookup(|var| {
+            (var == "HOSTNAME").then(|| "shipping-7d9f-x2k4".to_string())
+        });
+        assert_eq!(
+            labels.with(&[KeyValue::new("cpu", "0")]),
+            [
+                KeyValue::new("cpu", "0"),
+                KeyValue::new(K8S_POD_NAME, "shipping-7d9f-x2k4"),
+            ]
+        );
+        assert!(KubernetesLabels::from_lookup(|_| Some(" ".to_string()))
+            .as_key_values()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_attribute_whitelist_keeps_kubernetes_labels() {
+        let whitelist = AttributeWhitelist::from_json(WHITELIST).unwrap();
+        let attributes = [
+            KeyValue::new("shipping.zone", "europe"),
+            KeyValue::new(K8S_POD_NAME, "shipping-7d9f-x2k4"),
+        ];
+        assert!(!whitelist.check("quote_amount_usd", &attributes));
+    }
+
+    #[test]
+    fn test_attribute_whitelist_rejects_invalid_json() {
+        assert!(AttributeWhitelist::from_json(r#"{"quote_amount_usd": "shipping.zone"}"#).is_err());
+        assert!(AttributeWhitelist::from_json("{}")
+            .unwrap()
+            .allowed
+            .is_empty());
+    }
+
+    #[test]
+    fn test_exemplar_filter_samples_every_nth() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = provider.tracer("test");
+        let filter = ExemplarFilter::new(10);
+
+        for _ in 0..20 {
+            tracer.in_span("get-quote", |_| {
+                filter.record("quote_generation_duration_seconds", 0.01)
+            });
+        }
+
+        let sampled: Vec<_> = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .filter_map(|span| {
+                let event = span.events.iter().find(|e| e.name == "exemplar")?.clone();
+                Some((span.span_context, event.attributes))
+            })
+            .collect();
+        assert_eq!(sampled.len(), 2);
+        for (span_context, attributes) in sampled {
+            assert_ne!(span_context.trace_id(), TraceId::INVALID);
+            assert!(attributes.contains(&KeyValue::new(
+                "trace.id",
+                span_context.trace_id().to_string()
+            )));
+            assert!(attributes.contains(&KeyValue::new(
+                "span.id",
+                span_context.span_id().to_string()
+            )));
+        }
+    }
+
+    #[test]
+    fn test_exemplar_filter_needs_a_span() {
+        let filter = ExemplarFilter::new(1);
+        assert_eq!(filter.sample(), None);
+    }
+
+    #[test]
+    fn test_valid_names() {
+        for name in [
+            "cpu_frequency_hz",
+            "app.shipping.items_count",
+            "http-server/request.duration",
+            "a",
+        ] {
+            assert_eq!(validate_instrument_name(name), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_length_limit() {
+        let longest = format!("a{}", "b".repeat(254));
+        assert_eq!(validate_instrument_name(&longest), Ok(()));
+
+        let too_long = format!("{longest}c");
+        assert_eq!(
+            validate_instrument_name(&too_long),
+            Err(InstrumentNameError::TooLong(256))
+        );
+    }
+
+    #[test]
+    fn test_invalid_characters() {
+        assert_eq!(
+            validate_instrument_name(""),
+            Err(InstrumentNameError::Empty)
+        );
+        for (name, c) in [("1cpu", '1'), ("_cpu", '_'), (".cpu", '.'), ("-cpu", '-')] {
+            assert_eq!(
+                validate_instrument_name(name),
+                Err(InstrumentNameError::InvalidLeadingChar(c))
+            );
+        }
+        assert_eq!(
+            validate_instrument_name("cpu frequency"),
+            Err(InstrumentNameError::InvalidChar(' '))
+        );
+        assert_eq!(
+            validate_instrument_name("cpu_µs"),
+            Err(InstrumentNameError::InvalidChar('µ'))
+        );
+    }
+}
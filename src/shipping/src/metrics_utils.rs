@@ -0,0 +1,257 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use opentelemetry::{metrics::Meter, KeyValue};
+use tracing::warn;
+
+use crate::metrics::{checked_instrument_name, sort_attributes, KUBERNETES_LABELS};
+
+/// Bits of the hash that pick a register; 2^14 registers estimate within
+/// about 1% (1.04 / sqrt(2^14)).
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+const DEFAULT_CARDINALITY_WARN_THRESHOLD: u64 = 1000;
+
+/// HyperLogLog estimate of how many distinct values have been added, in a
+/// fixed 16 KiB.
+#[derive(Debug, Clone)]
+pub struct HllCounter {
+    registers: Vec<u8>,
+    /// Sum of 2^-register over all registers, kept up to date so estimating
+    /// doesn't scan them.
+    harmonic_sum: f64,
+    zero_registers: usize,
+}
+
+impl Default for HllCounter {
+    fn default() -> Self {
+        HllCounter {
+            registers: vec![0; REGISTERS],
+            harmonic_sum: REGISTERS as f64,
+            zero_registers: REGISTERS,
+        }
+    }
+}
+
+impl HllCounter {
+    pub fn add(&mut self, value: impl Hash) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // Position of the first 1 in the remaining bits, capped for a zero hash
+        let rank = ((hash << PRECISION).leading_zeros() + 1).min(64 - PRECISION + 1) as u8;
+        let register = &mut self.registers[index];
+        if rank > *register {
+            if *register == 0 {
+                self.zero_registers -= 1;
+            }
+            self.harmonic_sum += 2_f64.powi(-i32::from(rank)) - 2_f64.powi(-i32::from(*register));
+            *register = rank;
+        }
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1_f64 + 1.079 / m);
+        let raw = alpha * m * m / self.harmonic_sum;
+        // Linear counting is more accurate while many registers are empty
+        let estimate = if raw <= 2.5 * m && self.zero_registers > 0 {
+            m * (m / self.zero_registers as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+/// Attribute sets the cardinality tracker shares with the meter provider.
+pub static CARDINALITY_TRACKER: LazyLock<CardinalityTracker> =
+    LazyLock::new(CardinalityTracker::from_env);
+
+/// Estimates how many attribute sets each instrument has been recorded with,
+/// i.e. how many time series it creates, and warns once an instrument passes
+/// the threshold.
+#[derive(Debug, Clone)]
+pub struct CardinalityTracker {
+    counters: Arc<Mutex<HashMap<String, HllCounter>>>,
+    warned: Arc<Mutex<HashSet<String>>>,
+    threshold: u64,
+}
+
+impl CardinalityTracker {
+    pub fn new(threshold: u64) -> Self {
+        CardinalityTracker {
+            counters: Default::default(),
+            warned: Default::default(),
+            threshold,
+        }
+    }
+
+    /// Reads `CARDINALITY_WARN_THRESHOLD`, defaulting to 1000.
+    pub fn from_env() -> Self {
+        let threshold = env::var("CARDINALITY_WARN_THRESHOLD")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_CARDINALITY_WARN_THRESHOLD);
+        Self::new(threshold)
+    }
+
+    /// Counts `attributes`, in any order, as one of `instrument`'s sets.
+    /// Returns the new estimate.
+    pub fn record(&self, instrument: &str, attributes: &[KeyValue]) -> u64 {
+        let mut attributes = attributes.to_vec();
+        sort_attributes(&mut attributes);
+        let set: Vec<(&str, String)> = attributes
+            .iter()
+            .map(|kv| (kv.key.as_str(), kv.value.as_str().into_owned()))
+            .collect();
+
+        let estimate = {
+            let mut counters = self.counters.lock().unwrap();
+            let counter = counters.entry(instrument.to_string()).or_default();
+            counter.add(&set);
+            counter.estimate()
+        };
+        if estimate > self.threshold && self.warned.lock().unwrap().insert(instrument.to_string()) {
+            warn!(
+                name = "HighMetricCardinality",
+                instrument = instrument,
+                unique_attribute_sets = estimate,
+                threshold = self.threshold,
+                message = "Instrument is recorded with many distinct attribute sets"
+            );
+        }
+        estimate
+    }
+
+    pub fn estimate(&self, instrument: &str) -> Option<u64> {
+        self.counters
+            .lock()
+            .unwrap()
+            .get(instrument)
+            .map(HllCounter::estimate)
+    }
+
+    pub fn register_gauge(&self, meter: &Meter) {
+        let counters = self.counters.clone();
+        meter
+            .u64_observable_gauge(checked_instrument_name("metrics_unique_attribute_sets"))
+            .with_description("Estimated distinct attribute sets recorded per instrument")
+            .with_callback(move |observer| {
+                for (instrument, counter) in counters.lock().unwrap().iter() {
+                    observer.observe(
+                        counter.estimate(),
+                        &KUBERNETES_LABELS.with(&[KeyValue::new("instrument", instrument.clone())]),
+                    );
+                }
+            })
+            .build();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::test_utils::{test_meter_provider, CaptureLayer};
+
+    fn assert_within_5_percent(estimate: u64, actual: u64) {
+        let error = (estimate as f64 - actual as f64).abs() / actual as f64;
+        assert!(error <= 0.05, "estimated {estimate} for {actual}");
+    }
+
+    #[test]
+    fn test_estimates_known_cardinalities() {
+        for actual in [10_u64, 100, 1_000, 10_000, 100_000, 1_000_000] {
+            let mut counter = HllCounter::default();
+            for value in 0..actual {
+                counter.add(value);
+            }
+            assert_within_5_percent(counter.estimate(), actual);
+        }
+    }
+
+    #[test]
+    fn test_duplicates_are_not_counted() {
+        let mut counter = HllCounter::default();
+        assert_eq!(counter.estimate(), 0);
+        for _ in 0..10 {
+            for value in 0..5_000_u64 {
+                counter.add(format!("tenant-{value}"));
+            }
+        }
+        assert_within_5_percent(counter.estimate(), 5_000);
+    }
+
+    #[test]
+    fn test_attribute_order_is_ignored() {
+        let tracker = CardinalityTracker::new(DEFAULT_CARDINALITY_WARN_THRESHOLD);
+        let zone = KeyValue::new("shipping.zone", "domestic");
+        let tenant = KeyValue::new("tenant.id", "acme");
+        tracker.record("quote", &[zone.clone(), tenant.clone()]);
+        tracker.record("quote", &[tenant, zone]);
+        assert_eq!(tracker.estimate("quote"), Some(1));
+        assert_eq!(tracker.estimate("other"), None);
+    }
+
+    #[test]
+    fn test_warns_once_over_threshold() {
+        let tracker = CardinalityTracker::new(100);
+        let capture = CaptureLayer::default();
+        tracing::subscriber::with_default(
+            tracing_subscriber::registry().with(capture.clone()),
+            || {
+                for id in 0..500 {
+                    tracker.record("quote", &[KeyValue::new("request.id", id)]);
+                }
+            },
+        );
+        let events = capture.events.lock().unwrap();
+        let warnings: Vec<_> = events
+            .iter()
+            .filter(|(_, fields)| {
+                fields.get("name").map(String::as_str) == Some("HighMetricCardinality")
+            })
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].1["instrument"], "quote");
+    }
+
+    #[test]
+    fn test_gauge_reports_estimate_per_instrument() {
+        let (provider, exporter) = test_meter_provider();
+        let tracker = CardinalityTracker::new(DEFAULT_CARDINALITY_WARN_THRESHOLD);
+        tracker.register_gauge(&provider.meter("test"));
+        for zone in ["domestic", "europe", "international"] {
+            tracker.record("quote", &[KeyValue::new("shipping.zone", zone)]);
+        }
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metric = metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .find(|m| m.name() == "metrics_unique_attribute_sets")
+            .unwrap();
+        let AggregatedMetrics::U64(MetricData::Gauge(gauge)) = metric.data() else {
+            panic!("metrics_unique_attribute_sets is not a u64 gauge");
+        };
+        let point = gauge.data_points().next().unwrap();
+        assert_eq!(point.value(), 3);
+        assert!(point
+            .attributes()
+            .any(|kv| *kv == KeyValue::new("instrument", "quote")));
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::future::{ready, Ready};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use opentelemetry::Context;
+
+/// The OTel context the request started with, so code that only has the
+/// request can find its span after the thread-local context has moved on.
+#[derive(Debug, Clone)]
+pub struct OtelContext(pub Context);
+
+/// The context `store_otel_context` saved for `req`, or the current one
+/// when the middleware isn't installed.
+pub fn current_context(req: &HttpRequest) -> Context {
+    req.extensions()
+        .get::<OtelContext>()
+        .map(|cx| cx.0.clone())
+        .unwrap_or_else(Context::current)
+}
+
+impl FromRequest for OtelContext {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(OtelContext(current_context(req))))
+    }
+}
+
+/// Must be registered inside `RequestTracing` so the current context holds
+/// the request span.
+pub async fn store_otel_context(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    req.extensions_mut().insert(OtelContext(Context::current()));
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, test, web, App, HttpResponse};
+    use opentelemetry::{
+        global,
+        trace::{FutureExt, Span, TraceContextExt, Tracer},
+    };
+
+    use super::*;
+    use crate::test_utils::global_span_exporter;
+
+    fn nested_span_id(req: &HttpRequest) -> String {
+        current_context(req)
+            .span()
+            .span_context()
+            .span_id()
+            .to_string()
+    }
+
+    async fn stored_span_id(req: HttpRequest) -> HttpResponse {
+        assert_eq!(
+            current_context(&req).span().span_context(),
+            Context::current().span().span_context()
+        );
+        // Still found once the request's context is no longer attached
+        let _guard = Context::new().attach();
+        HttpResponse::Ok().body(nested_span_id(&req))
+    }
+
+    #[actix_web::test]
+    async fn test_stores_active_span_context() {
+        global_span_exporter();
+        let span = global::tracer("test").start("request");
+        let span_id = span.span_context().span_id();
+        let cx = Context::current_with_span(span);
+
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(store_otel_context))
+                .route("/", web::get().to(stored_span_id)),
+        )
+        .await;
+        let req = test::TestRequest::get().to_request();
+        let body = test::call_and_read_body(&app, req).with_context(cx).await;
+        assert_eq!(body, span_id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_falls_back_to_current_context() {
+        let req = test::TestRequest::default().to_http_request();
+        assert!(!current_context(&req).has_active_span());
+    }
+}
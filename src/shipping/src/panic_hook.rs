@@ -0,0 +1,103 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::panic::{self, PanicHookInfo};
+
+use backtrace::Backtrace;
+use opentelemetry::{
+    trace::{Span, Status, Tracer, TracerProvider},
+    KeyValue,
+};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_semantic_conventions::attribute::{
+    EXCEPTION_MESSAGE, EXCEPTION_STACKTRACE, EXCEPTION_TYPE,
+};
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    match info.location() {
+        Some(location) => format!("{payload} at {location}"),
+        None => payload.to_string(),
+    }
+}
+
+/// Records the panic as a `service.panic` span and flushes it, so the crash
+/// is exported before the process goes down.
+fn record_panic(provider: &SdkTracerProvider, info: &PanicHookInfo) {
+    let mut span = provider
+        .tracer("otel_demo.shipping.panic")
+        .start("service.panic");
+    let message = panic_message(info);
+    span.set_attributes([
+        KeyValue::new(EXCEPTION_TYPE, "panic"),
+        KeyValue::new(EXCEPTION_MESSAGE, message.clone()),
+        KeyValue::new(EXCEPTION_STACKTRACE, format!("{:?}", Backtrace::new())),
+    ]);
+    span.set_status(Status::error(message));
+    span.end();
+    let _ = provider.force_flush();
+}
+
+/// Installs a panic hook that records the panic through `provider`, then
+/// hands over to the previous hook.
+pub fn install_panic_hook(provider: SdkTracerProvider) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        record_panic(&provider, info);
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+
+    use super::*;
+
+    #[test]
+    fn test_panic_is_recorded_as_span() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+
+        let previous = panic::take_hook();
+        // keep a handle, dropping the last one shuts down and clears the exporter
+        install_panic_hook(provider.clone());
+        let result = thread::spawn(|| panic!("controlled panic")).join();
+        // restores `previous`, dropping ours
+        let _ = panic::take_hook();
+        panic::set_hook(previous);
+        assert!(result.is_err());
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let span = spans
+            .iter()
+            .find(|span| {
+                span.name == "service.panic"
+                    && span.attributes.iter().any(|kv| {
+                        kv.key.as_str() == EXCEPTION_MESSAGE
+                            && kv.value.as_str().starts_with("controlled panic at ")
+                    })
+            })
+            .expect("service.panic span was not recorded");
+
+        let attribute = |key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute(EXCEPTION_TYPE), Some(Value::from("panic")));
+        assert!(attribute(EXCEPTION_STACKTRACE).is_some_and(|trace| !trace.as_str().is_empty()));
+        assert!(matches!(span.status, Status::Error { .. }));
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::{
+    error::{OTelSdkError, OTelSdkResult},
+    metrics::{
+        data::{AggregatedMetrics, ExponentialHistogramDataPoint, MetricData, ResourceMetrics},
+        exporter::PushMetricExporter,
+        PeriodicReader, Temporality,
+    },
+};
+use prometheus::proto::{
+    Bucket, Counter, Gauge, Histogram, LabelPair, Metric, MetricFamily, MetricType,
+};
+
+use crate::metrics::KUBERNETES_LABELS;
+
+pub const PUSH_JOB: &str = "shippingservice";
+const DEFAULT_PUSH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Pushes every collection to a Prometheus Pushgateway, for runs too short
+/// to be scraped. The `prometheus` client blocks, which is fine on the
+/// periodic reader's own thread.
+#[derive(Debug)]
+pub struct PushgatewayExporter {
+    url: String,
+    instance: String,
+}
+
+impl PushgatewayExporter {
+    pub fn new(url: impl Into<String>, instance: impl Into<String>) -> Self {
+        PushgatewayExporter {
+            url: url.into(),
+            instance: instance.into(),
+        }
+    }
+}
+
+/// Reader pushing to `PROMETHEUS_PUSHGATEWAY_URL` every
+/// `PROMETHEUS_PUSH_INTERVAL_SECS` (default 15), as
+/// `job=shippingservice,instance=<pod name>`. `None` when the URL isn't set.
+pub fn pushgateway_reader() -> Option<PeriodicReader<PushgatewayExporter>> {
+    let url = env::var("PROMETHEUS_PUSHGATEWAY_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())?;
+    let interval = env::var("PROMETHEUS_PUSH_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_PUSH_INTERVAL);
+    let instance = KUBERNETES_LABELS
+        .pod_name
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+    Some(
+        PeriodicReader::builder(PushgatewayExporter::new(url, instance))
+            .with_interval(interval)
+            .build(),
+    )
+}
+
+/// Replaces what Prometheus doesn't allow in names, like the dots of OTel
+/// attribute keys, with underscores.
+fn sanitize(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn labels<'a>(attributes: impl Iterator<Item = &'a KeyValue>) -> Vec<LabelPair> {
+    attributes
+        // The grouping labels, which the push API refuses in metrics
+        .filter(|kv| !matches!(kv.key.as_str(), "job" | "instance"))
+        .map(|kv| LabelPair {
+            name: Some(sanitize(kv.key.as_str())),
+            value: Some(kv.value.to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+trait AsF64: Copy {
+    fn as_f64(self) -> f64;
+}
+
+impl AsF64 for f64 {
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
+
+impl AsF64 for u64 {
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl AsF64 for i64 {
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+/// Classic cumulative buckets for an exponential histogram point, bounded
+/// by the powers of its base, `2^(2^-scale)`, so the Pushgateway can take it.
+fn exponential_buckets<T>(point: &ExponentialHistogramDataPoint<T>) -> Vec<Bucket> {
+    // `base^index` as `2^(index * 2^-scale)`, which is exact at powers of 2
+    let width = 2_f64.powi(-i32::from(point.scale()));
+    let bound = move |index: i32| (f64::from(index) * width).exp2();
+    // Bucket `index` holds the values whose magnitude is in
+    // `(base^index, base^(index + 1)]`.
+    let negative = point.negative_bucket();
+    let negative_counts: Vec<u64> = negative.counts().collect();
+    let negative = negative_counts
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, count)| (-bound(negative.offset() + i as i32), *count));
+    let positive = point.positive_bucket();
+    let positive = positive
+        .counts()
+        .enumerate()
+        .map(|(i, count)| (bound(positive.offset() + i as i32 + 1), count));
+
+    let mut cumulative = 0;
+    negative
+        .chain([(point.zero_threshold(), point.zero_count())])
+        .chain(positive)
+        .map(|(upper_bound, count)| {
+            cumulative += count;
+            Bucket {
+                cumulative_count: Some(cumulative),
+                upper_bound: Some(upper_bound),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Prometheus type and series of one OTel metric. Exponential histograms
+/// become classic ones.
+fn series<T: AsF64>(data: &MetricData<T>) -> Option<(MetricType, Vec<Metric>)> {
+    match data {
+        MetricData::Gauge(gauge) => Some((
+            MetricType::GAUGE,
+            gauge
+                .data_points()
+                .map(|point| Metric {
+                    label: labels(point.attributes()),
+                    gauge: Some(Gauge {
+                        value: Some(point.value().as_f64()),
+                        ..Default::default()
+                    })
+                    .into(),
+                    ..Default::default()
+                })
+                .collect(),
+        )),
+        MetricData::Sum(sum) if sum.is_monotonic() => Some((
+            MetricType::COUNTER,
+            sum.data_points()
+                .map(|point| Metric {
+                    label: labels(point.attributes()),
+                    counter: Some(Counter {
+                        value: Some(point.value().as_f64()),
+                        ..Default::default()
+                    })
+                    .into(),
+                    ..Default::default()
+                })
+                .collect(),
+        )),
+        MetricData::Sum(sum) => Some((
+            MetricType::GAUGE,
+            sum.data_points()
+                .map(|point| Metric {
+                    label: labels(point.attributes()),
+                    gauge: Some(Gauge {
+                        value: Some(point.value().as_f64()),
+                        ..Default::default()
+                    })
+                    .into(),
+                    ..Default::default()
+                })
+                .collect(),
+        )),
+        MetricData::Histogram(histogram) => Some((
+            MetricType::HISTOGRAM,
+            histogram
+                .data_points()
+                .map(|point| {
+                    let mut cumulative = 0;
+                    let bucket = point
+                        .bounds()
+                        .zip(point.bucket_counts())
+                        .map(|(upper_bound, count)| {
+                            cumulative += count;
+                            Bucket {
+                                cumulative_count: Some(cumulative),
+                                upper_bound: Some(upper_bound),
+                                ..Default::default()
+                            }
+                        })
+                        .collect();
+                    Metric {
+                        label: labels(point.attributes()),
+                        histogram: Some(Histogram {
+                            sample_count: Some(point.count()),
+                            sample_sum: Some(point.sum().as_f64()),
+                            bucket,
+                            ..Default::default()
+                        })
+                        .into(),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+        )),
+        MetricData::ExponentialHistogram(histogram) => Some((
+            MetricType::HISTOGRAM,
+            histogram
+                .data_points()
+                .map(|point| Metric {
+                    label: labels(point.attributes()),
+                    histogram: Some(Histogram {
+                        sample_count: Some(point.count() as u64),
+                        sample_sum: Some(point.sum().as_f64()),
+                        bucket: exponential_buckets(point),
+                        ..Default::default()
+                    })
+                    .into(),
+                    ..Default::default()
+                })
+                .collect(),
+        )),
+    }
+}
+
+pub fn metric_families(metrics: &ResourceMetrics) -> Vec<MetricFamily> {
+    metrics
+        .scope_metrics()
+        .flat_map(|scope| scope.metrics())
+        .filter_map(|metric| {
+            let (kind, series) = match metric.data() {
+                AggregatedMetrics::F64(data) => series(data),
+                AggregatedMetrics::U64(data) => series(data),
+                AggregatedMetrics::I64(data) => series(data),
+            }?;
+            let mut family = MetricFamily {
+                name: Some(sanitize(metric.name())),
+                help: Some(metric.description().to_string()),
+                metric: series,
+                ..Default::default()
+            };
+            family.set_field_type(kind);
+            Some(family)
+        })
+        .collect()
+}
+
+impl PushMetricExporter for PushgatewayExporter {
+    async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        let grouping = HashMap::from([("instance".to_string(), self.instance.clone())]);
+        prometheus::push_metrics(
+            PUSH_JOB,
+            grouping,
+            &self.url,
+            metric_families(metrics),
+            None,
+        )
+        .map_err(|err| OTelSdkError::InternalFailure(format!("Pushgateway push failed: {err}")))
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn temporality(&self) -> Temporality {
+        Temporality::Cumulative
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, SdkMeterProvider};
+
+    use super::*;
+    use crate::metrics::ATTRIBUTE_WHITELIST;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(
+            sanitize("app.shipping.items_count"),
+            "app_shipping_items_count"
+        );
+        assert_eq!(sanitize("2xx-total"), "_2xx_total");
+    }
+
+    #[test]
+    fn test_exponential_histogram_becomes_classic() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .with_view(ATTRIBUTE_WHITELIST.view())
+            .build();
+        let histogram = provider
+            .meter("test")
+            .f64_histogram("quote_generation_duration_seconds")
+            .build();
+        for seconds in [0.25, 0.5, 2.0] {
+            histogram.record(seconds, &[]);
+        }
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let family = metric_families(&metrics[0])
+            .into_iter()
+            .find(|family| family.name() == "quote_generation_duration_seconds")
+            .expect("exponential histogram was dropped");
+        assert_eq!(family.get_field_type(), MetricType::HISTOGRAM);
+        let histogram = family.metric[0].histogram.as_ref().unwrap();
+        assert_eq!(histogram.sample_count(), 3);
+        assert_eq!(histogram.sample_sum(), 2.75);
+
+        let bucket_counts: Vec<(f64, u64)> = histogram
+            .bucket
+            .iter()
+            .map(|bucket| (bucket.upper_bound(), bucket.cumulative_count()))
+            .collect();
+        let count_upto = |value: f64| {
+            bucket_counts
+                .iter()
+                .find(|(upper_bound, _)| *upper_bound >= value)
+                .map(|(_, count)| *count)
+        };
+        assert_eq!(count_upto(0.25), Some(1));
+        assert_eq!(count_upto(0.5), Some(2));
+        assert_eq!(count_upto(2.0), Some(3));
+        assert!(bucket_counts
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1));
+    }
+
+    #[test]
+    fn test_pushes_metric_names() {
+        let server = MockServer::start();
+        let push = server.mock(|when, then| {
+            when.method(PUT)
+                .path("/metrics/job/shippingservice/instance/shipping-7d9f-x2k4")
+                .matches(|req| {
+                    let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                    [
+                        "app_shipping_items_count",
+                        "quote_amount_usd",
+                        "http_requests_in_flight",
+                    ]
+                    .iter()
+                    .all(|name| body.contains(name))
+                });
+            then.status(200);
+        });
+
+        let provider = SdkMeterProvider::builder()
+            .with_reader(
+                PeriodicReader::builder(PushgatewayExporter::new(
+                    server.base_url(),
+                    "shipping-7d9f-x2k4",
+                ))
+                .build(),
+            )
+            .build();
+        let meter = provider.meter("test");
+        meter
+            .u64_counter("app.shipping.items_count")
+            .build()
+            .add(3, &[KeyValue::new("shipping.zone", "europe")]);
+        meter
+            .f64_histogram("quote_amount_usd")
+            .build()
+            .record(12.5, &[]);
+        meter
+            .i64_up_down_counter("http_requests_in_flight")
+            .build()
+            .add(1, &[]);
+        provider.force_flush().unwrap();
+        push.assert();
+    }
+}
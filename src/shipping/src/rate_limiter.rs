@@ -0,0 +1,335 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    web, Error,
+};
+use opentelemetry::{global, KeyValue};
+
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+use crate::metrics::KUBERNETES_LABELS;
+use crate::shipping_service::Canary;
+
+pub const DEFAULT_RATE_LIMIT_RPS: f64 = 10_f64;
+pub const DEFAULT_RATE_LIMIT_BURST_SIZE: f64 = 20_f64;
+/// Past this many clients, buckets that have fully refilled are dropped.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// How requests above the steady rate are treated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitAlgorithm {
+    /// Steady `RATE_LIMIT_RPS`, no bursting.
+    LeakyBucket,
+    /// Bursts of up to `RATE_LIMIT_BURST_SIZE`, refilled at `RATE_LIMIT_RPS`.
+    TokenBucket,
+}
+
+impl RateLimitAlgorithm {
+    /// `token_bucket` or `leaky_bucket`, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            name if name.eq_ignore_ascii_case("token_bucket") => {
+                Some(RateLimitAlgorithm::TokenBucket)
+            }
+            name if name.eq_ignore_ascii_case("leaky_bucket") => {
+                Some(RateLimitAlgorithm::LeakyBucket)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitAlgorithm::TokenBucket => "token_bucket",
+            RateLimitAlgorithm::LeakyBucket => "leaky_bucket",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        TokenBucket {
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    fn tokens_at(&self, now: Instant, capacity: f64, refill_per_sec: f64) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * refill_per_sec).min(capacity)
+    }
+
+    fn try_acquire(&mut self, now: Instant, capacity: f64, refill_per_sec: f64) -> bool {
+        self.tokens = self.tokens_at(now, capacity, refill_per_sec);
+        self.updated = now;
+        if self.tokens >= 1_f64 {
+            self.tokens -= 1_f64;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Per-client-IP request limits.
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+impl RateLimiter {
+    pub fn new(algorithm: RateLimitAlgorithm, rps: f64, burst_size: f64) -> Self {
+        let capacity = match algorithm {
+            RateLimitAlgorithm::LeakyBucket => 1_f64,
+            RateLimitAlgorithm::TokenBucket => burst_size.max(1_f64),
+        };
+        RateLimiter {
+            buckets: Mutex::new(HashMap::new()),
+            capacity,
+            refill_per_sec: rps,
+        }
+    }
+
+    /// Takes a token from `client`'s bucket, returning `false` if it is empty.
+    pub fn try_acquire(&self, client: &str, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                bucket.tokens_at(now, self.capacity, self.refill_per_sec) < self.capacity
+            });
+        }
+        buckets
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket::new(self.capacity, now))
+            .try_acquire(now, self.capacity, self.refill_per_sec)
+    }
+
+    /// Tokens left in every client's bucket as of `now`.
+    pub fn current_tokens(&self, now: Instant) -> Vec<(String, f64)> {
+        self.buckets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(client, bucket)| {
+                let tokens = bucket.tokens_at(now, self.capacity, self.refill_per_sec);
+                (client.clone(), tokens)
+            })
+            .collect()
+    }
+}
+
+pub fn register_rate_limiter_metrics(limiter: Arc<RateLimiter>) {
+    global::meter("otel_demo.shipping.rate_limiter")
+        .f64_observable_gauge("rate_limiter_current_tokens")
+        .with_description("Tokens left in each client's rate limit bucket")
+        .with_callback(move |observer| {
+            for (client, tokens) in limiter.current_tokens(Instant::now()) {
+                observer.observe(
+                    tokens,
+                    &KUBERNETES_LABELS.with(&[KeyValue::new("client.address", client)]),
+                );
+            }
+        })
+        .build();
+}
+
+/// Responds `429 Too Many Requests` once a client's bucket is empty. Needs a
+/// `web::Data<RateLimiter>`; without one, requests pass through. Canary
+/// requests skip the limiter, so QA traffic neither takes tokens nor shows
+/// up as rate-limited.
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let limiter = req
+        .app_data::<web::Data<RateLimiter>>()
+        .filter(|_| !Canary::from_headers(req.headers()).0);
+    if let Some(limiter) = limiter {
+        let client = req
+            .peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        if !limiter.try_acquire(&client, Instant::now()) {
+            let res = ErrorResponse::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                "Rate limit exceeded",
+                CorrelationId::of(req.request()),
+            )
+            .into_response();
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{middleware::from_fn, test, App, HttpResponse, Responder};
+
+    use super::*;
+    use crate::error_response::tests::assert_error;
+
+    #[actix_web::test]
+    async fn test_token_bucket_bursts_then_refills() {
+        let limiter = RateLimiter::new(RateLimitAlgorithm::TokenBucket, 10_f64, 20_f64);
+        let start = Instant::now();
+
+        let allowed: Vec<bool> = (0..25)
+            .map(|_| limiter.try_acquire("10.0.0.1", start))
+            .collect();
+        assert!(allowed[..20].iter().all(|ok| *ok));
+        assert!(allowed[20..].iter().all(|ok| !*ok));
+        assert_eq!(
+            limiter.current_tokens(start),
+            vec![("10.0.0.1".into(), 0_f64)]
+        );
+
+        // other clients have their own bucket
+        assert!(limiter.try_acquire("10.0.0.2", start));
+
+        let later = start + Duration::from_secs(5);
+        assert_eq!(
+            limiter
+                .current_tokens(later)
+                .into_iter()
+                .find(|(client, _)| client == "10.0.0.1"),
+            Some(("10.0.0.1".into(), 20_f64))
+        );
+        let allowed = (0..25)
+            .filter(|_| limiter.try_acquire("10.0.0.1", later))
+            .count();
+        assert_eq!(allowed, 20);
+    }
+
+    #[actix_web::test]
+    async fn test_leaky_bucket_does_not_burst() {
+        let limiter = RateLimiter::new(RateLimitAlgorithm::LeakyBucket, 10_f64, 20_f64);
+        let start = Instant::now();
+        assert!(limiter.try_acquire("10.0.0.1", start));
+        assert!(!limiter.try_acquire("10.0.0.1", start));
+        assert!(limiter.try_acquire("10.0.0.1", start + Duration::from_millis(100)));
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit_middleware() {
+        async fn ok() -> impl Responder {
+            HttpResponse::Ok()
+        }
+
+        let limiter = web::Data::new(RateLimiter::new(
+            RateLimitAlgorithm::TokenBucket,
+            1_f64,
+            20_f64,
+        ));
+        let app = test::init_service(
+            App::new()
+                .app_data(limiter)
+                .wrap(from_fn(rate_limit))
+                .route("/", web::get().to(ok)),
+        )
+        .await;
+
+        let mut statuses = Vec::new();
+        for _ in 0..25 {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .peer_addr("10.0.0.1:4000".parse().unwrap())
+                .to_request();
+            statuses.push(test::call_service(&app, req).await.status());
+        }
+        assert_eq!(
+            statuses.iter().filter(|s| **s == StatusCode::OK).count(),
+            20
+        );
+        assert!(statuses[20..]
+            .iter()
+            .all(|s| *s == StatusCode::TOO_MANY_REQUESTS));
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::TOO_MANY_REQUESTS, "rate_limited").await;
+    }
+
+    #[actix_web::test]
+    async fn test_canary_requests_bypass_the_limiter() {
+        async fn ok() -> impl Responder {
+            HttpResponse::Ok()
+        }
+
+        let limiter = web::Data::new(RateLimiter::new(
+            RateLimitAlgorithm::LeakyBucket,
+            0.001,
+            1_f64,
+        ));
+        let app = test::init_service(
+            App::new()
+                .app_data(limiter.clone())
+                .wrap(from_fn(rate_limit))
+                .route("/", web::get().to(ok)),
+        )
+        .await;
+        let request = |canary: &'static str| {
+            test::TestRequest::get()
+                .uri("/")
+                .peer_addr("10.0.0.1:4000".parse().unwrap())
+                .insert_header(("X-Canary", canary))
+                .to_request()
+        };
+
+        assert_eq!(
+            test::call_service(&app, request("false")).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            test::call_service(&app, request("false")).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        let tokens = limiter.current_tokens(Instant::now());
+        for _ in 0..3 {
+            assert_eq!(
+                test::call_service(&app, request("true")).await.status(),
+                StatusCode::OK
+            );
+        }
+        // canaries didn't take tokens either
+        assert!(limiter.current_tokens(Instant::now())[0].1 >= tokens[0].1);
+    }
+
+    #[actix_web::test]
+    async fn test_algorithm_parse() {
+        assert_eq!(
+            RateLimitAlgorithm::parse("token_bucket"),
+            Some(RateLimitAlgorithm::TokenBucket)
+        );
+        assert_eq!(
+            RateLimitAlgorithm::parse("Leaky_Bucket"),
+            Some(RateLimitAlgorithm::LeakyBucket)
+        );
+        assert_eq!(RateLimitAlgorithm::parse("bogus"), None);
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Instant;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error,
+};
+use opentelemetry::{global, KeyValue};
+
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+pub const REQUEST_DURATION_HEADER: HeaderName = HeaderName::from_static("x-request-duration");
+
+/// Sets `X-Request-Duration` to the milliseconds the server spent on the
+/// request, and records it in `http_request_duration_seconds` by route and
+/// status. Register it outside the other `from_fn` middleware so their time
+/// is included.
+pub async fn request_duration(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let start = Instant::now();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let mut res = next.call(req).await?;
+    let elapsed = start.elapsed();
+
+    res.headers_mut().insert(
+        REQUEST_DURATION_HEADER,
+        HeaderValue::from_str(&format!("{:.3}", elapsed.as_secs_f64() * 1000.0))
+            .expect("numbers are valid header values"),
+    );
+    global::meter("otel_demo.shipping.http")
+        .f64_histogram(checked_instrument_name("http_request_duration_seconds"))
+        .with_unit("s")
+        .with_description("Server-side time to handle a request, as sent in X-Request-Duration")
+        .build()
+        .record(
+            elapsed.as_secs_f64(),
+            &KUBERNETES_LABELS.with(&[
+                KeyValue::new("http.route", route),
+                KeyValue::new(
+                    "http.response.status_code",
+                    i64::from(res.status().as_u16()),
+                ),
+            ]),
+        );
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    use super::*;
+    use crate::shipping_service::{get_quote, RealShippingBackend, ShippingBackend};
+
+    #[actix_web::test]
+    async fn test_get_quote_reports_duration() {
+        let backend: web::Data<dyn ShippingBackend> =
+            web::Data::from(Arc::new(RealShippingBackend) as Arc<dyn ShippingBackend>);
+        let app = test::init_service(
+            App::new()
+                .app_data(backend)
+                .wrap(from_fn(request_duration))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .insert_header(("X-Canary", "true"))
+            .set_json(serde_json::json!({ "items": [{ "quantity": 2 }] }))
+            .to_request();
+        let start = Instant::now();
+        let resp = test::call_service(&app, req).await;
+        let measured_ms = start.elapsed().as_secs_f64() * 1000.0;
+        assert!(resp.status().is_success());
+
+        let header = resp.headers().get(REQUEST_DURATION_HEADER).unwrap();
+        let duration_ms: f64 = header.to_str().unwrap().parse().unwrap();
+        assert!(duration_ms > 0.0, "{duration_ms}");
+        assert!(duration_ms <= measured_ms, "{duration_ms} > {measured_ms}");
+    }
+}
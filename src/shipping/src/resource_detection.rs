@@ -0,0 +1,344 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use opentelemetry_resource_detectors::ProcessResourceDetector;
+use opentelemetry_sdk::{resource::ResourceDetector, Resource};
+use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
+use tracing::warn;
+
+use crate::host_resource::HostResourceDetector;
+use crate::metrics::{KubernetesLabels, KUBERNETES_LABELS};
+
+const DEFAULT_DETECTOR_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Detects `k8s.pod.name`, `k8s.node.name` and `k8s.namespace.name` from
+/// the same variables as the Kubernetes metric labels.
+#[derive(Debug, Clone)]
+pub struct KubernetesResourceDetector {
+    labels: KubernetesLabels,
+}
+
+impl Default for KubernetesResourceDetector {
+    fn default() -> Self {
+        Self::new(KUBERNETES_LABELS.clone())
+    }
+}
+
+impl KubernetesResourceDetector {
+    pub fn new(labels: KubernetesLabels) -> Self {
+        KubernetesResourceDetector { labels }
+    }
+}
+
+impl ResourceDetector for KubernetesResourceDetector {
+    fn detect(&self) -> Resource {
+        Resource::builder_empty()
+            .with_attributes(self.labels.as_key_values())
+            .build()
+    }
+}
+
+/// Detects the `key1=value1,key2=value2` pairs of `OTEL_RESOURCE_ATTRIBUTES`,
+/// and `service.name` from `OTEL_SERVICE_NAME`, which wins over it.
+#[derive(Debug, Clone, Default)]
+pub struct OtelEnvResourceDetector {
+    resource_attributes: Option<String>,
+    service_name: Option<String>,
+}
+
+impl OtelEnvResourceDetector {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|var| env::var(var).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        OtelEnvResourceDetector {
+            resource_attributes: lookup("OTEL_RESOURCE_ATTRIBUTES"),
+            service_name: lookup("OTEL_SERVICE_NAME").filter(|name| !name.trim().is_empty()),
+        }
+    }
+}
+
+impl ResourceDetector for OtelEnvResourceDetector {
+    fn detect(&self) -> Resource {
+        let mut attributes: Vec<KeyValue> = self
+            .resource_attributes
+            .iter()
+            .flat_map(|pairs| pairs.split(','))
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                let key = key.trim();
+                (!key.is_empty()).then(|| KeyValue::new(key.to_string(), value.trim().to_string()))
+            })
+            .collect();
+        if let Some(service_name) = &self.service_name {
+            attributes.push(KeyValue::new(SERVICE_NAME, service_name.trim().to_string()));
+        }
+        Resource::builder_empty()
+            .with_attributes(attributes)
+            .build()
+    }
+}
+
+type SharedDetector = Arc<dyn ResourceDetector + Send + Sync>;
+
+/// A detector that panicked or didn't finish in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorFailure {
+    pub detector: &'static str,
+    pub reason: String,
+}
+
+impl DetectorFailure {
+    pub fn log(&self) {
+        warn!(
+            name = "ResourceDetectorFailed",
+            detector = self.detector,
+            reason = self.reason.as_str(),
+            message = "Resource detector failed, skipping its attributes"
+        );
+    }
+}
+
+/// Runs the host, process, Kubernetes and `OTEL_*` env detectors at once,
+/// each within a timeout, and merges what they found. For keys found by
+/// several, the later detector in that order wins.
+pub struct ResourceDetectorChain {
+    host: SharedDetector,
+    process: SharedDetector,
+    kubernetes: SharedDetector,
+    otel_env: SharedDetector,
+    timeout: Duration,
+}
+
+impl Default for ResourceDetectorChain {
+    fn default() -> Self {
+        Self::new(
+            Arc::new(HostResourceDetector::new()),
+            Arc::new(ProcessResourceDetector),
+            Arc::new(KubernetesResourceDetector::default()),
+            Arc::new(OtelEnvResourceDetector::from_env()),
+        )
+    }
+}
+
+impl ResourceDetectorChain {
+    pub fn new(
+        host: SharedDetector,
+        process: SharedDetector,
+        kubernetes: SharedDetector,
+        otel_env: SharedDetector,
+    ) -> Self {
+        ResourceDetectorChain {
+            host,
+            process,
+            kubernetes,
+            otel_env,
+            timeout: DEFAULT_DETECTOR_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The merged resource, and the detectors that contributed nothing
+    /// because they failed. Those are returned rather than logged, as
+    /// detection runs before there is a subscriber to log to.
+    pub async fn detect(&self) -> (Resource, Vec<DetectorFailure>) {
+        let (host, process, kubernetes, otel_env) = tokio::join!(
+            detect_within("host", self.host.clone(), self.timeout),
+            detect_within("process", self.process.clone(), self.timeout),
+            detect_within("kubernetes", self.kubernetes.clone(), self.timeout),
+            detect_within("otel_env", self.otel_env.clone(), self.timeout),
+        );
+        let mut builder = Resource::builder_empty();
+        let mut failures = Vec::new();
+        for detected in [host, process, kubernetes, otel_env] {
+            match detected {
+                Ok(resource) => {
+                    builder = builder.with_attributes(
+                        resource
+                            .iter()
+                            .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+                    );
+                }
+                Err(failure) => failures.push(failure),
+            }
+        }
+        (builder.build(), failures)
+    }
+}
+
+/// Runs the blocking `detector` off the runtime, giving up after `timeout`.
+async fn detect_within(
+    name: &'static str,
+    detector: SharedDetector,
+    timeout: Duration,
+) -> Result<Resource, DetectorFailure> {
+    let detected = tokio::time::timeout(
+        timeout,
+        tokio::task::spawn_blocking(move || detector.detect()),
+    )
+    .await;
+    let failure = |reason: String| DetectorFailure {
+        detector: name,
+        reason,
+    };
+    match detected {
+        Ok(Ok(resource)) => Ok(resource),
+        Ok(Err(err)) => Err(failure(err.to_string())),
+        Err(_) => Err(failure(format!(
+            "timed out after {}s",
+            timeout.as_secs_f64()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use opentelemetry::{Key, Value};
+    use opentelemetry_semantic_conventions::resource::{
+        HOST_NAME, K8S_NAMESPACE_NAME, K8S_POD_NAME, PROCESS_PID,
+    };
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn get(resource: &Resource, key: &'static str) -> Option<Value> {
+        resource.get(&Key::from_static_str(key))
+    }
+
+    /// Detects `attributes`, after sleeping for `delay` or panicking.
+    struct FixedDetector {
+        attributes: Vec<KeyValue>,
+        delay: Duration,
+        panics: bool,
+    }
+
+    impl FixedDetector {
+        fn shared(attributes: &[(&'static str, &'static str)]) -> SharedDetector {
+            Arc::new(FixedDetector {
+                attributes: attributes
+                    .iter()
+                    .map(|(key, value)| KeyValue::new(*key, *value))
+                    .collect(),
+                delay: Duration::ZERO,
+                panics: false,
+            })
+        }
+    }
+
+    impl ResourceDetector for FixedDetector {
+        fn detect(&self) -> Resource {
+            std::thread::sleep(self.delay);
+            assert!(!self.panics, "detector panicked");
+            Resource::builder_empty()
+                .with_attributes(self.attributes.clone())
+                .build()
+        }
+    }
+
+    #[test]
+    fn test_kubernetes_detector() {
+        let labels = KubernetesLabels::from_lookup(|var| match var {
+            "HOSTNAME" => Some("shipping-7d9f".to_string()),
+            "K8S_NAMESPACE" => Some("demo".to_string()),
+            _ => None,
+        });
+        let resource = KubernetesResourceDetector::new(labels).detect();
+        assert_eq!(get(&resource, K8S_POD_NAME), Some("shipping-7d9f".into()));
+        assert_eq!(get(&resource, K8S_NAMESPACE_NAME), Some("demo".into()));
+        assert_eq!(resource.len(), 2);
+
+        let resource = KubernetesResourceDetector::new(KubernetesLabels::default()).detect();
+        assert!(resource.is_empty());
+    }
+
+    #[test]
+    fn test_otel_env_detector() {
+        let detector = OtelEnvResourceDetector::from_lookup(|var| match var {
+            "OTEL_RESOURCE_ATTRIBUTES" => {
+                Some("service.name=from-attributes, deployment.environment.name=prod,=x,bad".into())
+            }
+            "OTEL_SERVICE_NAME" => Some("shipping".into()),
+            _ => None,
+        });
+        let resource = detector.detect();
+        assert_eq!(get(&resource, SERVICE_NAME), Some("shipping".into()));
+        assert_eq!(
+            get(&resource, "deployment.environment.name"),
+            Some("prod".into())
+        );
+        assert_eq!(resource.len(), 2);
+
+        assert!(OtelEnvResourceDetector::from_lookup(|_| None)
+            .detect()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_process_detector() {
+        let resource = ProcessResourceDetector.detect();
+        assert_eq!(
+            get(&resource, PROCESS_PID),
+            Some(i64::from(std::process::id()).into())
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_chain_merges_in_order() {
+        let root = env::temp_dir().join(format!("resource-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::write(root.join("etc/hostname"), "shipping-host\n").unwrap();
+        let chain = ResourceDetectorChain::new(
+            Arc::new(HostResourceDetector::with_root(&root, None)),
+            FixedDetector::shared(&[("service.name", "process"), ("process.pid", "1")]),
+            FixedDetector::shared(&[("service.name", "kubernetes")]),
+            FixedDetector::shared(&[("service.name", "env")]),
+        );
+
+        let (resource, failures) = chain.detect().await;
+        assert!(failures.is_empty());
+        assert_eq!(get(&resource, HOST_NAME), Some("shipping-host".into()));
+        assert_eq!(get(&resource, "process.pid"), Some("1".into()));
+        assert_eq!(get(&resource, SERVICE_NAME), Some("env".into()));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_failed_detectors_contribute_nothing() {
+        let slow = Arc::new(FixedDetector {
+            attributes: vec![KeyValue::new("slow", "yes")],
+            delay: Duration::from_millis(500),
+            panics: false,
+        });
+        let panicking = Arc::new(FixedDetector {
+            attributes: vec![KeyValue::new("panicking", "yes")],
+            delay: Duration::ZERO,
+            panics: true,
+        });
+        let chain = ResourceDetectorChain::new(
+            FixedDetector::shared(&[("host.name", "h")]),
+            slow,
+            panicking,
+            FixedDetector::shared(&[]),
+        )
+        .with_timeout(Duration::from_millis(100));
+
+        let (resource, failures) = chain.detect().await;
+        assert_eq!(resource.len(), 1);
+        assert_eq!(get(&resource, "host.name"), Some("h".into()));
+        let failed: Vec<_> = failures.iter().map(|failure| failure.detector).collect();
+        assert_eq!(failed, ["process", "kubernetes"]);
+        assert!(failures[0].reason.contains("timed out"));
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::env;
+
+use opentelemetry::{
+    trace::{Link, SamplingDecision, SamplingResult, SpanKind, TraceContextExt, TraceId},
+    Context, KeyValue,
+};
+use opentelemetry_sdk::trace::{Config, Sampler, ShouldSample};
+use opentelemetry_semantic_conventions::trace::HTTP_ROUTE;
+use tracing::warn;
+
+/// Routes whose sampling rate can be overridden, and the variable that sets it.
+const ROUTE_OVERRIDES: [(&str, &str); 2] = [
+    ("/get-quote", "TRACE_SAMPLING_OVERRIDE_GET_QUOTE"),
+    ("/ship-order", "TRACE_SAMPLING_OVERRIDE_SHIP_ORDER"),
+];
+
+/// Samples the server spans of some routes at their own rate, e.g. to keep
+/// only a tenth of the `/get-quote` traces under load. Spans of other routes,
+/// and spans without an `http.route`, go to the global sampler set by
+/// `OTEL_TRACES_SAMPLER`.
+///
+/// An override takes precedence over the parent's decision too, so it can
+/// drop the shipping service's spans from traces sampled upstream.
+#[derive(Debug, Clone)]
+pub struct RouteSampler {
+    rates: HashMap<&'static str, f64>,
+    fallback: Box<dyn ShouldSample>,
+}
+
+impl RouteSampler {
+    pub fn new(rates: HashMap<&'static str, f64>, fallback: Box<dyn ShouldSample>) -> Self {
+        RouteSampler { rates, fallback }
+    }
+
+    /// Reads `TRACE_SAMPLING_OVERRIDE_GET_QUOTE` and
+    /// `TRACE_SAMPLING_OVERRIDE_SHIP_ORDER`, each a rate from 0 to 1.
+    /// Invalid rates are logged and ignored.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|var| env::var(var).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut rates = HashMap::new();
+        for (route, var) in ROUTE_OVERRIDES {
+            let Some(value) = lookup(var) else {
+                continue;
+            };
+            match value.trim().parse::<f64>() {
+                Ok(rate) if (0_f64..=1_f64).contains(&rate) => {
+                    rates.insert(route, rate);
+                }
+                _ => warn!(
+                    name = "InvalidSamplingOverride",
+                    variable = var,
+                    value = value.as_str(),
+                    message = "Expected a sampling rate from 0 to 1, using the global sampler"
+                ),
+            }
+        }
+        Self::new(rates, Config::default().sampler)
+    }
+
+    pub fn rate(&self, route: &str) -> Option<f64> {
+        self.rates.get(route).copied()
+    }
+}
+
+impl ShouldSample for RouteSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        let rate = attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == HTTP_ROUTE)
+            .and_then(|route| self.rate(route.value.as_str().as_ref()));
+        match rate {
+            Some(rate) => Sampler::TraceIdRatioBased(rate).should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            ),
+            None => self.fallback.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            ),
+        }
+    }
+}
+
+/// Prefix of the health check routes, e.g. `/health/live`.
+const HEALTH_CHECK_ROUTE_PREFIX: &str = "/health/";
+
+/// Drops the spans of health check routes, which would only be noise, even
+/// in traces sampled upstream. Everything else goes to `inner`.
+#[derive(Debug, Clone)]
+pub struct HealthCheckSampler {
+    inner: Box<dyn ShouldSample>,
+}
+
+impl HealthCheckSampler {
+    pub fn new(inner: Box<dyn ShouldSample>) -> Self {
+        HealthCheckSampler { inner }
+    }
+}
+
+impl ShouldSample for HealthCheckSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        let health_check = attributes.iter().any(|attribute| {
+            attribute.key.as_str() == HTTP_ROUTE
+                && attribute
+                    .value
+                    .as_str()
+                    .starts_with(HEALTH_CHECK_ROUTE_PREFIX)
+        });
+        if !health_check {
+            return self.inner.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            );
+        }
+        SamplingResult {
+            decision: SamplingDecision::Drop,
+            attributes: Vec::new(),
+            trace_state: parent_context
+                .map(|cx| cx.span().span_context().trace_state().clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceFlags, TraceState, Tracer, TracerProvider,
+    };
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    use super::*;
+
+    const SPANS: usize = 2000;
+
+    /// How many of `SPANS` spans for `route` are exported.
+    fn exported(sampler: impl ShouldSample + 'static, route: &'static str) -> usize {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_sampler(sampler)
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = provider.tracer("test");
+        for _ in 0..SPANS {
+            tracer
+                .span_builder(route)
+                .with_attributes([KeyValue::new(HTTP_ROUTE, route)])
+                .start(&tracer);
+        }
+        exporter.get_finished_spans().unwrap().len()
+    }
+
+    fn sampler() -> RouteSampler {
+        RouteSampler::from_lookup(|var| match var {
+            "TRACE_SAMPLING_OVERRIDE_GET_QUOTE" => Some("0.1".to_string()),
+            "TRACE_SAMPLING_OVERRIDE_SHIP_ORDER" => Some("1.0".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_route_override_sets_sampling_rate() {
+        let quotes = exported(sampler(), "/get-quote");
+        assert!((150..=250).contains(&quotes), "{quotes} of {SPANS}");
+        assert_eq!(exported(sampler(), "/ship-order"), SPANS);
+    }
+
+    #[test]
+    fn test_other_routes_use_fallback() {
+        let sampler = RouteSampler::new(
+            HashMap::from([("/get-quote", 0_f64)]),
+            Box::new(Sampler::AlwaysOff),
+        );
+        assert_eq!(exported(sampler.clone(), "/get-quote"), 0);
+        assert_eq!(exported(sampler, "/version"), 0);
+
+        let sampler = RouteSampler::new(
+            HashMap::from([("/get-quote", 0_f64)]),
+            Box::new(Sampler::AlwaysOn),
+        );
+        assert_eq!(exported(sampler, "/version"), SPANS);
+    }
+
+    #[test]
+    fn test_invalid_override_is_ignored() {
+        let sampler = RouteSampler::from_lookup(|var| match var {
+            "TRACE_SAMPLING_OVERRIDE_GET_QUOTE" => Some("1.5".to_string()),
+            "TRACE_SAMPLING_OVERRIDE_SHIP_ORDER" => Some("often".to_string()),
+            _ => None,
+        });
+        assert_eq!(sampler.rate("/get-quote"), None);
+        assert_eq!(sampler.rate("/ship-order"), None);
+    }
+
+    #[test]
+    fn test_health_checks_are_dropped() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_sampler(HealthCheckSampler::new(Box::new(Sampler::ParentBased(
+                Box::new(Sampler::AlwaysOn),
+            ))))
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = provider.tracer("test");
+        let sampled_parent = Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from(1),
+            SpanId::from(1),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ));
+
+        for i in 0..1000 {
+            let route = if i % 2 == 0 {
+                "/health/live"
+            } else {
+                "/health/ready"
+            };
+            tracer
+                .span_builder(route)
+                .with_attributes([KeyValue::new(HTTP_ROUTE, route)])
+                .start_with_context(&tracer, &sampled_parent);
+        }
+        assert!(exporter.get_finished_spans().unwrap().is_empty());
+
+        tracer
+            .span_builder("/get-quote")
+            .with_attributes([KeyValue::new(HTTP_ROUTE, "/get-quote")])
+            .start_with_context(&tracer, &sampled_parent);
+        assert_eq!(exporter.get_finished_spans().unwrap().len(), 1);
+    }
+}
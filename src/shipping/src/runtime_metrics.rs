@@ -0,0 +1,186 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use opentelemetry::global;
+use opentelemetry::metrics::AsyncInstrument;
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions::attribute::THREAD_NAME;
+use tokio::runtime::Handle;
+
+use crate::metrics::KUBERNETES_LABELS;
+
+/// Last sampled values of the Tokio runtime metrics.
+///
+/// `worker_thread_count` and `injection_queue_depth` only need the stable
+/// `rt` feature of Tokio. The poll and steal counters are behind Tokio's
+/// unstable metrics API, so they are only collected when the service is built
+/// with `RUSTFLAGS="--cfg tokio_unstable"`; otherwise they stay at 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RuntimeMetricsState {
+    pub worker_thread_count: u64,
+    pub injection_queue_depth: u64,
+    pub scheduler_polls_total: u64,
+    pub task_steal_count_total: u64,
+}
+
+impl RuntimeMetricsState {
+    pub fn refresh(&mut self, handle: &Handle) {
+        let metrics = handle.metrics();
+        self.worker_thread_count = metrics.num_workers() as u64;
+        self.injection_queue_depth = metrics.global_queue_depth() as u64;
+
+        #[cfg(tokio_unstable)]
+        {
+            self.scheduler_polls_total = (0..metrics.num_workers())
+                .map(|worker| metrics.worker_poll_count(worker))
+                .sum();
+            self.task_steal_count_total = (0..metrics.num_workers())
+                .map(|worker| metrics.worker_steal_count(worker))
+                .sum();
+        }
+    }
+}
+
+/// The runtime of one `HttpServer` worker and its last sampled metrics.
+#[derive(Debug)]
+struct WorkerRuntime {
+    thread: String,
+    handle: Handle,
+    state: RuntimeMetricsState,
+}
+
+/// The runtimes of the `HttpServer` workers, each of which runs its own
+/// single-threaded Tokio runtime.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerRuntimes(Arc<Mutex<Vec<WorkerRuntime>>>);
+
+impl WorkerRuntimes {
+    /// Records the runtime of the calling worker. Meant to be called from the
+    /// `HttpServer::new` factory, which actix runs on every worker once per
+    /// listener; a worker that registers again replaces its old entry.
+    pub fn register(&self) {
+        let thread = thread::current().name().unwrap_or("unnamed").to_string();
+        let handle = Handle::current();
+        let mut workers = self.0.lock().unwrap();
+        match workers.iter_mut().find(|worker| worker.thread == thread) {
+            Some(worker) => worker.handle = handle,
+            None => workers.push(WorkerRuntime {
+                thread,
+                handle,
+                state: RuntimeMetricsState::default(),
+            }),
+        }
+    }
+
+    /// Samples the metrics of every registered worker runtime.
+    pub fn refresh(&self) {
+        for worker in self.0.lock().unwrap().iter_mut() {
+            worker.state.refresh(&worker.handle);
+        }
+    }
+
+    /// The last sampled metrics of each worker, by thread name.
+    pub fn states(&self) -> Vec<(String, RuntimeMetricsState)> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|worker| (worker.thread.clone(), worker.state))
+            .collect()
+    }
+
+    fn observe(&self, observer: &dyn AsyncInstrument<u64>, value: fn(&RuntimeMetricsState) -> u64) {
+        for (thread, state) in self.states() {
+            observer.observe(
+                value(&state),
+                &KUBERNETES_LABELS.with(&[KeyValue::new(THREAD_NAME, thread)]),
+            );
+        }
+    }
+}
+
+/// Registers the runtime instruments, labelled by worker thread, and returns
+/// the registry the workers add their runtimes to. The values are sampled by
+/// the CPU metrics loop, see `start_cpu_metrics_collection`.
+pub fn register_runtime_metrics() -> WorkerRuntimes {
+    let runtimes = WorkerRuntimes::default();
+    let meter = global::meter("otel_demo.shipping.runtime");
+
+    let r = runtimes.clone();
+    meter
+        .u64_observable_counter("tokio_scheduler_polls_total")
+        .with_description("Number of tasks polled by the runtime workers")
+        .with_callback(move |observer| r.observe(observer, |state| state.scheduler_polls_total))
+        .build();
+
+    let r = runtimes.clone();
+    meter
+        .u64_observable_counter("tokio_task_steal_count_total")
+        .with_description("Number of tasks stolen between runtime workers")
+        .with_callback(move |observer| r.observe(observer, |state| state.task_steal_count_total))
+        .build();
+
+    let r = runtimes.clone();
+    meter
+        .u64_observable_gauge("tokio_injection_queue_depth")
+        .with_description("Number of tasks waiting in the runtime injection queue")
+        .with_callback(move |observer| r.observe(observer, |state| state.injection_queue_depth))
+        .build();
+
+    let r = runtimes.clone();
+    meter
+        .u64_observable_gauge("tokio_worker_thread_count")
+        .with_description("Number of worker threads used by the runtime")
+        .with_callback(move |observer| r.observe(observer, |state| state.worker_thread_count))
+        .build();
+
+    runtimes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_refresh_reads_worker_runtimes() {
+        use actix_web::{App, HttpServer};
+
+        let runtimes = WorkerRuntimes::default();
+        let registered = runtimes.clone();
+        let server = HttpServer::new(move || {
+            registered.register();
+            App::new()
+        })
+        .workers(2)
+        .bind(("127.0.0.1", 0))
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        for _ in 0..100 {
+            if runtimes.states().len() == 2 {
+                break;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+        }
+        runtimes.refresh();
+        let mut states = runtimes.states();
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        handle.stop(false).await;
+
+        // One entry per worker, none for the test's own runtime
+        let threads: Vec<_> = states.iter().map(|(thread, _)| thread.as_str()).collect();
+        assert_eq!(threads.len(), 2);
+        assert!(!threads.contains(&thread::current().name().unwrap_or("unnamed")));
+        for (_, state) in states {
+            // Every actix worker runs a single-threaded runtime
+            assert_eq!(state.worker_thread_count, 1);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{
+    http::header::{X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS},
+    middleware::DefaultHeaders,
+};
+
+/// `X-Frame-Options: DENY` and `X-Content-Type-Options: nosniff` on every
+/// response that doesn't set them itself. The API serves no pages, so
+/// nothing should frame it or sniff its content type.
+pub fn security_headers() -> DefaultHeaders {
+    DefaultHeaders::new()
+        .add((X_FRAME_OPTIONS, "DENY"))
+        .add((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_security_headers() {
+        let app = test::init_service(
+            App::new()
+                .wrap(security_headers())
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert_eq!(
+            resp.headers().get(X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+    }
+}
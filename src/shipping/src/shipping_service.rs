@@ -1,78 +1,1154 @@
 // Copyright The OpenTelemetry Authors
 // SPDX-License-Identifier: Apache-2.0
 
-use actix_web::{post, web, HttpResponse, Responder};
-use tracing::info;
+use actix_web::{http::StatusCode, post, web, HttpResponse, Responder};
+use opentelemetry::{
+    global,
+    logs::AnyValue,
+    trace::{get_active_span, SpanContext, SpanId, TraceFlags, TraceId, TraceState, Tracer},
+    Key, KeyValue,
+};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, instrument, warn};
 
-mod quote;
-use quote::create_quote_from_count;
+use crate::audit::{AuditLogger, ORDER_PLACED, QUOTE_ISSUED};
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+use crate::utils::spawn_with_context;
+
+pub mod quote;
+use quote::{with_timeout, QuoteConfig, QuoteError};
+
+mod backend;
+#[cfg(test)]
+pub use backend::MockShippingBackend;
+pub use backend::{dispatch_order, QuoteOrder, RealShippingBackend, ShippingBackend};
+
+mod canary;
+pub use canary::{Canary, CANARY_HEADER};
+
+mod tenant;
+pub use tenant::{Tenant, TenantConfig, Tenants, API_KEY_HEADER, TENANT_ID};
+
+mod estimator;
+pub use estimator::DeliveryEstimator;
 
 mod tracking;
-use tracking::create_tracking_id;
+pub use tracking::{TrackingId, WeightClass};
 
 mod shipping_types;
 pub use shipping_types::*;
 
+mod responses;
+use responses::json_response;
+
+mod api_version;
+pub use api_version::{ApiVersion, API_VERSION_HEADER, SUPPORTED_API_VERSIONS};
+
+pub mod validation;
+use validation::Validated;
+
+pub mod zones;
+use zones::{zone_from_country, ShippingZone};
+
+mod idempotency;
+pub use idempotency::{
+    start_idempotency_eviction, CachedReply, IdempotencyCache, IdempotencyKey, CACHE_HEADER,
+    IDEMPOTENCY_KEY_HEADER,
+};
+
+mod currency;
+pub use currency::{CurrencyConverter, CurrencyMeta};
+
+mod exchange_rates;
+pub use exchange_rates::{
+    start_exchange_rate_refresh, ExchangeRates, RateError, RateTable,
+    DEFAULT_EXCHANGE_RATES_REFRESH, DEFAULT_EXCHANGE_RATES_STALE_EXTENSION,
+};
+
+mod dead_letter_queue;
+pub use dead_letter_queue::{start_dead_letter_retries, DeadLetterQueue};
+
+mod order_events;
+pub use order_events::{
+    DisconnectedProducer, KafkaProducerWrapper, KafkaRecord, KafkaSend, OrderShipped,
+    ORDER_EVENTS_TOPIC,
+};
+
+/// Request headers the handlers read, which browsers must be allowed to send.
+pub const REQUEST_HEADERS: [&str; 4] = [
+    IDEMPOTENCY_KEY_HEADER,
+    API_VERSION_HEADER,
+    API_KEY_HEADER,
+    CANARY_HEADER,
+];
+
+pub type ShipOrderDeadLetters = Mutex<DeadLetterQueue<ShipOrderRequest>>;
+
+/// Quote replies by `X-Idempotency-Key`, so a retried request gets the quote
+/// it was already given.
+pub type QuoteCache = Mutex<IdempotencyCache<GetQuoteResponse>>;
+
+/// Quote replies by cart, for requests without an idempotency key, so a
+/// double-click isn't priced twice. Only held for a moment, as a cart
+/// quoted again later should get a fresh price.
+pub struct QuoteDedup(QuoteCache);
+
+impl Default for QuoteDedup {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW)
+    }
+}
+
+impl QuoteDedup {
+    pub fn new(window: Duration) -> Self {
+        QuoteDedup(Mutex::new(IdempotencyCache::new(window, DEDUP_MAX_ENTRIES)))
+    }
+}
+
+const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(2);
+const DEDUP_MAX_ENTRIES: usize = 1_000;
+
 const NANOS_MULTIPLE: u32 = 10000000u32;
 
 #[post("/get-quote")]
-pub async fn get_quote(req: web::Json<GetQuoteRequest>) -> impl Responder {
-    let itemct: u32 = req.items.iter().map(|item| item.quantity as u32).sum();
+#[instrument(skip_all)]
+pub async fn get_quote(
+    req: Validated<GetQuoteRequest>,
+    correlation_id: CorrelationId,
+    canary: Canary,
+    (backend, quote_config): (
+        web::Data<dyn ShippingBackend>,
+        Option<web::Data<QuoteConfig>>,
+    ),
+    (idempotency_key, cache, dedup): (
+        IdempotencyKey,
+        Option<web::Data<QuoteCache>>,
+        Option<web::Data<QuoteDedup>>,
+    ),
+    audit: Option<web::Data<AuditLogger>>,
+    Tenant(tenant): Tenant,
+) -> impl Responder {
+    debug!(
+        name = "QuoteRequested",
+        item_count = req.items.len(),
+        correlation.id = %correlation_id,
+        message = "Received quote request"
+    );
 
-    let quote = match create_quote_from_count(itemct).await {
-        Ok(q) => q,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("Failed to get quote: {}", e));
+    // Without an idempotency key, the same cart shares a quote for the
+    // dedup window. Either is skipped unless the app registers it, and
+    // scoped to the tenant so one can't be served another's price.
+    let body_hash = req.fingerprint();
+    let cache_key: Option<(&QuoteCache, String)> = match &idempotency_key.0 {
+        Some(key) => cache
+            .as_deref()
+            .map(|cache| (&**cache, format!("{}:{}", tenant.id, key))),
+        None => dedup.as_deref().map(|dedup| {
+            let key = format!("{}:cart:{:016x}:{}", tenant.id, body_hash, canary.0);
+            (&dedup.0, key)
+        }),
+    };
+    if let Some((cache, key)) = &cache_key {
+        let cached = cache.lock().unwrap().get(key, body_hash, Instant::now());
+        if matches!(cached, CachedReply::Mismatch) {
+            return ErrorResponse::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "idempotency_key_reused",
+                "Idempotency key was already used for a different request",
+                correlation_id,
+            )
+            .into_response();
+        }
+        if let CachedReply::Hit(reply) = cached {
+            info!(
+                name = "SendingCachedQuote",
+                cache.key = key.as_str(),
+                correlation.id = %correlation_id,
+                message = "Sending cached Quote"
+            );
+            if let Some(audit) = &audit {
+                audit.debug(
+                    "quote.cache_hit",
+                    vec![
+                        (Key::new("cache.key"), key.clone().into()),
+                        (
+                            Key::new("correlation.id"),
+                            correlation_id.to_string().into(),
+                        ),
+                    ],
+                );
+            }
+            let mut builder = HttpResponse::Ok();
+            builder.insert_header((CACHE_HEADER, "hit"));
+            return json_response(builder, "/get-quote", &reply);
         }
+    }
+
+    if !tenant.allows_currency("USD") {
+        return ErrorResponse::new(
+            StatusCode::FORBIDDEN,
+            "currency_not_allowed",
+            "Quotes are only available in USD",
+            correlation_id,
+        )
+        .into_response();
+    }
+
+    let Some(itemct) = req
+        .items
+        .iter()
+        .try_fold(0_u32, |count, item| count.checked_add(item.quantity))
+    else {
+        return ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "quantity_too_large",
+            "Total item quantity is too large",
+            correlation_id,
+        )
+        .into_response();
     };
+    let zone = req
+        .address
+        .as_ref()
+        .and_then(|address| address.country.as_deref())
+        .map(zone_from_country)
+        .unwrap_or(ShippingZone::Domestic);
 
-    let reply = GetQuoteResponse {
-        cost_usd: Some(Money {
-            currency_code: "USD".into(),
-            units: quote.dollars,
-            nanos: quote.cents * NANOS_MULTIPLE,
-        }),
+    let quote_config =
+        quote_config.map_or_else(QuoteConfig::default, |config| config.get_ref().clone());
+    let strategy = quote_config.strategy_for(canary.0);
+
+    let speed = req.delivery_speed.unwrap_or_default();
+
+    // Nothing to ship costs nothing, without asking for a price.
+    let quote = if itemct == 0 {
+        Ok(Quote::default())
+    } else {
+        let order = QuoteOrder {
+            zone,
+            items: &req.items,
+            speed,
+            strategy,
+            canary: canary.0,
+            tenant: &tenant,
+            discounts: &quote_config.discounts,
+        };
+        with_timeout(quote_config.timeout, backend.quote(itemct, &order)).await
+    };
+    let quote = match quote {
+        Ok(q) => q,
+        Err(e) => {
+            let (status, code) = match e {
+                QuoteError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, "quote_timeout"),
+                QuoteError::Overflow => (StatusCode::BAD_REQUEST, "quote_too_large"),
+                QuoteError::Pricing(_) => (StatusCode::INTERNAL_SERVER_ERROR, "quote_failed"),
+            };
+            let message = format!("Failed to get quote: {}", e);
+            return ErrorResponse::new(status, code, message, correlation_id).into_response();
+        }
     };
 
     info!(
         name = "SendingQuoteValue",
         quote.dollars = quote.dollars,
         quote.cents = quote.cents,
+        correlation.id = %correlation_id,
+        canary = canary.0,
         message = "Sending Quote"
     );
+    if let Some(audit) = &audit {
+        audit.audit(
+            QUOTE_ISSUED,
+            vec![
+                (Key::new("quote.item_count"), AnyValue::from(itemct as i64)),
+                (Key::new("quote.zone"), format!("{zone:?}").into()),
+                (
+                    Key::new("quote.delivery_speed"),
+                    format!("{speed:?}").to_lowercase().into(),
+                ),
+                (Key::new("quote.strategy"), strategy.as_str().into()),
+                (
+                    Key::new("quote.cost_usd"),
+                    format!("{}.{:02}", quote.dollars, quote.cents).into(),
+                ),
+                (
+                    Key::new("correlation.id"),
+                    correlation_id.to_string().into(),
+                ),
+                (Key::new("canary"), canary.0.into()),
+                (Key::new(TENANT_ID), tenant.id.clone().into()),
+            ],
+        );
+    }
+
+    global::tracer("otel_demo.shipping.quote").in_span("quote.serialize", |_| {
+        let reply = GetQuoteResponse {
+            cost_usd: Some(Money {
+                currency_code: "USD".into(),
+                units: quote.dollars,
+                nanos: quote.cents * NANOS_MULTIPLE,
+            }),
+            delivery_speed: speed,
+        };
+        let mut builder = HttpResponse::Ok();
+        if let Some((cache, key)) = cache_key {
+            cache
+                .lock()
+                .unwrap()
+                .insert(key, body_hash, reply.clone(), Instant::now());
+            builder.insert_header((CACHE_HEADER, "miss"));
+        }
+        json_response(builder, "/get-quote", &reply)
+    })
+}
 
-    HttpResponse::Ok().json(reply)
+/// Context to link the order's span to the quote's trace by. The quote's
+/// span isn't known, so only its trace ID is set. Like in `traceparent`, it
+/// must be 32 lowercase hex digits and not all zeros.
+fn quote_link(quote_trace_id: &str) -> Option<SpanContext> {
+    let well_formed = quote_trace_id.len() == 32
+        && quote_trace_id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    if !well_formed {
+        return None;
+    }
+    let trace_id = TraceId::from_hex(quote_trace_id).ok()?;
+    (trace_id != TraceId::INVALID).then(|| {
+        SpanContext::new(
+            trace_id,
+            SpanId::INVALID,
+            TraceFlags::default(),
+            true,
+            TraceState::default(),
+        )
+    })
 }
 
 #[post("/ship-order")]
-pub async fn ship_order(_req: web::Json<ShipOrderRequest>) -> impl Responder {
-    let tid = create_tracking_id();
+#[instrument(skip_all)]
+pub async fn ship_order(
+    req: Validated<ShipOrderRequest>,
+    correlation_id: CorrelationId,
+    dead_letters: web::Data<ShipOrderDeadLetters>,
+    backend: web::Data<dyn ShippingBackend>,
+    audit: Option<web::Data<AuditLogger>>,
+    order_events: Option<web::Data<KafkaProducerWrapper>>,
+) -> impl Responder {
+    if let Some(quote_trace_id) = &req.quote_trace_id {
+        let Some(link) = quote_link(quote_trace_id) else {
+            return ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_quote_trace_id",
+                "quote_trace_id is not a valid trace ID",
+                correlation_id,
+            )
+            .into_response();
+        };
+        get_active_span(|span| span.add_link(link, vec![KeyValue::new("link.type", "quote")]));
+    }
+
+    let weight_class = WeightClass::from_grams(req.total_weight_g);
+    let tid = match backend.track(weight_class).await {
+        Ok(tid) => tid.to_string(),
+        Err(e) => {
+            let error = match dead_letters.lock().unwrap().push(req.into_inner()) {
+                Ok(()) => ErrorResponse::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "order_queued_for_retry",
+                    format!("Failed to ship order, queued for retry: {}", e),
+                    correlation_id,
+                ),
+                Err(_) => ErrorResponse::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "ship_order_failed",
+                    format!("Failed to ship order: {}", e),
+                    correlation_id,
+                ),
+            };
+            return error.into_response();
+        }
+    };
+
     info!(
         name = "CreatingTrackingId",
         tracking_id = tid.as_str(),
+        correlation.id = %correlation_id,
         message = "Tracking ID Created"
     );
-    HttpResponse::Ok().json(ShipOrderResponse { tracking_id: tid })
+    if let Some(audit) = &audit {
+        let mut attributes = vec![
+            (
+                Key::new("order.weight_class"),
+                AnyValue::from(format!("{weight_class:?}")),
+            ),
+            (Key::new("order.tracking_id"), tid.clone().into()),
+            (
+                Key::new("correlation.id"),
+                correlation_id.to_string().into(),
+            ),
+        ];
+        if let Some(grams) = req.total_weight_g {
+            attributes.push((Key::new("order.total_weight_g"), (grams as i64).into()));
+        }
+        audit.audit(ORDER_PLACED, attributes);
+    }
+    if let Some(order_events) = order_events {
+        let event = OrderShipped {
+            tracking_id: tid.clone(),
+            weight_class,
+            total_weight_g: req.total_weight_g,
+        };
+        // Published off the request path, so a slow broker doesn't hold up
+        // the response
+        spawn_with_context(async move { order_events.order_shipped(&event).await });
+    }
+    let zone = req
+        .address
+        .as_ref()
+        .and_then(|address| address.country.as_deref())
+        .map(zone_from_country);
+    let estimate = DeliveryEstimator.estimate(zone, req.delivery_speed);
+    if let Some((days, _)) = &estimate {
+        get_active_span(|span| span.set_attribute(KeyValue::new("estimate.days", *days)));
+    }
+    json_response(
+        HttpResponse::Ok(),
+        "/ship-order",
+        &ShipOrderResponse {
+            tracking_id: tid,
+            estimated_delivery_date: estimate.map(|(_, date)| date),
+        },
+    )
+}
+
+/// Retry callback for the ship-order dead letter queue.
+pub fn retry_ship_order(req: &ShipOrderRequest) -> bool {
+    match dispatch_order(WeightClass::from_grams(req.total_weight_g)) {
+        Ok(tid) => {
+            info!(
+                name = "CreatingTrackingId",
+                tracking_id = %tid,
+                message = "Tracking ID Created for retried order"
+            );
+            true
+        }
+        Err(e) => {
+            warn!(
+                name = "ShipOrderRetryFailed",
+                error = %e,
+                message = "Failed to retry order"
+            );
+            false
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use actix_web::{http::header::ContentType, test, App};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, App,
+    };
+    use opentelemetry::{
+        trace::{FutureExt, Span, TraceContextExt},
+        Context, KeyValue,
+    };
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
 
     use super::*;
+    use crate::audit::tests::{attribute, test_audit_logger};
+    use crate::error_response::tests::assert_error;
+    use crate::telemetry_conf::otel_trace_layer;
+    use crate::test_utils::{global_span_exporter, global_tracer_provider};
+
+    fn backend(backend: impl ShippingBackend + 'static) -> web::Data<dyn ShippingBackend> {
+        web::Data::from(Arc::new(backend) as Arc<dyn ShippingBackend>)
+    }
+
+    #[actix_web::test]
+    async fn test_canary_quote_uses_tiered_pricing() {
+        let app = test::init_service(
+            App::new()
+                .app_data(backend(RealShippingBackend))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .insert_header(("X-Canary", "true"))
+            .set_json(serde_json::json!({ "items": [{ "quantity": 20 }] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let quote: serde_json::Value = test::read_body_json(resp).await;
+        // 22.50 tiered, less the 10% discount for 6-20 items
+        assert_eq!(quote["cost_usd"]["units"], 20);
+        assert_eq!(quote["cost_usd"]["nanos"], 250_000_000);
+        assert_eq!(quote["delivery_speed"], "standard");
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_delivery_speed() {
+        let app = test::init_service(
+            App::new()
+                .app_data(backend(RealShippingBackend))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .insert_header(("X-Canary", "true"))
+            .set_json(serde_json::json!({
+                "items": [{ "quantity": 20 }],
+                "delivery_speed": "overnight"
+            }))
+            .to_request();
+        let quote: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        // 22.50 tiered, 2.5x for overnight, less the 10% discount
+        assert_eq!(quote["cost_usd"]["units"], 50);
+        assert_eq!(quote["cost_usd"]["nanos"], 630_000_000);
+        assert_eq!(quote["delivery_speed"], "overnight");
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_child_spans() {
+        let exporter = global_span_exporter();
+        let parent = global::tracer("test").start("/get-quote");
+        let parent_context = parent.span_context().clone();
+        let cx = Context::current_with_span(parent);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(backend(RealShippingBackend))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .insert_header(("X-Canary", "true"))
+            .set_json(serde_json::json!({ "items": [{ "quantity": 20 }] }))
+            .to_request();
+        let resp = test::call_service(&app, req).with_context(cx.clone()).await;
+        assert!(resp.status().is_success());
+        cx.span().end();
+
+        let children: Vec<_> = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .filter(|span| span.span_context.trace_id() == parent_context.trace_id())
+            .filter(|span| span.span_context != parent_context)
+            .collect();
+        let names: Vec<_> = children.iter().map(|span| span.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            [
+                "quote.parse_items",
+                "quote.apply_discount",
+                "quote.convert_currency",
+                "quote.serialize"
+            ]
+        );
+        for span in &children {
+            assert_eq!(
+                span.parent_span_id,
+                parent_context.span_id(),
+                "{}",
+                span.name
+            );
+        }
+        assert!(children[0]
+            .attributes
+            .contains(&KeyValue::new("quote.item_count", 20)));
+        assert!(children[1]
+            .attributes
+            .contains(&KeyValue::new("quote.discount_percent", 10_f64)));
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_from_backend() {
+        let mock = Arc::new(MockShippingBackend {
+            quote: Some(Quote {
+                dollars: 12,
+                cents: 34,
+            }),
+            ..Default::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(mock.clone() as Arc<dyn ShippingBackend>))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .set_json(serde_json::json!({ "items": [{ "quantity": 2 }, { "quantity": 3 }] }))
+            .to_request();
+        let quote: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(quote["cost_usd"]["units"], 12);
+        assert_eq!(quote["cost_usd"]["nanos"], 340_000_000);
+        assert_eq!(*mock.quoted.lock().unwrap(), [5]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_idempotency_key_hit() {
+        let mock = Arc::new(MockShippingBackend {
+            quote: Some(Quote {
+                dollars: 7,
+                cents: 50,
+            }),
+            ..Default::default()
+        });
+        let cache = web::Data::new(QuoteCache::new(IdempotencyCache::new(
+            Duration::from_secs(120),
+            100,
+        )));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(mock.clone() as Arc<dyn ShippingBackend>))
+                .app_data(cache.clone())
+                .service(get_quote),
+        )
+        .await;
+        let request = |key: &'static str| {
+            test::TestRequest::post()
+                .uri("/get-quote")
+                .insert_header((IDEMPOTENCY_KEY_HEADER, key))
+                .set_json(serde_json::json!({ "items": [{ "quantity": 2 }] }))
+                .to_request()
+        };
+
+        let first = test::call_service(&app, request("cart-1")).await;
+        assert_eq!(first.headers().get(CACHE_HEADER).unwrap(), "miss");
+        let first = test::read_body(first).await;
+
+        let second = test::call_service(&app, request("cart-1")).await;
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(second.headers().get(CACHE_HEADER).unwrap(), "hit");
+        assert_eq!(test::read_body(second).await, first);
+        assert_eq!(*mock.quoted.lock().unwrap(), [2]);
+
+        test::call_service(&app, request("cart-2")).await;
+        assert_eq!(*mock.quoted.lock().unwrap(), [2, 2]);
+        assert_eq!(cache.lock().unwrap().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_idempotency_key_reused_for_other_cart() {
+        let mock = Arc::new(MockShippingBackend {
+            quote: Some(Quote {
+                dollars: 7,
+                cents: 50,
+            }),
+            ..Default::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(mock.clone() as Arc<dyn ShippingBackend>))
+                .app_data(web::Data::new(QuoteCache::new(IdempotencyCache::new(
+                    Duration::from_secs(120),
+                    100,
+                ))))
+                .service(get_quote),
+        )
+        .await;
+        let request = |quantity: u32| {
+            test::TestRequest::post()
+                .uri("/get-quote")
+                .insert_header((IDEMPOTENCY_KEY_HEADER, "cart-1"))
+                .set_json(serde_json::json!({ "items": [{ "quantity": quantity }] }))
+                .to_request()
+        };
+
+        test::call_service(&app, request(2)).await;
+        let reused = test::call_service(&app, request(3)).await;
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = test::read_body_json(reused).await;
+        assert_eq!(body["code"], "idempotency_key_reused");
+        assert_eq!(*mock.quoted.lock().unwrap(), [2]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_same_cart_in_any_order_is_cached() {
+        let mock = Arc::new(MockShippingBackend {
+            quote: Some(Quote {
+                dollars: 7,
+                cents: 50,
+            }),
+            ..Default::default()
+        });
+        let cache = web::Data::new(QuoteCache::new(IdempotencyCache::new(
+            Duration::from_secs(120),
+            100,
+        )));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(mock.clone() as Arc<dyn ShippingBackend>))
+                .app_data(cache.clone())
+                .app_data(web::Data::new(QuoteDedup::new(Duration::from_millis(200))))
+                .service(get_quote),
+        )
+        .await;
+        let request = |items: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/get-quote")
+                .set_json(serde_json::json!({ "items": items }))
+                .to_request()
+        };
+
+        let first = test::call_service(
+            &app,
+            request(serde_json::json!([{ "quantity": 1, "weight_g": 500 }, { "quantity": 2 }])),
+        )
+        .await;
+        assert_eq!(first.headers().get(CACHE_HEADER).unwrap(), "miss");
+        let first = test::read_body(first).await;
+
+        let second = test::call_service(
+            &app,
+            request(serde_json::json!([{ "quantity": 2 }, { "quantity": 1, "weight_g": 500 }])),
+        )
+        .await;
+        assert_eq!(second.headers().get(CACHE_HEADER).unwrap(), "hit");
+        assert_eq!(test::read_body(second).await, first);
+        assert_eq!(*mock.quoted.lock().unwrap(), [3]);
+
+        let other = test::call_service(&app, request(serde_json::json!([{ "quantity": 3 }]))).await;
+        assert_eq!(other.headers().get(CACHE_HEADER).unwrap(), "miss");
+        assert_eq!(*mock.quoted.lock().unwrap(), [3, 3]);
+        // Keyless carts are only deduplicated for the window, and never
+        // held in the idempotency cache.
+        assert!(cache.lock().unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let later = test::call_service(&app, request(serde_json::json!([{ "quantity": 3 }]))).await;
+        assert_eq!(later.headers().get(CACHE_HEADER).unwrap(), "miss");
+        assert_eq!(*mock.quoted.lock().unwrap(), [3, 3, 3]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_emits_audit_log() {
+        let (audit, exporter) = test_audit_logger();
+        let app = test::init_service(
+            App::new()
+                .app_data(backend(RealShippingBackend))
+                .app_data(web::Data::new(audit))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .insert_header(("X-Canary", "true"))
+            .set_json(serde_json::json!({ "items": [{ "quantity": 20 }] }))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        let [log] = logs.as_slice() else {
+            panic!("expected one audit log, got {}", logs.len());
+        };
+        assert_eq!(
+            attribute(&log.record, "event.name"),
+            Some(AnyValue::from(QUOTE_ISSUED))
+        );
+        assert_eq!(
+            attribute(&log.record, "quote.cost_usd"),
+            Some(AnyValue::from("20.25"))
+        );
+        assert_eq!(
+            attribute(&log.record, "quote.item_count"),
+            Some(AnyValue::from(20_i64))
+        );
+    }
+
+    async fn tenant_quote(api_key: Option<&str>) -> (StatusCode, serde_json::Value) {
+        let tenants = Tenants::from_json(
+            r#"{"acme-key": {"id": "acme", "pricing_multiplier": 0.8},
+                "euro-key": {"id": "euro", "allowed_currencies": ["EUR"]},
+                "whale-key": {"id": "whale", "pricing_multiplier": 1e18}}"#,
+        )
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(backend(RealShippingBackend))
+                .app_data(web::Data::new(tenants))
+                .service(get_quote),
+        )
+        .await;
+        let mut req = test::TestRequest::post()
+            .uri("/get-quote")
+            .insert_header(("X-Canary", "true"))
+            .set_json(serde_json::json!({ "items": [{ "quantity": 20 }] }));
+        if let Some(key) = api_key {
+            req = req.insert_header((API_KEY_HEADER, key));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        let status = resp.status();
+        let body = test::read_body(resp).await;
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_default_tenant() {
+        let (status, quote) = tenant_quote(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(quote["cost_usd"]["units"], 20);
+        assert_eq!(quote["cost_usd"]["nanos"], 250_000_000);
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_known_tenant() {
+        // 22.50 tiered, 0.8x for the tenant, less the 10% discount
+        let (status, quote) = tenant_quote(Some("acme-key")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(quote["cost_usd"]["units"], 16);
+        assert_eq!(quote["cost_usd"]["nanos"], 200_000_000);
+
+        let (status, error) = tenant_quote(Some("euro-key")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(error["code"], "currency_not_allowed");
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_overflow() {
+        let (status, error) = tenant_quote(Some("whale-key")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "quote_too_large");
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_unknown_api_key() {
+        let (status, error) = tenant_quote(Some("stolen-key")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error["code"], "unknown_api_key");
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_backend_error() {
+        let app = test::init_service(
+            App::new()
+                .app_data(backend(MockShippingBackend::default()))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .set_json(serde_json::json!({ "items": [{ "quantity": 1 }] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::INTERNAL_SERVER_ERROR, "quote_failed").await;
+    }
+
+    #[actix_web::test]
+    async fn test_get_quote_times_out() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(QuoteConfig {
+                    timeout: Duration::from_millis(100),
+                    ..Default::default()
+                }))
+                .app_data(backend(MockShippingBackend {
+                    quote: Some(Quote::default()),
+                    delay: Duration::from_secs(5),
+                    ..Default::default()
+                }))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .set_json(serde_json::json!({ "items": [{ "quantity": 1 }] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::GATEWAY_TIMEOUT, "quote_timeout").await;
+    }
+
+    #[actix_web::test]
+    async fn test_canary_header() {
+        let req = test::TestRequest::default()
+            .insert_header(("X-Canary", "TRUE"))
+            .to_http_request();
+        assert_eq!(Canary::from_headers(req.headers()), Canary(true));
+
+        let req = test::TestRequest::default()
+            .insert_header(("X-Canary", "false"))
+            .to_http_request();
+        assert_eq!(Canary::from_headers(req.headers()), Canary(false));
+
+        let req = test::TestRequest::default().to_http_request();
+        assert_eq!(Canary::from_headers(req.headers()), Canary(false));
+    }
 
     #[actix_web::test]
     async fn test_ship_order() {
-        let app = test::init_service(App::new().service(ship_order)).await;
+        let dead_letters = web::Data::new(ShipOrderDeadLetters::new(DeadLetterQueue::new(1, 1)));
+        let tracking_id = TrackingId::new(WeightClass::Heavy);
+        let app = test::init_service(
+            App::new()
+                .app_data(dead_letters)
+                .app_data(backend(MockShippingBackend {
+                    tracking_id: Some(tracking_id),
+                    ..Default::default()
+                }))
+                .service(ship_order),
+        )
+        .await;
         let req = test::TestRequest::post()
             .uri("/ship-order")
             .insert_header(ContentType::json())
-            .set_json(&ShipOrderRequest {})
+            .set_json(&ShipOrderRequest {
+                total_weight_g: Some(12_000),
+                ..Default::default()
+            })
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success());
 
         let order: ShipOrderResponse = test::read_body_json(resp).await;
-        assert!(!order.tracking_id.is_empty());
+        assert_eq!(order.tracking_id, tracking_id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_does_not_wait_for_the_broker() {
+        struct StuckProducer;
+
+        #[async_trait::async_trait]
+        impl KafkaSend for StuckProducer {
+            async fn send(&self, _: KafkaRecord) -> anyhow::Result<()> {
+                std::future::pending().await
+            }
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ShipOrderDeadLetters::new(
+                    DeadLetterQueue::new(1, 1),
+                )))
+                .app_data(backend(MockShippingBackend {
+                    tracking_id: Some(TrackingId::new(WeightClass::Light)),
+                    ..Default::default()
+                }))
+                .app_data(web::Data::new(KafkaProducerWrapper::new(
+                    Box::new(StuckProducer),
+                    &global::meter("test"),
+                )))
+                .service(ship_order),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/ship-order")
+            .set_json(ShipOrderRequest::default())
+            .to_request();
+        let resp = tokio::time::timeout(Duration::from_secs(1), test::call_service(&app, req))
+            .await
+            .expect("ship_order waited for the publish");
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_estimates_delivery() {
+        let exporter = global_span_exporter();
+        let parent = global::tracer("test").start("/ship-order");
+        let trace_id = parent.span_context().trace_id();
+        let cx = Context::current_with_span(parent);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ShipOrderDeadLetters::new(
+                    DeadLetterQueue::new(1, 1),
+                )))
+                .app_data(backend(RealShippingBackend))
+                .service(ship_order),
+        )
+        .await;
+
+        let body = serde_json::json!({
+            "address": { "zip_code": "10115", "country": "DE" },
+            "delivery_speed": "express"
+        });
+        let req = test::TestRequest::post()
+            .uri("/ship-order")
+            .set_json(&body)
+            .to_request();
+        let order: ShipOrderResponse = test::call_and_read_body_json(&app, req)
+            .with_context(cx.clone())
+            .await;
+        cx.span().end();
+        let date = order.estimated_delivery_date.unwrap();
+        let date = chrono::DateTime::parse_from_rfc3339(&date).unwrap();
+        let hours = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_hours();
+        assert!((119..=120).contains(&hours), "{hours} hours");
+
+        let span = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| span.span_context.trace_id() == trace_id && span.name == "/ship-order")
+            .unwrap();
+        assert!(span.attributes.contains(&KeyValue::new("estimate.days", 5)));
+
+        // Without a delivery speed there is no estimate
+        let req = test::TestRequest::post()
+            .uri("/ship-order")
+            .set_json(serde_json::json!({ "address": { "zip_code": "10115", "country": "DE" } }))
+            .to_request();
+        let order: ShipOrderResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(order.estimated_delivery_date, None);
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_failure_is_dead_lettered() {
+        let dead_letters = web::Data::new(ShipOrderDeadLetters::new(DeadLetterQueue::new(1, 1)));
+        let app = test::init_service(
+            App::new()
+                .app_data(dead_letters.clone())
+                .app_data(backend(MockShippingBackend::default()))
+                .service(ship_order),
+        )
+        .await;
+        let order = || {
+            test::TestRequest::post()
+                .uri("/ship-order")
+                .set_json(ShipOrderRequest::default())
+                .to_request()
+        };
+        let resp = test::call_service(&app, order()).await;
+        assert_error(
+            resp,
+            StatusCode::SERVICE_UNAVAILABLE,
+            "order_queued_for_retry",
+        )
+        .await;
+        assert_eq!(dead_letters.lock().unwrap().len(), 1);
+
+        // The queue is full now
+        let resp = test::call_service(&app, order()).await;
+        assert_error(resp, StatusCode::INTERNAL_SERVER_ERROR, "ship_order_failed").await;
+    }
+
+    #[actix_web::test]
+    async fn test_retry_ship_order() {
+        assert!(retry_ship_order(&ShipOrderRequest {
+            total_weight_g: Some(12_000),
+            ..Default::default()
+        }));
+        let tracking_id = dispatch_order(WeightClass::Heavy).unwrap();
+        assert_eq!(tracking_id.weight_class, WeightClass::Heavy);
+    }
+
+    #[actix_web::test]
+    async fn test_instrumented_handler_is_child_of_inbound_span() {
+        let exporter = global_span_exporter();
+        let subscriber = Registry::default().with(otel_trace_layer(&global_tracer_provider()));
+        let _subscriber = tracing::subscriber::set_default(subscriber);
+        let span = global::tracer("test").start("/ship-order");
+        let span_context = span.span_context().clone();
+        let cx = Context::current_with_span(span);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ShipOrderDeadLetters::new(
+                    DeadLetterQueue::new(1, 1),
+                )))
+                .app_data(backend(MockShippingBackend {
+                    tracking_id: Some(TrackingId::new(WeightClass::Light)),
+                    ..Default::default()
+                }))
+                .service(ship_order),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/ship-order")
+            .set_json(serde_json::json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).with_context(cx.clone()).await;
+        assert!(resp.status().is_success());
+        cx.span().end();
+
+        let handler = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| {
+                span.span_context.trace_id() == span_context.trace_id() && span.name == "ship_order"
+            })
+            .unwrap();
+        assert_eq!(handler.parent_span_id, span_context.span_id());
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_links_quote_trace() {
+        let exporter = global_span_exporter();
+        let span = global::tracer("test").start("/ship-order");
+        let span_context = span.span_context().clone();
+        let cx = Context::current_with_span(span);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ShipOrderDeadLetters::new(
+                    DeadLetterQueue::new(1, 1),
+                )))
+                .app_data(backend(MockShippingBackend {
+                    tracking_id: Some(TrackingId::new(WeightClass::Light)),
+                    ..Default::default()
+                }))
+                .service(ship_order),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/ship-order")
+            .set_json(serde_json::json!({
+                "quote_trace_id": "4bf92f3577b34da6a3ce929d0e0e4736"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).with_context(cx.clone()).await;
+        assert!(resp.status().is_success());
+        cx.span().end();
+
+        let span = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| span.span_context == span_context)
+            .unwrap();
+        let link = span.links.iter().next().unwrap();
+        assert_eq!(
+            link.span_context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+        assert_eq!(link.attributes, [KeyValue::new("link.type", "quote")]);
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_malformed_quote_trace_id() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ShipOrderDeadLetters::new(
+                    DeadLetterQueue::new(1, 1),
+                )))
+                .app_data(backend(MockShippingBackend::default()))
+                .service(ship_order),
+        )
+        .await;
+        for quote_trace_id in [
+            "not-a-trace",
+            "00000000000000000000000000000000",
+            "4bf92f3577b34da6",
+            "4BF92F3577B34DA6A3CE929D0E0E4736",
+            " 4bf92f3577b34da6a3ce929d0e0e4736",
+            "+bf92f3577b34da6a3ce929d0e0e4736",
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/ship-order")
+                .set_json(serde_json::json!({ "quote_trace_id": quote_trace_id }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_error(resp, StatusCode::BAD_REQUEST, "invalid_quote_trace_id").await;
+        }
     }
 }
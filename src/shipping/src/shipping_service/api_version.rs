@@ -0,0 +1,95 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::future::{ready, Ready};
+
+use actix_web::{
+    dev::Payload, error::InternalError, http::StatusCode, Error, FromRequest, HttpRequest,
+};
+use opentelemetry::{trace::get_active_span, KeyValue};
+
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Values `X-API-Version` may take, oldest first.
+pub const SUPPORTED_API_VERSIONS: [&str; 2] = ["2023-10", "2024-01"];
+
+/// Shape of the request bodies a client sends. `V1` bodies have only item
+/// quantities and an address; `V2` adds item dimensions and delivery speed.
+/// Requests without `X-API-Version` get the latest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "2023-10" => Some(ApiVersion::V1),
+            "2024-01" => Some(ApiVersion::V2),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => SUPPORTED_API_VERSIONS[0],
+            ApiVersion::V2 => SUPPORTED_API_VERSIONS[1],
+        }
+    }
+
+    /// The version `req` asks for, or a 400 naming the supported ones.
+    pub fn of(req: &HttpRequest) -> Result<Self, Error> {
+        let Some(value) = req.headers().get(API_VERSION_HEADER) else {
+            return Ok(ApiVersion::LATEST);
+        };
+        let version = value.to_str().ok().and_then(ApiVersion::parse);
+        let Some(version) = version else {
+            let request_id = CorrelationId::extract(req).into_inner()?;
+            let response = ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "unsupported_api_version",
+                format!(
+                    "X-API-Version must be one of {}",
+                    SUPPORTED_API_VERSIONS.join(", ")
+                ),
+                request_id,
+            )
+            .into_response();
+            return Err(InternalError::from_response("unsupported API version", response).into());
+        };
+        get_active_span(|span| span.set_attribute(KeyValue::new("api.version", version.as_str())));
+        Ok(version)
+    }
+}
+
+impl FromRequest for ApiVersion {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(ApiVersion::of(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_versions_parse() {
+        for version in SUPPORTED_API_VERSIONS {
+            assert_eq!(ApiVersion::parse(version).unwrap().as_str(), version);
+        }
+        assert_eq!(ApiVersion::parse("2025-01"), None);
+        assert_eq!(
+            ApiVersion::LATEST.as_str(),
+            *SUPPORTED_API_VERSIONS.last().unwrap()
+        );
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::quote::{create_quote_from_count, DiscountEngine, QuoteError, QuoteStrategy};
+use super::tenant::TenantConfig;
+use super::tracking::{TrackingId, WeightClass};
+use super::zones::ShippingZone;
+use super::{CartItem, DeliverySpeed, Quote};
+
+/// What a quote is priced on, besides the item count.
+#[derive(Debug, Clone, Copy)]
+pub struct QuoteOrder<'a> {
+    pub zone: ShippingZone,
+    pub items: &'a [CartItem],
+    pub speed: DeliverySpeed,
+    pub strategy: QuoteStrategy,
+    pub canary: bool,
+    pub tenant: &'a TenantConfig,
+    pub discounts: &'a DiscountEngine,
+}
+
+/// Prices and dispatches orders for the handlers, which get it from the app
+/// data as `web::Data<dyn ShippingBackend>` so tests can swap it out. Its
+/// futures aren't `Send`, like the `awc` client the quote service is called
+/// with.
+#[async_trait(?Send)]
+pub trait ShippingBackend: Send + Sync {
+    async fn quote(&self, item_count: u32, order: &QuoteOrder<'_>) -> Result<Quote, QuoteError>;
+
+    /// Hands the order over for fulfilment and returns its tracking ID.
+    async fn track(&self, weight_class: WeightClass) -> Result<TrackingId>;
+}
+
+/// Prices with `create_quote_from_count` and generates tracking IDs locally.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealShippingBackend;
+
+#[async_trait(?Send)]
+impl ShippingBackend for RealShippingBackend {
+    async fn quote(&self, item_count: u32, order: &QuoteOrder<'_>) -> Result<Quote, QuoteError> {
+        create_quote_from_count(item_count, order).await
+    }
+
+    async fn track(&self, weight_class: WeightClass) -> Result<TrackingId> {
+        dispatch_order(weight_class)
+    }
+}
+
+/// The synchronous part of `RealShippingBackend::track`, also used to retry
+/// dead-lettered orders.
+pub fn dispatch_order(weight_class: WeightClass) -> Result<TrackingId> {
+    Ok(TrackingId::new(weight_class))
+}
+
+#[cfg(test)]
+pub use mock::MockShippingBackend;
+
+#[cfg(test)]
+mod mock {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    /// Answers every call with canned values, and records the item counts it
+    /// was asked to quote.
+    #[derive(Debug, Default)]
+    pub struct MockShippingBackend {
+        /// Returned by `quote`, which fails when `None`.
+        pub quote: Option<Quote>,
+        /// How long `quote` takes.
+        pub delay: Duration,
+        /// Returned by `track`, which fails when `None`.
+        pub tracking_id: Option<TrackingId>,
+        pub quoted: Mutex<Vec<u32>>,
+    }
+
+    #[async_trait(?Send)]
+    impl ShippingBackend for MockShippingBackend {
+        async fn quote(
+            &self,
+            item_count: u32,
+            _order: &QuoteOrder<'_>,
+        ) -> Result<Quote, QuoteError> {
+            self.quoted.lock().unwrap().push(item_count);
+            tokio::time::sleep(self.delay).await;
+            self.quote
+                .clone()
+                .ok_or_else(|| QuoteError::Pricing(anyhow!("mock quote failed")))
+        }
+
+        async fn track(&self, _weight_class: WeightClass) -> Result<TrackingId> {
+            self.tracking_id
+                .ok_or_else(|| anyhow!("mock dispatch failed"))
+        }
+    }
+}
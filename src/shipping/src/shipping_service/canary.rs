@@ -0,0 +1,33 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, http::header::HeaderMap, Error, FromRequest, HttpRequest};
+
+pub const CANARY_HEADER: &str = "x-canary";
+
+/// Whether the request opted into the canary quote strategy with
+/// `X-Canary: true`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Canary(pub bool);
+
+impl Canary {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Canary(
+            headers
+                .get(CANARY_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("true")),
+        )
+    }
+}
+
+impl FromRequest for Canary {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Canary::from_headers(req.headers())))
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use phf::phf_map;
+
+use super::exchange_rates::{ExchangeRates, RateError};
+use super::Money;
+
+/// How amounts in a currency are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrencyMeta {
+    /// Digits after the decimal point of the smallest unit, per ISO 4217.
+    pub decimal_places: u8,
+}
+
+/// Currencies whose minor unit isn't the usual cent.
+static CURRENCIES: phf::Map<&'static str, CurrencyMeta> = phf_map! {
+    "BHD" => CurrencyMeta { decimal_places: 3 },
+    "CLP" => CurrencyMeta { decimal_places: 0 },
+    "ISK" => CurrencyMeta { decimal_places: 0 },
+    "JOD" => CurrencyMeta { decimal_places: 3 },
+    "JPY" => CurrencyMeta { decimal_places: 0 },
+    "KRW" => CurrencyMeta { decimal_places: 0 },
+    "KWD" => CurrencyMeta { decimal_places: 3 },
+    "OMR" => CurrencyMeta { decimal_places: 3 },
+    "TND" => CurrencyMeta { decimal_places: 3 },
+    "VND" => CurrencyMeta { decimal_places: 0 },
+};
+
+const DEFAULT_META: CurrencyMeta = CurrencyMeta { decimal_places: 2 };
+
+/// Amounts this close to half a minor unit are treated as exactly half, so
+/// e.g. 1.005, stored as 1.00499..., still rounds up.
+const HALF_UNIT_TOLERANCE: f64 = 1e-6;
+
+impl CurrencyMeta {
+    /// Two decimal places for any currency not listed.
+    pub fn of(currency_code: &str) -> CurrencyMeta {
+        CURRENCIES
+            .get(currency_code.to_ascii_uppercase().as_str())
+            .copied()
+            .unwrap_or(DEFAULT_META)
+    }
+
+    /// Rounds a non-negative `amount` to the currency's minor unit, halves
+    /// away from zero, as `Money` whose `nanos` are a multiple of it.
+    pub fn round(&self, currency_code: &str, amount: f64) -> Money {
+        let scale = 10_f64.powi(i32::from(self.decimal_places));
+        let scaled = amount * scale;
+        let minor = if (scaled.fract() - 0.5).abs() < HALF_UNIT_TOLERANCE {
+            scaled.trunc() + 1.0
+        } else {
+            scaled.round()
+        } as u64;
+
+        let minor_per_unit = scale as u64;
+        let nanos_per_minor = 1_000_000_000 / minor_per_unit as u32;
+        Money {
+            currency_code: currency_code.to_ascii_uppercase(),
+            units: minor / minor_per_unit,
+            nanos: (minor % minor_per_unit) as u32 * nanos_per_minor,
+        }
+    }
+}
+
+/// Converts US dollar amounts with the current exchange rates.
+#[derive(Debug, Clone, Copy)]
+pub struct CurrencyConverter<'a> {
+    rates: &'a ExchangeRates,
+}
+
+impl<'a> CurrencyConverter<'a> {
+    pub fn new(rates: &'a ExchangeRates) -> Self {
+        CurrencyConverter { rates }
+    }
+
+    /// Fails when there is no usable rate for `currency_code`. US dollars
+    /// don't need one.
+    pub fn convert(&self, usd: f64, currency_code: &str) -> Result<Money, RateError> {
+        let rate = if currency_code.eq_ignore_ascii_case("USD") {
+            1.0
+        } else {
+            self.rates.rate(currency_code)?
+        };
+        Ok(CurrencyMeta::of(currency_code).round(currency_code, usd * rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shipping_service::RateTable;
+
+    fn round(currency_code: &str, amount: f64) -> (u64, u32) {
+        let money = CurrencyMeta::of(currency_code).round(currency_code, amount);
+        (money.units, money.nanos)
+    }
+
+    #[test]
+    fn test_decimal_places() {
+        assert_eq!(CurrencyMeta::of("jpy").decimal_places, 0);
+        assert_eq!(CurrencyMeta::of("KWD").decimal_places, 3);
+        assert_eq!(CurrencyMeta::of("EUR").decimal_places, 2);
+    }
+
+    #[test]
+    fn test_zero_decimal_places_round_at_half_unit() {
+        assert_eq!(round("JPY", 152.49), (152, 0));
+        assert_eq!(round("JPY", 152.5), (153, 0));
+        assert_eq!(round("JPY", 0.5), (1, 0));
+    }
+
+    #[test]
+    fn test_two_decimal_places_round_at_half_cent() {
+        assert_eq!(round("USD", 1.004), (1, 0));
+        assert_eq!(round("USD", 1.005), (1, 10_000_000));
+        assert_eq!(round("EUR", 9.995), (10, 0));
+    }
+
+    #[test]
+    fn test_three_decimal_places_round_at_half_fils() {
+        assert_eq!(round("KWD", 0.3074), (0, 307_000_000));
+        assert_eq!(round("KWD", 0.3075), (0, 308_000_000));
+        assert_eq!(round("KWD", 2.9995), (3, 0));
+    }
+
+    #[test]
+    fn test_convert() {
+        let rates = ExchangeRates::new(RateTable::from([
+            ("JPY".to_string(), 151.3),
+            ("KWD".to_string(), 0.3075),
+        ]));
+        let converter = CurrencyConverter::new(&rates);
+
+        let yen = converter.convert(10.0, "jpy").unwrap();
+        assert_eq!(
+            (yen.currency_code.as_str(), yen.units, yen.nanos),
+            ("JPY", 1513, 0)
+        );
+        let dinar = converter.convert(1.0, "KWD").unwrap();
+        assert_eq!((dinar.units, dinar.nanos), (0, 308_000_000));
+        let dollars = converter.convert(12.345, "USD").unwrap();
+        assert_eq!((dollars.units, dollars.nanos), (12, 350_000_000));
+        assert!(converter.convert(1.0, "GBP").is_err());
+    }
+}
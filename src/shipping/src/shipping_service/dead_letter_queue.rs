@@ -0,0 +1,201 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use opentelemetry::global;
+use tracing::warn;
+
+use crate::metrics::KUBERNETES_LABELS;
+use crate::utils::spawn_with_context;
+
+const DEFAULT_CAPACITY: usize = 1000;
+const DEFAULT_MAX_RETRIES: usize = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bounded queue of items that failed to process, each stored with the time of
+/// its last attempt and how many retries it has had.
+#[derive(Debug)]
+pub struct DeadLetterQueue<T> {
+    entries: VecDeque<(T, Instant, usize)>,
+    capacity: usize,
+    max_retries: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RetryOutcome {
+    pub attempted: usize,
+    pub succeeded: usize,
+    pub abandoned: usize,
+}
+
+impl<T> DeadLetterQueue<T> {
+    pub fn new(capacity: usize, max_retries: usize) -> Self {
+        DeadLetterQueue {
+            entries: VecDeque::new(),
+            capacity,
+            max_retries,
+        }
+    }
+
+    /// Reads `DLQ_CAPACITY` and `DLQ_MAX_RETRIES`.
+    pub fn from_env() -> Self {
+        let var = |name, default| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            var("DLQ_CAPACITY", DEFAULT_CAPACITY),
+            var("DLQ_MAX_RETRIES", DEFAULT_MAX_RETRIES),
+        )
+    }
+
+    /// Hands the item back if the queue is full.
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        if self.entries.len() >= self.capacity {
+            return Err(item);
+        }
+        self.entries.push_back((item, Instant::now(), 0));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Retries every entry whose backoff (`BASE_BACKOFF * 2^retries`) has
+    /// elapsed at `now`. Entries are removed when `retry` succeeds or once
+    /// they have been retried `max_retries` times.
+    pub fn retry_due(&mut self, now: Instant, mut retry: impl FnMut(&T) -> bool) -> RetryOutcome {
+        let mut outcome = RetryOutcome::default();
+        let max_retries = self.max_retries;
+
+        self.entries.retain_mut(|(item, last_attempt, retries)| {
+            let backoff = BASE_BACKOFF * 2u32.saturating_pow(*retries as u32);
+            if now.saturating_duration_since(*last_attempt) < backoff {
+                return true;
+            }
+
+            outcome.attempted += 1;
+            if retry(item) {
+                outcome.succeeded += 1;
+                return false;
+            }
+
+            *retries += 1;
+            *last_attempt = now;
+            if *retries >= max_retries {
+                outcome.abandoned += 1;
+                return false;
+            }
+            true
+        });
+
+        outcome
+    }
+}
+
+/// Periodically retries the queued items with `retry` and reports the queue
+/// size and retry count through the global meter provider.
+pub fn start_dead_letter_retries<T, F>(queue: Arc<Mutex<DeadLetterQueue<T>>>, mut retry: F)
+where
+    T: Send + 'static,
+    F: FnMut(&T) -> bool + Send + 'static,
+{
+    let meter = global::meter("otel_demo.shipping.dead_letter_queue");
+    let retries_total = meter.u64_counter("dlq_retries_total").build();
+
+    let q = queue.clone();
+    meter
+        .u64_observable_gauge("dlq_size")
+        .with_description("Number of items waiting in the dead letter queue")
+        .with_callback(move |observer| {
+            observer.observe(
+                q.lock().unwrap().len() as u64,
+                &KUBERNETES_LABELS.as_key_values(),
+            )
+        })
+        .build();
+
+    spawn_with_context(async move {
+        let mut interval = tokio::time::interval(RETRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            let outcome = queue.lock().unwrap().retry_due(Instant::now(), &mut retry);
+            retries_total.add(outcome.attempted as u64, &[]);
+            if outcome.abandoned > 0 {
+                warn!(
+                    name = "DeadLetterAbandoned",
+                    count = outcome.abandoned,
+                    message = "Dropping dead letters that exhausted their retries"
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drains_after_second_attempt() {
+        let mut dlq = DeadLetterQueue::new(10, 3);
+        for item in 1..=3 {
+            dlq.push(item).unwrap();
+        }
+
+        let start = Instant::now();
+        let mut attempts = [0; 3];
+        let mut retry = |item: &i32| {
+            attempts[*item as usize - 1] += 1;
+            attempts[*item as usize - 1] == 2
+        };
+
+        // nothing is due before the first backoff elapses
+        assert_eq!(dlq.retry_due(start, &mut retry).attempted, 0);
+
+        let first = start + BASE_BACKOFF;
+        let outcome = dlq.retry_due(first, &mut retry);
+        assert_eq!(outcome.attempted, 3);
+        assert_eq!(outcome.succeeded, 0);
+        assert_eq!(dlq.len(), 3);
+
+        // the backoff doubles after a failed retry
+        assert_eq!(dlq.retry_due(first + BASE_BACKOFF, &mut retry).attempted, 0);
+
+        let outcome = dlq.retry_due(first + BASE_BACKOFF * 2, &mut retry);
+        assert_eq!(outcome.succeeded, 3);
+        assert_eq!(dlq.len(), 0);
+        assert_eq!(attempts, [2, 2, 2]);
+    }
+
+    #[test]
+    fn test_abandons_after_max_retries() {
+        let mut dlq = DeadLetterQueue::new(10, 2);
+        dlq.push(()).unwrap();
+
+        let far_future = Instant::now() + Duration::from_secs(3600);
+        assert_eq!(dlq.retry_due(far_future, |_| false).abandoned, 0);
+        let outcome = dlq.retry_due(far_future + Duration::from_secs(3600), |_| false);
+        assert_eq!(outcome.abandoned, 1);
+        assert_eq!(dlq.len(), 0);
+    }
+
+    #[test]
+    fn test_push_rejects_when_full() {
+        let mut dlq = DeadLetterQueue::new(1, 1);
+        assert_eq!(dlq.push("a"), Ok(()));
+        assert_eq!(dlq.push("b"), Err("b"));
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+
+use super::zones::ShippingZone;
+use super::DeliverySpeed;
+
+/// Estimates when an order arrives from where it is going and how fast it
+/// was paid to go.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeliveryEstimator;
+
+impl DeliveryEstimator {
+    /// Days from dispatch to delivery.
+    pub fn estimate_days(&self, zone: ShippingZone, speed: DeliverySpeed) -> i64 {
+        use DeliverySpeed::*;
+        use ShippingZone::*;
+        match (zone, speed) {
+            (Domestic, Standard) => 5,
+            (Domestic, Express) => 2,
+            (Domestic, Overnight) => 1,
+            (NorthAmerica, Standard) => 7,
+            (NorthAmerica, Express) => 3,
+            (NorthAmerica, Overnight) => 2,
+            (Europe, Standard) => 10,
+            (Europe, Express) => 5,
+            (Europe, Overnight) => 3,
+            (Asia, Standard) => 12,
+            (Asia, Express) => 6,
+            (Asia, Overnight) => 3,
+            (Rest, Standard) => 15,
+            (Rest, Express) => 8,
+            (Rest, Overnight) => 5,
+        }
+    }
+
+    /// The RFC 3339 delivery date for an order dispatched at `now`, or `None`
+    /// when the zone or speed isn't known.
+    pub fn estimate_at(
+        &self,
+        now: DateTime<Utc>,
+        zone: Option<ShippingZone>,
+        speed: Option<DeliverySpeed>,
+    ) -> Option<(i64, String)> {
+        let days = self.estimate_days(zone?, speed?);
+        let date = now + Duration::days(days);
+        Some((days, date.to_rfc3339_opts(SecondsFormat::Secs, true)))
+    }
+
+    pub fn estimate(
+        &self,
+        zone: Option<ShippingZone>,
+        speed: Option<DeliverySpeed>,
+    ) -> Option<(i64, String)> {
+        self.estimate_at(Utc::now(), zone, speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_zone_and_speed() {
+        let now = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let cases = [
+            (
+                ShippingZone::Domestic,
+                DeliverySpeed::Standard,
+                "2024-03-06T12:00:00Z",
+            ),
+            (
+                ShippingZone::Domestic,
+                DeliverySpeed::Express,
+                "2024-03-03T12:00:00Z",
+            ),
+            (
+                ShippingZone::Domestic,
+                DeliverySpeed::Overnight,
+                "2024-03-02T12:00:00Z",
+            ),
+            (
+                ShippingZone::NorthAmerica,
+                DeliverySpeed::Standard,
+                "2024-03-08T12:00:00Z",
+            ),
+            (
+                ShippingZone::NorthAmerica,
+                DeliverySpeed::Express,
+                "2024-03-04T12:00:00Z",
+            ),
+            (
+                ShippingZone::NorthAmerica,
+                DeliverySpeed::Overnight,
+                "2024-03-03T12:00:00Z",
+            ),
+            (
+                ShippingZone::Europe,
+                DeliverySpeed::Standard,
+                "2024-03-11T12:00:00Z",
+            ),
+            (
+                ShippingZone::Europe,
+                DeliverySpeed::Express,
+                "2024-03-06T12:00:00Z",
+            ),
+            (
+                ShippingZone::Europe,
+                DeliverySpeed::Overnight,
+                "2024-03-04T12:00:00Z",
+            ),
+            (
+                ShippingZone::Asia,
+                DeliverySpeed::Standard,
+                "2024-03-13T12:00:00Z",
+            ),
+            (
+                ShippingZone::Asia,
+                DeliverySpeed::Express,
+                "2024-03-07T12:00:00Z",
+            ),
+            (
+                ShippingZone::Asia,
+                DeliverySpeed::Overnight,
+                "2024-03-04T12:00:00Z",
+            ),
+            (
+                ShippingZone::Rest,
+                DeliverySpeed::Standard,
+                "2024-03-16T12:00:00Z",
+            ),
+            (
+                ShippingZone::Rest,
+                DeliverySpeed::Express,
+                "2024-03-09T12:00:00Z",
+            ),
+            (
+                ShippingZone::Rest,
+                DeliverySpeed::Overnight,
+                "2024-03-06T12:00:00Z",
+            ),
+        ];
+        for (zone, speed, expected) in cases {
+            let (days, date) = DeliveryEstimator
+                .estimate_at(now, Some(zone), Some(speed))
+                .unwrap();
+            assert_eq!(date, expected, "{zone:?} {speed:?}");
+            assert_eq!(days, DeliveryEstimator.estimate_days(zone, speed));
+        }
+    }
+
+    #[test]
+    fn test_faster_is_never_later() {
+        for zone in [
+            ShippingZone::Domestic,
+            ShippingZone::NorthAmerica,
+            ShippingZone::Europe,
+            ShippingZone::Asia,
+            ShippingZone::Rest,
+        ] {
+            let days = |speed| DeliveryEstimator.estimate_days(zone, speed);
+            assert!(days(DeliverySpeed::Overnight) <= days(DeliverySpeed::Express));
+            assert!(days(DeliverySpeed::Express) <= days(DeliverySpeed::Standard));
+        }
+    }
+
+    #[test]
+    fn test_unknown_zone_or_speed() {
+        let estimator = DeliveryEstimator;
+        assert_eq!(estimator.estimate(None, Some(DeliverySpeed::Express)), None);
+        assert_eq!(estimator.estimate(Some(ShippingZone::Europe), None), None);
+        assert!(estimator
+            .estimate(Some(ShippingZone::Europe), Some(DeliverySpeed::Express))
+            .is_some());
+    }
+}
@@ -0,0 +1,523 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context as _};
+use arc_swap::ArcSwap;
+use opentelemetry::{
+    global,
+    metrics::Meter,
+    trace::{FutureExt, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use opentelemetry_instrumentation_actix_web::ClientExt;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::config::redact_url;
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+pub const DEFAULT_EXCHANGE_RATES_REFRESH: Duration = Duration::from_secs(300);
+pub const DEFAULT_EXCHANGE_RATES_STALE_EXTENSION: Duration = Duration::from_secs(300);
+
+/// Units of each currency per US dollar, keyed by upper-case ISO 4217 code.
+pub type RateTable = HashMap<String, f64>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateError {
+    /// The table has no rate for this currency.
+    Unknown(String),
+    /// Revalidation has failed for longer than
+    /// `EXCHANGE_RATES_STALE_EXTENSION_SECS`.
+    Expired { stale_for: Duration },
+}
+
+impl fmt::Display for RateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateError::Unknown(code) => write!(f, "no exchange rate for {code}"),
+            RateError::Expired { stale_for } => {
+                write!(f, "exchange rates have been stale for {stale_for:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RateError {}
+
+/// `Freshness` instants for events that haven't happened.
+const NEVER: u64 = u64::MAX;
+
+/// When the table was fetched and may be fetched again, as nanoseconds
+/// since `epoch`, so reads check it without taking a lock.
+#[derive(Debug)]
+struct Freshness {
+    epoch: Instant,
+    /// When the table was last fetched, `NEVER` until the first success.
+    fetched_at: AtomicU64,
+    /// Set by the first failed revalidation: stale rates are served until
+    /// then, and are errors after.
+    serve_stale_until: AtomicU64,
+    /// After a failed revalidation, stale reads only wake the refresh task
+    /// again from then on, so an upstream outage isn't fetched from at the
+    /// request rate.
+    retry_at: AtomicU64,
+    revalidating: AtomicBool,
+}
+
+impl Freshness {
+    fn new() -> Self {
+        Freshness {
+            epoch: Instant::now(),
+            fetched_at: AtomicU64::new(NEVER),
+            serve_stale_until: AtomicU64::new(NEVER),
+            retry_at: AtomicU64::new(0),
+            revalidating: AtomicBool::new(false),
+        }
+    }
+
+    fn nanos(&self, instant: Instant) -> u64 {
+        instant.saturating_duration_since(self.epoch).as_nanos() as u64
+    }
+
+    /// Time since the last fetch, `None` before the first one.
+    fn age(&self, now: Instant) -> Option<Duration> {
+        let fetched_at = self.fetched_at.load(Ordering::Relaxed);
+        (fetched_at != NEVER)
+            .then(|| Duration::from_nanos(self.nanos(now).saturating_sub(fetched_at)))
+    }
+}
+
+/// Exchange rate table that can be replaced while requests read it.
+///
+/// A revalidating table is fresh for `max_age` after each fetch. Reading a
+/// stale rate returns it right away and wakes the refresh task started by
+/// [`start_exchange_rate_refresh`] to fetch a new table, at most once per
+/// `max_age` while fetches fail.
+#[derive(Debug)]
+pub struct ExchangeRates {
+    rates: ArcSwap<RateTable>,
+    /// `None` for tables that never go stale.
+    max_age: Option<Duration>,
+    stale_extension: Duration,
+    freshness: Freshness,
+    revalidate: Notify,
+}
+
+impl ExchangeRates {
+    /// A fixed table, which never goes stale.
+    pub fn new(rates: RateTable) -> Self {
+        ExchangeRates {
+            rates: ArcSwap::from_pointee(rates),
+            max_age: None,
+            stale_extension: Duration::ZERO,
+            freshness: Freshness::new(),
+            revalidate: Notify::new(),
+        }
+    }
+
+    /// An empty table, filled and revalidated by
+    /// [`start_exchange_rate_refresh`].
+    pub fn revalidating(max_age: Duration, stale_extension: Duration) -> Self {
+        ExchangeRates {
+            max_age: Some(max_age),
+            stale_extension,
+            ..Self::new(RateTable::new())
+        }
+    }
+
+    /// Serves stale rates, waking the refresh task, until revalidation has
+    /// failed for longer than the stale extension.
+    pub fn rate(&self, currency_code: &str) -> Result<f64, RateError> {
+        self.check_freshness(Instant::now())?;
+        let code = currency_code.to_ascii_uppercase();
+        self.rates
+            .load()
+            .get(&code)
+            .copied()
+            .ok_or(RateError::Unknown(code))
+    }
+
+    fn check_freshness(&self, now: Instant) -> Result<(), RateError> {
+        let Some(max_age) = self.max_age else {
+            return Ok(());
+        };
+        let freshness = &self.freshness;
+        let stale_for = match freshness.age(now) {
+            Some(age) if age <= max_age => return Ok(()),
+            Some(age) => age - max_age,
+            None => Duration::ZERO,
+        };
+        let now = freshness.nanos(now);
+        if now >= freshness.retry_at.load(Ordering::Relaxed)
+            && !freshness.revalidating.swap(true, Ordering::AcqRel)
+        {
+            self.revalidate.notify_one();
+        }
+        if now > freshness.serve_stale_until.load(Ordering::Relaxed) {
+            return Err(RateError::Expired { stale_for });
+        }
+        Ok(())
+    }
+
+    /// How long the table has been past its max age, zero while fresh.
+    pub fn staleness(&self) -> Duration {
+        match (self.max_age, self.freshness.age(Instant::now())) {
+            (Some(max_age), Some(age)) => age.saturating_sub(max_age),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn register_staleness_gauge(self: &Arc<Self>, meter: &Meter) {
+        let rates = self.clone();
+        meter
+            .f64_observable_gauge(checked_instrument_name("exchange_rates_staleness_seconds"))
+            .with_description("Seconds the exchange rates have been served past their max age")
+            .with_callback(move |observer| {
+                observer.observe(
+                    rates.staleness().as_secs_f64(),
+                    &KUBERNETES_LABELS.as_key_values(),
+                )
+            })
+            .build();
+    }
+
+    /// The current table. Later reloads don't change it.
+    pub fn snapshot(&self) -> Arc<RateTable> {
+        self.rates.load_full()
+    }
+
+    pub fn replace(&self, rates: RateTable) {
+        self.rates.store(Arc::new(rates));
+    }
+
+    /// Fetches `url` and, if it returns a valid table, swaps it in. Returns
+    /// the number of rates loaded. The first failure after a success starts
+    /// the stale extension, and every failure holds off the next fetch for
+    /// `max_age`.
+    pub async fn reload(&self, url: &str) -> anyhow::Result<usize> {
+        let result = self.fetch(url).await;
+        let freshness = &self.freshness;
+        let now = Instant::now();
+        let result = match result {
+            Ok(rates) => {
+                let count = rates.len();
+                self.replace(rates);
+                freshness
+                    .fetched_at
+                    .store(freshness.nanos(now), Ordering::Relaxed);
+                freshness.serve_stale_until.store(NEVER, Ordering::Relaxed);
+                freshness.retry_at.store(0, Ordering::Relaxed);
+                Ok(count)
+            }
+            Err(err) => {
+                let _ = freshness.serve_stale_until.compare_exchange(
+                    NEVER,
+                    freshness.nanos(now + self.stale_extension),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                );
+                let backoff = self.max_age.unwrap_or_default();
+                freshness
+                    .retry_at
+                    .store(freshness.nanos(now + backoff), Ordering::Relaxed);
+                Err(err)
+            }
+        };
+        // Cleared last, so a read can't wake the task before the retry time
+        // is in place.
+        freshness.revalidating.store(false, Ordering::Release);
+        result
+    }
+
+    async fn fetch(&self, url: &str) -> anyhow::Result<RateTable> {
+        let tracer = global::tracer("otel_demo.shipping.exchange_rates");
+        let cx = Context::current_with_span(tracer.start("exchange_rates.reload"));
+        let fetch = async {
+            let mut resp = awc::Client::new()
+                .get(url)
+                .trace_request()
+                .send()
+                .await
+                .map_err(|err| anyhow!("Couldn't fetch {}: {err}", redact_url(url)))?;
+            if !resp.status().is_success() {
+                bail!("{} answered {}", redact_url(url), resp.status());
+            }
+            let body = resp.body().await.context("Couldn't read exchange rates")?;
+            parse_rates(&body)
+        };
+        let result = FutureExt::with_context(fetch, cx.clone()).await;
+
+        let span = cx.span();
+        match &result {
+            Ok(rates) => {
+                let reloaded_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                span.add_event(
+                    "exchange_rates.reloaded",
+                    vec![
+                        KeyValue::new("exchange_rates.reloaded_at", reloaded_at),
+                        KeyValue::new("exchange_rates.count", rates.len() as i64),
+                    ],
+                );
+            }
+            Err(err) => span.set_status(Status::error(format!("{err:#}"))),
+        }
+        span.end();
+        result
+    }
+}
+
+/// Parses `{"EUR": 0.92, ...}`. Every rate must be positive and finite.
+pub fn parse_rates(json: &[u8]) -> anyhow::Result<RateTable> {
+    let rates: HashMap<String, f64> =
+        serde_json::from_slice(json).context("Exchange rates aren't a JSON object of numbers")?;
+    rates
+        .into_iter()
+        .map(|(code, rate)| {
+            if rate.is_finite() && rate > 0.0 {
+                Ok((code.to_ascii_uppercase(), rate))
+            } else {
+                bail!("Exchange rate for {code} is {rate}")
+            }
+        })
+        .collect()
+}
+
+/// Fills `rates` from `url`, then fetches again whenever a read finds the
+/// table stale. Reads keep getting the previous table while a fetch is running
+/// or after it fails. `awc` isn't `Send`, so this runs on the current Actix
+/// runtime.
+pub fn start_exchange_rate_refresh(rates: Arc<ExchangeRates>, url: String) {
+    let shown_url = redact_url(&url);
+    actix_web::rt::spawn(async move {
+        loop {
+            match rates.reload(&url).await {
+                Ok(count) => info!(
+                    name = "ExchangeRatesReloaded",
+                    exchange_rates.url = shown_url.as_str(),
+                    exchange_rates.count = count,
+                    message = "Loaded exchange rates"
+                ),
+                Err(err) => warn!(
+                    name = "ExchangeRatesReloadFailed",
+                    exchange_rates.url = shown_url.as_str(),
+                    error = format!("{err:#}"),
+                    message = "Serving the previous exchange rates"
+                ),
+            }
+            rates.revalidate.notified().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+
+    use super::*;
+    use crate::test_utils::{global_span_exporter, test_meter_provider};
+
+    #[test]
+    fn test_parse_rates() {
+        let rates = parse_rates(br#"{"eur": 0.92, "JPY": 151.3}"#).unwrap();
+        assert_eq!(rates["EUR"], 0.92);
+        assert_eq!(rates["JPY"], 151.3);
+
+        assert!(parse_rates(br#"{"EUR": 0}"#).is_err());
+        assert!(parse_rates(br#"{"EUR": "0.92"}"#).is_err());
+        assert!(parse_rates(b"[]").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_is_unchanged_by_replace() {
+        let rates = ExchangeRates::new(RateTable::from([("EUR".to_string(), 0.92)]));
+        let before = rates.snapshot();
+        rates.replace(RateTable::from([("EUR".to_string(), 0.95)]));
+        assert_eq!(before["EUR"], 0.92);
+        assert_eq!(rates.rate("eur"), Ok(0.95));
+        assert_eq!(
+            rates.rate("GBP"),
+            Err(RateError::Unknown("GBP".to_string()))
+        );
+    }
+
+    async fn wait_for(mut done: impl FnMut() -> bool) {
+        while !done() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_stale_read_revalidates_in_background() {
+        let exporter = global_span_exporter();
+        let server = MockServer::start_async().await;
+        let mut mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(200).body(r#"{"EUR": 0.92}"#);
+            })
+            .await;
+
+        let rates = Arc::new(ExchangeRates::revalidating(
+            Duration::from_millis(50),
+            DEFAULT_EXCHANGE_RATES_STALE_EXTENSION,
+        ));
+        start_exchange_rate_refresh(rates.clone(), server.url("/rates"));
+        wait_for(|| rates.rate("EUR").is_ok()).await;
+        assert_eq!(rates.rate("EUR"), Ok(0.92));
+
+        mock.delete_async().await;
+        mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(200).body(r#"{"EUR": 0.95, "GBP": 0.79}"#);
+            })
+            .await;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(rates.staleness() > Duration::ZERO);
+        // The stale read answers at once and starts the fetch
+        assert_eq!(rates.rate("EUR"), Ok(0.92));
+        wait_for(|| rates.rate("EUR") == Ok(0.95)).await;
+        assert_eq!(rates.rate("GBP"), Ok(0.79));
+        assert_eq!(rates.staleness(), Duration::ZERO);
+        mock.assert_hits_async(1).await;
+
+        let reloaded = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .filter(|span| span.name == "exchange_rates.reload")
+            .filter(|span| {
+                span.events
+                    .iter()
+                    .any(|event| event.name == "exchange_rates.reloaded")
+            })
+            .count();
+        assert!(reloaded >= 2, "{reloaded}");
+    }
+
+    #[actix_web::test]
+    async fn test_failed_refresh_serves_stale_rates() {
+        let server = MockServer::start_async().await;
+        let mut mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(200).body(r#"{"EUR": 0.92}"#);
+            })
+            .await;
+
+        let rates = Arc::new(ExchangeRates::revalidating(
+            Duration::from_millis(50),
+            Duration::from_millis(300),
+        ));
+        start_exchange_rate_refresh(rates.clone(), server.url("/rates"));
+        wait_for(|| rates.rate("EUR").is_ok()).await;
+
+        mock.delete_async().await;
+        mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(500);
+            })
+            .await;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(rates.rate("EUR"), Ok(0.92));
+        while mock.hits_async().await < 1 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        // Still within the stale extension after the failure
+        assert_eq!(rates.rate("EUR"), Ok(0.92));
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        match rates.rate("EUR") {
+            Err(RateError::Expired { stale_for }) => {
+                assert!(stale_for >= Duration::from_millis(350), "{stale_for:?}")
+            }
+            other => panic!("expected expired rates, got {other:?}"),
+        }
+
+        // A successful retry makes the table fresh again
+        mock.delete_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(200).body(r#"{"EUR": 0.95}"#);
+            })
+            .await;
+        wait_for(|| rates.rate("EUR") == Ok(0.95)).await;
+    }
+
+    #[actix_web::test]
+    async fn test_failed_refresh_backs_off() {
+        let server = MockServer::start_async().await;
+        let mut mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(200).body(r#"{"EUR": 0.92}"#);
+            })
+            .await;
+        let max_age = Duration::from_millis(200);
+        let rates = Arc::new(ExchangeRates::revalidating(
+            max_age,
+            DEFAULT_EXCHANGE_RATES_STALE_EXTENSION,
+        ));
+        start_exchange_rate_refresh(rates.clone(), server.url("/rates"));
+        wait_for(|| rates.rate("EUR").is_ok()).await;
+
+        mock.delete_async().await;
+        mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/rates");
+                then.status(500);
+            })
+            .await;
+        tokio::time::sleep(max_age).await;
+        assert_eq!(rates.rate("EUR"), Ok(0.92));
+        while mock.hits_async().await < 1 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // Stale reads right after the failure don't refetch
+        for _ in 0..20 {
+            assert_eq!(rates.rate("EUR"), Ok(0.92));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        mock.assert_hits_async(1).await;
+
+        tokio::time::sleep(max_age).await;
+        assert_eq!(rates.rate("EUR"), Ok(0.92));
+        while mock.hits_async().await < 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn test_staleness_gauge() {
+        let (provider, exporter) = test_meter_provider();
+        let rates = Arc::new(ExchangeRates::new(RateTable::new()));
+        rates.register_staleness_gauge(&provider.meter("test"));
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metric = metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .find(|m| m.name() == "exchange_rates_staleness_seconds")
+            .unwrap();
+        let AggregatedMetrics::F64(MetricData::Gauge(gauge)) = metric.data() else {
+            panic!("expected an f64 gauge");
+        };
+        assert_eq!(gauge.data_points().next().unwrap().value(), 0.0);
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::env;
+use std::future::{ready, Ready};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{dev::Payload, Error, FromRequest, HttpRequest};
+
+use crate::utils::spawn_with_context;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "x-idempotency-key";
+pub const CACHE_HEADER: &str = "x-cache";
+
+const DEFAULT_TTL: Duration = Duration::from_secs(120);
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+const EVICTION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The request's `X-Idempotency-Key`, if it sent a non-empty one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotencyKey(pub Option<String>);
+
+impl FromRequest for IdempotencyKey {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let key = req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string);
+        ready(Ok(IdempotencyKey(key)))
+    }
+}
+
+/// What a key holds for a request body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachedReply<T> {
+    Hit(T),
+    Miss,
+    /// The key was cached for a different body.
+    Mismatch,
+}
+
+/// Responses by idempotency key, each stored with a hash of the body it
+/// answered and the time it was cached. Entries older than the TTL are
+/// misses, and are dropped by `evict_expired`. Once `max_entries` are
+/// cached, the oldest makes room for the next.
+#[derive(Debug)]
+pub struct IdempotencyCache<T> {
+    entries: HashMap<String, (T, u64, Instant)>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl<T: Clone> IdempotencyCache<T> {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        IdempotencyCache {
+            entries: HashMap::new(),
+            ttl,
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    /// Reads `IDEMPOTENCY_TTL_SECS` and `IDEMPOTENCY_MAX_ENTRIES`.
+    pub fn from_env() -> Self {
+        let var = |name| env::var(name).ok().and_then(|v| v.trim().parse().ok());
+        let ttl = var("IDEMPOTENCY_TTL_SECS")
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+        let max_entries = var("IDEMPOTENCY_MAX_ENTRIES")
+            .map(|max| max as usize)
+            .unwrap_or(DEFAULT_MAX_ENTRIES);
+        Self::new(ttl, max_entries)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str, body_hash: u64, now: Instant) -> CachedReply<T> {
+        match self
+            .entries
+            .get(key)
+            .filter(|(_, _, cached_at)| now.duration_since(*cached_at) < self.ttl)
+        {
+            Some((value, hash, _)) if *hash == body_hash => CachedReply::Hit(value.clone()),
+            Some(_) => CachedReply::Mismatch,
+            None => CachedReply::Miss,
+        }
+    }
+
+    pub fn insert(&mut self, key: String, body_hash: u64, value: T, now: Instant) {
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            self.evict_expired(now);
+            if self.entries.len() >= self.max_entries {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, _, cached_at))| *cached_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        self.entries.insert(key, (value, body_hash, now));
+    }
+
+    /// Returns how many entries were dropped.
+    pub fn evict_expired(&mut self, now: Instant) -> usize {
+        let before = self.entries.len();
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, (_, _, cached_at)| now.duration_since(*cached_at) < ttl);
+        before - self.entries.len()
+    }
+}
+
+/// Periodically drops expired entries, so keys that are never reused don't
+/// pile up.
+pub fn start_idempotency_eviction<T>(cache: Arc<Mutex<IdempotencyCache<T>>>)
+where
+    T: Clone + Send + 'static,
+{
+    spawn_with_context(async move {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            cache.lock().unwrap().evict_expired(Instant::now());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_until_ttl() {
+        let start = Instant::now();
+        let mut cache = IdempotencyCache::new(Duration::from_secs(120), 10);
+        cache.insert("abc".to_string(), 7, 1, start);
+
+        let later = start + Duration::from_secs(119);
+        assert_eq!(cache.get("abc", 7, later), CachedReply::Hit(1));
+        assert_eq!(
+            cache.get("abc", 7, start + Duration::from_secs(120)),
+            CachedReply::Miss
+        );
+        assert_eq!(cache.get("other", 7, start), CachedReply::Miss);
+    }
+
+    #[test]
+    fn test_same_key_other_body_is_a_mismatch() {
+        let start = Instant::now();
+        let mut cache = IdempotencyCache::new(Duration::from_secs(120), 10);
+        cache.insert("abc".to_string(), 7, 1, start);
+
+        assert_eq!(cache.get("abc", 8, start), CachedReply::Mismatch);
+    }
+
+    #[test]
+    fn test_evict_expired() {
+        let start = Instant::now();
+        let mut cache = IdempotencyCache::new(Duration::from_secs(10), 10);
+        cache.insert("old".to_string(), 0, 1, start);
+        cache.insert("new".to_string(), 0, 2, start + Duration::from_secs(5));
+
+        let now = start + Duration::from_secs(12);
+        assert_eq!(cache.evict_expired(now), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get("new", 0, now), CachedReply::Hit(2));
+    }
+
+    #[test]
+    fn test_full_cache_drops_the_oldest() {
+        let start = Instant::now();
+        let mut cache = IdempotencyCache::new(Duration::from_secs(120), 2);
+        for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+            cache.insert(key.to_string(), 0, i, start + Duration::from_secs(i as u64));
+        }
+
+        let now = start + Duration::from_secs(3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("a", 0, now), CachedReply::Miss);
+        assert_eq!(cache.get("c", 0, now), CachedReply::Hit(2));
+
+        cache.insert("c".to_string(), 0, 3, now);
+        assert_eq!(cache.get("b", 0, now), CachedReply::Hit(1));
+    }
+}
@@ -0,0 +1,373 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+use opentelemetry::{
+    global,
+    metrics::{Histogram, Meter},
+    propagation::Injector,
+    trace::{FutureExt, SpanKind, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use opentelemetry_semantic_conventions::attribute::{
+    ERROR_TYPE, MESSAGING_DESTINATION_NAME, MESSAGING_KAFKA_MESSAGE_KEY,
+    MESSAGING_MESSAGE_BODY_SIZE, MESSAGING_MESSAGE_ID, MESSAGING_OPERATION_NAME,
+    MESSAGING_OPERATION_TYPE, MESSAGING_SYSTEM,
+};
+use serde::Serialize;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::tracking::WeightClass;
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+pub const ORDER_EVENTS_TOPIC: &str = "shipping-orders";
+/// How long a send waits for room in a full producer queue before failing.
+#[cfg(feature = "kafka")]
+const QUEUE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// What is published to `ORDER_EVENTS_TOPIC` for every shipped order.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderShipped {
+    pub tracking_id: String,
+    pub weight_class: WeightClass,
+    pub total_weight_g: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KafkaRecord {
+    pub topic: String,
+    pub key: String,
+    pub payload: Vec<u8>,
+    /// The OTel context, so consumers can continue the trace.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Injector for KafkaRecord {
+    fn set(&mut self, key: &str, value: String) {
+        self.headers.push((key.to_string(), value));
+    }
+}
+
+/// Hands records to a broker. `FutureProducer` with the `kafka` feature,
+/// `DisconnectedProducer` otherwise.
+#[async_trait]
+pub trait KafkaSend: Send + Sync {
+    async fn send(&self, record: KafkaRecord) -> Result<()>;
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl KafkaSend for rdkafka::producer::FutureProducer {
+    async fn send(&self, record: KafkaRecord) -> Result<()> {
+        use rdkafka::message::{Header, OwnedHeaders};
+        use rdkafka::producer::FutureRecord;
+        use rdkafka::util::Timeout;
+
+        let headers = record
+            .headers
+            .iter()
+            .fold(OwnedHeaders::new(), |headers, (key, value)| {
+                headers.insert(Header {
+                    key,
+                    value: Some(value),
+                })
+            });
+        let future_record = FutureRecord::to(&record.topic)
+            .key(&record.key)
+            .payload(&record.payload)
+            .headers(headers);
+        rdkafka::producer::FutureProducer::send(self, future_record, Timeout::After(QUEUE_TIMEOUT))
+            .await
+            .map(|_| ())
+            .map_err(|(err, _)| anyhow::anyhow!("Failed to produce to Kafka: {err}"))
+    }
+}
+
+/// Stands in for a producer when `KAFKA_ADDR` isn't set, logging what would
+/// have been sent, so the publish spans are there either way.
+#[derive(Debug, Default)]
+pub struct DisconnectedProducer;
+
+#[async_trait]
+impl KafkaSend for DisconnectedProducer {
+    async fn send(&self, record: KafkaRecord) -> Result<()> {
+        debug!(
+            name = "KafkaDisconnected",
+            messaging.destination.name = record.topic.as_str(),
+            messaging.message.body.size = record.payload.len(),
+            message = "No Kafka broker configured, dropping the record"
+        );
+        Ok(())
+    }
+}
+
+/// Publishes records in a `publish <topic>` producer span carrying the
+/// messaging semantic conventions, with the span's context in the record
+/// headers.
+pub struct KafkaProducerWrapper {
+    producer: Box<dyn KafkaSend>,
+    message_size: Histogram<u64>,
+}
+
+impl KafkaProducerWrapper {
+    pub fn new(producer: Box<dyn KafkaSend>, meter: &Meter) -> Self {
+        KafkaProducerWrapper {
+            producer,
+            message_size: meter
+                .u64_histogram(checked_instrument_name("messaging_message_body_size_bytes"))
+                .with_unit("By")
+                .with_description("Size of the Kafka records published")
+                .build(),
+        }
+    }
+
+    /// A Kafka producer for the brokers in `addr`, or a disconnected one
+    /// when it is empty or the service was built without `kafka`.
+    pub fn from_addr(addr: &str, meter: &Meter) -> Self {
+        Self::new(producer_for(addr), meter)
+    }
+
+    /// Sends `payload` to `topic`, returning the message ID it was sent
+    /// with.
+    pub async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<String> {
+        let message_id = Uuid::new_v4().to_string();
+        let tracer = global::tracer("otel_demo.shipping.kafka");
+        let span = tracer
+            .span_builder(format!("publish {topic}"))
+            .with_kind(SpanKind::Producer)
+            .with_attributes([
+                KeyValue::new(MESSAGING_SYSTEM, "kafka"),
+                KeyValue::new(MESSAGING_DESTINATION_NAME, topic.to_string()),
+                KeyValue::new(MESSAGING_MESSAGE_ID, message_id.clone()),
+                KeyValue::new(MESSAGING_OPERATION_TYPE, "send"),
+                KeyValue::new(MESSAGING_OPERATION_NAME, "publish"),
+                KeyValue::new(MESSAGING_KAFKA_MESSAGE_KEY, key.to_string()),
+                KeyValue::new(MESSAGING_MESSAGE_BODY_SIZE, payload.len() as i64),
+            ])
+            .start(&tracer);
+        let cx = Context::current_with_span(span);
+
+        let mut record = KafkaRecord {
+            topic: topic.to_string(),
+            key: key.to_string(),
+            payload,
+            headers: Vec::new(),
+        };
+        global::get_text_map_propagator(|propagator| propagator.inject_context(&cx, &mut record));
+        self.message_size.record(
+            record.payload.len() as u64,
+            &KUBERNETES_LABELS
+                .with(&[KeyValue::new(MESSAGING_DESTINATION_NAME, topic.to_string())]),
+        );
+
+        let result = self.producer.send(record).with_context(cx.clone()).await;
+        if let Err(err) = &result {
+            let span = cx.span();
+            span.set_attribute(KeyValue::new(ERROR_TYPE, "publish_failed"));
+            span.set_status(Status::error(err.to_string()));
+        }
+        result.map(|()| message_id)
+    }
+
+    /// Publishes an `OrderShipped` event, logging rather than failing if it
+    /// can't be sent.
+    pub async fn order_shipped(&self, event: &OrderShipped) {
+        let payload = match serde_json::to_vec(event) {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!(
+                    name = "OrderEventSerializationFailed",
+                    error = %err,
+                    message = "Failed to serialize the order event"
+                );
+                return;
+            }
+        };
+        if let Err(err) = self
+            .publish(ORDER_EVENTS_TOPIC, &event.tracking_id, payload)
+            .await
+        {
+            warn!(
+                name = "OrderEventPublishFailed",
+                tracking_id = event.tracking_id.as_str(),
+                error = format!("{err:#}"),
+                message = "Failed to publish the order event"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+fn producer_for(addr: &str) -> Box<dyn KafkaSend> {
+    if addr.is_empty() {
+        return Box::new(DisconnectedProducer);
+    }
+    let producer = rdkafka::ClientConfig::new()
+        .set("bootstrap.servers", addr)
+        .set("message.timeout.ms", "5000")
+        .create::<rdkafka::producer::FutureProducer>();
+    match producer {
+        Ok(producer) => Box::new(producer),
+        Err(err) => {
+            warn!(
+                name = "KafkaProducerFailed",
+                kafka.addr = addr,
+                error = %err,
+                message = "Failed to create the Kafka producer, order events are dropped"
+            );
+            Box::new(DisconnectedProducer)
+        }
+    }
+}
+
+#[cfg(not(feature = "kafka"))]
+fn producer_for(addr: &str) -> Box<dyn KafkaSend> {
+    if !addr.is_empty() {
+        warn!(
+            name = "KafkaUnavailable",
+            kafka.addr = addr,
+            message = "KAFKA_ADDR needs the kafka feature, order events are dropped"
+        );
+    }
+    Box::new(DisconnectedProducer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use opentelemetry::{metrics::MeterProvider, trace::TraceId, Value};
+
+    use super::*;
+    use crate::telemetry_conf::init_propagator;
+    use crate::test_utils::{global_span_exporter, test_meter_provider, u64_histogram_points};
+
+    #[derive(Default, Clone)]
+    struct RecordingProducer {
+        records: Arc<Mutex<Vec<KafkaRecord>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl KafkaSend for RecordingProducer {
+        async fn send(&self, record: KafkaRecord) -> Result<()> {
+            self.records.lock().unwrap().push(record);
+            if self.fail {
+                anyhow::bail!("broker unavailable");
+            }
+            Ok(())
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_publish_span_and_headers() {
+        init_propagator();
+        let exporter = global_span_exporter();
+        let (provider, metrics) = test_meter_provider();
+        let producer = RecordingProducer::default();
+        let wrapper =
+            KafkaProducerWrapper::new(Box::new(producer.clone()), &provider.meter("test"));
+
+        let message_id = wrapper
+            .publish("orders", "tracking-1", b"{\"a\":1}".to_vec())
+            .await
+            .unwrap();
+
+        let span = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| {
+                span.attributes
+                    .contains(&KeyValue::new(MESSAGING_MESSAGE_ID, message_id.clone()))
+            })
+            .unwrap();
+        assert_eq!(span.name, "publish orders");
+        assert_eq!(span.span_kind, SpanKind::Producer);
+        for expected in [
+            KeyValue::new(MESSAGING_SYSTEM, "kafka"),
+            KeyValue::new(MESSAGING_DESTINATION_NAME, "orders"),
+            KeyValue::new(MESSAGING_OPERATION_TYPE, "send"),
+            KeyValue::new(MESSAGING_MESSAGE_BODY_SIZE, 7),
+        ] {
+            assert!(span.attributes.contains(&expected), "{:?}", span.attributes);
+        }
+
+        let records = producer.records.lock().unwrap();
+        let traceparent = records[0]
+            .headers
+            .iter()
+            .find(|(key, _)| key == "traceparent")
+            .map(|(_, value)| value.clone())
+            .unwrap();
+        let trace_id = span.span_context.trace_id();
+        assert_ne!(trace_id, TraceId::INVALID);
+        assert_eq!(
+            traceparent,
+            format!("00-{trace_id}-{}-01", span.span_context.span_id())
+        );
+
+        provider.force_flush().unwrap();
+        let sizes = u64_histogram_points(&metrics, "messaging_message_body_size_bytes");
+        assert_eq!(sizes[0].sum(), 7);
+    }
+
+    #[actix_web::test]
+    async fn test_failed_publish_marks_the_span() {
+        let exporter = global_span_exporter();
+        let (provider, _) = test_meter_provider();
+        let producer = RecordingProducer {
+            fail: true,
+            ..Default::default()
+        };
+        let wrapper = KafkaProducerWrapper::new(Box::new(producer), &provider.meter("test"));
+        assert!(wrapper.publish("failing", "k", Vec::new()).await.is_err());
+
+        let span = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| span.name == "publish failing")
+            .unwrap();
+        assert!(matches!(span.status, Status::Error { .. }));
+        assert!(span
+            .attributes
+            .iter()
+            .any(|kv| kv.key.as_str() == ERROR_TYPE && kv.value == Value::from("publish_failed")));
+    }
+
+    #[test]
+    fn test_order_shipped_payload() {
+        let event = OrderShipped {
+            tracking_id: "H123".to_string(),
+            weight_class: WeightClass::Heavy,
+            total_weight_g: Some(12_000),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "tracking_id": "H123",
+                "weight_class": "heavy",
+                "total_weight_g": 12_000
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_disconnected_producer_still_traces() {
+        let exporter = global_span_exporter();
+        let (provider, _) = test_meter_provider();
+        let wrapper = KafkaProducerWrapper::from_addr("", &provider.meter("test"));
+        assert!(wrapper
+            .publish("disconnected", "k", b"{}".to_vec())
+            .await
+            .is_ok());
+        assert!(exporter
+            .get_finished_spans()
+            .unwrap()
+            .iter()
+            .any(|span| span.name == "publish disconnected"));
+    }
+}
@@ -4,48 +4,354 @@
 use core::fmt;
 use opentelemetry::global;
 use opentelemetry_instrumentation_actix_web::ClientExt;
-use std::{collections::HashMap, env};
+use std::{
+    collections::HashMap,
+    env,
+    future::Future,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use opentelemetry::{trace::get_active_span, KeyValue};
-use tracing::info;
+use opentelemetry::{
+    trace::{get_active_span, TraceContextExt, Tracer},
+    KeyValue,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 
-use super::shipping_types::Quote;
+use crate::metrics::{ExemplarFilter, ATTRIBUTE_WHITELIST};
+use crate::metrics_utils::CARDINALITY_TRACKER;
 
-pub async fn create_quote_from_count(count: u32) -> Result<Quote, tonic::Status> {
-    let f = match request_quote(count).await {
-        Ok(float) => float,
-        Err(err) => {
-            let msg = format!("{}", err);
-            return Err(tonic::Status::unknown(msg));
+use super::backend::QuoteOrder;
+use super::shipping_types::{CartItem, Quote};
+
+pub const DEFAULT_QUOTE_TIMEOUT: Duration = Duration::from_millis(3000);
+/// Largest `Money.units` the protobuf's `int64` can carry.
+pub const MAX_QUOTE_UNITS: u64 = i64::MAX as u64;
+const DEFAULT_DIMENSIONAL_WEIGHT_DIVISOR: f64 = 5000_f64;
+const WEIGHT_RATE_USD_PER_KG: f64 = 0.5;
+
+static DURATION_EXEMPLARS: LazyLock<ExemplarFilter> = LazyLock::new(ExemplarFilter::from_env);
+
+const TIERED_BASE_USD: f64 = 5_f64;
+/// Per-item rate up to (and including) each item count.
+const TIERED_RATES_USD: [(u32, f64); 3] = [(10, 1_f64), (50, 0.75), (u32::MAX, 0.5)];
+
+/// Discount applied to orders of at least `min_items` items.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DiscountTier {
+    pub min_items: u32,
+    pub percent: f64,
+}
+
+/// Volume discounts, by item count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscountEngine {
+    /// Sorted by `min_items`.
+    tiers: Vec<DiscountTier>,
+}
+
+impl Default for DiscountEngine {
+    fn default() -> Self {
+        DiscountEngine::new(
+            [(1, 0_f64), (6, 10_f64), (21, 20_f64), (101, 30_f64)]
+                .into_iter()
+                .map(|(min_items, percent)| DiscountTier { min_items, percent })
+                .collect(),
+        )
+    }
+}
+
+impl DiscountEngine {
+    /// Percentages are clamped to `0..=100`, so a discount never raises the
+    /// price or takes it below zero.
+    pub fn new(mut tiers: Vec<DiscountTier>) -> Self {
+        for tier in &mut tiers {
+            tier.percent = if tier.percent.is_nan() {
+                0_f64
+            } else {
+                tier.percent.clamp(0_f64, 100_f64)
+            };
         }
-    };
+        tiers.sort_by_key(|tier| tier.min_items);
+        DiscountEngine { tiers }
+    }
+
+    /// Parses tiers like
+    /// `[{"min_items": 6, "percent": 10}, {"min_items": 21, "percent": 20}]`,
+    /// rejecting percentages outside `0..=100` rather than clamping them.
+    pub fn parse(json: &str) -> Option<Self> {
+        let tiers: Vec<DiscountTier> = serde_json::from_str(json).ok()?;
+        tiers
+            .iter()
+            .all(|tier| (0_f64..=100_f64).contains(&tier.percent))
+            .then(|| Self::new(tiers))
+    }
+
+    pub fn tiers(&self) -> &[DiscountTier] {
+        &self.tiers
+    }
+
+    pub fn discount_percent(&self, count: u32) -> f64 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| count >= tier.min_items)
+            .map_or(0_f64, |tier| tier.percent)
+    }
+
+    /// `price` with the discount for `count` items taken off, along with the
+    /// percentage applied.
+    pub fn apply(&self, count: u32, price: f64) -> (f64, f64) {
+        let percent = self.discount_percent(count);
+        (price * (1_f64 - percent / 100_f64), percent)
+    }
+}
+
+/// How the base price of a quote is computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuoteStrategy {
+    /// Ask the quote service.
+    Remote,
+    /// Price locally, with a per-item rate that drops for larger orders.
+    Tiered,
+}
+
+impl QuoteStrategy {
+    /// `remote` or `tiered`, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            name if name.eq_ignore_ascii_case("remote") => Some(QuoteStrategy::Remote),
+            name if name.eq_ignore_ascii_case("tiered") => Some(QuoteStrategy::Tiered),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuoteStrategy::Remote => "remote",
+            QuoteStrategy::Tiered => "tiered",
+        }
+    }
+
+    async fn base_quote(&self, count: u32) -> Result<f64> {
+        match self {
+            QuoteStrategy::Remote => request_quote(count).await,
+            QuoteStrategy::Tiered => Ok(tiered_quote(count)),
+        }
+    }
+}
+
+/// How `/get-quote` prices orders, resolved once from the service config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteConfig {
+    pub strategy: QuoteStrategy,
+    pub timeout: Duration,
+    pub discounts: DiscountEngine,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig {
+            strategy: QuoteStrategy::Remote,
+            timeout: DEFAULT_QUOTE_TIMEOUT,
+            discounts: DiscountEngine::default(),
+        }
+    }
+}
+
+impl QuoteConfig {
+    /// Canary requests always get `Tiered`, whatever is configured.
+    pub fn strategy_for(&self, canary: bool) -> QuoteStrategy {
+        if canary {
+            QuoteStrategy::Tiered
+        } else {
+            self.strategy
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum QuoteError {
+    /// The quote took longer than `QUOTE_TIMEOUT_MS`.
+    Timeout(Duration),
+    /// The base price couldn't be computed.
+    Pricing(anyhow::Error),
+    /// The price doesn't fit in `Money.units`, an `int64` in the protobuf.
+    Overflow,
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuoteError::Timeout(timeout) => write!(f, "quote timed out after {timeout:?}"),
+            QuoteError::Pricing(err) => write!(f, "{err:#}"),
+            QuoteError::Overflow => write!(f, "quote exceeds {MAX_QUOTE_UNITS} dollars"),
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+/// Fails `quote` with `QuoteError::Timeout` if it doesn't finish within
+/// `timeout`, recording a `quote_timeout` event on the active span.
+pub async fn with_timeout(
+    timeout: Duration,
+    quote: impl Future<Output = Result<Quote, QuoteError>>,
+) -> Result<Quote, QuoteError> {
+    match tokio::time::timeout(timeout, quote).await {
+        Ok(result) => result,
+        Err(_) => {
+            let timeout_ms = timeout.as_millis() as i64;
+            get_active_span(|span| {
+                span.add_event(
+                    "quote_timeout",
+                    vec![KeyValue::new("quote.timeout_ms", timeout_ms)],
+                )
+            });
+            global::meter("otel_demo.shipping.quote")
+                .u64_counter("quote_timeouts_total")
+                .with_description("Quotes abandoned after QUOTE_TIMEOUT_MS")
+                .build()
+                .add(1, &[]);
+            warn!(
+                name = "QuoteTimedOut",
+                quote.timeout_ms = timeout_ms,
+                message = "Quote calculation timed out"
+            );
+            Err(QuoteError::Timeout(timeout))
+        }
+    }
+}
+
+pub async fn create_quote_from_count(
+    count: u32,
+    order: &QuoteOrder<'_>,
+) -> Result<Quote, QuoteError> {
+    let QuoteOrder {
+        zone,
+        items,
+        speed,
+        strategy,
+        canary,
+        tenant,
+        discounts,
+    } = *order;
+    let start = Instant::now();
+    let f = strategy
+        .base_quote(count)
+        .await
+        .map_err(QuoteError::Pricing)?
+        * speed.multiplier()
+        * tenant.pricing_multiplier;
+
+    let mut attributes = vec![
+        KeyValue::new("shipping.zone", zone.as_str()),
+        tenant.key_value(),
+    ];
+    if canary {
+        attributes.push(KeyValue::new("canary", true));
+    }
 
     let meter = global::meter("otel_demo.shipping.quote");
     let counter = meter.u64_counter("app.shipping.items_count").build();
-    counter.add(count as u64, &[]);
+    ATTRIBUTE_WHITELIST.check("app.shipping.items_count", &attributes);
+    CARDINALITY_TRACKER.record("app.shipping.items_count", &attributes);
+    counter.add(count as u64, &attributes);
 
-    Ok(get_active_span(|span| {
-        let q = create_quote_from_float(f);
+    let tracer = global::tracer("otel_demo.shipping.quote");
+    let billable_weight = tracer.in_span("quote.parse_items", |cx| {
+        cx.span()
+            .set_attribute(KeyValue::new("quote.item_count", i64::from(count)));
+        let divisor = dimensional_weight_divisor();
+        let dimensional_weight = meter
+            .f64_histogram("quote_dimensional_weight_kg")
+            .with_unit("kg")
+            .build();
+        for weight in items
+            .iter()
+            .filter_map(|item| dimensional_weight_kg(item, divisor))
+        {
+            dimensional_weight.record(weight, &[tenant.key_value()]);
+        }
+        billable_weight_kg(items, divisor)
+    });
+
+    let f = f + billable_weight * WEIGHT_RATE_USD_PER_KG;
+    let (f, discount_percent) = tracer.in_span("quote.apply_discount", |cx| {
+        let (f, discount_percent) = discounts.apply(count, f * zone.surcharge());
+        cx.span()
+            .set_attribute(KeyValue::new("quote.discount_percent", discount_percent));
+        ((f * 100_f64).round() / 100_f64, discount_percent)
+    });
+    let discount = KeyValue::new("quote.discount_percent", discount_percent);
+
+    let Some(quote) = tracer.in_span("quote.convert_currency", |_| checked_quote_from_float(f))
+    else {
+        warn!(
+            name = "QuoteOverflowed",
+            quote.item_count = count,
+            message = "Quote is too large to represent"
+        );
+        return Err(QuoteError::Overflow);
+    };
+    get_active_span(|span| {
         span.add_event(
             "Received Quote".to_string(),
-            vec![KeyValue::new("app.shipping.cost.total", format!("{}", q))],
+            vec![KeyValue::new(
+                "app.shipping.cost.total",
+                format!("{}", quote),
+            )],
         );
-        span.set_attribute(KeyValue::new("app.shipping.cost.total", format!("{}", q)));
-        q
-    }))
+        span.set_attribute(KeyValue::new(
+            "app.shipping.cost.total",
+            format!("{}", quote),
+        ));
+        span.set_attributes(attributes.clone());
+        span.set_attribute(discount.clone());
+    });
+
+    let mut amount_attributes = attributes.clone();
+    amount_attributes.push(discount);
+    amount_attributes.push(KeyValue::new("delivery_speed", speed.as_str()));
+    ATTRIBUTE_WHITELIST.check("quote_amount_usd", &amount_attributes);
+    CARDINALITY_TRACKER.record("quote_amount_usd", &amount_attributes);
+    meter
+        .f64_histogram("quote_amount_usd")
+        .with_unit("USD")
+        .build()
+        .record(f, &amount_attributes);
+    let duration = start.elapsed().as_secs_f64();
+    ATTRIBUTE_WHITELIST.check("quote_generation_duration_seconds", &attributes);
+    CARDINALITY_TRACKER.record("quote_generation_duration_seconds", &attributes);
+    meter
+        .f64_histogram("quote_generation_duration_seconds")
+        .with_unit("s")
+        .build()
+        .record(duration, &attributes);
+    DURATION_EXEMPLARS.record("quote_generation_duration_seconds", duration);
+
+    Ok(quote)
+}
+
+/// Lets the collector's service graph processor draw an edge from the
+/// shipping service to the quote service.
+fn pricing_client_attributes() -> [KeyValue; 2] {
+    [
+        KeyValue::new("http.system", "http"),
+        KeyValue::new("rpc.service", "pricing"),
+    ]
 }
 
 async fn request_quote(count: u32) -> Result<f64, anyhow::Error> {
+    let quote_addr = env::var("QUOTE_ADDR").unwrap_or_else(|_| "http://quote:8090".to_string());
+    request_quote_from(&quote_addr, count).await
+}
+
+async fn request_quote_from(quote_addr: &str, count: u32) -> Result<f64, anyhow::Error> {
     let client = awc::Client::new();
-    let quote_service_addr: String = format!(
-        "{}{}",
-        env::var("QUOTE_ADDR")
-            .unwrap_or_else(|_| "http://quote:8090".to_string())
-            .parse::<String>()
-            .expect("Invalid quote service address"),
-        "/getquote"
-    );
+    let quote_service_addr = format!("{quote_addr}/getquote");
 
     info!(
         name = "RequestingQuote",
@@ -59,6 +365,7 @@ async fn request_quote(count: u32) -> Result<f64, anyhow::Error> {
     let mut response = client
         .post(quote_service_addr)
         .trace_request()
+        .with_attributes(pricing_client_attributes())
         .send_json(&reqbody)
         .await
         .map_err(|err| anyhow::anyhow!("Failed to call quote service: {err}"))?;
@@ -79,13 +386,67 @@ async fn request_quote(count: u32) -> Result<f64, anyhow::Error> {
     Ok(f)
 }
 
+pub fn tiered_quote(count: u32) -> f64 {
+    let mut total = TIERED_BASE_USD;
+    let mut priced = 0;
+    for (upto, rate) in TIERED_RATES_USD {
+        if count <= priced {
+            break;
+        }
+        total += (count.min(upto) - priced) as f64 * rate;
+        priced = upto;
+    }
+    total
+}
+
+fn dimensional_weight_divisor() -> f64 {
+    env::var("DIMENSIONAL_WEIGHT_DIVISOR")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|divisor| *divisor > 0_f64)
+        .unwrap_or(DEFAULT_DIMENSIONAL_WEIGHT_DIVISOR)
+}
+
+/// Volumetric weight of one unit of `item` in kg, if all its dimensions are
+/// known: `length × width × height / divisor`, with dimensions in cm.
+pub fn dimensional_weight_kg(item: &CartItem, divisor: f64) -> Option<f64> {
+    match (item.length_cm, item.width_cm, item.height_cm) {
+        (Some(length), Some(width), Some(height)) => Some(length * width * height / divisor),
+        _ => None,
+    }
+}
+
+/// Total weight to charge for, taking the larger of actual and dimensional
+/// weight for each item. Items without weight or dimensions add nothing.
+pub fn billable_weight_kg(items: &[CartItem], divisor: f64) -> f64 {
+    items
+        .iter()
+        .filter_map(|item| {
+            let actual = item.weight_g.map(|grams| grams as f64 / 1000_f64);
+            let weight = match (actual, dimensional_weight_kg(item, divisor)) {
+                (Some(actual), Some(dimensional)) => actual.max(dimensional),
+                (actual, dimensional) => actual.or(dimensional)?,
+            };
+            Some(weight * item.quantity as f64)
+        })
+        .sum()
+}
+
 pub fn create_quote_from_float(value: f64) -> Quote {
     Quote {
         dollars: value.floor() as u64,
-        cents: ((value * 100_f64) as u32) % 100,
+        cents: ((value * 100_f64) % 100_f64) as u32,
     }
 }
 
+/// `create_quote_from_float`, or `None` if `value` isn't a finite,
+/// non-negative amount of at most `MAX_QUOTE_UNITS` dollars.
+pub fn checked_quote_from_float(value: f64) -> Option<Quote> {
+    // `MAX_QUOTE_UNITS as f64` rounds up to 2^63, which is already too large
+    (value.is_finite() && (0_f64..MAX_QUOTE_UNITS as f64).contains(&value))
+        .then(|| create_quote_from_float(value))
+}
+
 impl fmt::Display for Quote {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}.{}", self.dollars, self.cents)
@@ -94,7 +455,15 @@ impl fmt::Display for Quote {
 
 #[cfg(test)]
 mod tests {
+    use httpmock::prelude::*;
+    use opentelemetry::trace::{FutureExt, Span, SpanKind};
+    use proptest::prelude::*;
+
     use super::*;
+    use crate::shipping_service::shipping_types::DeliverySpeed;
+    use crate::shipping_service::tenant::TenantConfig;
+    use crate::shipping_service::zones::ShippingZone;
+    use crate::test_utils::global_span_exporter;
 
     #[test]
     fn test_create_quote_from_float() {
@@ -111,6 +480,288 @@ mod tests {
         assert_eq!(quote.cents, 0);
     }
 
+    #[actix_web::test]
+    async fn test_delivery_speed_multiplier() {
+        let items = [CartItem {
+            quantity: 10,
+            length_cm: None,
+            width_cm: None,
+            height_cm: None,
+            weight_g: None,
+        }];
+        let mut quotes = Vec::new();
+        for speed in [
+            DeliverySpeed::Standard,
+            DeliverySpeed::Express,
+            DeliverySpeed::Overnight,
+        ] {
+            let order = QuoteOrder {
+                zone: ShippingZone::Domestic,
+                items: &items,
+                speed,
+                strategy: QuoteStrategy::Tiered,
+                canary: false,
+                tenant: &TenantConfig::default(),
+                discounts: &DiscountEngine::default(),
+            };
+            let quote = create_quote_from_count(10, &order).await.unwrap();
+            quotes.push(quote.dollars as f64 + f64::from(quote.cents) / 100_f64);
+        }
+        // 15.00 tiered, less the 10% discount for 6-20 items
+        assert_eq!(quotes, [13.5, 20.25, 33.75]);
+        assert_eq!(quotes[1] / quotes[0], 1.5);
+        assert_eq!(quotes[2] / quotes[0], 2.5);
+    }
+
+    #[actix_web::test]
+    async fn test_with_timeout() {
+        let timeout = Duration::from_millis(10);
+        let stalled = with_timeout(timeout, std::future::pending()).await;
+        assert!(matches!(stalled, Err(QuoteError::Timeout(t)) if t == timeout));
+
+        let quote = with_timeout(timeout, async { Ok(Quote::default()) }).await;
+        assert!(quote.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_quote_client_span_has_service_graph_attributes() {
+        let exporter = global_span_exporter();
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/getquote");
+                then.status(200).body("12.5");
+            })
+            .await;
+
+        let parent = global::tracer("test").start("parent");
+        let trace_id = parent.span_context().trace_id();
+        let quote = request_quote_from(&server.url(""), 1)
+            .with_context(opentelemetry::Context::current_with_span(parent))
+            .await
+            .unwrap();
+        assert_eq!(quote, 12.5);
+
+        let client_span = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| {
+                span.span_context.trace_id() == trace_id && span.span_kind == SpanKind::Client
+            })
+            .unwrap();
+        for attribute in pricing_client_attributes() {
+            assert!(
+                client_span.attributes.contains(&attribute),
+                "{:?}",
+                client_span.attributes
+            );
+        }
+    }
+
+    #[test]
+    fn test_tiered_quote() {
+        assert_eq!(tiered_quote(0), 5_f64);
+        assert_eq!(tiered_quote(10), 15_f64);
+        assert_eq!(tiered_quote(20), 22.5);
+        assert_eq!(tiered_quote(60), 50_f64);
+        assert_eq!(tiered_quote(u32::MAX), 2147483667.5);
+    }
+
+    #[test]
+    fn test_strategy_parse() {
+        assert_eq!(QuoteStrategy::parse("remote"), Some(QuoteStrategy::Remote));
+        assert_eq!(QuoteStrategy::parse("Tiered"), Some(QuoteStrategy::Tiered));
+        assert_eq!(QuoteStrategy::parse("bogus"), None);
+    }
+
+    #[test]
+    fn test_canary_selects_tiered() {
+        let config = QuoteConfig::default();
+        assert_eq!(config.strategy_for(false), QuoteStrategy::Remote);
+        assert_eq!(config.strategy_for(true), QuoteStrategy::Tiered);
+    }
+
+    #[test]
+    fn test_default_discount_tiers() {
+        let engine = DiscountEngine::default();
+        assert_eq!(engine.discount_percent(0), 0_f64);
+        assert_eq!(engine.discount_percent(5), 0_f64);
+        assert_eq!(engine.discount_percent(6), 10_f64);
+        assert_eq!(engine.discount_percent(20), 10_f64);
+        assert_eq!(engine.discount_percent(21), 20_f64);
+        assert_eq!(engine.discount_percent(100), 20_f64);
+        assert_eq!(engine.discount_percent(101), 30_f64);
+        assert_eq!(engine.apply(50, 200_f64), (160_f64, 20_f64));
+    }
+
+    #[test]
+    fn test_discount_tiers_parse() {
+        let engine = DiscountEngine::parse(
+            r#"[{"min_items": 50, "percent": 25}, {"min_items": 2, "percent": 100}]"#,
+        )
+        .unwrap();
+        assert_eq!(engine.discount_percent(1), 0_f64);
+        assert_eq!(engine.discount_percent(2), 100_f64);
+        assert_eq!(engine.discount_percent(50), 25_f64);
+
+        assert_eq!(DiscountEngine::parse("not json"), None);
+        assert_eq!(
+            DiscountEngine::parse(r#"[{"min_items": 2, "percent": 150}]"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_checked_quote_from_float() {
+        assert_eq!(
+            checked_quote_from_float(10.99).map(|q| (q.dollars, q.cents)),
+            Some((10, 99))
+        );
+        for value in [-0.01, f64::NAN, f64::INFINITY, 2_f64.powi(63)] {
+            assert!(checked_quote_from_float(value).is_none(), "{value}");
+        }
+    }
+
+    /// Tiered overnight quote for `items` as priced for `tenant`.
+    fn blocking_quote(
+        count: u32,
+        items: &[CartItem],
+        tenant: &TenantConfig,
+    ) -> Result<Quote, QuoteError> {
+        // Not `futures_executor`, which the simple span processor can't nest
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(create_quote_from_count(
+                count,
+                &QuoteOrder {
+                    zone: ShippingZone::Domestic,
+                    items,
+                    speed: DeliverySpeed::Overnight,
+                    strategy: QuoteStrategy::Tiered,
+                    canary: false,
+                    tenant,
+                    discounts: &DiscountEngine::default(),
+                },
+            ))
+    }
+
+    #[test]
+    fn test_oversized_quote_overflows() {
+        let tenant = TenantConfig {
+            pricing_multiplier: 1e10,
+            ..Default::default()
+        };
+        let quote = blocking_quote(u32::MAX, &[], &tenant);
+        assert!(matches!(quote, Err(QuoteError::Overflow)));
+    }
+
+    proptest! {
+        #[test]
+        fn checked_quote_fits_money_units(value in any::<f64>()) {
+            if let Some(quote) = checked_quote_from_float(value) {
+                prop_assert!(quote.dollars <= MAX_QUOTE_UNITS);
+                prop_assert!(quote.cents < 100);
+                prop_assert_eq!(quote.dollars, value.floor() as u64);
+            }
+        }
+
+        #[test]
+        fn quotes_fit_or_overflow_for_any_count(
+            count in any::<u32>(),
+            multiplier in 0_f64..1e12,
+            size_cm in 0_f64..1e7,
+        ) {
+            let tenant = TenantConfig {
+                pricing_multiplier: multiplier,
+                ..Default::default()
+            };
+            let items = [CartItem {
+                quantity: count,
+                length_cm: Some(size_cm),
+                width_cm: Some(size_cm),
+                height_cm: Some(size_cm),
+                weight_g: None,
+            }];
+            match blocking_quote(count, &items, &tenant) {
+                Ok(quote) => {
+                    prop_assert!(quote.dollars <= MAX_QUOTE_UNITS);
+                    prop_assert!(quote.cents < 100);
+                }
+                Err(err) => prop_assert!(matches!(err, QuoteError::Overflow), "{err}"),
+            }
+        }
+
+        #[test]
+        fn discounted_price_never_exceeds_price(
+            count in any::<u32>(),
+            price in 0_f64..1_000_000_f64,
+            tiers in prop::collection::vec((any::<u32>(), -50_f64..200_f64), 0..6),
+        ) {
+            let engine = DiscountEngine::new(
+                tiers
+                    .into_iter()
+                    .map(|(min_items, percent)| DiscountTier { min_items, percent })
+                    .collect(),
+            );
+            let (discounted, percent) = engine.apply(count, price);
+            prop_assert!(discounted <= price);
+            prop_assert!(discounted >= 0_f64);
+            prop_assert!((0_f64..=100_f64).contains(&percent));
+
+            let (discounted, _) = DiscountEngine::default().apply(count, price);
+            prop_assert!(discounted <= price);
+        }
+    }
+
+    fn item(
+        quantity: u32,
+        dimensions_cm: Option<(f64, f64, f64)>,
+        weight_g: Option<u32>,
+    ) -> CartItem {
+        CartItem {
+            quantity,
+            length_cm: dimensions_cm.map(|d| d.0),
+            width_cm: dimensions_cm.map(|d| d.1),
+            height_cm: dimensions_cm.map(|d| d.2),
+            weight_g,
+        }
+    }
+
+    #[test]
+    fn test_dimensional_weight() {
+        let boxed = item(1, Some((50_f64, 40_f64, 30_f64)), None);
+        assert_eq!(dimensional_weight_kg(&boxed, 5000_f64), Some(12_f64));
+        assert_eq!(dimensional_weight_kg(&boxed, 6000_f64), Some(10_f64));
+
+        let partial = CartItem {
+            length_cm: Some(10_f64),
+            ..item(1, None, None)
+        };
+        assert_eq!(dimensional_weight_kg(&partial, 5000_f64), None);
+    }
+
+    #[test]
+    fn test_billable_weight_takes_larger_weight() {
+        // 12kg dimensional vs 2kg actual
+        let bulky = item(2, Some((50_f64, 40_f64, 30_f64)), Some(2000));
+        // 0.2kg dimensional vs 3kg actual
+        let dense = item(1, Some((10_f64, 10_f64, 10_f64)), Some(3000));
+        let only_weight = item(1, None, Some(500));
+
+        assert_eq!(billable_weight_kg(&[bulky], 5000_f64), 24_f64);
+        assert_eq!(billable_weight_kg(&[dense], 5000_f64), 3_f64);
+        assert_eq!(billable_weight_kg(&[only_weight], 5000_f64), 0.5);
+    }
+
+    #[test]
+    fn test_billable_weight_without_dimensions() {
+        let items = [item(3, None, None), item(1, None, None)];
+        assert_eq!(billable_weight_kg(&items, 5000_f64), 0_f64);
+        assert_eq!(billable_weight_kg(&[], 5000_f64), 0_f64);
+    }
+
     #[test]
     fn test_quote_display() {
         let quote = Quote {
@@ -0,0 +1,87 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{http::header::ContentType, HttpResponse, HttpResponseBuilder};
+use opentelemetry::{global, metrics::Histogram, KeyValue};
+use serde::Serialize;
+
+const BODY_SIZE_BOUNDARIES: [f64; 6] = [100.0, 500.0, 1000.0, 5000.0, 10000.0, 50000.0];
+
+fn body_size_histogram() -> Histogram<u64> {
+    global::meter("otel_demo.shipping.http")
+        .u64_histogram("http_response_body_size_bytes")
+        .with_unit("By")
+        .with_boundaries(BODY_SIZE_BOUNDARIES.to_vec())
+        .build()
+}
+
+/// Serializes `value` as the JSON body of the response and records its size
+/// under `route`.
+pub fn json_response<T: Serialize>(
+    builder: HttpResponseBuilder,
+    route: &'static str,
+    value: &T,
+) -> HttpResponse {
+    json_response_with(&body_size_histogram(), builder, route, value)
+}
+
+fn json_response_with<T: Serialize>(
+    histogram: &Histogram<u64>,
+    mut builder: HttpResponseBuilder,
+    route: &'static str,
+    value: &T,
+) -> HttpResponse {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .body(format!("Failed to serialize response: {}", e));
+        }
+    };
+
+    histogram.record(body.len() as u64, &[KeyValue::new("http.route", route)]);
+    builder.content_type(ContentType::json()).body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::to_bytes, http::StatusCode};
+    use opentelemetry::metrics::MeterProvider;
+
+    use super::*;
+    use crate::shipping_service::ShipOrderResponse;
+    use crate::test_utils::{test_meter_provider, u64_histogram_points};
+
+    #[actix_web::test]
+    async fn test_records_body_size() {
+        let (provider, exporter) = test_meter_provider();
+        let histogram = provider
+            .meter("test")
+            .u64_histogram("http_response_body_size_bytes")
+            .with_boundaries(BODY_SIZE_BOUNDARIES.to_vec())
+            .build();
+
+        let order = ShipOrderResponse {
+            tracking_id: "a".repeat(600),
+            estimated_delivery_date: None,
+        };
+        let resp = json_response_with(&histogram, HttpResponse::Ok(), "/ship-order", &order);
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+
+        provider.force_flush().unwrap();
+        let points = u64_histogram_points(&exporter, "http_response_body_size_bytes");
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].sum(), body.len() as u64);
+        assert_eq!(points[0].count(), 1);
+        assert_eq!(
+            points[0].attributes().collect::<Vec<_>>(),
+            vec![&KeyValue::new("http.route", "/ship-order")]
+        );
+        // 600+ bytes lands in the (500, 1000] bucket
+        assert_eq!(
+            points[0].bucket_counts().collect::<Vec<_>>(),
+            vec![0, 0, 1, 0, 0, 0, 0]
+        );
+    }
+}
@@ -1,46 +1,170 @@
 // Copyright The OpenTelemetry Authors
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CartItem {
     pub quantity: u32,
+    pub length_cm: Option<f64>,
+    pub width_cm: Option<f64>,
+    pub height_cm: Option<f64>,
+    pub weight_g: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Address {
     pub zip_code: String,
+    pub country: Option<String>,
+}
+
+/// How fast an order is delivered, and so how much is charged for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliverySpeed {
+    #[default]
+    Standard,
+    Express,
+    Overnight,
+}
+
+impl DeliverySpeed {
+    /// Multiplier applied to the base quote.
+    pub fn multiplier(&self) -> f64 {
+        match self {
+            DeliverySpeed::Standard => 1.0,
+            DeliverySpeed::Express => 1.5,
+            DeliverySpeed::Overnight => 2.5,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliverySpeed::Standard => "standard",
+            DeliverySpeed::Express => "express",
+            DeliverySpeed::Overnight => "overnight",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GetQuoteRequest {
     pub items: Vec<CartItem>,
     pub address: Option<Address>,
+    pub delivery_speed: Option<DeliverySpeed>,
 }
 
-#[derive(Debug, Serialize)]
+impl GetQuoteRequest {
+    /// Identifies what is being quoted, whatever the order of the items.
+    /// Items have no product ID, so they are ordered by their JSON instead.
+    /// Only stable within a process.
+    pub fn fingerprint(&self) -> u64 {
+        let mut items: Vec<String> = self
+            .items
+            .iter()
+            .map(|item| serde_json::to_string(item).expect("cart items serialize"))
+            .collect();
+        items.sort();
+        let mut hasher = DefaultHasher::new();
+        items.hash(&mut hasher);
+        serde_json::to_string(&self.address)
+            .expect("addresses serialize")
+            .hash(&mut hasher);
+        self.delivery_speed
+            .unwrap_or_default()
+            .as_str()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A `/get-quote` body from `X-API-Version: 2023-10`, before items had
+/// dimensions and quotes a delivery speed.
+#[derive(Debug, Deserialize)]
+pub struct GetQuoteRequestV1 {
+    pub items: Vec<CartItemV1>,
+    pub address: Option<Address>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CartItemV1 {
+    pub quantity: u32,
+}
+
+impl From<GetQuoteRequestV1> for GetQuoteRequest {
+    fn from(v1: GetQuoteRequestV1) -> Self {
+        GetQuoteRequest {
+            items: v1
+                .items
+                .into_iter()
+                .map(|item| CartItem {
+                    quantity: item.quantity,
+                    length_cm: None,
+                    width_cm: None,
+                    height_cm: None,
+                    weight_g: None,
+                })
+                .collect(),
+            address: v1.address,
+            delivery_speed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Money {
     pub currency_code: String,
     pub units: u64,
     pub nanos: u32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct GetQuoteResponse {
     pub cost_usd: Option<Money>,
+    pub delivery_speed: DeliverySpeed,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Quote {
     pub dollars: u64,
     pub cents: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ShipOrderRequest {}
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ShipOrderRequest {
+    pub total_weight_g: Option<u64>,
+    /// Hex trace ID of the `/get-quote` request the order was quoted by.
+    pub quote_trace_id: Option<String>,
+    /// Where and how fast it ships, for the delivery estimate.
+    pub address: Option<Address>,
+    pub delivery_speed: Option<DeliverySpeed>,
+}
+
+/// A `/ship-order` body from `X-API-Version: 2023-10`, before orders had a
+/// delivery estimate.
+#[derive(Debug, Deserialize)]
+pub struct ShipOrderRequestV1 {
+    pub total_weight_g: Option<u64>,
+    pub quote_trace_id: Option<String>,
+}
+
+impl From<ShipOrderRequestV1> for ShipOrderRequest {
+    fn from(v1: ShipOrderRequestV1) -> Self {
+        ShipOrderRequest {
+            total_weight_g: v1.total_weight_g,
+            quote_trace_id: v1.quote_trace_id,
+            ..Default::default()
+        }
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ShipOrderResponse {
     pub tracking_id: String,
+    /// RFC 3339; `None` without both an address country and a delivery
+    /// speed.
+    pub estimated_delivery_date: Option<String>,
 }
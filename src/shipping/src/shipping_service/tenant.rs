@@ -0,0 +1,212 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::env;
+use std::future::{ready, Ready};
+use std::sync::{Arc, LazyLock};
+
+use actix_web::{
+    dev::Payload, error::InternalError, http::StatusCode, web, Error, FromRequest, HttpRequest,
+};
+use anyhow::{ensure, Context, Result};
+use opentelemetry::{global, metrics::Counter, trace::get_active_span, KeyValue};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+use crate::metrics::checked_instrument_name;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+pub const TENANT_ID: &str = "tenant.id";
+const DEFAULT_TENANT_ID: &str = "default";
+
+static TENANT_REQUESTS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    global::meter("otel_demo.shipping.tenant")
+        .u64_counter(checked_instrument_name("tenant_requests_total"))
+        .with_description("Quote requests, by tenant.id")
+        .build()
+});
+
+fn default_currencies() -> Vec<String> {
+    vec!["USD".to_string()]
+}
+
+/// Pricing for one tenant.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TenantConfig {
+    pub id: String,
+    /// Applied to the quote along with the delivery speed multiplier.
+    #[serde(default = "TenantConfig::default_multiplier")]
+    pub pricing_multiplier: f64,
+    /// Currencies the tenant may be quoted in.
+    #[serde(default = "default_currencies")]
+    pub allowed_currencies: Vec<String>,
+}
+
+impl TenantConfig {
+    fn default_multiplier() -> f64 {
+        1.0
+    }
+
+    pub fn allows_currency(&self, currency_code: &str) -> bool {
+        self.allowed_currencies
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(currency_code))
+    }
+
+    pub fn key_value(&self) -> KeyValue {
+        KeyValue::new(TENANT_ID, self.id.clone())
+    }
+}
+
+/// For requests without an API key: list prices in USD.
+impl Default for TenantConfig {
+    fn default() -> Self {
+        TenantConfig {
+            id: DEFAULT_TENANT_ID.to_string(),
+            pricing_multiplier: Self::default_multiplier(),
+            allowed_currencies: default_currencies(),
+        }
+    }
+}
+
+/// Tenants by API key.
+#[derive(Debug, Clone, Default)]
+pub struct Tenants {
+    by_api_key: HashMap<String, Arc<TenantConfig>>,
+    default: Arc<TenantConfig>,
+}
+
+impl Tenants {
+    /// Parses a `{"api key": {"id": ..., "pricing_multiplier": ...,
+    /// "allowed_currencies": [...]}}` object. Multipliers must be finite and
+    /// positive.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let by_api_key: HashMap<String, TenantConfig> =
+            serde_json::from_str(json).context("invalid tenant config")?;
+        for tenant in by_api_key.values() {
+            ensure!(
+                tenant.pricing_multiplier.is_finite() && tenant.pricing_multiplier > 0.0,
+                "tenant {}: pricing_multiplier must be a positive number, got {}",
+                tenant.id,
+                tenant.pricing_multiplier
+            );
+        }
+        Ok(Tenants {
+            by_api_key: by_api_key
+                .into_iter()
+                .map(|(key, tenant)| (key, Arc::new(tenant)))
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Reads `TENANT_CONFIG_JSON`. An unset or invalid value leaves only the
+    /// default tenant.
+    pub fn from_env() -> Self {
+        let Ok(json) = env::var("TENANT_CONFIG_JSON") else {
+            return Self::default();
+        };
+        Self::from_json(&json).unwrap_or_else(|err| {
+            warn!(
+                name = "InvalidTenantConfig",
+                error = format!("{err:#}"),
+                message = "Ignoring TENANT_CONFIG_JSON"
+            );
+            Self::default()
+        })
+    }
+
+    /// The default tenant without a key, `None` for an unknown one.
+    pub fn resolve(&self, api_key: Option<&str>) -> Option<Arc<TenantConfig>> {
+        match api_key {
+            None => Some(self.default.clone()),
+            Some(key) => self.by_api_key.get(key).cloned(),
+        }
+    }
+}
+
+/// The tenant named by the request's `X-API-Key`, from the `Tenants` in the
+/// app data. Unknown keys are rejected with 401. Apps without `Tenants`
+/// treat every request as the default tenant.
+///
+/// Counts the request in `tenant_requests_total` and tags the request span
+/// with `tenant.id`.
+#[derive(Debug, Clone)]
+pub struct Tenant(pub Arc<TenantConfig>);
+
+impl FromRequest for Tenant {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let api_key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty());
+        let tenant = match req.app_data::<web::Data<Tenants>>() {
+            Some(tenants) => tenants.resolve(api_key),
+            None => Some(Arc::new(TenantConfig::default())),
+        };
+        let Some(tenant) = tenant else {
+            let response = ErrorResponse::new(
+                StatusCode::UNAUTHORIZED,
+                "unknown_api_key",
+                "Unknown API key",
+                CorrelationId::of(req),
+            )
+            .into_response();
+            return ready(Err(InternalError::from_response(
+                "unknown API key",
+                response,
+            )
+            .into()));
+        };
+        TENANT_REQUESTS.add(1, &[tenant.key_value()]);
+        get_active_span(|span| span.set_attribute(tenant.key_value()));
+        ready(Ok(Tenant(tenant)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let tenants = Tenants::from_json(
+            r#"{"acme-key": {"id": "acme", "pricing_multiplier": 0.8, "allowed_currencies": ["USD", "EUR"]}}"#,
+        )
+        .unwrap();
+
+        let acme = tenants.resolve(Some("acme-key")).unwrap();
+        assert_eq!(acme.id, "acme");
+        assert_eq!(acme.pricing_multiplier, 0.8);
+        assert!(acme.allows_currency("eur"));
+        assert_eq!(*tenants.resolve(None).unwrap(), TenantConfig::default());
+        assert_eq!(tenants.resolve(Some("other")), None);
+    }
+
+    #[test]
+    fn test_defaults_for_missing_fields() {
+        let tenants = Tenants::from_json(r#"{"k": {"id": "t"}}"#).unwrap();
+        let tenant = tenants.resolve(Some("k")).unwrap();
+        assert_eq!(tenant.pricing_multiplier, 1.0);
+        assert!(tenant.allows_currency("USD"));
+        assert!(Tenants::from_json(r#"{"k": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_multipliers_are_rejected() {
+        for multiplier in ["0", "-0.5"] {
+            let json = format!(r#"{{"k": {{"id": "t", "pricing_multiplier": {multiplier}}}}}"#);
+            let err = Tenants::from_json(&json).unwrap_err();
+            assert!(format!("{err:#}").contains("pricing_multiplier"), "{err:#}");
+        }
+        assert!(Tenants::from_json(r#"{"k": {"id": "t", "pricing_multiplier": 1e400}}"#).is_err());
+    }
+}
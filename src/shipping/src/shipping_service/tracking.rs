@@ -1,9 +1,131 @@
 // Copyright The OpenTelemetry Authors
 // SPDX-License-Identifier: Apache-2.0
 
+use core::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
 use uuid::Uuid;
 
-/// returns a tracking ID
-pub fn create_tracking_id() -> String {
-    Uuid::new_v4().to_string()
+const LIGHT_MAX_G: u64 = 1_000;
+const MEDIUM_MAX_G: u64 = 10_000;
+
+/// Weight class of a shipment, encoded as the first character of its
+/// tracking ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeightClass {
+    /// Under 1kg.
+    Light,
+    /// 1kg to 10kg.
+    Medium,
+    /// Over 10kg.
+    Heavy,
+}
+
+impl WeightClass {
+    /// Orders without a weight are classed as `Medium`.
+    pub fn from_grams(grams: Option<u64>) -> Self {
+        match grams {
+            Some(grams) if grams < LIGHT_MAX_G => WeightClass::Light,
+            Some(grams) if grams > MEDIUM_MAX_G => WeightClass::Heavy,
+            _ => WeightClass::Medium,
+        }
+    }
+
+    pub fn code(&self) -> char {
+        match self {
+            WeightClass::Light => 'L',
+            WeightClass::Medium => 'M',
+            WeightClass::Heavy => 'H',
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        match code {
+            'L' => Some(WeightClass::Light),
+            'M' => Some(WeightClass::Medium),
+            'H' => Some(WeightClass::Heavy),
+            _ => None,
+        }
+    }
+}
+
+/// A random tracking ID prefixed with the shipment's weight class, e.g.
+/// `M-67e55044-10b1-426f-9247-bb680e5fe0c8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackingId {
+    pub weight_class: WeightClass,
+    id: Uuid,
+}
+
+impl TrackingId {
+    pub fn new(weight_class: WeightClass) -> Self {
+        TrackingId {
+            weight_class,
+            id: Uuid::new_v4(),
+        }
+    }
+}
+
+impl fmt::Display for TrackingId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.weight_class.code(), self.id)
+    }
+}
+
+impl FromStr for TrackingId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (code, id) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("tracking ID {s:?} has no weight class prefix"))?;
+        let mut chars = code.chars();
+        let weight_class = match (chars.next(), chars.next()) {
+            (Some(code), None) => WeightClass::from_code(code),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("unknown weight class {code:?}"))?;
+        Ok(TrackingId {
+            weight_class,
+            id: id.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weight_class_prefix() {
+        for (grams, prefix) in [
+            (Some(0), 'L'),
+            (Some(999), 'L'),
+            (Some(1_000), 'M'),
+            (Some(10_000), 'M'),
+            (None, 'M'),
+            (Some(10_001), 'H'),
+        ] {
+            let id = TrackingId::new(WeightClass::from_grams(grams)).to_string();
+            assert!(id.starts_with(&format!("{prefix}-")), "{grams:?}g: {id}");
+            assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+        }
+    }
+
+    #[test]
+    fn test_decode_is_inverse_of_encode() {
+        for class in [WeightClass::Light, WeightClass::Medium, WeightClass::Heavy] {
+            let id = TrackingId::new(class);
+            assert_eq!(id.to_string().parse::<TrackingId>().unwrap(), id);
+            assert_eq!(WeightClass::from_code(class.code()), Some(class));
+        }
+        assert!("X-67e55044-10b1-426f-9247-bb680e5fe0c8"
+            .parse::<TrackingId>()
+            .is_err());
+        assert!("67e55044-10b1-426f-9247-bb680e5fe0c8"
+            .parse::<TrackingId>()
+            .is_err());
+    }
 }
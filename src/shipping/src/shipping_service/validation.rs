@@ -0,0 +1,328 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::LazyLock;
+
+use actix_web::{
+    dev::Payload, error::InternalError, http::StatusCode, web, Error, FromRequest, HttpRequest,
+    HttpResponse,
+};
+use jsonschema::Validator;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::api_version::ApiVersion;
+use super::zones::country_code;
+use super::{GetQuoteRequest, GetQuoteRequestV1, ShipOrderRequest, ShipOrderRequestV1};
+use crate::correlation_id::CorrelationId;
+use crate::error_response::ErrorResponse;
+
+fn compile(schema: &str) -> Validator {
+    let schema = serde_json::from_str(schema).expect("embedded schema is valid JSON");
+    jsonschema::validator_for(&schema).expect("embedded schema is a valid JSON schema")
+}
+
+static GET_QUOTE_SCHEMA: LazyLock<Validator> =
+    LazyLock::new(|| compile(include_str!("schemas/get_quote_request.json")));
+
+static SHIP_ORDER_SCHEMA: LazyLock<Validator> =
+    LazyLock::new(|| compile(include_str!("schemas/ship_order_request.json")));
+
+/// A request body with a JSON schema for the constraints its type can't
+/// express.
+pub trait RequestSchema: DeserializeOwned {
+    fn validator() -> &'static Validator;
+
+    /// Rewrites a body sent for `version` into the latest shape, which the
+    /// schema then checks. Fields older versions didn't have get their
+    /// defaults, even if the body has them.
+    fn upgrade(_version: ApiVersion, body: Value) -> serde_json::Result<Value> {
+        Ok(body)
+    }
+}
+
+/// Deserializes `body` as `V` and reserializes it as the `T` it converts to.
+fn upgrade_from<V, T>(body: Value) -> serde_json::Result<Value>
+where
+    V: DeserializeOwned,
+    T: From<V> + Serialize,
+{
+    serde_json::to_value(T::from(V::deserialize(body)?))
+}
+
+impl RequestSchema for GetQuoteRequest {
+    fn validator() -> &'static Validator {
+        &GET_QUOTE_SCHEMA
+    }
+
+    fn upgrade(version: ApiVersion, body: Value) -> serde_json::Result<Value> {
+        match version {
+            ApiVersion::V1 => upgrade_from::<GetQuoteRequestV1, GetQuoteRequest>(body),
+            ApiVersion::V2 => Ok(body),
+        }
+    }
+}
+
+impl RequestSchema for ShipOrderRequest {
+    fn validator() -> &'static Validator {
+        &SHIP_ORDER_SCHEMA
+    }
+
+    fn upgrade(version: ApiVersion, body: Value) -> serde_json::Result<Value> {
+        match version {
+            ApiVersion::V1 => upgrade_from::<ShipOrderRequestV1, ShipOrderRequest>(body),
+            ApiVersion::V2 => Ok(body),
+        }
+    }
+}
+
+/// One schema violation, at a JSON pointer into the body.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+/// Countries are checked as `country_code` makes them: trimmed, uppercased,
+/// and mapped to their code if the zones module knows the name.
+fn normalize_country(body: &mut Value) {
+    if let Some(country) = body.pointer_mut("/address/country") {
+        if let Some(name) = country.as_str() {
+            *country = Value::String(country_code(name));
+        }
+    }
+}
+
+/// Every violation of `T`'s schema in `body`.
+pub fn schema_violations<T: RequestSchema>(body: &Value) -> Vec<SchemaViolation> {
+    let mut body = body.clone();
+    normalize_country(&mut body);
+    T::validator()
+        .iter_errors(&body)
+        .map(|err| SchemaViolation {
+            path: err.instance_path.to_string(),
+            message: err.to_string(),
+        })
+        .collect()
+}
+
+/// Like `web::Json<T>`, but also checks the body against `T`'s schema, after
+/// upgrading it from the request's `X-API-Version`. Unsupported versions and
+/// bodies that aren't a `T` get a 400; bodies that are but break the schema
+/// get a 422 listing every violation.
+#[derive(Debug)]
+pub struct Validated<T>(pub T);
+
+impl<T> Validated<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Validated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: RequestSchema + 'static> FromRequest for Validated<T> {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let version = ApiVersion::of(req);
+        let request_id = CorrelationId::of(req);
+        let body = web::Json::<Value>::from_request(req, payload);
+        Box::pin(async move {
+            let version = version?;
+            let deserialize_error = |err: serde_json::Error| -> Error {
+                let response = ErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_request",
+                    format!("Json deserialize error: {err}"),
+                    request_id,
+                )
+                .into_response();
+                InternalError::from_response(err, response).into()
+            };
+            let body = T::upgrade(version, body.await?.into_inner()).map_err(deserialize_error)?;
+            let parsed = T::deserialize(&body).map_err(deserialize_error)?;
+            let violations = schema_violations::<T>(&body);
+            if violations.is_empty() {
+                Ok(Validated(parsed))
+            } else {
+                let response = HttpResponse::UnprocessableEntity()
+                    .json(serde_json::json!({ "errors": violations }));
+                Err(InternalError::from_response("schema validation failed", response).into())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{post, test, App, Responder};
+    use serde_json::json;
+
+    use super::*;
+    use crate::error_response::json_error;
+    use crate::shipping_service::API_VERSION_HEADER;
+
+    #[post("/")]
+    async fn echo_quantity(req: Validated<GetQuoteRequest>) -> impl Responder {
+        HttpResponse::Ok().json(req.items.iter().map(|i| i.quantity).collect::<Vec<_>>())
+    }
+
+    #[post("/v")]
+    async fn echo_request(req: Validated<GetQuoteRequest>) -> impl Responder {
+        HttpResponse::Ok().json(req.into_inner())
+    }
+
+    async fn call(body: &str) -> (StatusCode, Value) {
+        call_as("/", None, body).await
+    }
+
+    async fn call_as(uri: &str, version: Option<&str>, body: &str) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().error_handler(json_error))
+                .service(echo_quantity)
+                .service(echo_request),
+        )
+        .await;
+        let mut req = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body.to_string());
+        if let Some(version) = version {
+            req = req.insert_header((API_VERSION_HEADER, version));
+        }
+        let req = req.to_request();
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let body = test::read_body(resp).await;
+        (
+            status,
+            serde_json::from_slice(&body)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned())),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_schema_valid_body() {
+        let (status, body) = call(
+            r#"{"items": [{"quantity": 2}], "address": {"zip_code": "94043", "country": "United States"}}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([2]));
+
+        for country in ["de", "Spain", " côte d'ivoire ", "UNITED KINGDOM"] {
+            let body = json!({"items": [], "address": {"zip_code": "10115", "country": country}});
+            let (status, body) = call(&body.to_string()).await;
+            assert_eq!(status, StatusCode::OK, "{country}: {body}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_schema_violations_are_listed() {
+        let (status, body) = call(
+            r#"{"items": [{"quantity": 1}, {"quantity": 0}], "address": {"zip_code": "28001", "country": "E5"}}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let mut paths: Vec<_> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|err| err["path"].as_str().unwrap())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["/address/country", "/items/1/quantity"]);
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_body_is_bad_request() {
+        let (status, body) = call(r#"{"items": [{"quantity": 1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_json");
+        assert!(!body["request_id"].as_str().unwrap().is_empty());
+
+        let (status, body) = call(r#"{"items": "none"}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_request");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("Json deserialize error"));
+    }
+
+    #[actix_web::test]
+    async fn test_v1_request_gets_v2_defaults() {
+        let body = r#"{"items": [{"quantity": 2, "weight_g": 900}], "address": {"zip_code": "10115", "country": "DE"}, "delivery_speed": "overnight"}"#;
+        let (status, v1) = call_as("/v", Some("2023-10"), body).await;
+        assert_eq!(status, StatusCode::OK);
+        // V1 had no dimensions or delivery speed, so they are ignored
+        assert_eq!(
+            v1,
+            json!({
+                "items": [{"quantity": 2, "length_cm": null, "width_cm": null, "height_cm": null, "weight_g": null}],
+                "address": {"zip_code": "10115", "country": "DE"},
+                "delivery_speed": null,
+            })
+        );
+
+        for version in [Some("2024-01"), None] {
+            let (status, v2) = call_as("/v", version, body).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(v2["items"][0]["weight_g"], 900);
+            assert_eq!(v2["delivery_speed"], "overnight");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_v1_request_is_still_schema_checked() {
+        let (status, body) = call_as("/", Some("2023-10"), r#"{"items": [{"quantity": 0}]}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["path"], "/items/0/quantity");
+    }
+
+    #[actix_web::test]
+    async fn test_unsupported_api_version_is_bad_request() {
+        let (status, body) = call_as("/", Some("2025-06"), r#"{"items": []}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "unsupported_api_version");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("2023-10, 2024-01"));
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_v1_upgrade() {
+        let body = ShipOrderRequest::upgrade(
+            ApiVersion::V1,
+            json!({"total_weight_g": 1200, "delivery_speed": "express"}),
+        )
+        .unwrap();
+        let order: ShipOrderRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(order.total_weight_g, Some(1200));
+        assert_eq!(order.delivery_speed, None);
+    }
+
+    #[actix_web::test]
+    async fn test_ship_order_schema() {
+        let violations = schema_violations::<ShipOrderRequest>(&json!({
+            "total_weight_g": 0,
+            "items": [{"product_id": "OLJCESPC7Z"}],
+            "address": {"country": "CA"},
+        }));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "/total_weight_g");
+    }
+}
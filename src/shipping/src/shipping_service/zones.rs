@@ -0,0 +1,122 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+
+/// Destination region of a shipment, relative to the US warehouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShippingZone {
+    Domestic,
+    NorthAmerica,
+    Europe,
+    Asia,
+    Rest,
+}
+
+const NORTH_AMERICA: &[&str] = &["CA", "MX"];
+
+const EUROPE: &[&str] = &[
+    "AD", "AL", "AT", "BA", "BE", "BG", "BY", "CH", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR",
+    "GB", "GR", "HR", "HU", "IE", "IS", "IT", "LI", "LT", "LU", "LV", "MC", "MD", "ME", "MK", "MT",
+    "NL", "NO", "PL", "PT", "RO", "RS", "SE", "SI", "SK", "SM", "UA", "VA",
+];
+
+const ASIA: &[&str] = &[
+    "AE", "BD", "BN", "CN", "HK", "ID", "IL", "IN", "JP", "KH", "KR", "KZ", "LK", "MN", "MO", "MY",
+    "NP", "PH", "PK", "QA", "SA", "SG", "TH", "TR", "TW", "UZ", "VN",
+];
+
+/// The frontend sends full country names, other clients ISO 3166-1 alpha-2
+/// codes, so both are accepted for the countries we ship to most.
+pub fn country_code(country: &str) -> String {
+    let country = country.trim().to_ascii_uppercase();
+    let code = match country.as_str() {
+        "UNITED STATES" | "UNITED STATES OF AMERICA" | "USA" => "US",
+        "CANADA" => "CA",
+        "MEXICO" => "MX",
+        "UNITED KINGDOM" => "GB",
+        "GERMANY" => "DE",
+        "FRANCE" => "FR",
+        "JAPAN" => "JP",
+        "CHINA" => "CN",
+        "INDIA" => "IN",
+        _ => return country,
+    };
+    code.into()
+}
+
+pub fn zone_from_country(country: &str) -> ShippingZone {
+    let code = country_code(country);
+    let code = code.as_str();
+
+    if code == "US" {
+        ShippingZone::Domestic
+    } else if NORTH_AMERICA.contains(&code) {
+        ShippingZone::NorthAmerica
+    } else if EUROPE.contains(&code) {
+        ShippingZone::Europe
+    } else if ASIA.contains(&code) {
+        ShippingZone::Asia
+    } else {
+        ShippingZone::Rest
+    }
+}
+
+impl ShippingZone {
+    /// Multiplier applied on top of the base quote.
+    pub fn surcharge(&self) -> f64 {
+        match self {
+            ShippingZone::Domestic => 1.0,
+            ShippingZone::NorthAmerica => 1.2,
+            ShippingZone::Europe => 1.5,
+            ShippingZone::Asia => 1.75,
+            ShippingZone::Rest => 2.0,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShippingZone::Domestic => "domestic",
+            ShippingZone::NorthAmerica => "north_america",
+            ShippingZone::Europe => "europe",
+            ShippingZone::Asia => "asia",
+            ShippingZone::Rest => "rest",
+        }
+    }
+}
+
+impl fmt::Display for ShippingZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_from_country() {
+        assert_eq!(zone_from_country("US"), ShippingZone::Domestic);
+        assert_eq!(zone_from_country("United States"), ShippingZone::Domestic);
+        assert_eq!(zone_from_country("ca"), ShippingZone::NorthAmerica);
+        assert_eq!(zone_from_country("Mexico"), ShippingZone::NorthAmerica);
+        assert_eq!(zone_from_country("DE"), ShippingZone::Europe);
+        assert_eq!(zone_from_country(" United Kingdom "), ShippingZone::Europe);
+        assert_eq!(zone_from_country("JP"), ShippingZone::Asia);
+        assert_eq!(zone_from_country("India"), ShippingZone::Asia);
+        assert_eq!(zone_from_country("BR"), ShippingZone::Rest);
+    }
+
+    #[test]
+    fn test_unknown_country_falls_back_to_rest() {
+        assert_eq!(zone_from_country(""), ShippingZone::Rest);
+        assert_eq!(zone_from_country("Atlantis"), ShippingZone::Rest);
+    }
+
+    #[test]
+    fn test_surcharge() {
+        assert_eq!(ShippingZone::Domestic.surcharge(), 1.0);
+        assert!(ShippingZone::Rest.surcharge() > ShippingZone::Europe.surcharge());
+    }
+}
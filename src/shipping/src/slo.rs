@@ -0,0 +1,389 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error,
+};
+use opentelemetry::metrics::Meter;
+use tracing::{info, warn};
+
+use crate::metrics::{checked_instrument_name, KUBERNETES_LABELS};
+
+pub const DEFAULT_SLO_TARGET: f64 = 0.999;
+/// Spends 2% of a 30-day error budget in an hour, the usual paging
+/// threshold for a 1h/5m burn rate alert.
+pub const DEFAULT_SLO_CRITICAL_BURN_RATE: f64 = 14.4;
+
+const BUCKET: Duration = Duration::from_secs(10);
+/// Requests each window needs before its burn rate can raise an alert, so
+/// a single early error doesn't page.
+const MIN_WINDOW_REQUESTS: u64 = 20;
+
+/// A rolling window the burn rate is computed over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SloWindow {
+    FiveMinutes,
+    OneHour,
+}
+
+impl SloWindow {
+    const ALL: [SloWindow; 2] = [SloWindow::FiveMinutes, SloWindow::OneHour];
+
+    fn duration(self) -> Duration {
+        match self {
+            SloWindow::FiveMinutes => Duration::from_secs(5 * 60),
+            SloWindow::OneHour => Duration::from_secs(60 * 60),
+        }
+    }
+
+    fn buckets(self) -> u64 {
+        self.duration().as_secs() / BUCKET.as_secs()
+    }
+
+    fn gauge_name(self) -> &'static str {
+        match self {
+            SloWindow::FiveMinutes => "slo_burn_rate_5m",
+            SloWindow::OneHour => "slo_burn_rate_1h",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    index: u64,
+    requests: u64,
+    errors: u64,
+}
+
+/// How fast `/get-quote` and `/ship-order` spend their error budget: the
+/// ratio of 5xx responses over a window divided by the ratio the SLO
+/// allows, `1 - target`. A burn rate of 1 exhausts the budget exactly at
+/// the end of the SLO period.
+#[derive(Debug)]
+pub struct SloTracker {
+    target: f64,
+    critical_burn_rate: f64,
+    started_at: Instant,
+    /// 10 second buckets covering the longest window, oldest first.
+    buckets: Mutex<VecDeque<Bucket>>,
+    /// Whether both windows burn above the critical rate, so each crossing
+    /// is logged once.
+    critical: AtomicBool,
+}
+
+impl SloTracker {
+    pub fn new(target: f64, critical_burn_rate: f64) -> Self {
+        SloTracker {
+            target,
+            critical_burn_rate,
+            started_at: Instant::now(),
+            buckets: Mutex::new(VecDeque::new()),
+            critical: Default::default(),
+        }
+    }
+
+    fn bucket_index(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started_at).as_secs() / BUCKET.as_secs()
+    }
+
+    pub fn record(&self, error: bool) {
+        self.record_at(Instant::now(), error);
+    }
+
+    fn record_at(&self, now: Instant, error: bool) {
+        let index = self.bucket_index(now);
+        {
+            let mut buckets = self.buckets.lock().unwrap();
+            match buckets.back_mut() {
+                Some(bucket) if bucket.index == index => {
+                    bucket.requests += 1;
+                    bucket.errors += error as u64;
+                }
+                _ => buckets.push_back(Bucket {
+                    index,
+                    requests: 1,
+                    errors: error as u64,
+                }),
+            }
+            let oldest = index.saturating_sub(SloWindow::OneHour.buckets() - 1);
+            while buckets.front().is_some_and(|bucket| bucket.index < oldest) {
+                buckets.pop_front();
+            }
+        }
+        self.check_burn_rates(now);
+    }
+
+    /// 0 when there were no requests in the window.
+    pub fn burn_rate(&self, window: SloWindow) -> f64 {
+        self.burn_rate_at(Instant::now(), window)
+    }
+
+    fn burn_rate_at(&self, now: Instant, window: SloWindow) -> f64 {
+        let (requests, errors) = self.counts_at(now, window);
+        if requests == 0 {
+            return 0.0;
+        }
+        burn_rate(errors as f64 / requests as f64, self.target)
+    }
+
+    /// Requests and errors recorded in `window` as of `now`.
+    fn counts_at(&self, now: Instant, window: SloWindow) -> (u64, u64) {
+        let oldest = self.bucket_index(now).saturating_sub(window.buckets() - 1);
+        self.buckets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|bucket| bucket.index >= oldest)
+            .fold((0, 0), |(requests, errors), bucket| {
+                (requests + bucket.requests, errors + bucket.errors)
+            })
+    }
+
+    /// Critical once both windows have `MIN_WINDOW_REQUESTS` and burn above
+    /// the critical rate: the 1h window shows the budget is really going,
+    /// the 5m one that it still is.
+    fn check_burn_rates(&self, now: Instant) {
+        let [short, long] = SloWindow::ALL.map(|window| {
+            let (requests, _) = self.counts_at(now, window);
+            (requests, self.burn_rate_at(now, window))
+        });
+        let is_critical = [short, long].iter().all(|(requests, burn_rate)| {
+            *requests >= MIN_WINDOW_REQUESTS && *burn_rate > self.critical_burn_rate
+        });
+        if self.critical.swap(is_critical, Ordering::Relaxed) == is_critical {
+            return;
+        }
+        if is_critical {
+            warn!(
+                name = "SloBurnRateCritical",
+                slo.burn_rate_5m = short.1,
+                slo.burn_rate_1h = long.1,
+                slo.critical_burn_rate = self.critical_burn_rate,
+                slo.target = self.target,
+                message = "Error budget is burning faster than the critical rate"
+            );
+        } else {
+            info!(
+                name = "SloBurnRateRecovered",
+                slo.burn_rate_5m = short.1,
+                slo.burn_rate_1h = long.1,
+                message = "Error budget burn rate is back under the critical rate"
+            );
+        }
+    }
+
+    pub fn register_burn_rate_gauges(self: &Arc<Self>, meter: &Meter) {
+        for window in SloWindow::ALL {
+            let tracker = self.clone();
+            meter
+                .f64_observable_gauge(checked_instrument_name(window.gauge_name()))
+                .with_description(
+                    "Error budget burn rate of the shipping service's availability SLO",
+                )
+                .with_callback(move |observer| {
+                    observer.observe(
+                        tracker.burn_rate(window),
+                        &KUBERNETES_LABELS.as_key_values(),
+                    )
+                })
+                .build();
+        }
+    }
+}
+
+/// `error_ratio / (1 - slo_target)`.
+pub fn burn_rate(error_ratio: f64, slo_target: f64) -> f64 {
+    error_ratio / (1.0 - slo_target)
+}
+
+/// Records `/get-quote` and `/ship-order` responses in the app's
+/// `SloTracker`, counting 5xx as errors.
+pub async fn track_slo(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let tracker = req.app_data::<web::Data<SloTracker>>().cloned();
+    let tracked = matches!(req.path(), "/get-quote" | "/ship-order");
+    let res = next.call(req).await?;
+    if let Some(tracker) = tracker.filter(|_| tracked) {
+        tracker.record(res.status().is_server_error());
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, App, HttpResponse};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use tracing::Level;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::test_utils::{test_meter_provider, CaptureLayer};
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_burn_rate_formula() {
+        assert_close(burn_rate(0.001, 0.999), 1.0);
+        assert_close(burn_rate(0.0144, 0.999), 14.4);
+        assert_close(burn_rate(0.05, 0.99), 5.0);
+        assert_close(burn_rate(0.0, 0.999), 0.0);
+    }
+
+    #[test]
+    fn test_burn_rate_from_synthetic_errors() {
+        let tracker = SloTracker::new(0.99, DEFAULT_SLO_CRITICAL_BURN_RATE);
+        let start = tracker.started_at;
+        assert_eq!(tracker.burn_rate_at(start, SloWindow::OneHour), 0.0);
+
+        // 2 errors in 100 requests is twice the 1% allowance
+        for i in 0..100 {
+            tracker.record_at(start, i % 50 == 0);
+        }
+        assert_close(tracker.burn_rate_at(start, SloWindow::FiveMinutes), 2.0);
+        assert_close(tracker.burn_rate_at(start, SloWindow::OneHour), 2.0);
+
+        // 10 minutes later, 10 errors in 100 requests: only the hour still
+        // sees the first ones
+        let later = start + Duration::from_secs(10 * 60);
+        for i in 0..100 {
+            tracker.record_at(later, i % 10 == 0);
+        }
+        assert_close(tracker.burn_rate_at(later, SloWindow::FiveMinutes), 10.0);
+        assert_close(tracker.burn_rate_at(later, SloWindow::OneHour), 6.0);
+
+        // Both windows have moved past everything
+        let much_later = start + Duration::from_secs(2 * 60 * 60);
+        assert_eq!(
+            tracker.burn_rate_at(much_later, SloWindow::FiveMinutes),
+            0.0
+        );
+        assert_eq!(tracker.burn_rate_at(much_later, SloWindow::OneHour), 0.0);
+        tracker.record_at(much_later, false);
+        assert_eq!(tracker.buckets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_critical_burn_rate_warns_once() {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let tracker = SloTracker::new(0.999, 14.4);
+            let start = tracker.started_at;
+            // 1 error in 50 requests burns at 20
+            for i in 0..50 {
+                tracker.record_at(start, i == 0);
+            }
+            // Back under 14.4 once the window has at least 70 requests
+            for _ in 0..50 {
+                tracker.record_at(start, false);
+            }
+        });
+
+        let events = capture.events.lock().unwrap();
+        let names = |level| {
+            events
+                .iter()
+                .filter(move |(l, _)| *l == level)
+                .filter_map(|(_, fields)| fields.get("name").cloned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(Level::WARN), ["SloBurnRateCritical"]);
+        assert_eq!(names(Level::INFO), ["SloBurnRateRecovered"]);
+    }
+
+    #[test]
+    fn test_critical_needs_both_windows_and_enough_requests() {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            // A lone first error burns at 1000, but in too few requests
+            let tracker = SloTracker::new(0.999, 14.4);
+            let start = tracker.started_at;
+            tracker.record_at(start, true);
+            for _ in 0..10 {
+                tracker.record_at(start, false);
+            }
+
+            // Lots of good requests, then a burst of errors 10 minutes
+            // later: the 5m window burns at 500, the hour at under 14.4
+            let tracker = SloTracker::new(0.999, 14.4);
+            let start = tracker.started_at;
+            for _ in 0..10_000 {
+                tracker.record_at(start, false);
+            }
+            let later = start + Duration::from_secs(10 * 60);
+            for i in 0..20 {
+                tracker.record_at(later, i < 10);
+            }
+            assert!(tracker.burn_rate_at(later, SloWindow::FiveMinutes) > 14.4);
+            assert!(tracker.burn_rate_at(later, SloWindow::OneHour) < 14.4);
+        });
+
+        assert!(capture.events.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_track_slo_counts_server_errors() {
+        use actix_web::test;
+
+        let tracker = web::Data::new(SloTracker::new(0.9, DEFAULT_SLO_CRITICAL_BURN_RATE));
+        let app = test::init_service(
+            App::new()
+                .app_data(tracker.clone())
+                .wrap(from_fn(track_slo))
+                .route(
+                    "/get-quote",
+                    web::post().to(HttpResponse::ServiceUnavailable),
+                )
+                .route("/ship-order", web::post().to(HttpResponse::BadRequest))
+                .route("/version", web::get().to(HttpResponse::InternalServerError)),
+        )
+        .await;
+        for (method, uri) in [
+            (test::TestRequest::post(), "/get-quote"),
+            (test::TestRequest::post(), "/ship-order"),
+            (test::TestRequest::get(), "/version"),
+        ] {
+            test::call_service(&app, method.uri(uri).to_request()).await;
+        }
+        // One 5xx in two tracked requests, against a 10% allowance
+        assert_close(tracker.burn_rate(SloWindow::FiveMinutes), 5.0);
+    }
+
+    #[test]
+    fn test_burn_rate_gauges() {
+        let (provider, exporter) = test_meter_provider();
+        let tracker = Arc::new(SloTracker::new(0.99, DEFAULT_SLO_CRITICAL_BURN_RATE));
+        tracker.register_burn_rate_gauges(&provider.meter("test"));
+        tracker.record(true);
+        tracker.record(false);
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        for name in ["slo_burn_rate_5m", "slo_burn_rate_1h"] {
+            let metric = metrics
+                .iter()
+                .flat_map(|rm| rm.scope_metrics())
+                .flat_map(|sm| sm.metrics())
+                .find(|m| m.name() == name)
+                .unwrap();
+            let AggregatedMetrics::F64(MetricData::Gauge(gauge)) = metric.data() else {
+                panic!("expected an f64 gauge");
+            };
+            assert_close(gauge.data_points().next().unwrap().value(), 50.0);
+        }
+    }
+}
@@ -0,0 +1,494 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::env;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+use opentelemetry::{trace::Span as _, Context, KeyValue, Value};
+use opentelemetry_otlp::{WithExportConfig, WithTonicConfig};
+use opentelemetry_sdk::{
+    error::{OTelSdkError, OTelSdkResult},
+    trace::{BatchSpanProcessor, Span, SpanData, SpanProcessor},
+    Resource,
+};
+use tracing::{error, warn};
+
+use crate::baggage::dedup_attributes;
+use crate::telemetry_conf::otlp_metadata;
+
+/// The message a panic was raised with.
+pub fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Fans every span out to several processors, so spans can be exported to
+/// more than one backend. A panicking processor is logged and skipped rather
+/// than taking the others down with it. Repeated attribute keys are reduced
+/// to their last value first.
+#[derive(Debug)]
+pub struct MultiSpanProcessor {
+    processors: Vec<Box<dyn SpanProcessor>>,
+}
+
+impl MultiSpanProcessor {
+    pub fn new(processors: Vec<Box<dyn SpanProcessor>>) -> Self {
+        MultiSpanProcessor { processors }
+    }
+
+    fn for_each(
+        &self,
+        operation: &str,
+        f: impl Fn(&dyn SpanProcessor) -> OTelSdkResult,
+    ) -> OTelSdkResult {
+        let mut result = Ok(());
+        for processor in &self.processors {
+            let outcome =
+                catch_unwind(AssertUnwindSafe(|| f(processor.as_ref()))).unwrap_or_else(|panic| {
+                    let message = panic_message(panic.as_ref()).to_string();
+                    error!(
+                        name = "SpanProcessorPanicked",
+                        operation = operation,
+                        error = message.as_str(),
+                        message = "Span processor panicked"
+                    );
+                    Err(OTelSdkError::InternalFailure(message))
+                });
+            if outcome.is_err() {
+                result = outcome;
+            }
+        }
+        result
+    }
+}
+
+impl SpanProcessor for MultiSpanProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        for processor in &self.processors {
+            if let Err(panic) = catch_unwind(AssertUnwindSafe(|| processor.on_start(span, cx))) {
+                error!(
+                    name = "SpanProcessorPanicked",
+                    operation = "on_start",
+                    error = panic_message(panic.as_ref()),
+                    message = "Span processor panicked"
+                );
+            }
+        }
+    }
+
+    fn on_end(&self, mut span: SpanData) {
+        dedup_attributes(&mut span.attributes);
+        let _ = self.for_each("on_end", |processor| {
+            processor.on_end(span.clone());
+            Ok(())
+        });
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.for_each("force_flush", |processor| processor.force_flush())
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.for_each("shutdown", |processor| {
+            processor.shutdown_with_timeout(timeout)
+        })
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        for processor in &mut self.processors {
+            processor.set_resource(resource);
+        }
+    }
+}
+
+pub const DEFAULT_ATTRIBUTE_VALUE_LENGTH_LIMIT: usize = 1024;
+pub const TRUNCATION_MARKER: &str = "...<truncated>";
+
+/// Cuts string attribute values on spans and their events, e.g.
+/// `exception.stacktrace`, down to `limit` characters followed by
+/// `TRUNCATION_MARKER`, before passing the span on to `inner`.
+#[derive(Debug)]
+pub struct TruncatingSpanProcessor {
+    inner: Box<dyn SpanProcessor>,
+    limit: usize,
+}
+
+impl TruncatingSpanProcessor {
+    pub fn new(inner: Box<dyn SpanProcessor>, limit: usize) -> Self {
+        TruncatingSpanProcessor { inner, limit }
+    }
+
+    /// Reads the limit from `OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT`, defaulting
+    /// to 1024.
+    pub fn from_env(inner: Box<dyn SpanProcessor>) -> Self {
+        let limit = env::var("OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|limit| *limit > 0)
+            .unwrap_or(DEFAULT_ATTRIBUTE_VALUE_LENGTH_LIMIT);
+        Self::new(inner, limit)
+    }
+
+    fn truncate(&self, attributes: &mut [KeyValue]) {
+        for attribute in attributes {
+            let Value::String(value) = &attribute.value else {
+                continue;
+            };
+            if let Some((end, _)) = value.as_str().char_indices().nth(self.limit) {
+                let truncated = format!("{}{TRUNCATION_MARKER}", &value.as_str()[..end]);
+                attribute.value = Value::String(truncated.into());
+            }
+        }
+    }
+}
+
+impl SpanProcessor for TruncatingSpanProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, mut span: SpanData) {
+        self.truncate(&mut span.attributes);
+        for event in span.events.events.iter_mut() {
+            self.truncate(&mut event.attributes);
+        }
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+/// Adds attributes an organization requires on every span, e.g.
+/// `cost_center` or `team` for chargeback, when the span starts, before
+/// passing it on to `inner`. Attributes the span sets itself later win.
+#[derive(Debug)]
+pub struct MandatoryAttributesProcessor {
+    inner: Box<dyn SpanProcessor>,
+    attributes: Vec<KeyValue>,
+}
+
+impl MandatoryAttributesProcessor {
+    pub fn new(inner: Box<dyn SpanProcessor>, attributes: Vec<KeyValue>) -> Self {
+        MandatoryAttributesProcessor { inner, attributes }
+    }
+
+    /// Reads them from `OTEL_MANDATORY_SPAN_ATTRIBUTES`, a JSON object of
+    /// strings, e.g. `{"team": "shipping"}`. Invalid JSON is logged and
+    /// ignored.
+    pub fn from_env(inner: Box<dyn SpanProcessor>) -> Self {
+        let attributes = env::var("OTEL_MANDATORY_SPAN_ATTRIBUTES")
+            .map(|json| parse_mandatory_attributes(&json))
+            .unwrap_or_default();
+        Self::new(inner, attributes)
+    }
+}
+
+fn parse_mandatory_attributes(json: &str) -> Vec<KeyValue> {
+    match serde_json::from_str::<BTreeMap<String, String>>(json) {
+        Ok(attributes) => attributes
+            .into_iter()
+            .map(|(key, value)| KeyValue::new(key, value))
+            .collect(),
+        Err(err) => {
+            warn!(
+                name = "InvalidMandatorySpanAttributes",
+                error = %err,
+                message = "OTEL_MANDATORY_SPAN_ATTRIBUTES must be a JSON object of strings, ignoring it"
+            );
+            Vec::new()
+        }
+    }
+}
+
+impl SpanProcessor for MandatoryAttributesProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        span.set_attributes(self.attributes.iter().cloned());
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+/// Extra OTLP endpoints from the comma-separated
+/// `OTEL_MULTI_EXPORTER_ENDPOINTS`, exported to in addition to the default one.
+pub fn multi_exporter_endpoints(lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    lookup("OTEL_MULTI_EXPORTER_ENDPOINTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(feature = "zipkin-exporter")]
+const DEFAULT_ZIPKIN_ENDPOINT: &str = "http://localhost:9411/api/v2/spans";
+
+/// Batch processor exporting to `ZIPKIN_ENDPOINT`, or to the default Zipkin
+/// endpoint if it is set but empty. `None` when it isn't set at all.
+#[cfg(feature = "zipkin-exporter")]
+pub fn zipkin_processor(lookup: impl Fn(&str) -> Option<String>) -> Option<Box<dyn SpanProcessor>> {
+    let Some(endpoint) = lookup("ZIPKIN_ENDPOINT") else {
+        tracing::debug!(
+            name = "ZipkinExporterDisabled",
+            message = "ZIPKIN_ENDPOINT is not set, skipping the Zipkin exporter"
+        );
+        return None;
+    };
+    let endpoint = match endpoint.trim() {
+        "" => DEFAULT_ZIPKIN_ENDPOINT.to_string(),
+        endpoint => endpoint.to_string(),
+    };
+    let exporter = opentelemetry_zipkin::ZipkinExporter::builder()
+        .with_collector_endpoint(endpoint)
+        .build()
+        .expect("Failed to initialize Zipkin exporter");
+    Some(Box::new(BatchSpanProcessor::builder(exporter).build()))
+}
+
+/// Processors for every configured exporter besides the default OTLP one.
+pub fn extra_span_processors() -> Vec<Box<dyn SpanProcessor>> {
+    #[allow(unused_mut)]
+    let mut processors: Vec<Box<dyn SpanProcessor>> =
+        multi_exporter_endpoints(|var| env::var(var).ok())
+            .iter()
+            .map(|endpoint| batch_processor_for(endpoint))
+            .collect();
+    #[cfg(feature = "zipkin-exporter")]
+    processors.extend(zipkin_processor(|var| env::var(var).ok()));
+    processors
+}
+
+pub fn batch_processor_for(endpoint: &str) -> Box<dyn SpanProcessor> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .with_metadata(otlp_metadata("OTEL_EXPORTER_OTLP_TRACES_HEADERS"))
+        .build()
+        .expect("Failed to initialize tracing provider");
+    Box::new(BatchSpanProcessor::builder(exporter).build())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use opentelemetry::trace::{Span as _, Tracer, TracerProvider};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SimpleSpanProcessor};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct RecordingProcessor {
+        ended: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl SpanProcessor for RecordingProcessor {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.ended.lock().unwrap().push(span.name.into_owned());
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct PanickingProcessor;
+
+    impl SpanProcessor for PanickingProcessor {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {
+            panic!("on_start failed");
+        }
+
+        fn on_end(&self, _span: SpanData) {
+            panic!("on_end failed");
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            panic!("force_flush failed");
+        }
+
+        fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_on_end_reaches_all_processors_despite_panic() {
+        let first = RecordingProcessor::default();
+        let second = RecordingProcessor::default();
+        let (first_ended, second_ended) = (first.ended.clone(), second.ended.clone());
+
+        let multi = MultiSpanProcessor::new(vec![
+            Box::new(first),
+            Box::new(PanickingProcessor),
+            Box::new(second),
+        ]);
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(multi)
+            .build();
+
+        provider.tracer("test").start("quote").end();
+
+        assert_eq!(*first_ended.lock().unwrap(), vec!["quote"]);
+        assert_eq!(*second_ended.lock().unwrap(), vec!["quote"]);
+        assert!(provider.force_flush().is_err());
+    }
+
+    #[test]
+    fn test_truncates_long_string_attributes() {
+        let exporter = InMemorySpanExporter::default();
+        let truncating = TruncatingSpanProcessor::new(
+            Box::new(SimpleSpanProcessor::new(exporter.clone())),
+            DEFAULT_ATTRIBUTE_VALUE_LENGTH_LIMIT,
+        );
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(truncating)
+            .build();
+
+        let long = "é".repeat(10_000);
+        let mut span = provider.tracer("test").start("quote");
+        span.set_attribute(KeyValue::new("http.request.body", long.clone()));
+        span.set_attribute(KeyValue::new("short", "kept"));
+        span.set_attribute(KeyValue::new("count", 10_000_i64));
+        span.add_event(
+            "exception",
+            vec![KeyValue::new("exception.stacktrace", long.clone())],
+        );
+        span.end();
+
+        let expected = format!(
+            "{}{TRUNCATION_MARKER}",
+            &long[..long.char_indices().nth(1024).unwrap().0]
+        );
+        assert_eq!(expected.chars().count(), 1024 + TRUNCATION_MARKER.len());
+        let span = exporter.get_finished_spans().unwrap().pop().unwrap();
+        assert_eq!(
+            span.attributes,
+            [
+                KeyValue::new("http.request.body", expected.clone()),
+                KeyValue::new("short", "kept"),
+                KeyValue::new("count", 10_000_i64),
+            ]
+        );
+        assert_eq!(
+            span.events[0].attributes,
+            [KeyValue::new("exception.stacktrace", expected)]
+        );
+    }
+
+    #[test]
+    fn test_mandatory_attributes_on_every_span() {
+        let exporter = InMemorySpanExporter::default();
+        let mandatory = MandatoryAttributesProcessor::new(
+            Box::new(SimpleSpanProcessor::new(exporter.clone())),
+            parse_mandatory_attributes(r#"{"team": "shipping", "cost_center": "cc-42"}"#),
+        );
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(mandatory)
+            .build();
+
+        let tracer = provider.tracer("test");
+        tracer.in_span("get-quote", |_| {
+            tracer.in_span("quote.convert_currency", |_| {});
+        });
+        provider.tracer("other").start("ship-order").end();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 3);
+        for span in spans {
+            assert_eq!(
+                span.attributes,
+                [
+                    KeyValue::new("cost_center", "cc-42"),
+                    KeyValue::new("team", "shipping"),
+                ],
+                "{}",
+                span.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_mandatory_attributes_are_ignored() {
+        assert!(parse_mandatory_attributes(r#"{"team": 7}"#).is_empty());
+        assert!(parse_mandatory_attributes("team=shipping").is_empty());
+    }
+
+    #[test]
+    fn test_multi_exporter_endpoints() {
+        let endpoints = multi_exporter_endpoints(|var| {
+            (var == "OTEL_MULTI_EXPORTER_ENDPOINTS")
+                .then(|| " http://jaeger:4317, ,http://collector:4317".to_string())
+        });
+        assert_eq!(
+            endpoints,
+            vec!["http://jaeger:4317", "http://collector:4317"]
+        );
+        assert!(multi_exporter_endpoints(|_| None).is_empty());
+    }
+
+    #[cfg(feature = "zipkin-exporter")]
+    #[test]
+    fn test_zipkin_processor() {
+        use httpmock::prelude::*;
+
+        assert!(zipkin_processor(|_| None).is_none());
+
+        assert!(
+            zipkin_processor(|_| Some(String::new())).is_some(),
+            "empty falls back to the default"
+        );
+
+        let server = MockServer::start();
+        let spans = server.mock(|when, then| {
+            when.method(POST).path("/api/v2/spans");
+            then.status(202);
+        });
+        let processor = zipkin_processor(|_| Some(server.url("/api/v2/spans"))).unwrap();
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(MultiSpanProcessor::new(vec![processor]))
+            .build();
+
+        provider.tracer("test").start("quote").end();
+        provider.shutdown().unwrap();
+        spans.assert();
+    }
+}
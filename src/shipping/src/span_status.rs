@@ -0,0 +1,120 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    Error,
+};
+use opentelemetry::{
+    trace::{get_active_span, Status},
+    KeyValue,
+};
+use opentelemetry_semantic_conventions::attribute::{EXCEPTION_MESSAGE, EXCEPTION_TYPE};
+
+/// Span status for a server response, per the HTTP semantic conventions:
+/// `Error` for 5xx, `Unset` for 4xx since the server handled the client's
+/// mistake correctly, and `Ok` otherwise.
+pub fn status_for(code: StatusCode) -> Status {
+    if code.is_server_error() {
+        Status::error(code.canonical_reason().unwrap_or_default())
+    } else if code.is_client_error() {
+        Status::Unset
+    } else {
+        Status::Ok
+    }
+}
+
+/// Sets the request span's status from the response, and records 5xx
+/// responses as an `exception` event. Must be registered inside
+/// `RequestTracing` so the active span is the request span.
+pub async fn span_status(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let res = next.call(req).await?;
+    let code = res.status();
+    get_active_span(|span| {
+        if code.is_server_error() {
+            span.add_event(
+                "exception",
+                vec![
+                    KeyValue::new(EXCEPTION_TYPE, "HttpServerError"),
+                    KeyValue::new(EXCEPTION_MESSAGE, code.to_string()),
+                ],
+            );
+        }
+        match status_for(code) {
+            Status::Unset => {}
+            status => span.set_status(status),
+        }
+    });
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, test, web, App, HttpResponse};
+    use opentelemetry::{
+        global,
+        trace::{FutureExt, Span, TraceContextExt, Tracer},
+        Context,
+    };
+    use opentelemetry_sdk::trace::SpanData;
+
+    use super::*;
+    use crate::test_utils::global_span_exporter;
+
+    /// Calls a route answering `code` inside a request span, returning that
+    /// span once it has ended.
+    async fn request_span(code: StatusCode) -> SpanData {
+        let exporter = global_span_exporter();
+        let span = global::tracer("test").start("request");
+        let span_context = span.span_context().clone();
+        let cx = Context::current_with_span(span);
+
+        let app = test::init_service(App::new().wrap(from_fn(span_status)).route(
+            "/",
+            web::get().to(move || async move { HttpResponse::new(code) }),
+        ))
+        .await;
+        test::call_service(&app, test::TestRequest::get().to_request())
+            .with_context(cx.clone())
+            .await;
+        cx.span().end();
+
+        exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| span.span_context == span_context)
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_ok_status() {
+        let span = request_span(StatusCode::OK).await;
+        assert_eq!(span.status, Status::Ok);
+        assert!(span.events.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_server_error_status() {
+        let span = request_span(StatusCode::INTERNAL_SERVER_ERROR).await;
+        assert_eq!(span.status, Status::error("Internal Server Error"));
+        let event = span.events.iter().next().unwrap();
+        assert_eq!(event.name, "exception");
+        assert!(event.attributes.contains(&KeyValue::new(
+            EXCEPTION_MESSAGE,
+            "500 Internal Server Error"
+        )));
+    }
+
+    #[actix_web::test]
+    async fn test_client_error_leaves_status_unset() {
+        let span = request_span(StatusCode::NOT_FOUND).await;
+        assert_eq!(span.status, Status::Unset);
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    get,
+    middleware::Next,
+    web, Error, HttpResponse, Responder,
+};
+use serde_json::json;
+
+/// Registers `GET /stats` when `enabled`. It reports the `ServiceStats` app
+/// data that `track_stats` updates.
+pub fn configure(enabled: bool) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        if enabled {
+            cfg.service(get_stats);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RouteStats {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+impl RouteStats {
+    fn record(&self, error: bool, latency_micros: u64) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if error {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency_micros
+            .fetch_add(latency_micros, Ordering::Relaxed);
+    }
+}
+
+/// In-process request counts, for debugging a single pod without an OTel
+/// backend.
+#[derive(Debug)]
+pub struct ServiceStats {
+    started_at: Instant,
+    get_quote: RouteStats,
+    ship_order: RouteStats,
+}
+
+impl Default for ServiceStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServiceStats {
+    pub fn new() -> Self {
+        ServiceStats {
+            started_at: Instant::now(),
+            get_quote: RouteStats::default(),
+            ship_order: RouteStats::default(),
+        }
+    }
+
+    fn route(&self, path: &str) -> Option<&RouteStats> {
+        match path {
+            "/get-quote" => Some(&self.get_quote),
+            "/ship-order" => Some(&self.ship_order),
+            _ => None,
+        }
+    }
+
+    /// 4xx and 5xx responses count as errors.
+    pub fn record(&self, path: &str, status: u16, latency_micros: u64) {
+        if let Some(route) = self.route(path) {
+            route.record(status >= 400, latency_micros);
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let requests = |route: &RouteStats| route.requests.load(Ordering::Relaxed);
+        let errors = |route: &RouteStats| route.errors.load(Ordering::Relaxed);
+        let quotes = requests(&self.get_quote);
+        let avg_quote_latency_ms = if quotes == 0 {
+            0_f64
+        } else {
+            self.get_quote.latency_micros.load(Ordering::Relaxed) as f64 / quotes as f64 / 1000_f64
+        };
+        json!({
+            "uptime_seconds": self.started_at.elapsed().as_secs(),
+            "requests_total": {
+                "get_quote": quotes,
+                "ship_order": requests(&self.ship_order),
+            },
+            "errors_total": {
+                "get_quote": errors(&self.get_quote),
+                "ship_order": errors(&self.ship_order),
+            },
+            "avg_quote_latency_ms": avg_quote_latency_ms,
+        })
+    }
+}
+
+/// Counts `/get-quote` and `/ship-order` requests, their errors and
+/// latency in the app's `ServiceStats`.
+pub async fn track_stats(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let stats = req.app_data::<web::Data<ServiceStats>>().cloned();
+    let path = req.path().to_string();
+    let start = Instant::now();
+    let res = next.call(req).await?;
+    if let Some(stats) = stats {
+        stats.record(
+            &path,
+            res.status().as_u16(),
+            start.elapsed().as_micros() as u64,
+        );
+    }
+    Ok(res)
+}
+
+/// Uptime, request and error counts per route, and the mean quote latency.
+#[get("/stats")]
+pub async fn get_stats(stats: web::Data<ServiceStats>) -> impl Responder {
+    HttpResponse::Ok().json(stats.to_json())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, middleware::from_fn, test, App};
+
+    use super::*;
+    use crate::shipping_service::{
+        get_quote, ship_order, DeadLetterQueue, MockShippingBackend, Quote, ShipOrderDeadLetters,
+        ShippingBackend,
+    };
+
+    #[actix_web::test]
+    async fn test_stats_disabled() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ServiceStats::new()))
+                .configure(configure(false)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/stats").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_stats_reflect_requests() {
+        let backend: Arc<dyn ShippingBackend> = Arc::new(MockShippingBackend {
+            quote: Some(Quote {
+                dollars: 1,
+                cents: 0,
+            }),
+            ..Default::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ServiceStats::new()))
+                .app_data(web::Data::from(backend))
+                .app_data(web::Data::new(ShipOrderDeadLetters::new(
+                    DeadLetterQueue::new(10, 1),
+                )))
+                .wrap(from_fn(track_stats))
+                .service(get_quote)
+                .service(ship_order)
+                .configure(configure(true)),
+        )
+        .await;
+
+        let post = |uri: &str, body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(uri)
+                .set_json(body)
+                .to_request()
+        };
+        for _ in 0..3 {
+            let resp = test::call_service(
+                &app,
+                post("/get-quote", json!({"items": [{"quantity": 1}]})),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp = test::call_service(&app, post("/get-quote", json!({"items": "none"}))).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        // The mock has no tracking ID to hand out, so orders are dead-lettered
+        for _ in 0..2 {
+            let resp = test::call_service(&app, post("/ship-order", json!({}))).await;
+            assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        let req = test::TestRequest::get().uri("/stats").to_request();
+        let stats: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            stats["requests_total"],
+            json!({"get_quote": 4, "ship_order": 2})
+        );
+        assert_eq!(
+            stats["errors_total"],
+            json!({"get_quote": 1, "ship_order": 2})
+        );
+        assert!(stats["avg_quote_latency_ms"].as_f64().unwrap() > 0_f64);
+        assert!(stats["uptime_seconds"].as_u64().is_some());
+    }
+}
@@ -1,79 +1,944 @@
 // Copyright The OpenTelemetry Authors
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
-use opentelemetry::global;
+use std::collections::HashMap;
+use std::env;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use log::LevelFilter;
+use opentelemetry::{
+    global,
+    trace::{
+        SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState, TracerProvider as _,
+    },
+    InstrumentationScope, KeyValue,
+};
 use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
+use opentelemetry_otlp::{
+    ExporterBuildError, LogExporter, MetricExporter, Protocol, WithExportConfig, WithHttpConfig,
+    WithTonicConfig,
+};
+use tonic::{
+    metadata::{MetadataKey, MetadataMap, MetadataValue},
+    transport::Channel,
+};
+use tracing::{info, warn, Subscriber};
+use tracing_opentelemetry::OpenTelemetryLayer;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{filter::filter_fn, registry::LookupSpan, EnvFilter};
 
-use opentelemetry_resource_detectors::{OsResourceDetector, ProcessResourceDetector};
+use opentelemetry::propagation::{TextMapCompositePropagator, TextMapPropagator};
 use opentelemetry_sdk::{
-    propagation::TraceContextPropagator, resource::ResourceDetector, Resource,
+    error::OTelSdkResult,
+    logs::SdkLoggerProvider,
+    metrics::{PeriodicReader, SdkMeterProvider, Temporality},
+    propagation::{BaggagePropagator, TraceContextPropagator},
+    trace::{
+        BatchSpanProcessor, SdkTracer, SdkTracerProvider, SpanData, SpanExporter as _,
+        SpanProcessor,
+    },
+    Resource,
 };
+use uuid::Uuid;
 
-fn get_resource() -> Resource {
-    let detectors: Vec<Box<dyn ResourceDetector>> = vec![
-        Box::new(OsResourceDetector),
-        Box::new(ProcessResourceDetector),
-    ];
+use crate::correlation_id::is_correlation_span;
+use crate::delta_metrics::{delta_conversion_enabled, DeltaMetricExporter};
+use crate::export_queue::{QueueCountingExporter, QueueCountingProcessor, EXPORT_QUEUE};
+use crate::internal_context::InternalContextPropagator;
+use crate::log_bridge::OtelLogBridge;
+use crate::metrics::{ATTRIBUTE_WHITELIST, KUBERNETES_LABELS};
+use crate::metrics_utils::CARDINALITY_TRACKER;
+use crate::resource_detection::{DetectorFailure, ResourceDetectorChain};
+use crate::route_sampler::{HealthCheckSampler, RouteSampler};
+use crate::span_processors::{
+    extra_span_processors, MandatoryAttributesProcessor, MultiSpanProcessor,
+    TruncatingSpanProcessor,
+};
+use crate::test_trace_context::{test_trace_injection_enabled, TestTraceContextPropagator};
+use crate::version::{build_resource_attributes, OTEL_SDK_VERSION};
 
-    Resource::builder().with_detectors(&detectors).build()
+/// The SDK's defaults, then what `ResourceDetectorChain` detects, then the
+/// build's version attributes.
+async fn get_resource() -> (Resource, Vec<DetectorFailure>) {
+    let (detected, failures) = ResourceDetectorChain::default().detect().await;
+    let resource = Resource::builder()
+        .with_attributes(
+            detected
+                .iter()
+                .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+        )
+        .with_attributes(build_resource_attributes())
+        .build();
+    (resource, failures)
 }
 
-fn init_tracer_provider() {
-    global::set_text_map_propagator(TraceContextPropagator::new());
+/// Parses a `key1=value1,key2=value2` header list into gRPC metadata.
+/// Values are split on the first `=` only, so base64 padding survives.
+/// Malformed entries are logged and skipped.
+fn parse_otlp_headers(headers: &str, metadata: &mut MetadataMap) {
+    for entry in headers.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parsed = entry.split_once('=').and_then(|(key, value)| {
+            let key = MetadataKey::from_bytes(key.trim().to_ascii_lowercase().as_bytes()).ok()?;
+            let value = MetadataValue::try_from(value.trim()).ok()?;
+            Some((key, value))
+        });
+        match parsed {
+            Some((key, value)) => {
+                metadata.insert(key, value);
+            }
+            None => warn!(
+                name = "InvalidOtlpHeader",
+                header = entry.split('=').next().unwrap_or_default(),
+                message = "Ignoring malformed OTLP header"
+            ),
+        }
+    }
+}
 
-    let tracer_provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
-        .with_resource(get_resource())
-        .with_batch_exporter(
-            opentelemetry_otlp::SpanExporter::builder()
-                .with_tonic()
-                .build()
-                .expect("Failed to initialize tracing provider"),
-        )
-        .build();
+/// Metadata from `OTEL_EXPORTER_OTLP_HEADERS`, with the signal's own
+/// `signal_var` (e.g. `OTEL_EXPORTER_OTLP_TRACES_HEADERS`) taking precedence
+/// for keys set in both.
+pub fn otlp_metadata(signal_var: &str) -> MetadataMap {
+    otlp_metadata_from_lookup(signal_var, |var| env::var(var).ok())
+}
 
-    global::set_tracer_provider(tracer_provider);
+/// `otlp_metadata`, reading the variables through `lookup`.
+pub fn otlp_metadata_from_lookup(
+    signal_var: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> MetadataMap {
+    let mut metadata = MetadataMap::new();
+    for var in ["OTEL_EXPORTER_OTLP_HEADERS", signal_var] {
+        if let Some(headers) = lookup(var) {
+            parse_otlp_headers(&headers, &mut metadata);
+        }
+    }
+    metadata
 }
 
-fn init_meter_provider() -> opentelemetry_sdk::metrics::SdkMeterProvider {
-    let meter_provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
-        .with_resource(get_resource())
-        .with_periodic_exporter(
-            opentelemetry_otlp::MetricExporter::builder()
-                .with_temporality(opentelemetry_sdk::metrics::Temporality::Delta)
-                .with_tonic()
-                .build()
-                .expect("Failed to initialize metric exporter"),
-        )
+/// `otlp_metadata_from_lookup` as plain headers, for the HTTP exporters.
+fn otlp_headers_from_lookup(
+    signal_var: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> HashMap<String, String> {
+    otlp_metadata_from_lookup(signal_var, lookup)
+        .into_headers()
+        .iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Transport an OTLP exporter sends its signal over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OtlpProtocol {
+    Grpc,
+    HttpProtobuf,
+    /// Human-readable, for debugging.
+    HttpJson,
+}
+
+impl OtlpProtocol {
+    /// `grpc`, `http/protobuf` or `http/json`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "grpc" => Some(OtlpProtocol::Grpc),
+            "http/protobuf" => Some(OtlpProtocol::HttpProtobuf),
+            "http/json" => Some(OtlpProtocol::HttpJson),
+            _ => None,
+        }
+    }
+
+    /// Reads the signal's own `signal_var` (e.g.
+    /// `OTEL_EXPORTER_OTLP_TRACES_PROTOCOL`), then
+    /// `OTEL_EXPORTER_OTLP_PROTOCOL`, defaulting to gRPC.
+    pub fn from_env(signal_var: &str) -> Self {
+        Self::from_lookup(signal_var, |var| env::var(var).ok())
+    }
+
+    /// `from_env`, reading the variables through `lookup`.
+    pub fn from_lookup(signal_var: &str, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        for var in [signal_var, "OTEL_EXPORTER_OTLP_PROTOCOL"] {
+            let Some(name) = lookup(var) else {
+                continue;
+            };
+            match Self::parse(&name) {
+                Some(protocol) => return protocol,
+                None => warn!(
+                    name = "InvalidOtlpProtocol",
+                    var = var,
+                    protocol = name.as_str(),
+                    message = "Ignoring unknown OTLP protocol"
+                ),
+            }
+        }
+        OtlpProtocol::Grpc
+    }
+
+    fn exporter_type(&self) -> &'static str {
+        match self {
+            OtlpProtocol::Grpc => "otlp-grpc",
+            OtlpProtocol::HttpProtobuf => "otlp-http-protobuf",
+            OtlpProtocol::HttpJson => "otlp-http-json",
+        }
+    }
+
+    fn http_protocol(&self) -> Protocol {
+        match self {
+            OtlpProtocol::Grpc => Protocol::Grpc,
+            OtlpProtocol::HttpProtobuf => Protocol::HttpBinary,
+            OtlpProtocol::HttpJson => Protocol::HttpJson,
+        }
+    }
+}
+
+const TRACES_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_TRACES_PROTOCOL";
+const TRACES_HEADERS: &str = "OTEL_EXPORTER_OTLP_TRACES_HEADERS";
+const TRACES_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+const METRICS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL";
+const METRICS_HEADERS: &str = "OTEL_EXPORTER_OTLP_METRICS_HEADERS";
+const METRICS_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT";
+const LOGS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_LOGS_PROTOCOL";
+const LOGS_HEADERS: &str = "OTEL_EXPORTER_OTLP_LOGS_HEADERS";
+
+/// OTLP span exporter over the configured traces protocol.
+pub fn span_exporter() -> Result<opentelemetry_otlp::SpanExporter, ExporterBuildError> {
+    span_exporter_from_lookup(|var| env::var(var).ok())
+}
+
+/// `span_exporter`, reading the protocol, headers and traces endpoint
+/// through `lookup`.
+pub fn span_exporter_from_lookup(
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<opentelemetry_otlp::SpanExporter, ExporterBuildError> {
+    let builder = opentelemetry_otlp::SpanExporter::builder();
+    let endpoint = lookup(TRACES_ENDPOINT);
+    match OtlpProtocol::from_lookup(TRACES_PROTOCOL, &lookup) {
+        OtlpProtocol::Grpc => builder
+            .with_tonic()
+            .with_metadata(otlp_metadata_from_lookup(TRACES_HEADERS, &lookup))
+            .with_endpoint_if_set(endpoint)
+            .build(),
+        protocol => builder
+            .with_http()
+            .with_protocol(protocol.http_protocol())
+            .with_headers(otlp_headers_from_lookup(TRACES_HEADERS, &lookup))
+            .with_endpoint_if_set(endpoint)
+            .build(),
+    }
+}
+
+/// Sets the endpoint the lookup found, leaving the exporter's own
+/// resolution alone otherwise.
+trait WithEndpointIfSet: WithExportConfig + Sized {
+    fn with_endpoint_if_set(self, endpoint: Option<String>) -> Self {
+        match endpoint {
+            Some(endpoint) => self.with_endpoint(endpoint),
+            None => self,
+        }
+    }
+}
+
+impl<T: WithExportConfig> WithEndpointIfSet for T {}
+
+/// `delta`, `cumulative` or `lowmemory`, ignoring case.
+pub fn parse_temporality(name: &str) -> Option<Temporality> {
+    match name.trim() {
+        name if name.eq_ignore_ascii_case("delta") => Some(Temporality::Delta),
+        name if name.eq_ignore_ascii_case("cumulative") => Some(Temporality::Cumulative),
+        name if name.eq_ignore_ascii_case("lowmemory") => Some(Temporality::LowMemory),
+        _ => None,
+    }
+}
+
+/// Reads `OTEL_METRIC_TEMPORALITY_PREFERENCE`, defaulting to delta.
+pub fn temporality_from_env() -> Temporality {
+    temporality_from_lookup(|var| env::var(var).ok())
+}
+
+/// `temporality_from_env`, reading the variable through `lookup`.
+pub fn temporality_from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Temporality {
+    let Some(name) = lookup("OTEL_METRIC_TEMPORALITY_PREFERENCE") else {
+        return Temporality::Delta;
+    };
+    parse_temporality(&name).unwrap_or_else(|| {
+        warn!(
+            name = "InvalidMetricTemporality",
+            temporality = name.as_str(),
+            message = "Ignoring unknown metric temporality, using delta"
+        );
+        Temporality::Delta
+    })
+}
+
+/// OTLP metric exporter over the configured metrics protocol, with the
+/// configured temporality for counters and histograms.
+pub fn metric_exporter() -> Result<MetricExporter, ExporterBuildError> {
+    metric_exporter_from_lookup(|var| env::var(var).ok())
+}
+
+/// `metric_exporter`, reading the protocol, headers, metrics endpoint and
+/// temporality through `lookup`.
+pub fn metric_exporter_from_lookup(
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<MetricExporter, ExporterBuildError> {
+    let builder = MetricExporter::builder().with_temporality(temporality_from_lookup(&lookup));
+    let endpoint = lookup(METRICS_ENDPOINT);
+    match OtlpProtocol::from_lookup(METRICS_PROTOCOL, &lookup) {
+        OtlpProtocol::Grpc => builder
+            .with_tonic()
+            .with_metadata(otlp_metadata_from_lookup(METRICS_HEADERS, &lookup))
+            .with_endpoint_if_set(endpoint)
+            .build(),
+        protocol => builder
+            .with_http()
+            .with_protocol(protocol.http_protocol())
+            .with_headers(otlp_headers_from_lookup(METRICS_HEADERS, &lookup))
+            .with_endpoint_if_set(endpoint)
+            .build(),
+    }
+}
+
+/// OTLP/gRPC exporter sending counters and histograms as deltas computed from
+/// the SDK's cumulative payloads. `None`, with a warning, for the HTTP
+/// protocols or an invalid endpoint.
+pub fn delta_metric_exporter() -> Option<DeltaMetricExporter> {
+    if !matches!(OtlpProtocol::from_env(METRICS_PROTOCOL), OtlpProtocol::Grpc) {
+        warn!(
+            name = "DeltaConversionUnsupported",
+            message =
+                "METRICS_DELTA_CONVERSION needs the grpc metrics protocol, exporting without it"
+        );
+        return None;
+    }
+    let endpoint = env::var(METRICS_ENDPOINT)
+        .or_else(|_| env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+        .unwrap_or_else(|_| "http://localhost:4317".to_string());
+    match Channel::from_shared(endpoint.clone()) {
+        Ok(channel) => Some(DeltaMetricExporter::new(
+            channel.timeout(Duration::from_secs(10)).connect_lazy(),
+            otlp_metadata(METRICS_HEADERS),
+        )),
+        Err(err) => {
+            warn!(
+                name = "InvalidOtlpEndpoint",
+                endpoint = endpoint.as_str(),
+                error = err.to_string(),
+                message = "Exporting metrics without delta conversion"
+            );
+            None
+        }
+    }
+}
+
+/// OTLP log exporter over the configured logs protocol.
+pub fn log_exporter() -> Result<LogExporter, ExporterBuildError> {
+    let builder = LogExporter::builder();
+    match OtlpProtocol::from_env(LOGS_PROTOCOL) {
+        OtlpProtocol::Grpc => builder
+            .with_tonic()
+            .with_metadata(otlp_metadata(LOGS_HEADERS))
+            .build(),
+        protocol => builder
+            .with_http()
+            .with_protocol(protocol.http_protocol())
+            .with_headers(otlp_headers_from_lookup(LOGS_HEADERS, |var| {
+                env::var(var).ok()
+            }))
+            .build(),
+    }
+}
+
+/// `traceparent` wins over `X-Internal-Context` when a request carries both,
+/// as later propagators overwrite what earlier ones extracted.
+///
+/// The injected `traceparent` carries the current span's sampled flag, and
+/// the SDK's default parent-based sampler keeps the incoming decision, so
+/// unsampled requests stay unsampled downstream.
+pub fn init_propagator() {
+    global::set_text_map_propagator(propagator(test_trace_injection_enabled()));
+}
+
+/// With `test_trace_injection`, `X-Test-Trace-Context` is read first, so a
+/// W3C `traceparent` still takes precedence over it.
+fn propagator(test_trace_injection: bool) -> TextMapCompositePropagator {
+    let mut propagators: Vec<Box<dyn TextMapPropagator + Send + Sync>> = Vec::new();
+    if test_trace_injection {
+        propagators.push(Box::new(TestTraceContextPropagator::new()));
+    }
+    propagators.push(Box::new(InternalContextPropagator::new()));
+    propagators.push(Box::new(TraceContextPropagator::new()));
+    propagators.push(Box::new(BaggagePropagator::new()));
+    TextMapCompositePropagator::new(propagators)
+}
+
+fn init_tracer_provider(resource: Resource) -> SdkTracerProvider {
+    init_propagator();
+
+    let exporter = span_exporter().expect("Failed to initialize tracing provider");
+    let builder = SdkTracerProvider::builder()
+        .with_resource(resource)
+        .with_sampler(HealthCheckSampler::new(Box::new(RouteSampler::from_env())));
+
+    // Always behind MultiSpanProcessor, which also resolves repeated
+    // attribute keys. Only the default exporter's queue is watched for
+    // backpressure.
+    let exporter = QueueCountingExporter::new(exporter, EXPORT_QUEUE.clone());
+    let mut processors: Vec<Box<dyn SpanProcessor>> = vec![Box::new(QueueCountingProcessor::new(
+        Box::new(BatchSpanProcessor::builder(exporter).build()),
+        EXPORT_QUEUE.clone(),
+    ))];
+    processors.extend(extra_span_processors());
+    let tracer_provider = builder
+        .with_span_processor(MandatoryAttributesProcessor::from_env(Box::new(
+            TruncatingSpanProcessor::from_env(Box::new(MultiSpanProcessor::new(processors))),
+        )))
         .build();
+
+    global::set_tracer_provider(tracer_provider.clone());
+
+    tracer_provider
+}
+
+fn init_meter_provider(resource: Resource) -> SdkMeterProvider {
+    let builder = SdkMeterProvider::builder()
+        .with_resource(resource)
+        .with_view(ATTRIBUTE_WHITELIST.view());
+    #[allow(unused_mut)]
+    let mut builder = match delta_conversion_enabled()
+        .then(delta_metric_exporter)
+        .flatten()
+    {
+        Some(exporter) => builder.with_periodic_exporter(exporter),
+        None => builder.with_periodic_exporter(
+            metric_exporter().expect("Failed to initialize metric exporter"),
+        ),
+    };
+    #[cfg(feature = "prometheus_push")]
+    if let Some(reader) = crate::prometheus_push::pushgateway_reader() {
+        builder = builder.with_reader(reader);
+    }
+    let meter_provider = builder.build();
     global::set_meter_provider(meter_provider.clone());
+    let meter = global::meter("otel_demo.shipping.metrics");
+    ATTRIBUTE_WHITELIST.register_overflow_gauge(&meter);
+    CARDINALITY_TRACKER.register_gauge(&meter);
 
     meter_provider
 }
 
-fn init_logger_provider() {
-    let logger_provider = opentelemetry_sdk::logs::SdkLoggerProvider::builder()
-        .with_resource(get_resource())
-        .with_batch_exporter(
-            opentelemetry_otlp::LogExporter::builder()
-                .with_tonic()
-                .build()
-                .expect("Failed to initialize logger provider"),
-        )
+/// `info`, overridden per module by `module_filter` in `env_logger` syntax,
+/// e.g. `shipping::cpu_metrics=debug,shipping::shipping_service=warn`.
+/// Directives that don't parse are skipped.
+pub fn log_filter(module_filter: Option<&str>) -> EnvFilter {
+    let directives = match module_filter.map(str::trim) {
+        Some(filter) if !filter.is_empty() => format!("info,{filter}"),
+        _ => "info".to_string(),
+    };
+    EnvFilter::builder().parse_lossy(directives)
+}
+
+fn init_logger_provider(resource: Resource) -> SdkLoggerProvider {
+    SdkLoggerProvider::builder()
+        .with_resource(resource)
+        .with_batch_exporter(log_exporter().expect("Failed to initialize logger provider"))
+        .build()
+}
+
+fn module_log_filter() -> EnvFilter {
+    log_filter(env::var("RUST_LOG_MODULE_FILTER").ok().as_deref())
+}
+
+/// Turns `tracing` spans, e.g. from `#[tracing::instrument]`, into OTel
+/// spans. Spans without a `tracing` parent are children of the current OTel
+/// context, so a handler's span sits under the inbound HTTP span.
+pub fn otel_trace_layer<S>(tracer_provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("otel_demo.shipping"))
+}
+
+/// Sends `tracing` events to the logger provider as log records, and spans to
+/// the tracer provider. `log` records are bridged to the logger provider too.
+///
+/// The subscriber is installed with `set_global_default` rather than
+/// `init`, which would also install `tracing-log`'s `LogTracer` as the `log`
+/// logger and leave no room for `OtelLogBridge`.
+pub fn init_subscriber(logger_provider: &SdkLoggerProvider, tracer_provider: &SdkTracerProvider) {
+    let subscriber = tracing_subscriber::registry()
+        .with(OpenTelemetryTracingBridge::new(logger_provider).with_filter(module_log_filter()))
+        .with(
+            otel_trace_layer(tracer_provider)
+                .with_filter(filter_fn(|metadata| !is_correlation_span(metadata)))
+                .with_filter(module_log_filter()),
+        );
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("Failed to install the tracing subscriber");
+
+    if let Err(err) = OtelLogBridge::new(logger_provider, LevelFilter::Info).install() {
+        warn!(
+            name = "LogBridgeNotInstalled",
+            error = err.to_string(),
+            message = "Another log crate logger is already installed"
+        );
+    }
+}
+
+fn self_test_span() -> SpanData {
+    let now = SystemTime::now();
+    let id = Uuid::new_v4().as_u128();
+    SpanData {
+        span_context: SpanContext::new(
+            TraceId::from(id),
+            SpanId::from(id as u64),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        ),
+        parent_span_id: SpanId::INVALID,
+        span_kind: SpanKind::Internal,
+        name: "otel.self_test".into(),
+        start_time: now,
+        end_time: now,
+        attributes: Vec::new(),
+        dropped_attributes_count: 0,
+        events: Default::default(),
+        links: Default::default(),
+        status: Status::Ok,
+        instrumentation_scope: InstrumentationScope::builder("otel_demo.shipping.self_test")
+            .build(),
+    }
+}
+
+async fn export_test_span() -> Result<()> {
+    if OtlpProtocol::from_env(TRACES_PROTOCOL) == OtlpProtocol::Grpc {
+        let exporter = span_exporter().context("Failed to build span exporter")?;
+        return exporter
+            .export(vec![self_test_span()])
+            .await
+            .map_err(|e| anyhow!("Failed to export test span: {e}"));
+    }
+    // The HTTP exporters use a blocking client, which panics if it is used
+    // or dropped inside the Tokio runtime.
+    tokio::task::spawn_blocking(move || {
+        let exporter = span_exporter().context("Failed to build span exporter")?;
+        futures_executor::block_on(exporter.export(vec![self_test_span()]))
+            .map_err(|e| anyhow!("Failed to export test span: {e}"))
+    })
+    .await
+    .context("Span export panicked")?
+}
+
+async fn export_test_metric() -> Result<()> {
+    let exporter = metric_exporter().context("Failed to build metric exporter")?;
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter).build())
+        .build();
+    opentelemetry::metrics::MeterProvider::meter(&provider, "otel_demo.shipping.self_test")
+        .u64_counter("otel.self_test")
+        .build()
+        .add(1, &[]);
+
+    // flushing blocks until the export finishes
+    tokio::task::spawn_blocking(move || provider.force_flush())
+        .await
+        .context("Metric flush panicked")?
+        .map_err(|e| anyhow!("Failed to export test metric: {e}"))
+}
+
+/// Sends one test span and one test metric to the configured OTLP endpoint,
+/// failing if either isn't accepted within `timeout`.
+pub async fn check_collector_connectivity(timeout: Duration) -> Result<()> {
+    tokio::time::timeout(timeout, async {
+        export_test_span().await?;
+        export_test_metric().await
+    })
+    .await
+    .map_err(|_| anyhow!("Timed out after {timeout:?} exporting to the collector"))?
+}
+
+/// Reports `connected` as the `service_otel_connected` gauge.
+pub fn register_otel_connected_gauge(connected: bool) {
+    global::meter("otel_demo.shipping")
+        .u64_observable_gauge("service_otel_connected")
+        .with_description("1 if the startup self-test reached the OTLP endpoint, 0 otherwise")
+        .with_callback(move |observer| {
+            observer.observe(connected as u64, &KUBERNETES_LABELS.as_key_values())
+        })
         .build();
+}
+
+/// Which OTel SDK the service runs, for support triage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdkVersionInfo {
+    pub language: &'static str,
+    pub sdk_version: &'static str,
+    pub exporter_type: String,
+}
+
+fn exporter_types() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut exporters = vec![OtlpProtocol::from_env(TRACES_PROTOCOL).exporter_type()];
+    #[cfg(feature = "zipkin-exporter")]
+    exporters.push("zipkin");
+    #[cfg(feature = "prometheus_push")]
+    exporters.push("prometheus_push");
+    exporters
+}
+
+pub fn log_sdk_version_info() -> SdkVersionInfo {
+    let sdk = SdkVersionInfo {
+        language: "rust",
+        sdk_version: OTEL_SDK_VERSION,
+        exporter_type: exporter_types().join(","),
+    };
+    info!(
+        name = "OtelSdkVersion",
+        otel.sdk.language = sdk.language,
+        otel.sdk.version = sdk.sdk_version,
+        otel.exporter.type = sdk.exporter_type.as_str(),
+        message = "OpenTelemetry SDK in use"
+    );
+    sdk
+}
 
-    let otel_layer = OpenTelemetryTracingBridge::new(&logger_provider);
-    let filter_otel = EnvFilter::new("info");
-    let otel_layer = otel_layer.with_filter(filter_otel);
+/// Default for `OTEL_SHUTDOWN_TIMEOUT_SECS`.
+pub const DEFAULT_OTEL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
-    tracing_subscriber::registry().with(otel_layer).init();
+/// The providers `init_otel` installed globally.
+#[derive(Debug, Clone)]
+pub struct OtelProviders {
+    pub tracer_provider: SdkTracerProvider,
+    pub meter_provider: SdkMeterProvider,
+    pub logger_provider: SdkLoggerProvider,
 }
 
-pub fn init_otel() -> Result<()> {
-    init_logger_provider();
-    init_tracer_provider();
-    init_meter_provider();
-    Ok(())
+/// Returns the providers, so the panic hook can flush the tracer provider,
+/// handlers can emit audit logs, and all can be shut down on exit.
+pub async fn init_otel() -> Result<OtelProviders> {
+    let (resource, detector_failures) = get_resource().await;
+    let logger_provider = init_logger_provider(resource.clone());
+    // The subscriber needs the tracer provider, so what's logged while
+    // building it only goes to the logger provider.
+    let tracer_provider = tracing::subscriber::with_default(
+        tracing_subscriber::registry().with(
+            OpenTelemetryTracingBridge::new(&logger_provider).with_filter(module_log_filter()),
+        ),
+        || init_tracer_provider(resource.clone()),
+    );
+    init_subscriber(&logger_provider, &tracer_provider);
+    detector_failures.iter().for_each(DetectorFailure::log);
+    Ok(OtelProviders {
+        tracer_provider,
+        meter_provider: init_meter_provider(resource),
+        logger_provider,
+    })
+}
+
+/// Runs a provider's blocking `shutdown` off the runtime, giving up after
+/// `timeout`. Returns false if it was still running then.
+async fn shutdown_within(
+    provider: &'static str,
+    timeout: Duration,
+    shutdown: impl FnOnce() -> OTelSdkResult + Send + 'static,
+) -> bool {
+    let Ok(outcome) = tokio::time::timeout(timeout, tokio::task::spawn_blocking(shutdown)).await
+    else {
+        warn!(
+            name = "OtelShutdownTimedOut",
+            provider = provider,
+            timeout_secs = timeout.as_secs_f64(),
+            message = "OpenTelemetry provider did not shut down in time"
+        );
+        return false;
+    };
+    let outcome = outcome
+        .map_err(|err| err.to_string())
+        .and_then(|result| result.map_err(|err| err.to_string()));
+    if let Err(error) = outcome {
+        warn!(
+            name = "OtelShutdownFailed",
+            provider = provider,
+            error = error.as_str(),
+            message = "OpenTelemetry provider failed to shut down"
+        );
+    }
+    true
+}
+
+/// Flushes and shuts down both providers, each within `timeout`, so an
+/// unreachable collector can't hang the exit. Returns false if either timed
+/// out.
+pub async fn shutdown_otel(providers: OtelProviders, timeout: Duration) -> bool {
+    let OtelProviders {
+        tracer_provider,
+        meter_provider,
+        logger_provider,
+    } = providers;
+    let traces = shutdown_within("tracer", timeout, move || tracer_provider.shutdown()).await;
+    let metrics = shutdown_within("meter", timeout, move || meter_provider.shutdown()).await;
+    let logs = shutdown_within("logger", timeout, move || logger_provider.shutdown()).await;
+    traces && metrics && logs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Instant;
+
+    use opentelemetry::{
+        metrics::MeterProvider,
+        trace::{get_active_span, TraceContextExt, TraceId, Tracer, TracerProvider},
+    };
+    use opentelemetry_sdk::{
+        metrics::{
+            data::{AggregatedMetrics, MetricData},
+            InMemoryMetricExporterBuilder,
+        },
+        trace::SpanExporter,
+    };
+
+    use super::*;
+    use crate::test_trace_context::TEST_TRACE_CONTEXT_HEADER;
+    use crate::test_utils::{global_span_exporter, CaptureLayer};
+
+    #[test]
+    fn test_injected_trace_id_is_the_active_trace() {
+        global_span_exporter();
+        let headers = std::collections::HashMap::from([(
+            TEST_TRACE_CONTEXT_HEADER.to_string(),
+            "traceid=5b8efff798038103d269b633813fc60c,spanid=eee19b7ec3c1b174".to_string(),
+        )]);
+        let injected = TraceId::from_hex("5b8efff798038103d269b633813fc60c").unwrap();
+
+        // Ignored unless enabled
+        let parent = propagator(false).extract(&headers);
+        assert!(!parent.span().span_context().is_valid());
+
+        let parent = propagator(true).extract(&headers);
+        let span = global::tracer("test").start_with_context("request", &parent);
+        let _guard = parent.with_span(span).attach();
+        assert_eq!(
+            get_active_span(|span| span.span_context().trace_id()),
+            injected
+        );
+    }
+
+    /// Exports instantly but takes `delay` to shut down, like an exporter
+    /// stuck on an unreachable collector.
+    #[derive(Debug)]
+    struct SlowShutdownExporter {
+        delay: Duration,
+    }
+
+    impl SpanExporter for SlowShutdownExporter {
+        async fn export(&self, _batch: Vec<SpanData>) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn shutdown_with_timeout(&mut self, _timeout: Duration) -> OTelSdkResult {
+            thread::sleep(self.delay);
+            Ok(())
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_times_out() {
+        let providers = OtelProviders {
+            tracer_provider: SdkTracerProvider::builder()
+                .with_simple_exporter(SlowShutdownExporter {
+                    delay: Duration::from_secs(1),
+                })
+                .build(),
+            meter_provider: SdkMeterProvider::default(),
+            logger_provider: SdkLoggerProvider::builder().build(),
+        };
+        providers
+            .tracer_provider
+            .tracer("test")
+            .in_span("quote", |_| {});
+
+        let started = Instant::now();
+        assert!(!shutdown_otel(providers, Duration::from_millis(100)).await);
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_completes() {
+        let providers = OtelProviders {
+            tracer_provider: SdkTracerProvider::builder()
+                .with_simple_exporter(SlowShutdownExporter {
+                    delay: Duration::ZERO,
+                })
+                .build(),
+            meter_provider: SdkMeterProvider::default(),
+            logger_provider: SdkLoggerProvider::builder().build(),
+        };
+        assert!(shutdown_otel(providers, Duration::from_secs(1)).await);
+    }
+
+    #[test]
+    fn test_sdk_version_info() {
+        let sdk = log_sdk_version_info();
+        assert_eq!(sdk.language, "rust");
+        assert!(!sdk.sdk_version.is_empty());
+        assert_ne!(
+            sdk.sdk_version, "unknown",
+            "build.rs reads it from Cargo.lock"
+        );
+        assert!(!sdk.exporter_type.is_empty());
+    }
+
+    #[test]
+    fn test_delta_temporality_exports_increments() {
+        let exporter = InMemoryMetricExporterBuilder::new()
+            .with_temporality(parse_temporality("Delta").unwrap())
+            .build();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let counter = provider.meter("test").u64_counter("items").build();
+
+        counter.add(5, &[]);
+        provider.force_flush().unwrap();
+        counter.add(3, &[]);
+        provider.force_flush().unwrap();
+
+        let sums: Vec<u64> = exporter
+            .get_finished_metrics()
+            .unwrap()
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .filter_map(|metric| match metric.data() {
+                AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                    Some(sum.data_points().map(|point| point.value()).sum())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sums, [5, 3]);
+    }
+
+    #[test]
+    fn test_temporality_from_lookup() {
+        let temporality = |value: Option<&str>| {
+            temporality_from_lookup(|var| {
+                assert_eq!(var, "OTEL_METRIC_TEMPORALITY_PREFERENCE");
+                value.map(str::to_string)
+            })
+        };
+        assert_eq!(temporality(Some("delta")), Temporality::Delta);
+        assert_eq!(temporality(Some(" Cumulative ")), Temporality::Cumulative);
+        assert_eq!(temporality(Some("lowmemory")), Temporality::LowMemory);
+        assert_eq!(temporality(Some("monthly")), Temporality::Delta);
+        assert_eq!(temporality(None), Temporality::Delta);
+    }
+
+    #[test]
+    fn test_parse_otlp_protocol() {
+        assert_eq!(OtlpProtocol::parse("grpc"), Some(OtlpProtocol::Grpc));
+        assert_eq!(
+            OtlpProtocol::parse(" http/protobuf "),
+            Some(OtlpProtocol::HttpProtobuf)
+        );
+        assert_eq!(
+            OtlpProtocol::parse("http/json"),
+            Some(OtlpProtocol::HttpJson)
+        );
+        assert_eq!(OtlpProtocol::parse("http"), None);
+    }
+
+    #[test]
+    fn test_parse_otlp_headers() {
+        let mut metadata = MetadataMap::new();
+        parse_otlp_headers(
+            "Authorization=Basic dXNlcjpwYXNz+/==, x-honeycomb-team = abc123 ,,broken,=novalue",
+            &mut metadata,
+        );
+
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(
+            metadata.get("authorization").unwrap(),
+            "Basic dXNlcjpwYXNz+/=="
+        );
+        assert_eq!(metadata.get("x-honeycomb-team").unwrap(), "abc123");
+    }
+
+    #[test]
+    fn test_signal_headers_override_shared_headers() {
+        let lookup = |var: &str| match var {
+            "OTEL_EXPORTER_OTLP_HEADERS" => {
+                Some("authorization=Bearer shared,x-tenant=demo".to_string())
+            }
+            "OTEL_EXPORTER_OTLP_TRACES_HEADERS" => Some("authorization=Bearer traces".to_string()),
+            _ => None,
+        };
+
+        let metadata = otlp_metadata_from_lookup("OTEL_EXPORTER_OTLP_TRACES_HEADERS", lookup);
+        assert_eq!(metadata.get("authorization").unwrap(), "Bearer traces");
+        assert_eq!(metadata.get("x-tenant").unwrap(), "demo");
+
+        let metadata = otlp_metadata_from_lookup("OTEL_EXPORTER_OTLP_METRICS_HEADERS", lookup);
+        assert_eq!(metadata.get("authorization").unwrap(), "Bearer shared");
+    }
+
+    #[actix_web::test]
+    async fn test_module_log_filter() {
+        use std::sync::Arc;
+
+        use actix_web::{test, web, App};
+
+        use crate::cpu_metrics::{CgroupMode, CpuMetricsState};
+        use crate::shipping_service::{get_quote, RealShippingBackend, ShippingBackend};
+
+        let capture = CaptureLayer::default();
+        let filter = log_filter(Some(
+            "shipping::cpu_metrics=debug,shipping::shipping_service=info",
+        ));
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(capture.clone().with_filter(filter)),
+        );
+
+        // No cpufreq under the root, which is logged at debug
+        let root = env::temp_dir().join(format!("log-filter-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("status"), "VmRSS:\t1024 kB\n").unwrap();
+        CpuMetricsState::try_with_roots(&root, root.join("status"), CgroupMode::Unknown).unwrap();
+
+        let backend: Arc<dyn ShippingBackend> = Arc::new(RealShippingBackend);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(backend))
+                .service(get_quote),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/get-quote")
+            .set_json(serde_json::json!({ "items": [] }))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let names: Vec<_> = capture
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(level, _)| *level == tracing::Level::DEBUG)
+            .filter_map(|(_, fields)| fields.get("name").cloned())
+            .collect();
+        assert!(names.contains(&"CpuFrequencyUnavailable".to_string()));
+        assert!(!names.contains(&"QuoteRequested".to_string()));
+    }
+
+    #[test]
+    fn test_log_filter_skips_bad_directives() {
+        let filter = log_filter(Some("shipping::cpu_metrics=debug,=nonsense=,"));
+        assert_eq!(
+            filter.to_string(),
+            log_filter(Some("shipping::cpu_metrics=debug")).to_string()
+        );
+        assert_eq!(log_filter(Some("  ")).to_string(), "info");
+    }
 }
@@ -0,0 +1,132 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+
+use opentelemetry::{
+    propagation::{text_map_propagator::FieldIter, Extractor, Injector, TextMapPropagator},
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use tracing::debug;
+
+pub const TEST_TRACE_CONTEXT_HEADER: &str = "x-test-trace-context";
+
+/// Whether `TEST_TRACE_INJECTION_ENABLED=true`. Never set it in production.
+pub fn test_trace_injection_enabled() -> bool {
+    env::var("TEST_TRACE_INJECTION_ENABLED").is_ok_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Parses `traceid=<32 hex>,spanid=<16 hex>` into a sampled remote span
+/// context.
+pub fn parse(value: &str) -> Result<SpanContext, String> {
+    let mut trace_id = None;
+    let mut span_id = None;
+    for part in value.split(',') {
+        match part.trim().split_once('=') {
+            Some(("traceid", hex)) if hex.len() == 32 => {
+                trace_id =
+                    Some(TraceId::from_hex(hex).map_err(|e| format!("invalid trace id: {e}"))?);
+            }
+            Some(("spanid", hex)) if hex.len() == 16 => {
+                span_id = Some(SpanId::from_hex(hex).map_err(|e| format!("invalid span id: {e}"))?);
+            }
+            _ => return Err(format!("unexpected {part:?}")),
+        }
+    }
+    let (Some(trace_id), Some(span_id)) = (trace_id, span_id) else {
+        return Err(format!(
+            "expected traceid=<hex128>,spanid=<hex64>, got {value:?}"
+        ));
+    };
+    let span_context = SpanContext::new(
+        trace_id,
+        span_id,
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    if !span_context.is_valid() {
+        return Err("trace id and span id must be non-zero".to_string());
+    }
+    Ok(span_context)
+}
+
+/// Lets integration tests pick the trace ID of a request with an
+/// `X-Test-Trace-Context` header. Only extracts; nothing is injected
+/// downstream besides the usual W3C headers.
+#[derive(Debug)]
+pub struct TestTraceContextPropagator {
+    fields: [String; 1],
+}
+
+impl Default for TestTraceContextPropagator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestTraceContextPropagator {
+    pub fn new() -> Self {
+        TestTraceContextPropagator {
+            fields: [TEST_TRACE_CONTEXT_HEADER.to_string()],
+        }
+    }
+}
+
+impl TextMapPropagator for TestTraceContextPropagator {
+    fn inject_context(&self, _cx: &Context, _injector: &mut dyn Injector) {}
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        let Some(value) = extractor.get(TEST_TRACE_CONTEXT_HEADER) else {
+            return cx.clone();
+        };
+        match parse(value) {
+            Ok(span_context) => cx.with_remote_span_context(span_context),
+            Err(error) => {
+                debug!(
+                    name = "InvalidTestTraceContext",
+                    error = error.as_str(),
+                    message = "Ignoring unparseable X-Test-Trace-Context header"
+                );
+                cx.clone()
+            }
+        }
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(&self.fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let span_context =
+            parse("traceid=4bf92f3577b34da6a3ce929d0e0e4736, spanid=00f067aa0ba902b7").unwrap();
+        assert_eq!(
+            span_context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+        assert_eq!(
+            span_context.span_id(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap()
+        );
+        assert!(span_context.is_sampled());
+        assert!(span_context.is_remote());
+
+        for value in [
+            "",
+            "traceid=4bf92f3577b34da6a3ce929d0e0e4736",
+            "traceid=4bf92f35,spanid=00f067aa0ba902b7",
+            "traceid=4bf92f3577b34da6a3ce929d0e0e4736,spanid=00f067aa0ba902b7,x=1",
+            "traceid=zzf92f3577b34da6a3ce929d0e0e4736,spanid=00f067aa0ba902b7",
+            "traceid=00000000000000000000000000000000,spanid=00f067aa0ba902b7",
+        ] {
+            assert!(parse(value).is_err(), "{value:?} should not parse");
+        }
+    }
+}
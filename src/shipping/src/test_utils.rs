@@ -0,0 +1,129 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers for asserting on the telemetry recorded by a test.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use opentelemetry::global;
+use opentelemetry_sdk::{
+    metrics::{
+        data::{AggregatedMetrics, HistogramDataPoint, MetricData},
+        InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+    },
+    trace::{InMemorySpanExporter, SdkTracerProvider},
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// The global tracer provider and its exporter, installed on first use.
+fn global_tracing() -> &'static (SdkTracerProvider, InMemorySpanExporter) {
+    static TRACING: OnceLock<(SdkTracerProvider, InMemorySpanExporter)> = OnceLock::new();
+    TRACING.get_or_init(|| {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        global::set_tracer_provider(provider.clone());
+        (provider, exporter)
+    })
+}
+
+/// Exporter behind the global tracer provider, installed on first use. It is
+/// shared by every test in the binary, so only look at your own trace.
+pub fn global_span_exporter() -> InMemorySpanExporter {
+    global_tracing().1.clone()
+}
+
+/// The provider `global_span_exporter` exports for, e.g. to build a
+/// `tracing` layer whose spans land there too.
+pub fn global_tracer_provider() -> SdkTracerProvider {
+    global_tracing().0.clone()
+}
+
+/// Meter provider whose metrics can be read back with `histogram_points`
+/// after a `force_flush`.
+pub fn test_meter_provider() -> (SdkMeterProvider, InMemoryMetricExporter) {
+    let exporter = InMemoryMetricExporter::default();
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter.clone()).build())
+        .build();
+    (provider, exporter)
+}
+
+pub fn u64_histogram_points(
+    exporter: &InMemoryMetricExporter,
+    name: &str,
+) -> Vec<HistogramDataPoint<u64>> {
+    exporter
+        .get_finished_metrics()
+        .unwrap()
+        .iter()
+        .flat_map(|rm| rm.scope_metrics())
+        .flat_map(|sm| sm.metrics())
+        .filter(|metric| metric.name() == name)
+        .flat_map(|metric| match metric.data() {
+            AggregatedMetrics::U64(MetricData::Histogram(histogram)) => {
+                histogram.data_points().cloned().collect()
+            }
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Sum of every data point of the u64 counter `name`.
+pub fn u64_counter_total(exporter: &InMemoryMetricExporter, name: &str) -> u64 {
+    exporter
+        .get_finished_metrics()
+        .unwrap()
+        .iter()
+        .flat_map(|rm| rm.scope_metrics())
+        .flat_map(|sm| sm.metrics())
+        .filter(|metric| metric.name() == name)
+        .map(|metric| match metric.data() {
+            AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                sum.data_points().map(|point| point.value()).sum()
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Events recorded by `CaptureLayer`, each as its level and field values.
+pub type CapturedEvents = Arc<Mutex<Vec<(Level, HashMap<String, String>)>>>;
+
+/// `tracing` layer that keeps every event it sees, so tests can assert on
+/// what was logged.
+#[derive(Default, Clone)]
+pub struct CaptureLayer {
+    pub events: CapturedEvents,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.events
+            .lock()
+            .unwrap()
+            .push((*event.metadata().level(), fields));
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::future::Future;
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use awc::ClientRequest;
+use opentelemetry::{global, propagation::Injector, trace::FutureExt, Context};
+use tokio::task::JoinHandle;
+use tonic::{
+    metadata::{MetadataKey, MetadataMap, MetadataValue},
+    service::{interceptor::InterceptedService, Interceptor},
+    Request, Status,
+};
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Injects the current OTel context (`traceparent`, `tracestate` and `baggage`)
+/// into an outgoing request using the global propagator.
+///
+/// Requests sent with `ClientExt::trace_request` are already covered; this is
+/// for calls that shouldn't get their own client span.
+pub fn propagate_context(mut request: ClientRequest) -> ClientRequest {
+    let cx = Context::current();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(request.headers_mut()))
+    });
+    request
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(value),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+/// Injects the current OTel context into the metadata of every RPC, like
+/// `propagate_context` does for HTTP requests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OtelContextInterceptor;
+
+impl Interceptor for OtelContextInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let cx = Context::current();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&cx, &mut MetadataInjector(request.metadata_mut()))
+        });
+        Ok(request)
+    }
+}
+
+/// Wraps a channel so gRPC clients built on it propagate the OTel context,
+/// e.g. `CurrencyServiceClient::new(traced_channel(channel))`.
+pub fn traced_channel<S>(channel: S) -> InterceptedService<S, OtelContextInterceptor> {
+    InterceptedService::new(channel, OtelContextInterceptor)
+}
+
+/// `tokio::spawn`, with the caller's OTel context attached to the task
+/// whenever it is polled, so spans it starts are children of the current one.
+pub fn spawn_with_context<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future.with_context(Context::current()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use httpmock::prelude::*;
+    use opentelemetry::{
+        baggage::BaggageExt,
+        trace::{
+            Span, SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, Tracer,
+        },
+        KeyValue,
+    };
+
+    use opentelemetry_instrumentation_actix_web::ClientExt;
+    use opentelemetry_proto::tonic::collector::trace::v1::{
+        trace_service_client::TraceServiceClient,
+        trace_service_server::{TraceService, TraceServiceServer},
+        ExportTraceServiceRequest, ExportTraceServiceResponse,
+    };
+    use tonic::{
+        transport::{server::TcpIncoming, Channel, Server},
+        Response,
+    };
+
+    use super::*;
+    use crate::telemetry_conf::init_propagator;
+    use crate::test_utils::global_span_exporter;
+
+    #[actix_web::test]
+    async fn test_spawn_with_context_parents_child_spans() {
+        let exporter = global_span_exporter();
+        let outer = global::tracer("test").start("outer");
+        let outer_context = outer.span_context().clone();
+
+        let handle = {
+            let _guard = Context::current_with_span(outer).attach();
+            spawn_with_context(async {
+                global::tracer("test").in_span("spawned", |cx| cx.span().span_context().clone())
+            })
+        };
+        let child_context = handle.await.unwrap();
+
+        let child = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .find(|span| span.span_context == child_context)
+            .unwrap();
+        assert_eq!(child.span_context.trace_id(), outer_context.trace_id());
+        assert_eq!(child.parent_span_id, outer_context.span_id());
+    }
+
+    #[actix_web::test]
+    async fn test_propagate_context_adds_w3c_headers() {
+        init_propagator();
+
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/")
+                    .header(
+                        "traceparent",
+                        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                    )
+                    .header("tracestate", "vendor=value")
+                    .header("baggage", "tenant=acme");
+                then.status(200);
+            })
+            .await;
+
+        let span_context = SpanContext::new(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            "vendor=value".parse::<TraceState>().unwrap(),
+        );
+        let _guard = Context::current()
+            .with_remote_span_context(span_context)
+            .with_baggage(vec![KeyValue::new("tenant", "acme")])
+            .attach();
+
+        let resp = propagate_context(awc::Client::new().get(server.url("/")))
+            .send()
+            .await
+            .unwrap();
+
+        assert!(resp.status().is_success());
+        mock.assert_async().await;
+    }
+
+    const UNSAMPLED_TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00";
+
+    #[actix_web::test]
+    async fn test_unsampled_parent_stays_unsampled_downstream() {
+        init_propagator();
+        global_span_exporter();
+
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                // Same trace, sampled flag still clear
+                when.method(GET).path("/").matches(|req| {
+                    req.headers.iter().flatten().any(|(name, value)| {
+                        name.eq_ignore_ascii_case("traceparent")
+                            && value.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-")
+                            && value.ends_with("-00")
+                    })
+                });
+                then.status(200);
+            })
+            .await;
+
+        let incoming =
+            HashMap::from([("traceparent".to_string(), UNSAMPLED_TRACEPARENT.to_string())]);
+        let cx = global::get_text_map_propagator(|propagator| propagator.extract(&incoming));
+        assert!(!cx.span().span_context().is_sampled());
+        let _guard = cx.attach();
+
+        // Through a client span, which must not re-decide, and without one
+        for client_span in [true, false] {
+            let request = awc::Client::new().get(server.url("/"));
+            let resp = if client_span {
+                request.trace_request().send().await
+            } else {
+                propagate_context(request).send().await
+            };
+            assert!(resp.unwrap().status().is_success(), "{client_span}");
+        }
+        mock.assert_hits_async(2).await;
+    }
+
+    /// Records the `traceparent` of every export it receives.
+    #[derive(Debug, Default, Clone)]
+    struct RecordingTraceService {
+        traceparents: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    #[tonic::async_trait]
+    impl TraceService for RecordingTraceService {
+        async fn export(
+            &self,
+            request: Request<ExportTraceServiceRequest>,
+        ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+            let traceparent = request
+                .metadata()
+                .get("traceparent")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            self.traceparents.lock().unwrap().push(traceparent);
+            Ok(Response::new(ExportTraceServiceResponse::default()))
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_intercepted_client_sends_traceparent() {
+        init_propagator();
+
+        let service = RecordingTraceService::default();
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(TraceServiceServer::new(service.clone()))
+                .serve_with_incoming(incoming),
+        );
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = TraceServiceClient::new(traced_channel(channel));
+
+        let span_context = SpanContext::new(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let _guard = Context::current()
+            .with_remote_span_context(span_context)
+            .attach();
+        client
+            .export(ExportTraceServiceRequest::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *service.traceparents.lock().unwrap(),
+            [Some(
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use actix_web::{get, HttpResponse, Responder};
+use opentelemetry::KeyValue;
+use serde::{Deserialize, Serialize};
+
+use crate::config::service_name;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Set by `build.rs`.
+pub const GIT_COMMIT: &str = match option_env!("GIT_COMMIT") {
+    Some(commit) => commit,
+    None => "unknown",
+};
+/// Set by `build.rs`.
+pub const BUILD_TIME: &str = match option_env!("BUILD_TIME") {
+    Some(time) => time,
+    None => "unknown",
+};
+
+/// `opentelemetry_sdk` version from `Cargo.lock`, set by `build.rs`.
+pub const OTEL_SDK_VERSION: &str = match option_env!("OTEL_SDK_VERSION") {
+    Some(version) => version,
+    None => "unknown",
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VersionInfo {
+    pub service: String,
+    pub version: String,
+    pub git_commit: String,
+    pub build_time: String,
+}
+
+/// Build metadata added to the OTel resource.
+pub fn build_resource_attributes() -> Vec<KeyValue> {
+    vec![
+        KeyValue::new("service.version", VERSION),
+        KeyValue::new("vcs.ref.head.revision", GIT_COMMIT),
+        KeyValue::new("app.build.time", BUILD_TIME),
+    ]
+}
+
+#[get("/version")]
+pub async fn get_version() -> impl Responder {
+    HttpResponse::Ok().json(VersionInfo {
+        service: service_name(),
+        version: VERSION.to_string(),
+        git_commit: GIT_COMMIT.to_string(),
+        build_time: BUILD_TIME.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_version() {
+        let app = test::init_service(App::new().service(get_version)).await;
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let info: VersionInfo = test::read_body_json(resp).await;
+        assert!(!info.service.is_empty());
+        assert!(!info.version.is_empty());
+        assert!(!info.git_commit.is_empty());
+        assert!(!info.build_time.is_empty());
+    }
+}
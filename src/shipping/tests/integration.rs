@@ -0,0 +1,88 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! `/get-quote` with item quantities at and past the edges of `u32`.
+//! Requests are canaries, so they're priced locally with the tiered rates.
+
+use std::sync::Arc;
+
+use actix_web::{
+    http::StatusCode,
+    test::{self, TestRequest},
+    web, App,
+};
+use serde_json::{json, Value};
+
+use shipping::shipping_service::{get_quote, RealShippingBackend, ShippingBackend};
+
+async fn quote(body: Value) -> (StatusCode, String) {
+    let backend: Arc<dyn ShippingBackend> = Arc::new(RealShippingBackend);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(backend))
+            .service(get_quote),
+    )
+    .await;
+    let req = TestRequest::post()
+        .uri("/get-quote")
+        .insert_header(("X-Canary", "true"))
+        .set_json(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let status = resp.status();
+    let body = test::read_body(resp).await;
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[actix_web::test]
+async fn test_zero_quantity_is_unprocessable() {
+    let (status, body) = quote(json!({ "items": [{ "quantity": 0 }] })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["errors"][0]["path"], "/items/0/quantity");
+}
+
+#[actix_web::test]
+async fn test_empty_cart_is_free() {
+    let (status, body) = quote(json!({ "items": [] })).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["cost_usd"]["units"], 0);
+    assert_eq!(body["cost_usd"]["nanos"], 0);
+}
+
+#[actix_web::test]
+async fn test_max_quantity_does_not_overflow() {
+    let (status, body) = quote(json!({ "items": [{ "quantity": u32::MAX }] })).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    // 2,147,483,667.50 tiered, less the 30% discount for 101+ items
+    assert_eq!(body["cost_usd"]["units"], 1_503_238_567_u64);
+    assert_eq!(body["cost_usd"]["nanos"], 250_000_000);
+
+    let (status, body) = quote(json!({
+        "items": [{ "quantity": u32::MAX }, { "quantity": 1 }]
+    }))
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["code"], "quantity_too_large");
+    assert_eq!(body["message"], "Total item quantity is too large");
+}
+
+#[actix_web::test]
+async fn test_missing_items_is_bad_request() {
+    let (status, body) = quote(json!({ "address": { "zip_code": "94043" } })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("missing field `items`"), "{body}");
+}
+
+#[actix_web::test]
+async fn test_negative_quantity_is_bad_request() {
+    let (status, body) = quote(json!({ "items": [{ "quantity": -1 }] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body.contains("invalid value: integer `-1`, expected u32"),
+        "{body}"
+    );
+}
@@ -0,0 +1,100 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fires concurrent `/get-quote` and `/ship-order` requests at a local server
+//! all at once, to check the shared state holds up under concurrent access.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use actix_web::{middleware::from_fn, web, App, HttpServer};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Barrier;
+
+use shipping::drain::{track_in_flight, Drain, DEFAULT_DRAIN_TIMEOUT};
+use shipping::shipping_service::{
+    get_quote, ship_order, DeadLetterQueue, RealShippingBackend, ShipOrderDeadLetters,
+    ShippingBackend,
+};
+
+const REQUESTS_PER_ROUTE: usize = 100;
+
+/// Sends one request over a fresh connection and returns the status code.
+async fn post(addr: SocketAddr, path: &str, body: &str) -> u16 {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {addr}\r\nX-Canary: true\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or_else(|| panic!("Malformed response: {response:?}"))
+}
+
+#[actix_web::test]
+async fn test_concurrent_quotes_and_orders() {
+    let drain = web::Data::new(Drain::new(DEFAULT_DRAIN_TIMEOUT));
+    let server_drain = drain.clone();
+    let dead_letters: web::Data<ShipOrderDeadLetters> =
+        web::Data::new(Mutex::new(DeadLetterQueue::from_env()));
+    let backend: Arc<dyn ShippingBackend> = Arc::new(RealShippingBackend);
+    let backend = web::Data::from(backend);
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(server_drain.clone())
+            .app_data(dead_letters.clone())
+            .app_data(backend.clone())
+            .wrap(from_fn(track_in_flight))
+            .service(get_quote)
+            .service(ship_order)
+    })
+    .workers(4)
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let addr = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let quote = json!({ "items": [{ "quantity": 3 }] }).to_string();
+    let order = json!({ "total_weight_g": 1200 }).to_string();
+    let barrier = Arc::new(Barrier::new(2 * REQUESTS_PER_ROUTE));
+    let requests: Vec<_> = (0..2 * REQUESTS_PER_ROUTE)
+        .map(|i| {
+            let barrier = barrier.clone();
+            let (path, body) = if i % 2 == 0 {
+                ("/get-quote", quote.clone())
+            } else {
+                ("/ship-order", order.clone())
+            };
+            tokio::spawn(async move {
+                barrier.wait().await;
+                post(addr, path, &body).await
+            })
+        })
+        .collect();
+
+    let mut succeeded = 0;
+    for request in requests {
+        let status = request.await.expect("request task panicked");
+        if (200..300).contains(&status) {
+            succeeded += 1;
+        }
+    }
+    assert!(
+        succeeded * 100 >= 2 * REQUESTS_PER_ROUTE * 95,
+        "only {succeeded} of {} requests succeeded",
+        2 * REQUESTS_PER_ROUTE
+    );
+    assert_eq!(drain.in_flight(), 0);
+    handle.stop(true).await;
+}
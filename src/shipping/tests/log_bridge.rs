@@ -0,0 +1,47 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Installs the real global subscriber and `log` bridge, so it runs in its
+//! own test binary.
+
+use opentelemetry::{logs::AnyValue, Key};
+use opentelemetry_sdk::logs::{InMemoryLogExporter, SdkLoggerProvider, SimpleLogProcessor};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use shipping::telemetry_conf::init_subscriber;
+
+#[test]
+fn test_log_records_reach_the_bridge_after_init_subscriber() {
+    let exporter = InMemoryLogExporter::default();
+    let logger_provider = SdkLoggerProvider::builder()
+        .with_log_processor(SimpleLogProcessor::new(exporter.clone()))
+        .build();
+    init_subscriber(&logger_provider, &SdkTracerProvider::builder().build());
+
+    log::warn!(target: "actix_server::builder", "from the log crate");
+    tracing::warn!(name = "FromTracing", message = "from tracing");
+
+    let logs = exporter.get_emitted_logs().unwrap();
+    let bodies: Vec<_> = logs
+        .iter()
+        .filter_map(|log| log.record.body().cloned())
+        .collect();
+    assert!(
+        bodies.contains(&AnyValue::from("from tracing".to_string())),
+        "{bodies:?}"
+    );
+
+    // Only `OtelLogBridge` sets `logger.name`; through `tracing-log`'s
+    // `LogTracer` the record would arrive without it.
+    let from_log = logs
+        .iter()
+        .find(|log| log.record.body() == Some(&AnyValue::from("from the log crate".to_string())))
+        .expect("the log record was not exported");
+    assert_eq!(
+        from_log
+            .record
+            .attributes_iter()
+            .find(|(key, _)| *key == Key::new("logger.name"))
+            .map(|(_, value)| value.clone()),
+        Some(AnyValue::from("actix_server::builder".to_string()))
+    );
+}
@@ -0,0 +1,130 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! End-to-end check that telemetry produced by the shipping binary reaches an
+//! OpenTelemetry Collector. Needs a Docker daemon, so it is ignored by default:
+//!
+//! ```sh
+//! cargo test --test otel_pipeline -- --ignored
+//! ```
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use testcontainers::{
+    core::{IntoContainerPort, WaitFor},
+    runners::AsyncRunner,
+    ContainerAsync, GenericImage, ImageExt,
+};
+
+const COLLECTOR_IMAGE: &str = "otel/opentelemetry-collector-contrib";
+const COLLECTOR_TAG: &str = "0.128.0";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Kills the service when the test ends, whatever the outcome.
+struct ServiceProcess(Child);
+
+impl Drop for ServiceProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Stands in for the quote service, always answering with the same price.
+fn start_quote_stub() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\n9.99",
+            );
+        }
+    });
+    port
+}
+
+fn start_service(port: u16, quote_port: u16, otlp_port: u16) -> ServiceProcess {
+    let child = Command::new(env!("CARGO_BIN_EXE_shipping"))
+        .env("SHIPPING_PORT", port.to_string())
+        .env("QUOTE_ADDR", format!("http://127.0.0.1:{quote_port}"))
+        .env(
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+            format!("http://127.0.0.1:{otlp_port}"),
+        )
+        .env("OTEL_BSP_SCHEDULE_DELAY", "500")
+        .env("OTEL_METRIC_EXPORT_INTERVAL", "1000")
+        .spawn()
+        .expect("Failed to start shipping service");
+
+    let deadline = Instant::now() + EXPORT_TIMEOUT;
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        assert!(Instant::now() < deadline, "Shipping service did not start");
+        thread::sleep(Duration::from_millis(100));
+    }
+    ServiceProcess(child)
+}
+
+/// Polls the collector's debug exporter output until every needle shows up.
+async fn wait_for_output(collector: &ContainerAsync<GenericImage>, needles: &[&str]) -> String {
+    let deadline = Instant::now() + EXPORT_TIMEOUT;
+    loop {
+        let stderr = collector.stderr_to_vec().await.unwrap();
+        let output = String::from_utf8_lossy(&stderr).into_owned();
+        if needles.iter().all(|needle| output.contains(needle)) || Instant::now() > deadline {
+            return output;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+#[actix_web::test]
+#[ignore = "requires a Docker daemon"]
+async fn test_telemetry_reaches_collector() {
+    let collector = GenericImage::new(COLLECTOR_IMAGE, COLLECTOR_TAG)
+        .with_exposed_port(4317.tcp())
+        .with_wait_for(WaitFor::message_on_stderr("Everything is ready"))
+        .with_copy_to(
+            "/etc/otelcol-contrib/config.yaml",
+            include_bytes!("otelcol-config.yml").to_vec(),
+        )
+        .start()
+        .await
+        .expect("Failed to start collector container");
+    let otlp_port = collector.get_host_port_ipv4(4317).await.unwrap();
+
+    let port = free_port();
+    let _service = start_service(port, start_quote_stub(), otlp_port);
+
+    let mut resp = awc::Client::new()
+        .post(format!("http://127.0.0.1:{port}/get-quote"))
+        .insert_header(("content-type", "application/json"))
+        .send_body(r#"{"items":[{"quantity":3}],"address":null}"#)
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{:?}", resp.body().await);
+
+    let expected = [
+        "Name           : /get-quote",
+        "Name: app.shipping.items_count",
+        "Name: http.server.duration",
+    ];
+    let output = wait_for_output(&collector, &expected).await;
+    for needle in expected {
+        assert!(output.contains(needle), "Collector never received {needle}");
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Startup behaviour when the OTLP endpoint can't be reached.
+
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Nothing listens on the discard port.
+const UNREACHABLE_ENDPOINT: &str = "http://127.0.0.1:9";
+
+/// Kills the service when the test ends, whatever the outcome.
+struct ServiceProcess(Child);
+
+impl Drop for ServiceProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn spawn_service(port: u16, otel_required: bool) -> ServiceProcess {
+    let child = Command::new(env!("CARGO_BIN_EXE_shipping"))
+        .env("SHIPPING_PORT", port.to_string())
+        .env("OTEL_EXPORTER_OTLP_ENDPOINT", UNREACHABLE_ENDPOINT)
+        .env("OTEL_REQUIRED", otel_required.to_string())
+        .spawn()
+        .expect("Failed to start shipping service");
+    ServiceProcess(child)
+}
+
+#[test]
+fn test_starts_degraded_without_collector() {
+    let port = free_port();
+    let mut service = spawn_service(port, false);
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        assert!(
+            service.0.try_wait().unwrap().is_none(),
+            "Shipping service exited instead of starting degraded"
+        );
+        assert!(Instant::now() < deadline, "Shipping service did not start");
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn test_otel_required_aborts_without_collector() {
+    let port = free_port();
+    let mut service = spawn_service(port, true);
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    let status = loop {
+        if let Some(status) = service.0.try_wait().unwrap() {
+            break status;
+        }
+        assert!(Instant::now() < deadline, "Shipping service did not exit");
+        thread::sleep(Duration::from_millis(100));
+    };
+    assert!(!status.success());
+    assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+}
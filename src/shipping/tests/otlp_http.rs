@@ -0,0 +1,89 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The OTLP exporters honour `OTEL_EXPORTER_OTLP_<SIGNAL>_PROTOCOL` and send
+//! well-formed HTTP bodies.
+
+use httpmock::prelude::*;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry::trace::{Tracer, TracerProvider};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use prost::Message;
+
+use shipping::telemetry_conf::{metric_exporter_from_lookup, span_exporter_from_lookup};
+
+fn is_quote_span(req: &HttpMockRequest) -> bool {
+    let Ok(request) = ExportTraceServiceRequest::decode(req.body.as_deref().unwrap_or_default())
+    else {
+        return false;
+    };
+    request
+        .resource_spans
+        .iter()
+        .flat_map(|rs| &rs.scope_spans)
+        .flat_map(|ss| &ss.spans)
+        .any(|span| span.name == "quote")
+}
+
+fn has_resource_metrics(req: &HttpMockRequest) -> bool {
+    serde_json::from_slice::<serde_json::Value>(req.body.as_deref().unwrap_or_default())
+        .is_ok_and(|body| body["resourceMetrics"].is_array())
+}
+
+#[test]
+fn test_traces_over_http_protobuf() {
+    let server = MockServer::start();
+    let traces = server.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/traces")
+            .header("content-type", "application/x-protobuf")
+            .matches(is_quote_span);
+        then.status(200);
+    });
+    let exporter = span_exporter_from_lookup(|var| match var {
+        "OTEL_EXPORTER_OTLP_TRACES_PROTOCOL" => Some("http/protobuf".to_string()),
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT" => Some(server.url("/v1/traces")),
+        _ => None,
+    })
+    .unwrap();
+
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter)
+        .build();
+    provider.tracer("test").in_span("quote", |_| {});
+    provider.shutdown().unwrap();
+
+    traces.assert();
+}
+
+#[test]
+fn test_metrics_over_http_json() {
+    let server = MockServer::start();
+    let metrics = server.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/metrics")
+            .header("content-type", "application/json")
+            .matches(has_resource_metrics);
+        then.status(200);
+    });
+    let exporter = metric_exporter_from_lookup(|var| match var {
+        "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL" => Some("http/json".to_string()),
+        "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT" => Some(server.url("/v1/metrics")),
+        _ => None,
+    })
+    .unwrap();
+
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter).build())
+        .build();
+    provider
+        .meter("test")
+        .u64_counter("app.shipping.items_count")
+        .build()
+        .add(1, &[]);
+    provider.force_flush().unwrap();
+
+    metrics.assert();
+}
@@ -0,0 +1,210 @@
+// Copyright The OpenTelemetry Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Every literal attribute key passed to `KeyValue::new` or `Key::new` is
+//! either in the OpenTelemetry semantic conventions, in the service's own
+//! namespace or listed as an exception, so a `trace_id` can't slip in where `trace.id`
+//! was meant.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use shipping::baggage::is_semconv_key;
+
+/// Prefixes of the keys the service defines itself.
+const SERVICE_NAMESPACES: &[&str] = &["app.shipping.", "shipping."];
+
+/// The fields of exemplar events, which aren't in the conventions.
+const EXEMPLAR_KEYS: &[&str] = &["trace.id", "span.id", "metric.name", "metric.value"];
+
+/// Keys outside the conventions and the service's namespaces that are kept
+/// on purpose.
+const OTHER_KEYS: &[&str] = &[
+    // Asked for by the collector's service graph processor
+    "http.system",
+    // Labels dashboards and alerts already use, named by the requests that
+    // added them
+    "encoding",
+    "canary",
+    "delivery_speed",
+    "instrument",
+    "metric",
+    "error_type",
+    "cpu",
+    "duration_ms",
+    // Order and quote attributes that predate the `shipping.` namespace
+    "api.version",
+    "cache.key",
+    "correlation.id",
+    "estimate.days",
+    "link.type",
+    "order.total_weight_g",
+    "order.tracking_id",
+    "order.weight_class",
+    "quote.cost_usd",
+    "quote.delivery_speed",
+    "quote.discount_percent",
+    "quote.item_count",
+    "quote.strategy",
+    "quote.timeout_ms",
+    "quote.zone",
+    // Startup and background job attributes
+    "app.build.time",
+    "cgroup.success",
+    "cgroup.version",
+    "exchange_rates.count",
+    "exchange_rates.reloaded_at",
+];
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// `source` with its `mod tests { ... }` blocks cut out. Braces in string
+/// and char literals don't count.
+fn without_tests(source: &str) -> String {
+    let mut code = String::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("mod tests {") {
+        code.push_str(&rest[..start]);
+        let body = &rest[start + "mod tests {".len()..];
+        rest = &body[block_len(body)..];
+    }
+    code.push_str(rest);
+    code
+}
+
+/// Length of `body` up to and including the `}` closing the block it is in.
+fn block_len(body: &str) -> usize {
+    let bytes = body.as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            b'r' if matches!(bytes.get(i + 1), Some(b'#' | b'"'))
+                && !bytes[i.saturating_sub(1)].is_ascii_alphanumeric() =>
+            {
+                let hashes = bytes[i + 1..].iter().take_while(|b| **b == b'#').count();
+                let open = i + 1 + hashes;
+                if bytes.get(open) == Some(&b'"') {
+                    let close = format!("\"{}", "#".repeat(hashes));
+                    i = body[open + 1..]
+                        .find(&close)
+                        .map_or(bytes.len(), |end| open + 1 + end + close.len() - 1);
+                }
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'\'' if matches!(bytes.get(i + 2), Some(b'\'')) => i += 2,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// The string literals passed as the first argument of `KeyValue::new` or
+/// `Key::new`, outside of the file's tests.
+fn literal_keys(source: &str) -> Vec<String> {
+    let code = without_tests(source);
+    let mut keys = Vec::new();
+    for call in ["KeyValue::new(", "Key::new("] {
+        for (start, _) in code.match_indices(call) {
+            // `Key::new(` is also the end of e.g. `MetadataKey::new(`
+            let path_start = code[..start]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !c.is_alphanumeric() && c != '_');
+            let args = code[start + call.len()..].trim_start();
+            if let (true, Some(key)) = (path_start, args.strip_prefix('"')) {
+                if let Some(end) = key.find('"') {
+                    keys.push(key[..end].to_string());
+                }
+            }
+        }
+    }
+    keys
+}
+
+fn is_allowed(key: &str) -> bool {
+    is_semconv_key(key)
+        || EXEMPLAR_KEYS.contains(&key)
+        || OTHER_KEYS.contains(&key)
+        || SERVICE_NAMESPACES
+            .iter()
+            .any(|namespace| key.starts_with(namespace))
+}
+
+#[test]
+fn test_literal_keys_follow_semantic_conventions() {
+    let mut files = Vec::new();
+    rust_files(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut files,
+    );
+
+    let mut found = 0;
+    let mut offending = Vec::new();
+    for file in files {
+        for key in literal_keys(&fs::read_to_string(&file).unwrap()) {
+            found += 1;
+            if !is_allowed(&key) {
+                offending.push(format!("{}: {key}", file.display()));
+            }
+        }
+    }
+    assert!(found > 0, "no KeyValue::new or Key::new calls found");
+    assert!(
+        offending.is_empty(),
+        "attribute keys are neither semantic conventions nor namespaced:\n{}",
+        offending.join("\n")
+    );
+}
+
+#[test]
+fn test_literal_keys_include_multiline_calls() {
+    let source = r##"
+        KeyValue::new("shipping.zone", zone);
+        KeyValue::new(
+            "trace_id",
+            id,
+        );
+        KeyValue::new(SERVICE_NAME, name);
+        (Key::new("cache.key"), key.into());
+        MetadataKey::new("x-not-an-attribute");
+        mod tests {
+            let braces = ("}", '}', r#"{"#);
+            KeyValue::new("canary", true);
+        }
+        KeyValue::new("after_tests", true);
+    "##;
+    assert_eq!(
+        literal_keys(source),
+        ["shipping.zone", "trace_id", "after_tests", "cache.key"]
+    );
+    assert!(is_allowed("shipping.zone"));
+    assert!(!is_allowed("trace_id"));
+    assert!(is_allowed("trace.id"));
+    assert!(!is_allowed("trace.flags"));
+    assert!(!is_allowed("span.name"));
+    assert!(is_allowed("http.request.method"));
+    assert!(!is_allowed("order.id"));
+}